@@ -0,0 +1,127 @@
+//! Triangulation benchmarks, run with `cargo bench -p delaunay --bench triangulate`.
+//!
+//! Feature comparisons go through criterion baselines, for instance the parallel merge scans:
+//! `cargo bench -p delaunay --bench triangulate -- --save-baseline serial wide_merge_front`, then
+//! the same with `--features rayon` and `--baseline serial`. `uniform` also prints the heap a
+//! triangulation of its points takes, which criterion doesn't measure.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use delaunay::gns_delaunay::triangulate;
+use delaunay::robust_float::Point2;
+#[cfg(not(feature = "no_std"))]
+use delaunay::triangulation::Triangulation;
+
+/// Heap bytes allocated and not freed yet, and the highest value seen
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// Tracks `LIVE` and `PEAK` for the memory of `uniform`
+struct PeakAllocator;
+
+impl PeakAllocator {
+    fn record(freed: usize, allocated: usize) {
+        let live = LIVE.fetch_add(allocated, Ordering::Relaxed) + allocated;
+        PEAK.fetch_max(live, Ordering::Relaxed);
+        LIVE.fetch_sub(freed, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for PeakAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        PeakAllocator::record(0, layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        PeakAllocator::record(layout.size(), 0);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        PeakAllocator::record(layout.size(), new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakAllocator = PeakAllocator;
+
+/// Uniform floats in [0, 1), the same sequence for the same nonzero seed
+fn xorshift(seed: u64) -> impl FnMut() -> f64 {
+    let mut state = seed;
+    move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Two tall strips far apart, the top merge zips along all of them
+fn wide_merge_front(c: &mut Criterion) {
+    let mut next = xorshift(5);
+    let points: Vec<Point2> = (0..1 << 20)
+        .map(|idx| Point2 {
+            x: next() + if idx % 2 == 0 { 0. } else { 100. },
+            y: next() * 1e6,
+        })
+        .collect();
+    let mut group = c.benchmark_group("wide_merge_front");
+    group.sample_size(10);
+    group.bench_function("1M", |b| {
+        b.iter_batched(
+            || points.clone(),
+            |mut points| triangulate(&mut points),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+/// Uniform points in a square: the time of `triangulate` and `Triangulation::new`, and the heap
+/// a triangulation holds at its peak, printed once
+fn uniform(c: &mut Criterion) {
+    let mut next = xorshift(7);
+    let points: Vec<Point2> = (0..1 << 20)
+        .map(|_| Point2 {
+            x: next(),
+            y: next(),
+        })
+        .collect();
+    // Triangulation needs std, no_std builds only have the line triangulation
+    #[cfg(not(feature = "no_std"))]
+    {
+        let before = LIVE.load(Ordering::Relaxed);
+        PEAK.store(before, Ordering::Relaxed);
+        let triangulation = Triangulation::new(&points);
+        let held = LIVE.load(Ordering::Relaxed) - before;
+        let peak = PEAK.load(Ordering::Relaxed) - before;
+        drop(triangulation);
+        println!(
+            "uniform/1M: Triangulation::new peaks at {} MiB and holds {} MiB, {} bytes per quad edge",
+            peak >> 20,
+            held >> 20,
+            std::mem::size_of::<delaunay::edge::QuadEdge>()
+        );
+    }
+
+    let mut group = c.benchmark_group("uniform");
+    group.sample_size(10);
+    group.bench_function("triangulate/1M", |b| {
+        b.iter_batched(
+            || points.clone(),
+            |mut points| triangulate(&mut points),
+            BatchSize::LargeInput,
+        )
+    });
+    #[cfg(not(feature = "no_std"))]
+    group.bench_function("Triangulation::new/1M", |b| {
+        b.iter(|| Triangulation::new(&points))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, wide_merge_front, uniform);
+criterion_main!(benches);
@@ -0,0 +1,170 @@
+/// Sparse adjacency matrix of the Delaunay graph, in compressed sparse row (CSR) form, the
+/// neighbor structure spatial statistics (spatial lag, Moran's I) work with
+use crate::triangulation::Triangulation;
+
+/// Adjacency matrix of the sites: row `i` holds the neighbors of site `i` in
+/// `col_idx[row_ptr[i]..row_ptr[i + 1]]`, increasing, and their weights at the same positions of
+/// `weights`. The matrix is symmetric and has no self-loops.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SparseAdjacency {
+    /// Start of every row in `col_idx`, one more than the number of sites
+    pub row_ptr: Vec<usize>,
+    /// Neighbor sites, row after row
+    pub col_idx: Vec<usize>,
+    /// Weight of every entry of `col_idx`
+    pub weights: Vec<f64>,
+}
+
+impl SparseAdjacency {
+    /// Number of rows, the number of sites
+    pub fn rows(&self) -> usize {
+        self.row_ptr.len().saturating_sub(1)
+    }
+
+    /// Neighbors of `row` and their weights
+    pub fn row(&self, row: usize) -> impl Iterator<Item = (usize, f64)> + '_ {
+        let range = self.row_ptr[row]..self.row_ptr[row + 1];
+        self.col_idx[range.clone()]
+            .iter()
+            .copied()
+            .zip(self.weights[range].iter().copied())
+    }
+
+    /// Same matrix with the weights of every row divided by their sum, so non empty rows sum
+    /// to 1. It isn't symmetric anymore unless all the rows had the same sum.
+    pub fn row_standardized(&self) -> SparseAdjacency {
+        let mut standardized = self.clone();
+        for row in 0..self.rows() {
+            let range = self.row_ptr[row]..self.row_ptr[row + 1];
+            let sum: f64 = self.weights[range.clone()].iter().sum();
+            for weight in &mut standardized.weights[range] {
+                *weight /= sum;
+            }
+        }
+        standardized
+    }
+}
+
+impl Triangulation {
+    /// Adjacency matrix of the Delaunay graph, with a weight of 1 for every edge. Rows and
+    /// columns are the site indices of `points()`. Removed and ghost sites have empty rows, as
+    /// have the sites without any edge.
+    pub fn adjacency(&self) -> SparseAdjacency {
+        self.adjacency_with(false)
+    }
+
+    /// Like `adjacency`, and when `inverse_distance` is set the weight of every edge is the
+    /// inverse of its length instead of 1
+    pub fn adjacency_with(&self, inverse_distance: bool) -> SparseAdjacency {
+        let points = self.points();
+        let mut adjacency = SparseAdjacency {
+            row_ptr: Vec::with_capacity(points.len() + 1),
+            ..SparseAdjacency::default()
+        };
+        adjacency.row_ptr.push(0);
+        let mut row = vec![];
+        for (site, point) in points.iter().enumerate() {
+            row.clear();
+            if !self.is_removed(site) && !self.is_ghost(*point) {
+                row.extend(
+                    self.onext_ring(site)
+                        .map(|edge| edge.dest(self.quad_arena()))
+                        .filter(|&neighbor| !self.is_ghost(neighbor))
+                        .map(|neighbor| (self.vertex_index(&neighbor).unwrap(), neighbor)),
+                );
+                row.sort_unstable_by_key(|&(neighbor, _)| neighbor);
+            }
+            for &(neighbor, position) in &row {
+                adjacency.col_idx.push(neighbor);
+                adjacency.weights.push(if inverse_distance {
+                    1. / point.distance(&position)
+                } else {
+                    1.
+                });
+            }
+            adjacency.row_ptr.push(adjacency.col_idx.len());
+        }
+        adjacency
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robust_float::Point2;
+    use crate::test_util::xorshift;
+
+    fn check(triangulation: &Triangulation, adjacency: &SparseAdjacency) {
+        let sites = triangulation.points().len();
+        assert_eq!(adjacency.rows(), sites);
+        assert_eq!(adjacency.row_ptr[0], 0);
+        assert_eq!(adjacency.row_ptr[sites], adjacency.col_idx.len());
+        assert_eq!(adjacency.weights.len(), adjacency.col_idx.len());
+        for site in 0..sites {
+            assert!(adjacency.row_ptr[site] <= adjacency.row_ptr[site + 1]);
+            let row: Vec<(usize, f64)> = adjacency.row(site).collect();
+            assert!(row.windows(2).all(|pair| pair[0].0 < pair[1].0));
+            assert!(row
+                .iter()
+                .all(|&(neighbor, _)| neighbor < sites && neighbor != site));
+            if !triangulation.is_removed(site) {
+                assert_eq!(row.len(), triangulation.onext_ring(site).count());
+            }
+            // Symmetric
+            for (neighbor, weight) in row {
+                assert!(adjacency.row(neighbor).any(|entry| entry == (site, weight)));
+            }
+        }
+        assert_eq!(adjacency.col_idx.len(), 2 * triangulation.edges().len());
+    }
+
+    #[test]
+    fn test_adjacency() {
+        let mut next = xorshift(41);
+        let points: Vec<Point2> = (0..300)
+            .map(|_| Point2 {
+                x: next() * 100.,
+                y: next() * 100.,
+            })
+            .collect();
+        let mut triangulation = Triangulation::new(&points);
+        triangulation.remove_vertex(7);
+        let adjacency = triangulation.adjacency();
+        check(&triangulation, &adjacency);
+        assert_eq!(adjacency.row(7).count(), 0);
+        assert!(adjacency.weights.iter().all(|&weight| weight == 1.));
+
+        let weighted = triangulation.adjacency_with(true);
+        check(&triangulation, &weighted);
+        assert_eq!(weighted.col_idx, adjacency.col_idx);
+        let sites = triangulation.points();
+        let (neighbor, weight) = weighted.row(0).next().unwrap();
+        assert_eq!(weight, 1. / sites[0].distance(&sites[neighbor]));
+
+        let standardized = weighted.row_standardized();
+        for site in (0..sites.len()).filter(|&site| site != 7) {
+            let sum: f64 = standardized.row(site).map(|(_, weight)| weight).sum();
+            assert!((sum - 1.).abs() < 1e-12);
+        }
+        assert_eq!(standardized.row(7).count(), 0);
+
+        // Collinear sites are a path, a lone site an empty row
+        let line: Vec<Point2> = (0..4).map(|x| Point2 { x: x as f64, y: 0. }).collect();
+        let triangulation = Triangulation::new(&line);
+        let adjacency = triangulation.adjacency();
+        check(&triangulation, &adjacency);
+        assert_eq!(adjacency.row_ptr, [0, 1, 3, 5, 6]);
+        assert_eq!(adjacency.col_idx, [1, 0, 2, 1, 3, 2]);
+        let lone = Triangulation::new(&line[..1]).adjacency();
+        assert_eq!((lone.row_ptr, lone.col_idx), (vec![0, 0], vec![]));
+        // Ghosts stay out
+        let ghosts = Triangulation::with_ghosts(&points);
+        let adjacency = ghosts.adjacency();
+        let is_ghost = |site: usize| ghosts.is_ghost(ghosts.points()[site]);
+        assert_eq!(adjacency.rows(), ghosts.points().len());
+        for site in 0..adjacency.rows() {
+            assert!(adjacency.row(site).all(|(neighbor, _)| !is_ghost(neighbor)));
+            assert!(!is_ghost(site) || adjacency.row(site).count() == 0);
+        }
+    }
+}
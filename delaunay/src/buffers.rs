@@ -0,0 +1,161 @@
+/// Flat f32 vertex and index buffers, the layout wgpu vertex buffers and lyon's `VertexBuffers`
+/// use
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::triangulation::Triangulation;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BufferError {
+    /// More vertices than u32 indices can address, holds the number of sites
+    TooManyVertices(usize),
+}
+
+impl fmt::Display for BufferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BufferError::TooManyVertices(count) => {
+                write!(f, "{} sites don't fit in u32 indices", count)
+            }
+        }
+    }
+}
+
+/// Vertex and index buffers of a triangulation
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshBuffers {
+    /// Interleaved x, y of every vertex, followed by its height when the triangulation has some
+    pub positions: Vec<f32>,
+    /// Three vertices per triangle, counter clockwise as `counter_clockwise` defines it
+    pub indices: Vec<u32>,
+}
+
+impl Triangulation {
+    /// Buffers of the sites and the triangles of `triangles_iter`, one vertex per site in the
+    /// `points()` order, removed and ghost sites left out. Fails when the vertices can't be
+    /// indexed with u32.
+    pub fn to_buffers(&self) -> Result<MeshBuffers, BufferError> {
+        self.to_buffers_with(false)
+    }
+
+    /// Like `to_buffers`, and when `weld` is set, sites with bitwise equal f32 positions share a
+    /// vertex. Distinct sites can round to the same f32 position; triangles left with two equal
+    /// vertices by the weld are dropped.
+    pub fn to_buffers_with(&self, weld: bool) -> Result<MeshBuffers, BufferError> {
+        let points = self.points();
+        let heights = self.heights();
+        let mut buffers = MeshBuffers::default();
+        // Buffer vertex of every site
+        let mut vertex_of = vec![u32::MAX; points.len()];
+        let mut welded: HashMap<[u32; 3], u32> = HashMap::new();
+        let mut count = 0usize;
+        for (site, point) in points.iter().enumerate() {
+            if self.is_removed(site) || self.is_ghost(*point) {
+                continue;
+            }
+            let position = [
+                point.x as f32,
+                point.y as f32,
+                heights.map_or(0., |heights| heights[site] as f32),
+            ];
+            if weld {
+                if let Some(&vertex) = welded.get(&position.map(f32::to_bits)) {
+                    vertex_of[site] = vertex;
+                    continue;
+                }
+            }
+            let vertex =
+                u32::try_from(count).map_err(|_| BufferError::TooManyVertices(points.len()))?;
+            count += 1;
+            vertex_of[site] = vertex;
+            if weld {
+                welded.insert(position.map(f32::to_bits), vertex);
+            }
+            let dimensions = if heights.is_some() { 3 } else { 2 };
+            buffers.positions.extend_from_slice(&position[..dimensions]);
+        }
+
+        for corners in self.triangles_iter() {
+            let triangle = corners.map(|corner| vertex_of[self.vertex_index(&corner).unwrap()]);
+            let [a, b, c] = triangle;
+            if a != b && b != c && c != a {
+                buffers.indices.extend_from_slice(&triangle);
+            }
+        }
+        Ok(buffers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robust_float::Point2;
+    use crate::test_util::xorshift;
+
+    /// Triangles of the buffers, with f64 coordinates
+    fn triangles(buffers: &MeshBuffers, dimensions: usize) -> Vec<[Point2; 3]> {
+        let point = |vertex: u32| {
+            let at = vertex as usize * dimensions;
+            Point2 {
+                x: buffers.positions[at] as f64,
+                y: buffers.positions[at + 1] as f64,
+            }
+        };
+        buffers
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| [point(triangle[0]), point(triangle[1]), point(triangle[2])])
+            .collect()
+    }
+
+    #[test]
+    fn test_to_buffers() {
+        let mut next = xorshift(29);
+        let points: Vec<Point2> = (0..300)
+            .map(|_| Point2 {
+                x: (next() * 1000.).round() / 8.,
+                y: (next() * 1000.).round() / 8.,
+            })
+            .collect();
+        let mut triangulation = Triangulation::new(&points);
+        triangulation.remove_vertex(3);
+        let buffers = triangulation.to_buffers().unwrap();
+        let (vertices, _, faces) = triangulation.counts();
+        assert_eq!(buffers.positions.len(), 2 * vertices);
+        assert_eq!(buffers.indices.len(), 3 * (faces - 1));
+        assert_eq!(
+            triangles(&buffers, 2),
+            triangulation.triangles_iter().collect::<Vec<_>>()
+        );
+        // Nothing to weld, and the ghosts stay out
+        assert_eq!(triangulation.to_buffers_with(true), Ok(buffers.clone()));
+        assert_eq!(
+            Triangulation::with_ghosts(&points).to_buffers(),
+            Triangulation::new(&points).to_buffers()
+        );
+
+        let heights: Vec<f64> = points.iter().map(|point| point.x - point.y).collect();
+        let terrain = Triangulation::with_heights(&points, &heights);
+        let buffers = terrain.to_buffers().unwrap();
+        assert_eq!(buffers.positions.len(), 3 * terrain.counts().0);
+        assert_eq!(
+            triangles(&buffers, 3),
+            terrain.triangles_iter().collect::<Vec<_>>()
+        );
+        for position in buffers.positions.chunks_exact(3) {
+            assert_eq!(position[2], position[0] - position[1]);
+        }
+
+        // Two sites closer than f32 can tell apart, the triangle between them goes with the weld
+        let close = [(0., 0.), (1., 0.), (1. + 1e-12, 1.), (1., 1.), (0., 1.)]
+            .map(|(x, y)| Point2 { x, y });
+        let triangulation = Triangulation::new(&close);
+        let buffers = triangulation.to_buffers().unwrap();
+        let welded = triangulation.to_buffers_with(true).unwrap();
+        assert_eq!(buffers.positions.len(), 10);
+        assert_eq!(welded.positions.len(), 8);
+        assert_eq!(buffers.indices.len(), 9);
+        assert_eq!(welded.indices.len(), 6);
+        assert!(welded.indices.iter().all(|&vertex| vertex < 4));
+    }
+}
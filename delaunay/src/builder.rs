@@ -0,0 +1,319 @@
+/// One entry point for the options of a `Triangulation`
+///
+/// The constructors of `Triangulation` each turn on one option; `TriangulationBuilder` combines
+/// them. Every option defaults to what `Triangulation::new` does.
+use std::collections::HashSet;
+
+#[cfg(feature = "exact")]
+use crate::exact::{with_predicate_backend, PredicateBackend};
+use crate::gns_delaunay::quad_capacity;
+use crate::preprocess::find_duplicates;
+use crate::robust_float::{sanitize_points_vec, Point2};
+use crate::triangulation::{bounding_rect, sanitize_with, Triangulation};
+
+/// Options of a triangulation, set one by one then applied by `build`
+///
+/// ```
+/// use delaunay::builder::TriangulationBuilder;
+/// use delaunay::robust_float::Point2;
+///
+/// let points = [
+///     Point2 { x: 0., y: 0. },
+///     Point2 { x: 1., y: 0. },
+///     Point2 { x: 1., y: 1. },
+///     Point2 { x: 0., y: 1. },
+///     Point2 { x: 0.001, y: 1. },
+/// ];
+/// let triangulation = TriangulationBuilder::new()
+///     .dedup_tolerance(0.01)
+///     .tiebreak_seed(7)
+///     .ghost_vertices(true)
+///     .build(&points);
+/// // The near-duplicate corner is merged, three ghosts wrap the square
+/// assert_eq!(triangulation.points().len(), 4 + 3);
+/// assert_eq!(triangulation.triangles().len(), 2);
+/// ```
+#[derive(Copy, Clone, Debug, Default)]
+pub struct TriangulationBuilder {
+    dedup_tolerance: Option<f64>,
+    #[cfg(feature = "exact")]
+    predicates: PredicateBackend,
+    capacity: Option<usize>,
+    tiebreak_seed: Option<u64>,
+    ghost_vertices: bool,
+    normalize: bool,
+}
+
+impl TriangulationBuilder {
+    pub fn new() -> TriangulationBuilder {
+        TriangulationBuilder::default()
+    }
+
+    /// Merge the sites within `tolerance` of each other, directly or through a chain of sites,
+    /// keeping the smallest one in `cmp_points` order. Near-equal sites are merged anyway.
+    ///
+    /// Panics if `tolerance` is negative or NaN.
+    pub fn dedup_tolerance(mut self, tolerance: f64) -> TriangulationBuilder {
+        assert!(
+            tolerance >= 0.,
+            "The tolerance must be positive, got {}",
+            tolerance
+        );
+        self.dedup_tolerance = Some(tolerance);
+        self
+    }
+
+    /// Evaluate the predicates with `backend` while building, see `with_predicate_backend`.
+    /// Queries on the triangulation use the backend of the thread running them.
+    #[cfg(feature = "exact")]
+    pub fn predicates(mut self, backend: PredicateBackend) -> TriangulationBuilder {
+        self.predicates = backend;
+        self
+    }
+
+    /// Room for `capacity` quad edges in the arena instead of `quad_capacity` of the sites. The
+    /// arena grows past a smaller capacity.
+    pub fn capacity(mut self, capacity: usize) -> TriangulationBuilder {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Choose the diagonals of cocircular quadrilaterals from `seed`, see
+    /// `Triangulation::with_tiebreak`
+    pub fn tiebreak_seed(mut self, seed: u64) -> TriangulationBuilder {
+        self.tiebreak_seed = Some(seed);
+        self
+    }
+
+    /// Wrap the sites in three ghost sites, see `Triangulation::with_ghosts`
+    pub fn ghost_vertices(mut self, ghosts: bool) -> TriangulationBuilder {
+        self.ghost_vertices = ghosts;
+        self
+    }
+
+    /// Triangulate copies of the points translated by the center of their bounding box, then give
+    /// the sites back the coordinates of the points, bit for bit. Sanitizing, `dedup_tolerance`
+    /// and the predicates see the translated copies, so a cloud far from the origin, like UTM
+    /// coordinates, is handled like the same cloud around the origin.
+    ///
+    /// Translating a cloud far from the origin is exact, the mesh is then the one of the given
+    /// points. Otherwise it is the mesh of the rounded copies: a point far from the others can
+    /// lose its low bits to the translation.
+    pub fn normalize(mut self, normalize: bool) -> TriangulationBuilder {
+        self.normalize = normalize;
+        self
+    }
+
+    /// Sanitize and triangulate the points with the options
+    pub fn build(&self, points: &[Point2]) -> Triangulation {
+        #[cfg(feature = "exact")]
+        {
+            with_predicate_backend(self.predicates, || self.build_with_backend(points))
+        }
+        #[cfg(not(feature = "exact"))]
+        {
+            self.build_with_backend(points)
+        }
+    }
+
+    fn build_with_backend(&self, points: &[Point2]) -> Triangulation {
+        // The sites to triangulate, and with `normalize` the point each one was translated from
+        let (mut points, mut originals) = match self.normalize.then(|| center(points)).flatten() {
+            Some(center) => {
+                let translated: Vec<Point2> = points
+                    .iter()
+                    .map(|point| Point2 {
+                        x: point.x - center.x,
+                        y: point.y - center.y,
+                    })
+                    .collect();
+                let (translated, originals) = sanitize_with(&translated, points);
+                (translated, Some(originals))
+            }
+            None => {
+                let mut points = points.to_vec();
+                sanitize_points_vec(&mut points);
+                (points, None)
+            }
+        };
+        if let Some(tolerance) = self.dedup_tolerance {
+            // Groups are sorted, the first site of every group stays
+            let merged: HashSet<usize> = find_duplicates(&points, tolerance)
+                .into_iter()
+                .flat_map(|group| group.into_iter().skip(1))
+                .collect();
+            let merged = &merged;
+            let kept = |mut idx: usize| {
+                move |_: &Point2| {
+                    idx += 1;
+                    !merged.contains(&(idx - 1))
+                }
+            };
+            points.retain(kept(0));
+            if let Some(originals) = &mut originals {
+                originals.retain(kept(0));
+            }
+        }
+        let capacity = self.capacity.unwrap_or_else(|| quad_capacity(points.len()));
+        let mut triangulation =
+            Triangulation::from_sanitized_recorded(points, None, capacity, &mut None);
+        if let Some(originals) = originals {
+            triangulation.restore_sites(originals);
+        }
+        if let Some(seed) = self.tiebreak_seed {
+            triangulation.break_ties(seed);
+        }
+        if self.ghost_vertices {
+            triangulation.add_ghosts();
+        }
+        triangulation
+    }
+}
+
+/// Center of the bounding box of the finite points, None without any
+fn center(points: &[Point2]) -> Option<Point2> {
+    let finite: Vec<Point2> = points
+        .iter()
+        .filter(|point| point.x.is_finite() && point.y.is_finite())
+        .copied()
+        .collect();
+    let bounds = bounding_rect(&finite)?;
+    Some(Point2 {
+        x: bounds.min.x / 2. + bounds.max.x / 2.,
+        y: bounds.min.y / 2. + bounds.max.y / 2.,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::random_points;
+
+    fn grid() -> Vec<Point2> {
+        (0..36)
+            .map(|idx| Point2 {
+                x: (idx % 6) as f64,
+                y: (idx / 6) as f64,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_defaults() {
+        let points = grid();
+        let built = TriangulationBuilder::new().build(&points);
+        let plain = Triangulation::new(&points);
+        assert_eq!(built.points(), plain.points());
+        assert_eq!(built.edges(), plain.edges());
+        // The capacity only sizes the arena
+        let sized = TriangulationBuilder::new().capacity(1).build(&points);
+        assert_eq!(sized.edges(), plain.edges());
+    }
+
+    #[test]
+    fn test_options() {
+        let mut points = grid();
+        points.push(Point2 { x: 2.05, y: 3. });
+        let merged = TriangulationBuilder::new()
+            .dedup_tolerance(0.1)
+            .build(&points);
+        assert_eq!(merged.points(), Triangulation::new(&grid()).points());
+        assert_eq!(
+            TriangulationBuilder::new().build(&points).points().len(),
+            37
+        );
+
+        // Every cell of the grid is a cocircular quadrilateral, the seeds pick their diagonals
+        let points = grid();
+        let seeded: Vec<Vec<(Point2, Point2)>> = [7, 8]
+            .map(|seed| {
+                let built = TriangulationBuilder::new()
+                    .tiebreak_seed(seed)
+                    .build(&points);
+                assert_eq!(
+                    built.edges(),
+                    Triangulation::with_tiebreak(&points, seed).edges()
+                );
+                built.edges()
+            })
+            .to_vec();
+        assert_ne!(seeded[0], seeded[1]);
+
+        let built = TriangulationBuilder::new()
+            .ghost_vertices(true)
+            .build(&points);
+        let ghosted = Triangulation::with_ghosts(&points);
+        assert_eq!(built.points(), ghosted.points());
+        assert!(built.is_ghost(built.points()[36]));
+        assert_eq!(built.live_edges().count(), ghosted.live_edges().count());
+    }
+
+    #[test]
+    fn test_normalize() {
+        // Coordinates in 1/1024 steps, which an offset of 1e9 keeps exact
+        let mut local: Vec<Point2> = random_points(61, 300, 100.)
+            .into_iter()
+            .map(|point| Point2 {
+                x: (point.x * 1024.).round() / 1024.,
+                y: (point.y * 1024.).round() / 1024.,
+            })
+            .collect();
+        local.push(Point2 {
+            x: local[0].x + 0.0625,
+            y: local[0].y,
+        });
+        let shift = |point: &Point2, by: f64| Point2 {
+            x: point.x + by,
+            y: point.y + by,
+        };
+        let far: Vec<Point2> = local.iter().map(|point| shift(point, 1e9)).collect();
+        let builder = TriangulationBuilder::new()
+            .normalize(true)
+            .dedup_tolerance(0.1);
+        let near = builder.build(&local);
+        let built = builder.build(&far);
+
+        // The same mesh at both offsets, the near-duplicate merged in both
+        assert_eq!(built.points().len(), 300);
+        let shifted: Vec<(Point2, Point2)> = built
+            .edges()
+            .iter()
+            .map(|(a, b)| (shift(a, -1e9), shift(b, -1e9)))
+            .collect();
+        assert_eq!(shifted, near.edges());
+        assert_eq!(
+            built.edges(),
+            TriangulationBuilder::new()
+                .dedup_tolerance(0.1)
+                .build(&far)
+                .edges()
+        );
+        // Sites and edge endpoints are the given points, bit for bit
+        let bits = |point: &Point2| (point.x.to_bits(), point.y.to_bits());
+        let inputs: HashSet<(u64, u64)> = far.iter().map(bits).collect();
+        assert!(built
+            .points()
+            .iter()
+            .all(|point| inputs.contains(&bits(point))));
+        assert!(built
+            .edges()
+            .iter()
+            .all(|(a, b)| inputs.contains(&bits(a)) && inputs.contains(&bits(b))));
+        assert!(far[1..300]
+            .iter()
+            .all(|point| built.vertex_index(point).is_some()));
+    }
+
+    #[cfg(feature = "exact")]
+    #[test]
+    fn test_exact_predicates() {
+        use crate::exact::predicate_backend;
+
+        let points = grid();
+        let built = TriangulationBuilder::new()
+            .predicates(PredicateBackend::Exact)
+            .build(&points);
+        assert_eq!(built.edges(), Triangulation::new(&points).edges());
+        assert_eq!(predicate_backend(), PredicateBackend::Robust);
+    }
+}
@@ -0,0 +1,168 @@
+/// Euclidean minimum spanning tree and single-linkage clustering
+///
+/// The minimum spanning tree of the sites only uses Delaunay edges, so Kruskal's algorithm over
+/// the edges of the mesh finds it in O(n log n). Cutting the tree's edges longer than a distance,
+/// or its k - 1 longest edges, leaves the single-linkage clusters of the sites.
+use crate::triangulation::{Triangulation, VertexHandle};
+
+/// Disjoint sets of site handles, with path halving and union by size
+pub(crate) struct UnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl UnionFind {
+    pub(crate) fn new(count: usize) -> UnionFind {
+        UnionFind {
+            parent: (0..count).collect(),
+            size: vec![1; count],
+        }
+    }
+
+    pub(crate) fn find(&mut self, mut item: usize) -> usize {
+        while self.parent[item] != item {
+            self.parent[item] = self.parent[self.parent[item]];
+            item = self.parent[item];
+        }
+        item
+    }
+
+    /// Merge the sets of `a` and `b`, return false if they were already the same
+    pub(crate) fn union(&mut self, a: usize, b: usize) -> bool {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        true
+    }
+}
+
+impl Triangulation {
+    /// Sites of the clustering: live sites, the ghosts left out
+    fn cluster_sites(&self) -> Vec<VertexHandle> {
+        (0..self.points().len())
+            .filter(|&vertex| !self.is_removed(vertex) && !self.is_ghost(self.points()[vertex]))
+            .collect()
+    }
+
+    /// Edges of the Euclidean minimum spanning tree of the sites, shortest first, with their
+    /// length. The ghosts and their edges are left out.
+    pub fn emst(&self) -> Vec<(VertexHandle, VertexHandle, f64)> {
+        let mut edges: Vec<(VertexHandle, VertexHandle, f64)> = self
+            .edges_iter()
+            .map(|(org, dest)| {
+                (
+                    self.vertex_index(&org).unwrap(),
+                    self.vertex_index(&dest).unwrap(),
+                    org.distance(&dest),
+                )
+            })
+            .collect();
+        edges.sort_by(|a, b| a.2.total_cmp(&b.2));
+        let mut sets = UnionFind::new(self.points().len());
+        edges.retain(|&(a, b, _)| sets.union(a, b));
+        edges
+    }
+
+    /// Group the sites, `tree` edges joining sites of a same cluster. Sites are sorted in a
+    /// cluster, and clusters by their first site.
+    fn clusters_of(
+        &self,
+        tree: impl Iterator<Item = (VertexHandle, VertexHandle)>,
+    ) -> Vec<Vec<VertexHandle>> {
+        let mut sets = UnionFind::new(self.points().len());
+        for (a, b) in tree {
+            sets.union(a, b);
+        }
+        let mut slots = vec![usize::MAX; self.points().len()];
+        let mut clusters: Vec<Vec<VertexHandle>> = vec![];
+        for vertex in self.cluster_sites() {
+            let root = sets.find(vertex);
+            if slots[root] == usize::MAX {
+                slots[root] = clusters.len();
+                clusters.push(vec![]);
+            }
+            clusters[slots[root]].push(vertex);
+        }
+        clusters
+    }
+
+    /// Single-linkage clusters: sites closer than `max_edge`, directly or through a chain of
+    /// sites, are in a same cluster. Clusters are lists of site handles, sorted, and come sorted
+    /// by their first site.
+    pub fn clusters_by_distance(&self, max_edge: f64) -> Vec<Vec<VertexHandle>> {
+        let tree = self.emst();
+        self.clusters_of(
+            tree.into_iter()
+                .take_while(|&(_, _, length)| length <= max_edge)
+                .map(|(a, b, _)| (a, b)),
+        )
+    }
+
+    /// Single-linkage clusters, cutting the `k - 1` longest edges of the spanning tree. Gives
+    /// `k` clusters, fewer when there are fewer sites, in the order of `clusters_by_distance`.
+    /// A `k` of 0 is taken as 1.
+    pub fn clusters_k(&self, k: usize) -> Vec<Vec<VertexHandle>> {
+        let tree = self.emst();
+        let kept = tree.len().saturating_sub(k.max(1) - 1);
+        self.clusters_of(tree.into_iter().take(kept).map(|(a, b, _)| (a, b)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robust_float::Point2;
+    use crate::test_util::xorshift;
+
+    #[test]
+    fn test_clusters() {
+        let mut next = xorshift(59);
+        // Two gaussian blobs of deviation 1, 40 apart, with the Box-Muller transform
+        let mut gaussian = move || {
+            let (u, v) = (1. - next(), next());
+            (-2. * u.ln()).sqrt() * (std::f64::consts::TAU * v).cos()
+        };
+        let points: Vec<Point2> = (0..400)
+            .map(|idx| Point2 {
+                x: gaussian() + if idx % 2 == 0 { 0. } else { 40. },
+                y: gaussian(),
+            })
+            .collect();
+        let triangulation = Triangulation::new(&points);
+        let tree = triangulation.emst();
+        assert_eq!(tree.len(), 399);
+        assert!(tree.windows(2).all(|pair| pair[0].2 <= pair[1].2));
+
+        let clusters = triangulation.clusters_by_distance(5.);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters, triangulation.clusters_k(2));
+        for cluster in &clusters {
+            assert_eq!(cluster.len(), 200);
+            let left = triangulation.points()[cluster[0]].x < 20.;
+            assert!(cluster
+                .iter()
+                .all(|&vertex| (triangulation.points()[vertex].x < 20.) == left));
+        }
+
+        // Everything connected, or every site alone
+        assert_eq!(triangulation.clusters_by_distance(100.).len(), 1);
+        assert_eq!(triangulation.clusters_k(0), triangulation.clusters_k(1));
+        let singletons = triangulation.clusters_by_distance(0.);
+        assert_eq!(singletons.len(), 400);
+        assert_eq!(triangulation.clusters_k(1000), singletons);
+        assert!(singletons
+            .iter()
+            .enumerate()
+            .all(|(idx, cluster)| *cluster == [idx]));
+
+        let single = Triangulation::new(&[Point2 { x: 1., y: 1. }]);
+        assert_eq!(single.emst(), vec![]);
+        assert_eq!(single.clusters_k(3), vec![vec![0]]);
+    }
+}
@@ -0,0 +1,147 @@
+/// Validation of constraint segments, given as pairs of indices into the sites
+///
+/// A constrained triangulation needs segments that only meet at shared endpoints: a crossing or
+/// an overlap can't be an edge of both segments at once.
+use std::fmt;
+
+use crate::robust_float::{orient2d_value, Point2};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConstraintError {
+    /// The constraint at this position refers to a site that doesn't exist
+    UnknownSite(usize),
+    /// The constraint at this position has both endpoints at the same place
+    Degenerate(usize),
+    /// The constraints at these positions cross, or one ends inside of the other
+    Crossing(usize, usize),
+    /// The constraints at these positions are collinear and share more than a point
+    Overlapping(usize, usize),
+}
+
+impl fmt::Display for ConstraintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstraintError::UnknownSite(idx) => {
+                write!(f, "Constraint {} refers to a missing site", idx)
+            }
+            ConstraintError::Degenerate(idx) => {
+                write!(f, "Constraint {} has a zero length", idx)
+            }
+            ConstraintError::Crossing(a, b) => write!(f, "Constraints {} and {} cross", a, b),
+            ConstraintError::Overlapping(a, b) => {
+                write!(f, "Constraints {} and {} overlap", a, b)
+            }
+        }
+    }
+}
+
+/// Return true if `p`, collinear with the segment, lies strictly between its endpoints
+fn inside_segment(p: &Point2, (a, b): (&Point2, &Point2)) -> bool {
+    let within =
+        p.x >= a.x.min(b.x) && p.x <= a.x.max(b.x) && p.y >= a.y.min(b.y) && p.y <= a.y.max(b.y);
+    within && p != a && p != b
+}
+
+/// How two segments meet, None when they are apart or only share an endpoint
+fn intersection(
+    (p1, p2): (&Point2, &Point2),
+    (q1, q2): (&Point2, &Point2),
+) -> Option<fn(usize, usize) -> ConstraintError> {
+    let sides = [
+        orient2d_value(q1, q2, p1),
+        orient2d_value(q1, q2, p2),
+        orient2d_value(p1, p2, q1),
+        orient2d_value(p1, p2, q2),
+    ];
+    if sides.iter().all(|&side| side == 0.) {
+        // Collinear, compare the extents along the main axis
+        let horizontal = (p1.x - p2.x).abs() >= (p1.y - p2.y).abs();
+        let coord = |p: &Point2| if horizontal { p.x } else { p.y };
+        let low = coord(p1).min(coord(p2)).max(coord(q1).min(coord(q2)));
+        let high = coord(p1).max(coord(p2)).min(coord(q1).max(coord(q2)));
+        return (low < high).then_some(ConstraintError::Overlapping);
+    }
+    let proper = sides[0] * sides[1] < 0. && sides[2] * sides[3] < 0.;
+    let touching = sides[0] == 0. && inside_segment(p1, (q1, q2))
+        || sides[1] == 0. && inside_segment(p2, (q1, q2))
+        || sides[2] == 0. && inside_segment(q1, (p1, p2))
+        || sides[3] == 0. && inside_segment(q2, (p1, p2));
+    (proper || touching).then_some(ConstraintError::Crossing)
+}
+
+/// Check that the constraint segments, pairs of indices into `points`, only meet at shared
+/// endpoints. The first offending constraint, or pair of constraints, is reported.
+pub fn check_constraints(
+    points: &[Point2],
+    constraints: &[(usize, usize)],
+) -> Result<(), ConstraintError> {
+    let mut segments = Vec::with_capacity(constraints.len());
+    for (idx, &(a, b)) in constraints.iter().enumerate() {
+        let (Some(a), Some(b)) = (points.get(a), points.get(b)) else {
+            return Err(ConstraintError::UnknownSite(idx));
+        };
+        if a == b {
+            return Err(ConstraintError::Degenerate(idx));
+        }
+        segments.push((a, b));
+    }
+    for (i, &first) in segments.iter().enumerate() {
+        for (j, &second) in segments.iter().enumerate().skip(i + 1) {
+            if let Some(error) = intersection(first, second) {
+                return Err(error(i, j));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_constraints() {
+        let points = [
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 4., y: 4. },
+            Point2 { x: 0., y: 4. },
+            Point2 { x: 4., y: 0. },
+            Point2 { x: 2., y: 2. },
+            Point2 { x: 6., y: 6. },
+            Point2 { x: 8., y: 0. },
+        ];
+        // A star around the center and a segment away from it
+        assert_eq!(
+            check_constraints(&points, &[(4, 0), (4, 1), (2, 4), (3, 4), (3, 6)]),
+            Ok(())
+        );
+        assert_eq!(check_constraints(&points, &[]), Ok(()));
+
+        assert_eq!(
+            check_constraints(&points, &[(3, 6), (0, 1), (2, 3)]),
+            Err(ConstraintError::Crossing(1, 2))
+        );
+        // Ending on the inside of another constraint
+        assert_eq!(
+            check_constraints(&points, &[(2, 3), (4, 5)]),
+            Err(ConstraintError::Crossing(0, 1))
+        );
+        assert_eq!(
+            check_constraints(&points, &[(0, 1), (4, 5)]),
+            Err(ConstraintError::Overlapping(0, 1))
+        );
+        // Collinear segments meeting at one end are fine
+        assert_eq!(
+            check_constraints(&points, &[(0, 4), (4, 1), (1, 5)]),
+            Ok(())
+        );
+        assert_eq!(
+            check_constraints(&points, &[(0, 1), (1, 7)]),
+            Err(ConstraintError::UnknownSite(1))
+        );
+        assert_eq!(
+            check_constraints(&points, &[(0, 0)]),
+            Err(ConstraintError::Degenerate(0))
+        );
+    }
+}
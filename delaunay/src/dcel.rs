@@ -0,0 +1,173 @@
+/// Doubly connected edge list (DCEL) view of a triangulation, for mesh processing code that
+/// expects one
+///
+/// Every live quad edge gives two twin half edges, `2 * i` and `2 * i + 1`. A half edge's `next`
+/// is its `lnext`, so following `next` goes around the face on the left of the half edge.
+use std::collections::HashMap;
+
+use crate::edge::EdgeRef;
+use crate::robust_float::Point2;
+use crate::triangulation::Triangulation;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Vertex {
+    pub point: Point2,
+    /// One half edge leaving the vertex, None for removed sites
+    pub half_edge: Option<usize>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HalfEdge {
+    pub origin: usize,
+    pub twin: usize,
+    pub next: usize,
+    /// Face on the left of the half edge
+    pub face: usize,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Face {
+    /// One half edge on the boundary of the face
+    pub half_edge: usize,
+    /// False for the unbounded face around the hull
+    pub bounded: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dcel {
+    /// Indexed like `Triangulation::points()`
+    pub vertices: Vec<Vertex>,
+    pub half_edges: Vec<HalfEdge>,
+    pub faces: Vec<Face>,
+}
+
+impl Triangulation {
+    /// Convert to a doubly connected edge list. The faces are the triangles and the unbounded
+    /// face, a collinear mesh only has the unbounded face.
+    pub fn to_dcel(&self) -> Dcel {
+        let arena = self.quad_arena();
+        let edges: Vec<EdgeRef> = self
+            .live_edges()
+            .flat_map(|edge| [edge, edge.sym()])
+            .collect();
+        let half_edge_of: HashMap<EdgeRef, usize> = edges
+            .iter()
+            .enumerate()
+            .map(|(idx, &edge)| (edge, idx))
+            .collect();
+
+        let mut dcel = Dcel {
+            vertices: self
+                .points()
+                .iter()
+                .map(|&point| Vertex {
+                    point,
+                    half_edge: None,
+                })
+                .collect(),
+            ..Dcel::default()
+        };
+        for (idx, edge) in edges.iter().enumerate() {
+            let origin = self.vertex_index(&edge.org(arena)).unwrap();
+            dcel.vertices[origin].half_edge.get_or_insert(idx);
+            dcel.half_edges.push(HalfEdge {
+                origin,
+                twin: idx ^ 1,
+                next: half_edge_of[&edge.lnext(arena)],
+                face: usize::MAX,
+            });
+        }
+
+        for (idx, &edge) in edges.iter().enumerate() {
+            if dcel.half_edges[idx].face != usize::MAX {
+                continue;
+            }
+            let face = dcel.faces.len();
+            dcel.faces.push(Face {
+                half_edge: idx,
+                bounded: self.left_triangle(edge).is_some(),
+            });
+            let mut current = idx;
+            while dcel.half_edges[current].face == usize::MAX {
+                dcel.half_edges[current].face = face;
+                current = dcel.half_edges[current].next;
+            }
+        }
+        dcel
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::xorshift;
+
+    fn check_invariants(dcel: &Dcel) {
+        for (idx, half_edge) in dcel.half_edges.iter().enumerate() {
+            assert_ne!(half_edge.twin, idx);
+            assert_eq!(dcel.half_edges[half_edge.twin].twin, idx);
+            // The twin ends where the next half edge starts
+            assert_eq!(
+                dcel.half_edges[half_edge.twin].origin,
+                dcel.half_edges[half_edge.next].origin
+            );
+            // Next cycles stay in the face and come back
+            let mut current = half_edge.next;
+            let mut steps = 1;
+            while current != idx {
+                assert_eq!(dcel.half_edges[current].face, half_edge.face);
+                current = dcel.half_edges[current].next;
+                steps += 1;
+                assert!(steps <= dcel.half_edges.len());
+            }
+            if dcel.faces[half_edge.face].bounded {
+                assert_eq!(steps, 3);
+            }
+        }
+        for face in &dcel.faces {
+            assert_eq!(
+                dcel.half_edges[face.half_edge].face,
+                dcel.faces.iter().position(|other| other == face).unwrap()
+            );
+        }
+        for (idx, vertex) in dcel.vertices.iter().enumerate() {
+            if let Some(half_edge) = vertex.half_edge {
+                assert_eq!(dcel.half_edges[half_edge].origin, idx);
+            }
+        }
+        assert_eq!(dcel.faces.iter().filter(|face| !face.bounded).count(), 1);
+    }
+
+    #[test]
+    fn test_dcel_invariants() {
+        let mut next = xorshift(43);
+        let points: Vec<Point2> = (0..150)
+            .map(|_| Point2 {
+                x: next() * 10.,
+                y: next() * 10.,
+            })
+            .collect();
+        let mut triangulation = Triangulation::new(&points);
+        triangulation.remove_vertex(12);
+        let dcel = triangulation.to_dcel();
+        check_invariants(&dcel);
+        assert_eq!(dcel.vertices[12].half_edge, None);
+        // Euler characteristic of the plane, the unbounded face included
+        let vertices = dcel.vertices.len() - 1;
+        assert_eq!(
+            vertices as i64 - (dcel.half_edges.len() / 2) as i64 + dcel.faces.len() as i64,
+            2
+        );
+
+        let collinear: Vec<Point2> = (0..5)
+            .map(|i| Point2 {
+                x: i as f64,
+                y: 2. * i as f64,
+            })
+            .collect();
+        let dcel = Triangulation::new(&collinear).to_dcel();
+        check_invariants(&dcel);
+        assert_eq!(dcel.faces.len(), 1);
+        assert_eq!(dcel.half_edges.len(), 8);
+    }
+}
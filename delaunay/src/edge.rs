@@ -1,102 +1,374 @@
-use std::fmt;
-use std::mem::MaybeUninit;
-use std::ptr::addr_of_mut;
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
 
 use id_arena::{Arena, Id};
 
 use crate::robust_float::{counter_clockwise, Point2};
 
-type QuadEdgeId = Id<QuadEdge>;
-pub type QuadEdgeArena = Arena<QuadEdge>;
+/// Arena id of a quad edge, as given by `QuadEdgeArena::iter`
+pub type QuadEdgeId = Id<QuadEdge>;
+
+/// Vertex of an edge whose origin was never set, read as the point 0, 0
+const UNSET: u32 = u32::MAX;
+
+/// Panic message of an `EdgeRef` whose quad edge isn't in the arena
+const STALE_ARENA: &str =
+    "Stale edge, not in this arena: it comes from another one or the arena was compacted";
+
+/// Quad edges of a mesh, and the coordinates of its vertices. Edges hold the index of their
+/// origin in the vertex table instead of its coordinates, so the edges leaving a vertex share
+/// them and a quad edge stays small.
+#[derive(Debug, Default)]
+pub struct QuadEdgeArena {
+    quads: Arena<QuadEdge>,
+    vertices: Vec<Point2>,
+    /// Deleted quad edges `make_edge` reuses, only filled while building a triangulation: no
+    /// `EdgeRef` to them is left around
+    free: Vec<QuadEdgeId>,
+    /// Generation of the quad edges made now, bumped each time `make_edge` reuses one
+    generation: Generation,
+}
+
+/// Generation of a quad edge, stamped on the `EdgeRef`s to it so debug builds catch a stale
+/// `EdgeRef` to a quad edge `make_edge` reused. Refs into another arena, a replaced or
+/// compacted one included, are already caught by the arena ids. Empty in release builds.
+#[cfg(debug_assertions)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+struct Generation(u32);
+
+#[cfg(not(debug_assertions))]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+struct Generation;
+
+impl Generation {
+    /// Generation of `EdgeRef::new`, matching every quad edge
+    const ANY: Generation = Generation::new(u32::MAX);
+
+    #[cfg(debug_assertions)]
+    const fn new(generation: u32) -> Generation {
+        Generation(generation)
+    }
+
+    #[cfg(not(debug_assertions))]
+    const fn new(_: u32) -> Generation {
+        Generation
+    }
+
+    /// The generation after this one, never `ANY`
+    #[cfg(debug_assertions)]
+    fn next(self) -> Generation {
+        Generation((self.0 + 1) % u32::MAX)
+    }
+
+    #[cfg(not(debug_assertions))]
+    fn next(self) -> Generation {
+        Generation
+    }
+
+    /// Return true if a ref of this generation may use a quad edge of generation `quad`
+    fn matches(self, quad: Generation) -> bool {
+        self == Generation::ANY || self == quad
+    }
+}
+
+impl QuadEdgeArena {
+    pub fn new() -> QuadEdgeArena {
+        QuadEdgeArena::default()
+    }
+
+    /// Arena with room for `capacity` quad edges
+    pub fn with_capacity(capacity: usize) -> QuadEdgeArena {
+        QuadEdgeArena {
+            quads: Arena::with_capacity(capacity),
+            vertices: vec![],
+            free: vec![],
+            generation: Generation::default(),
+        }
+    }
+
+    /// Number of quad edges, deleted ones included
+    pub fn len(&self) -> usize {
+        self.quads.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.quads.len() == 0
+    }
+
+    pub fn get(&self, quad_id: QuadEdgeId) -> Option<&QuadEdge> {
+        self.quads.get(quad_id)
+    }
+
+    pub fn get_mut(&mut self, quad_id: QuadEdgeId) -> Option<&mut QuadEdge> {
+        self.quads.get_mut(quad_id)
+    }
+
+    /// Edge `idx` of the quad edge with this id, see `EdgeRef::new`. The ref is stamped with the
+    /// generation of the quad edge: debug builds panic when it is dereferenced after
+    /// `make_edge` reused the quad edge.
+    ///
+    /// Panics if `idx` isn't below 4 or the quad edge isn't in the arena.
+    pub fn edge_ref(&self, quad_id: QuadEdgeId, idx: usize) -> EdgeRef {
+        EdgeRef {
+            generation: self.quads[quad_id].generation,
+            ..EdgeRef::new(quad_id, idx)
+        }
+    }
+
+    /// Every quad edge with its id, deleted ones included, in allocation order
+    pub fn iter(&self) -> id_arena::Iter<'_, QuadEdge, id_arena::DefaultArenaBehavior<QuadEdge>> {
+        self.quads.iter()
+    }
 
+    /// Append a vertex to the vertex table, return its index
+    ///
+    /// Panics past `u32::MAX - 1` vertices.
+    pub fn add_vertex(&mut self, point: Point2) -> u32 {
+        let vertex = u32::try_from(self.vertices.len())
+            .ok()
+            .filter(|&vertex| vertex != UNSET)
+            .expect("Too many vertices for a quad edge arena");
+        self.vertices.push(point);
+        vertex
+    }
+
+    /// Coordinates of a vertex of the table
+    pub fn vertex(&self, vertex: u32) -> Point2 {
+        self.vertices[vertex as usize]
+    }
+
+    /// Vertex table, edges of a same site usually share an entry but a site may have several
+    pub fn vertices(&self) -> &[Point2] {
+        &self.vertices
+    }
+
+    /// Stop reusing the quad edges deleted with `recycle_edge`, they stay deleted
+    pub(crate) fn end_recycling(&mut self) {
+        self.free.clear();
+    }
+
+    /// Delete every quad edge and empty the vertex table, keeping their memory: `make_edge`
+    /// reuses the quad edges in allocation order. The free list gets room for `capacity` of
+    /// them, so a mesh of as many quad edges recycled later doesn't grow it.
+    pub(crate) fn recycle_all(&mut self, capacity: usize) {
+        self.free.clear();
+        self.free.reserve(capacity.max(self.quads.len()));
+        for (quad_id, quad_edge) in self.quads.iter_mut().rev() {
+            quad_edge.deleted = true;
+            self.free.push(quad_id);
+        }
+        self.vertices.clear();
+    }
+
+    /// Move vertices of the table, every edge leaving them moves too
+    pub(crate) fn vertices_mut(&mut self) -> &mut [Point2] {
+        &mut self.vertices
+    }
+
+    /// Drop the deleted quad edges, moving the live ones to a new arena in allocation order, and
+    /// return where they went. The vertex table is kept as is.
+    ///
+    /// Every `EdgeRef` made before is stale: dereferencing one panics, `Compaction::edge` gives
+    /// the ref to the same edge in the compacted arena.
+    ///
+    /// Panics if a live edge links to a deleted one.
+    pub fn compact(&mut self) -> Compaction {
+        let live = self.iter().filter(|(_, quad)| !quad.is_deleted()).count();
+        let old = core::mem::replace(&mut self.quads, Arena::with_capacity(live));
+        let mut ids = vec![None; old.len()];
+        for (quad_id, quad_edge) in old.iter().filter(|(_, quad)| !quad.is_deleted()) {
+            let moved = self
+                .quads
+                .alloc_with_id(|new_id| fresh_quad(new_id, quad_edge.generation));
+            ids[quad_id.index()] = Some(moved);
+        }
+        let compaction = Compaction { ids };
+        for (quad_id, quad_edge) in old.iter() {
+            let Some(moved) = compaction.ids[quad_id.index()] else {
+                continue;
+            };
+            let moved = &mut self.quads[moved];
+            for (edge, old_edge) in moved.edges.iter_mut().zip(&quad_edge.edges) {
+                edge.vertex = old_edge.vertex;
+                edge.next = compaction
+                    .edge(old_edge.next)
+                    .expect("A live edge links to a deleted one");
+            }
+            moved.flags = quad_edge.flags;
+        }
+        self.free.clear();
+        compaction
+    }
+}
+
+/// Where `QuadEdgeArena::compact` moved the quad edges
+#[derive(Clone, Debug)]
+pub struct Compaction {
+    /// New id of every quad edge by old index, None for the deleted ones
+    ids: Vec<Option<QuadEdgeId>>,
+}
+
+impl Compaction {
+    /// Ref to the same edge in the compacted arena, None if its quad edge was deleted. `edge` must
+    /// be a ref to the arena before compaction.
+    pub fn edge(&self, edge: EdgeRef) -> Option<EdgeRef> {
+        let quad_edge = (*self.ids.get(edge.quad_edge.index())?)?;
+        Some(EdgeRef { quad_edge, ..edge })
+    }
+}
+
+/// Directed edge of a quad edge. Refs compare, hash and order by quad edge and rotation only.
 #[derive(Copy, Clone, Debug)]
 pub struct EdgeRef {
     quad_edge: QuadEdgeId,
     idx: usize,
+    generation: Generation,
 }
 
 #[derive(Debug)]
 pub struct Edge {
-    origin: Point2,
     next: EdgeRef,
+    /// Origin, an index in the vertex table of the arena
+    vertex: u32,
 }
 
 #[derive(Debug)]
 pub struct QuadEdge {
     edges: [Edge; 4],
     deleted: bool,
+    generation: Generation,
+    /// User bits, shared by the four edges of the quad edge: marking an edge marks its sym and
+    /// its duals too
+    flags: u8,
 }
 
-///
-/// Create a QuadEdge allocated in the QuadEdgeArena, initialise the Edges of the QuadEdge with default
-/// 0.0, 0.0 origin positions, and good edge_ref default values
-///
-#[allow(invalid_value)]
-pub fn make_edge(quad_arena: &mut QuadEdgeArena) -> EdgeRef {
-    let quad_id = quad_arena.alloc(unsafe { MaybeUninit::<QuadEdge>::uninit().assume_init() });
-    let quad_ptr = quad_arena.get_mut(quad_id).unwrap();
-
-    let ref0 = EdgeRef {
-        quad_edge: quad_id,
-        idx: 0,
-    };
-    let ref1 = EdgeRef {
-        quad_edge: quad_id,
-        idx: 1,
-    };
-    let ref2 = EdgeRef {
+/// Lone quad edge with this id and generation, with unset origins
+fn fresh_quad(quad_id: QuadEdgeId, generation: Generation) -> QuadEdge {
+    let edge_ref = |idx| EdgeRef {
         quad_edge: quad_id,
-        idx: 2,
+        idx,
+        generation,
     };
-    let ref3 = EdgeRef {
-        quad_edge: quad_id,
-        idx: 3,
-    };
-
-    unsafe {
-        addr_of_mut!((*quad_ptr).deleted).write(false);
-    }
-    unsafe {
-        addr_of_mut!((*quad_ptr).edges).write([
+    QuadEdge {
+        edges: [
             Edge {
-                origin: Point2::default(),
-                next: ref0.clone(),
+                next: edge_ref(0),
+                vertex: UNSET,
             },
             Edge {
-                origin: Point2::default(),
-                next: ref3.clone(),
+                next: edge_ref(3),
+                vertex: UNSET,
             },
             Edge {
-                origin: Point2::default(),
-                next: ref2.clone(),
+                next: edge_ref(2),
+                vertex: UNSET,
             },
             Edge {
-                origin: Point2::default(),
-                next: ref1.clone(),
+                next: edge_ref(1),
+                vertex: UNSET,
             },
-        ])
+        ],
+        deleted: false,
+        generation,
+        flags: 0,
     }
-    ref0
+}
+
+///
+/// Create a QuadEdge allocated in the QuadEdgeArena, initialise the Edges of the QuadEdge with unset
+/// origins, read as 0.0, 0.0, and good edge_ref default values. Quad edges deleted with
+/// `recycle_edge` are reused first.
+///
+pub fn make_edge(quad_arena: &mut QuadEdgeArena) -> EdgeRef {
+    let quad_id = match quad_arena.free.pop() {
+        Some(quad_id) => {
+            // Refs to the deleted quad edge must not reach the new one
+            quad_arena.generation = quad_arena.generation.next();
+            quad_arena.quads[quad_id] = fresh_quad(quad_id, quad_arena.generation);
+            quad_id
+        }
+        None => quad_arena
+            .quads
+            .alloc_with_id(|quad_id| fresh_quad(quad_id, quad_arena.generation)),
+    };
+    quad_arena.edge_ref(quad_id, 0)
 }
 
 impl EdgeRef {
+    /// Edge `idx` of the quad edge with this arena id: 0 is the primal edge, 2 its sym, 1 and 3
+    /// the dual edges. Goes from an arena iteration item back to an edge. The ref isn't stamped,
+    /// `QuadEdgeArena::edge_ref` gives one checked for reuse of the quad edge.
+    ///
+    /// Panics if `idx` isn't below 4.
+    pub fn new(quad_edge: QuadEdgeId, idx: usize) -> EdgeRef {
+        assert!(idx < 4, "A quad edge has 4 edges, got index {}", idx);
+        EdgeRef {
+            quad_edge,
+            idx,
+            generation: Generation::ANY,
+        }
+    }
+
+    /// Numeric key of the directed edge, the arena index of its quad edge and its rotation.
+    ///
+    /// Ids are only meaningful within one arena, and may be reused once the mesh is rebuilt or
+    /// compacted.
+    pub fn id(&self) -> u64 {
+        ((self.quad_edge.index() as u64) << 2) | self.idx as u64
+    }
+
+    /// Numeric key shared by the edge and its `sym`, for undirected side tables. The primal and
+    /// dual edges of a quad edge get different keys. Same validity as `id`.
+    pub fn canonical_id(&self) -> u64 {
+        ((self.quad_edge.index() as u64) << 2) | (self.idx % 2) as u64
+    }
+
     ///////////////////////////
     // Dereferencing methods //
     ///////////////////////////
-    pub fn quad_edge<'a, 'b>(&'a self, quad_arena: &'b QuadEdgeArena) -> &'b QuadEdge {
-        quad_arena.get(self.quad_edge).unwrap()
+    /// Return true if the edge belongs to the arena and wasn't deleted. In debug builds, a ref
+    /// to a quad edge `make_edge` reused since isn't live either.
+    pub fn is_live(&self, quad_arena: &QuadEdgeArena) -> bool {
+        quad_arena.get(self.quad_edge).is_some_and(|quad_edge| {
+            !quad_edge.is_deleted() && self.generation.matches(quad_edge.generation)
+        })
+    }
+
+    /// Quad edge of the ref. Debug builds panic if `make_edge` reused it since the ref was
+    /// made, release builds give the new quad edge. Every build panics if the ref comes from
+    /// another arena, or from this one before `compact`.
+    pub fn quad_edge<'b>(&self, quad_arena: &'b QuadEdgeArena) -> &'b QuadEdge {
+        let quad_edge = quad_arena.get(self.quad_edge).expect(STALE_ARENA);
+        self.check_generation(quad_edge);
+        quad_edge
     }
 
-    pub fn quad_edge_mut<'a, 'b>(&'a self, quad_arena: &'b mut QuadEdgeArena) -> &'b mut QuadEdge {
-        quad_arena.get_mut(self.quad_edge).unwrap()
+    pub fn quad_edge_mut<'b>(&self, quad_arena: &'b mut QuadEdgeArena) -> &'b mut QuadEdge {
+        let quad_edge = quad_arena.get_mut(self.quad_edge).expect(STALE_ARENA);
+        self.check_generation(quad_edge);
+        quad_edge
     }
 
-    pub fn raw_edge<'a, 'b>(&'a self, quad_arena: &'b QuadEdgeArena) -> &'b Edge {
+    #[inline(always)]
+    fn check_generation(&self, quad_edge: &QuadEdge) {
+        debug_assert!(
+            self.generation.matches(quad_edge.generation),
+            "Stale edge {}, its quad edge was reused",
+            self
+        );
+    }
+
+    pub fn raw_edge<'b>(&self, quad_arena: &'b QuadEdgeArena) -> &'b Edge {
         let quad = self.quad_edge(quad_arena);
         &quad.edges[self.idx]
     }
 
-    pub fn raw_edge_mut<'a, 'b>(&'a self, quad_arena: &'b mut QuadEdgeArena) -> &'b mut Edge {
+    pub fn raw_edge_mut<'b>(&self, quad_arena: &'b mut QuadEdgeArena) -> &'b mut Edge {
         let quad = self.quad_edge_mut(quad_arena);
         &mut quad.edges[self.idx]
     }
@@ -122,24 +394,24 @@ impl EdgeRef {
     /// Creates an Edgeref of the edge rotated once
     pub fn rot(&self) -> EdgeRef {
         EdgeRef {
-            quad_edge: self.quad_edge,
             idx: (self.idx + 1) % 4,
+            ..*self
         }
     }
 
     /// Creates an Edgeref of the edge rotated thrice
     pub fn inv_rot(&self) -> EdgeRef {
         EdgeRef {
-            quad_edge: self.quad_edge,
             idx: (self.idx + 3) % 4,
+            ..*self
         }
     }
 
     /// Creates an Edgeref of the edge rotated twice
     pub fn sym(&self) -> EdgeRef {
         EdgeRef {
-            quad_edge: self.quad_edge,
             idx: (self.idx + 2) % 4,
+            ..*self
         }
     }
 
@@ -159,13 +431,23 @@ impl EdgeRef {
     }
 
     pub fn org(&self, quad_arena: &QuadEdgeArena) -> Point2 {
-        let edge = self.raw_edge(quad_arena);
-        edge.origin
+        match self.org_vertex(quad_arena) {
+            UNSET => Point2::default(),
+            vertex => quad_arena.vertex(vertex),
+        }
     }
 
+    /// Set the origin. An unset origin gets a new entry of the vertex table, a set one has its
+    /// entry overwritten: the edges sharing it move too, like with `vertices_mut`. Use
+    /// `add_vertex` and `set_org_vertex` to give the edge an entry of its own.
     pub fn set_org(&mut self, quad_arena: &mut QuadEdgeArena, vert: Point2) {
-        let mut edge = self.raw_edge_mut(quad_arena);
-        edge.origin = vert;
+        match self.org_vertex(quad_arena) {
+            UNSET => {
+                let vertex = quad_arena.add_vertex(vert);
+                self.set_org_vertex(quad_arena, vertex);
+            }
+            vertex => quad_arena.vertices[vertex as usize] = vert,
+        }
     }
 
     pub fn dest(&self, quad_arena: &QuadEdgeArena) -> Point2 {
@@ -175,6 +457,67 @@ impl EdgeRef {
     pub fn set_dest(&mut self, quad_arena: &mut QuadEdgeArena, vert: Point2) {
         self.sym().set_org(quad_arena, vert);
     }
+
+    /// Index of the origin in the vertex table, `u32::MAX` while it was never set
+    pub fn org_vertex(&self, quad_arena: &QuadEdgeArena) -> u32 {
+        self.raw_edge(quad_arena).vertex
+    }
+
+    pub fn set_org_vertex(&self, quad_arena: &mut QuadEdgeArena, vertex: u32) {
+        self.raw_edge_mut(quad_arena).vertex = vertex;
+    }
+
+    pub fn dest_vertex(&self, quad_arena: &QuadEdgeArena) -> u32 {
+        self.sym().org_vertex(quad_arena)
+    }
+
+    pub fn set_dest_vertex(&self, quad_arena: &mut QuadEdgeArena, vertex: u32) {
+        self.sym().set_org_vertex(quad_arena, vertex);
+    }
+
+    ///////////////////
+    // Flags methods //
+    ///////////////////
+    /// Set the bits of `flag` on the quad edge, the other bits are left as they are
+    pub fn set_flag(&self, quad_arena: &mut QuadEdgeArena, flag: u8) {
+        self.quad_edge_mut(quad_arena).flags |= flag;
+    }
+
+    /// Return true if any bit of `flag` is set on the quad edge
+    pub fn get_flag(&self, quad_arena: &QuadEdgeArena, flag: u8) -> bool {
+        self.quad_edge(quad_arena).flags & flag != 0
+    }
+
+    /// Clear the bits of `flag` on the quad edge
+    pub fn clear_flag(&self, quad_arena: &mut QuadEdgeArena, flag: u8) {
+        self.quad_edge_mut(quad_arena).flags &= !flag;
+    }
+}
+
+impl PartialEq for EdgeRef {
+    fn eq(&self, other: &EdgeRef) -> bool {
+        (self.quad_edge, self.idx) == (other.quad_edge, other.idx)
+    }
+}
+
+impl Eq for EdgeRef {}
+
+impl Hash for EdgeRef {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.quad_edge, self.idx).hash(state);
+    }
+}
+
+impl PartialOrd for EdgeRef {
+    fn partial_cmp(&self, other: &EdgeRef) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for EdgeRef {
+    fn cmp(&self, other: &EdgeRef) -> Ordering {
+        (self.quad_edge, self.idx).cmp(&(other.quad_edge, other.idx))
+    }
 }
 
 impl fmt::Display for EdgeRef {
@@ -184,22 +527,152 @@ impl fmt::Display for EdgeRef {
 }
 
 impl QuadEdge {
-    pub fn get_points(&self) -> Option<(Point2, Point2)> {
-        if self.deleted {
+    /// Return true once the quad edge has been removed from the mesh with `delete_edge`. Deleted
+    /// quad edges stay in the arena.
+    pub fn is_deleted(&self) -> bool {
+        self.deleted
+    }
+
+    /// Endpoints (org, dest) of the primal edge, the one with index 0, or None once the quad
+    /// edge has been deleted or while one of the endpoints was never set. The origins stored on
+    /// the dual edges (indices 1 and 3) are never read: they aren't points of the triangulation.
+    pub fn get_points(&self, quad_arena: &QuadEdgeArena) -> Option<(Point2, Point2)> {
+        let (org, dest) = (self.edges[0].vertex, self.edges[2].vertex);
+        if self.deleted || org == UNSET || dest == UNSET {
             return None;
         }
-        Some((self.edges[0].origin, self.edges[2].origin))
+        Some((quad_arena.vertex(org), quad_arena.vertex(dest)))
     }
 }
 
+/// Readable dump of a quad edge mesh, one line per live quad edge like
+/// `Q17: (1.0, 2.0)->(3.5, 0.0)  onext=Q4.0 sym.onext=Q9.2 [hull]`, then the number of deleted
+/// quad edges. Every walk is bounded, so a corrupted mesh prints instead of looping.
+pub struct MeshDebug<'a> {
+    quad_arena: &'a QuadEdgeArena,
+    /// Only print this edge and the edges around its endpoints
+    around: Option<EdgeRef>,
+}
+
+impl<'a> MeshDebug<'a> {
+    pub fn new(quad_arena: &'a QuadEdgeArena) -> MeshDebug<'a> {
+        MeshDebug {
+            quad_arena,
+            around: None,
+        }
+    }
+
+    /// Dump limited to `edge` and the edges leaving its origin and its destination
+    pub fn around(quad_arena: &'a QuadEdgeArena, edge: EdgeRef) -> MeshDebug<'a> {
+        MeshDebug {
+            quad_arena,
+            around: Some(edge),
+        }
+    }
+
+    /// Edges met going around the origin of `start` with onext, stopping on a dangling or
+    /// repeated edge
+    fn ring(&self, start: EdgeRef) -> Vec<EdgeRef> {
+        let mut ring = vec![start];
+        let mut edge = start;
+        while ring.len() <= self.quad_arena.len() * 4 {
+            match self.quad_arena.get(edge.quad_edge) {
+                Some(quad) => edge = quad.edges[edge.idx].next,
+                None => break,
+            }
+            if ring.contains(&edge) {
+                break;
+            }
+            ring.push(edge);
+        }
+        ring
+    }
+
+    /// Return true if the face on the left of the edge is a counter clockwise triangle
+    fn left_triangle(&self, edge: EdgeRef) -> bool {
+        let mut corners = [Point2::default(); 3];
+        let mut current = edge;
+        for corner in corners.iter_mut() {
+            match self.quad_arena.get(current.quad_edge) {
+                Some(quad) if !quad.is_deleted() => {
+                    *corner = current.org(self.quad_arena);
+                }
+                _ => return false,
+            }
+            // lnext, checked step by step
+            let inv_rot = current.inv_rot();
+            match self.quad_arena.get(inv_rot.quad_edge) {
+                Some(quad) => current = quad.edges[inv_rot.idx].next.rot(),
+                None => return false,
+            }
+        }
+        current == edge && counter_clockwise(&corners[0], &corners[1], &corners[2])
+    }
+}
+
+impl fmt::Display for MeshDebug<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let shown: Option<Vec<QuadEdgeId>> = self.around.map(|edge| {
+            let mut quads: Vec<QuadEdgeId> = self
+                .ring(edge)
+                .into_iter()
+                .chain(self.ring(edge.sym()))
+                .map(|edge| edge.quad_edge)
+                .collect();
+            quads.sort_unstable();
+            quads.dedup();
+            quads
+        });
+        let mut deleted = 0;
+        for (quad_id, quad) in self.quad_arena.iter() {
+            if shown
+                .as_ref()
+                .is_some_and(|shown| !shown.contains(&quad_id))
+            {
+                continue;
+            }
+            if quad.is_deleted() {
+                deleted += 1;
+                continue;
+            }
+            let edge = self.quad_arena.edge_ref(quad_id, 0);
+            let (org, dest) = edge.org_dest(self.quad_arena);
+            let (onext, sym_onext) = (quad.edges[0].next, quad.edges[2].next);
+            write!(
+                f,
+                "Q{}: ({:?}, {:?})->({:?}, {:?})  onext=Q{}.{} sym.onext=Q{}.{}",
+                quad_id.index(),
+                org.x,
+                org.y,
+                dest.x,
+                dest.y,
+                onext.quad_edge.index(),
+                onext.idx,
+                sym_onext.quad_edge.index(),
+                sym_onext.idx
+            )?;
+            if !self.left_triangle(edge) || !self.left_triangle(edge.sym()) {
+                write!(f, " [hull]")?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "{} deleted", deleted)
+    }
+}
+
+/// Dump every quad edge of the arena, see `MeshDebug`
+pub fn dump_mesh(quad_arena: &QuadEdgeArena) -> String {
+    MeshDebug::new(quad_arena).to_string()
+}
+
 pub fn splice(quad_arena: &mut QuadEdgeArena, a: EdgeRef, b: EdgeRef) {
-    let alpha = a.onext(&quad_arena).rot();
-    let beta = b.onext(&quad_arena).rot();
+    let alpha = a.onext(quad_arena).rot();
+    let beta = b.onext(quad_arena).rot();
 
-    let a_next = a.onext(&quad_arena);
-    let b_next = b.onext(&quad_arena);
-    let alpha_next = alpha.onext(&quad_arena);
-    let beta_next = beta.onext(&quad_arena);
+    let a_next = a.onext(quad_arena);
+    let b_next = b.onext(quad_arena);
+    let alpha_next = alpha.onext(quad_arena);
+    let beta_next = beta.onext(quad_arena);
 
     a.set_onext(quad_arena, b_next);
     b.set_onext(quad_arena, a_next);
@@ -208,22 +681,22 @@ pub fn splice(quad_arena: &mut QuadEdgeArena, a: EdgeRef, b: EdgeRef) {
 }
 
 pub fn swap(quad_arena: &mut QuadEdgeArena, edge: &mut EdgeRef) {
-    let a = edge.oprev(&quad_arena);
+    let a = edge.oprev(quad_arena);
     let b = edge.sym().oprev(quad_arena);
-    splice(quad_arena, edge.to_owned(), a);
+    splice(quad_arena, *edge, a);
     splice(quad_arena, edge.sym(), b);
-    splice(quad_arena, edge.to_owned(), a.lnext(quad_arena));
+    splice(quad_arena, *edge, a.lnext(quad_arena));
     splice(quad_arena, edge.sym(), b.lnext(quad_arena));
-    edge.set_org(quad_arena, a.dest(quad_arena));
-    edge.set_dest(quad_arena, b.dest(quad_arena));
+    edge.set_org_vertex(quad_arena, a.dest_vertex(quad_arena));
+    edge.set_dest_vertex(quad_arena, b.dest_vertex(quad_arena));
 }
 
 pub fn connect(quad_arena: &mut QuadEdgeArena, a: EdgeRef, b: EdgeRef) -> EdgeRef {
-    let mut edge = make_edge(quad_arena);
-    edge.set_org(quad_arena, a.dest(quad_arena));
-    edge.set_dest(quad_arena, b.org(quad_arena));
+    let edge = make_edge(quad_arena);
+    edge.set_org_vertex(quad_arena, a.dest_vertex(quad_arena));
+    edge.set_dest_vertex(quad_arena, b.org_vertex(quad_arena));
     splice(quad_arena, edge, a.lnext(quad_arena));
-    splice(quad_arena, edge.sym(), b.to_owned());
+    splice(quad_arena, edge.sym(), b);
     edge
 }
 
@@ -234,26 +707,35 @@ pub fn delete_edge(quad_arena: &mut QuadEdgeArena, edge: EdgeRef) {
     quad_edge.deleted = true;
 }
 
+/// `delete_edge`, then let `make_edge` reuse the quad edge until `end_recycling`. Only for
+/// builders that keep no `EdgeRef` to deleted edges, the reused quad edge would alias them.
+pub(crate) fn recycle_edge(quad_arena: &mut QuadEdgeArena, edge: EdgeRef) {
+    delete_edge(quad_arena, edge);
+    quad_arena.free.push(edge.quad_edge);
+}
+
 /// Return true if point is strictly on the left side of the directed edge
 #[inline(always)]
 pub fn left_of(quad_arena: &QuadEdgeArena, point: &Point2, edge: EdgeRef) -> bool {
-    counter_clockwise(&point, &edge.org(quad_arena), &edge.dest(quad_arena))
+    counter_clockwise(point, &edge.org(quad_arena), &edge.dest(quad_arena))
 }
 
 /// Return true if point is strictly on the right side of the directed edge
 #[inline(always)]
 pub fn right_of(quad_arena: &QuadEdgeArena, point: &Point2, edge: EdgeRef) -> bool {
-    counter_clockwise(&point, &edge.dest(quad_arena), &edge.org(quad_arena))
+    counter_clockwise(point, &edge.dest(quad_arena), &edge.org(quad_arena))
 }
 
 /// Return true if the edge is above the left-oriented base edge
 pub fn valid(quad_arena: &QuadEdgeArena, edge: EdgeRef, basel: EdgeRef) -> bool {
-    right_of(&quad_arena, &edge.dest(&quad_arena), basel)
+    right_of(quad_arena, &edge.dest(quad_arena), basel)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::gns_delaunay::compute_delaunay;
+    use std::collections::HashSet;
 
     #[test]
     fn create_edge() {
@@ -261,4 +743,298 @@ mod tests {
         let edge_ref = make_edge(&mut quad_arena);
         println!("Edge ref: {}", edge_ref);
     }
+
+    #[test]
+    fn test_get_points() {
+        let mut quad_arena = QuadEdgeArena::new();
+        let mut edge = make_edge(&mut quad_arena);
+        let a = Point2 { x: 1., y: 2. };
+        let b = Point2 { x: 3., y: 4. };
+        assert_eq!(edge.quad_edge(&quad_arena).get_points(&quad_arena), None);
+        edge.set_org(&mut quad_arena, a);
+        assert_eq!(edge.quad_edge(&quad_arena).get_points(&quad_arena), None);
+        edge.set_dest(&mut quad_arena, b);
+        // Dual origins hold garbage, they must not leak into the primal endpoints
+        edge.rot()
+            .set_org(&mut quad_arena, Point2 { x: -1., y: -1. });
+        edge.inv_rot()
+            .set_org(&mut quad_arena, Point2 { x: -2., y: -2. });
+        assert_eq!(
+            edge.quad_edge(&quad_arena).get_points(&quad_arena),
+            Some((a, b))
+        );
+        assert_eq!(
+            edge.sym().quad_edge(&quad_arena).get_points(&quad_arena),
+            Some((a, b))
+        );
+
+        // Setting an origin again overwrites its entry, the edges sharing it follow
+        let next = make_edge(&mut quad_arena);
+        let shared = edge.dest_vertex(&quad_arena);
+        next.set_org_vertex(&mut quad_arena, shared);
+        let vertices = quad_arena.vertices().len();
+        let c = Point2 { x: 5., y: 6. };
+        edge.set_dest(&mut quad_arena, c);
+        edge.set_dest(&mut quad_arena, b);
+        edge.set_dest(&mut quad_arena, c);
+        assert_eq!(quad_arena.vertices().len(), vertices);
+        assert_eq!(next.org(&quad_arena), c);
+        edge.set_dest(&mut quad_arena, b);
+        delete_edge(&mut quad_arena, next);
+
+        // Back from the arena iteration to the edge
+        let (quad_id, quad_edge) = quad_arena.iter().next().unwrap();
+        assert!(!quad_edge.is_deleted());
+        assert_eq!(EdgeRef::new(quad_id, 0), edge);
+        assert_eq!(EdgeRef::new(quad_id, 2).org_dest(&quad_arena), (b, a));
+
+        delete_edge(&mut quad_arena, edge);
+        assert!(edge.quad_edge(&quad_arena).is_deleted());
+        assert_eq!(edge.quad_edge(&quad_arena).get_points(&quad_arena), None);
+    }
+
+    #[test]
+    fn test_ids() {
+        let mut quad_arena = QuadEdgeArena::new();
+        let edges: Vec<EdgeRef> = (0..5).map(|_| make_edge(&mut quad_arena)).collect();
+        let mut ids = HashSet::new();
+        let mut canonical_ids = HashSet::new();
+        for edge in &edges {
+            for dir in [*edge, edge.rot(), edge.sym(), edge.inv_rot()] {
+                assert!(ids.insert(dir.id()));
+                assert_eq!(dir.canonical_id(), dir.sym().canonical_id());
+                canonical_ids.insert(dir.canonical_id());
+            }
+            assert_ne!(edge.canonical_id(), edge.rot().canonical_id());
+        }
+        assert_eq!(ids.len(), 20);
+        assert_eq!(canonical_ids.len(), 10);
+    }
+
+    #[test]
+    fn test_generations() {
+        let mut quad_arena = QuadEdgeArena::new();
+        let kept = make_edge(&mut quad_arena);
+        let stale = make_edge(&mut quad_arena);
+        let (quad_id, _) = quad_arena.iter().nth(1).unwrap();
+        let unstamped = EdgeRef::new(quad_id, 0);
+        recycle_edge(&mut quad_arena, stale);
+        let reused = make_edge(&mut quad_arena);
+        quad_arena.end_recycling();
+
+        // Same quad edge, same place in maps and sets
+        assert_eq!(reused, stale);
+        assert_eq!(reused.sym(), unstamped.sym());
+        assert!(reused.is_live(&quad_arena));
+        assert!(unstamped.is_live(&quad_arena));
+        assert_eq!(
+            quad_arena.edge_ref(quad_id, 2).onext(&quad_arena),
+            reused.sym()
+        );
+        assert!(kept.rot().is_live(&quad_arena));
+        if cfg!(debug_assertions) {
+            assert!(!stale.is_live(&quad_arena));
+            assert!(!stale.sym().is_live(&quad_arena));
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "Stale edge")]
+    fn test_stale_edge() {
+        let mut quad_arena = QuadEdgeArena::new();
+        let stale = make_edge(&mut quad_arena);
+        recycle_edge(&mut quad_arena, stale);
+        make_edge(&mut quad_arena);
+        stale.org(&quad_arena);
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut quad_arena = QuadEdgeArena::new();
+        let mut points = crate::test_util::random_points(23, 200, 10.);
+        crate::robust_float::sort_points(&mut points);
+        let (left, _) = compute_delaunay(&mut quad_arena, &points);
+        let edges = |quad_arena: &QuadEdgeArena| {
+            let mut edges: Vec<(u32, u32)> = quad_arena
+                .iter()
+                .filter(|(_, quad_edge)| !quad_edge.is_deleted())
+                .map(|(quad_id, _)| {
+                    let edge = quad_arena.edge_ref(quad_id, 0);
+                    (edge.org_vertex(quad_arena), edge.dest_vertex(quad_arena))
+                })
+                .collect();
+            edges.sort_unstable();
+            edges
+        };
+        // Delete the edges of every 7th quad edge not on the hull walk from `left`
+        let doomed: Vec<EdgeRef> = quad_arena
+            .iter()
+            .filter(|(_, quad_edge)| !quad_edge.is_deleted())
+            .map(|(quad_id, _)| quad_arena.edge_ref(quad_id, 0))
+            .filter(|&edge| edge != left && edge != left.sym())
+            .step_by(7)
+            .collect();
+        for &edge in &doomed {
+            delete_edge(&mut quad_arena, edge);
+        }
+        let kept = edges(&quad_arena);
+        let vertices = quad_arena.vertices().to_vec();
+        let ring = |quad_arena: &QuadEdgeArena, start: EdgeRef| {
+            let mut ring = vec![start.dest(quad_arena)];
+            let mut edge = start.onext(quad_arena);
+            while edge != start {
+                ring.push(edge.dest(quad_arena));
+                edge = edge.onext(quad_arena);
+            }
+            ring
+        };
+        let around = ring(&quad_arena, left);
+
+        let compaction = quad_arena.compact();
+        assert_eq!(quad_arena.len(), kept.len());
+        assert_eq!(edges(&quad_arena), kept);
+        assert_eq!(quad_arena.vertices(), vertices);
+        assert!(doomed.iter().all(|&edge| compaction.edge(edge).is_none()));
+        let moved = compaction.edge(left).unwrap();
+        assert!(moved.is_live(&quad_arena));
+        assert_eq!(ring(&quad_arena, moved), around);
+        assert_eq!(compaction.edge(left.sym()), Some(moved.sym()));
+        assert!(!left.is_live(&quad_arena));
+    }
+
+    #[test]
+    #[should_panic(expected = "Stale edge")]
+    fn test_stale_edge_after_compact() {
+        let mut quad_arena = QuadEdgeArena::new();
+        let stale = make_edge(&mut quad_arena);
+        quad_arena.compact();
+        stale.org(&quad_arena);
+    }
+
+    #[test]
+    fn test_vertex_table() {
+        let points: Vec<Point2> = (0..30)
+            .map(|idx| Point2 {
+                x: idx as f64,
+                y: ((idx * 7) % 11) as f64,
+            })
+            .collect();
+        let mut quad_arena = QuadEdgeArena::new();
+        compute_delaunay(&mut quad_arena, &points);
+        // One entry per point, shared by every edge leaving it
+        assert_eq!(quad_arena.vertices(), &points[..]);
+        for (quad_id, quad_edge) in quad_arena.iter() {
+            if quad_edge.is_deleted() {
+                continue;
+            }
+            for edge in [EdgeRef::new(quad_id, 0), EdgeRef::new(quad_id, 2)] {
+                let vertex = edge.org_vertex(&quad_arena);
+                assert_eq!(points[vertex as usize], edge.org(&quad_arena));
+            }
+        }
+
+        // Moving an entry moves the edges sharing it
+        let edge = make_edge(&mut quad_arena);
+        let other = make_edge(&mut quad_arena);
+        assert_eq!(edge.org_vertex(&quad_arena), u32::MAX);
+        assert_eq!(edge.org(&quad_arena), Point2::default());
+        let vertex = quad_arena.add_vertex(Point2 { x: 1., y: 1. });
+        edge.set_org_vertex(&mut quad_arena, vertex);
+        other.set_dest_vertex(&mut quad_arena, vertex);
+        quad_arena.vertices_mut()[vertex as usize] = Point2 { x: 2., y: 3. };
+        assert_eq!(edge.org(&quad_arena), Point2 { x: 2., y: 3. });
+        assert_eq!(other.dest(&quad_arena), Point2 { x: 2., y: 3. });
+    }
+
+    #[test]
+    fn test_flags_survive_swap() {
+        const VISITED: u8 = 1;
+        const BOUNDARY: u8 = 1 << 3;
+        let mut quad_arena = QuadEdgeArena::new();
+        let points = [
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 1., y: -2. },
+            Point2 { x: 1., y: 2. },
+            Point2 { x: 2., y: 0. },
+        ];
+        compute_delaunay(&mut quad_arena, &points);
+        let find = |quad_arena: &QuadEdgeArena, a: Point2, b: Point2| {
+            quad_arena
+                .iter()
+                .find(|(_, quad_edge)| {
+                    quad_edge.get_points(quad_arena) == Some((a, b))
+                        || quad_edge.get_points(quad_arena) == Some((b, a))
+                })
+                .map(|(quad_id, _)| EdgeRef::new(quad_id, 0))
+                .unwrap()
+        };
+        let mut diagonal = find(&quad_arena, points[0], points[3]);
+        let side = find(&quad_arena, points[0], points[1]);
+
+        diagonal.set_flag(&mut quad_arena, VISITED | BOUNDARY);
+        diagonal.clear_flag(&mut quad_arena, BOUNDARY);
+        swap(&mut quad_arena, &mut diagonal);
+        // The other diagonal of the quad, the same quad edge
+        let (org, dest) = diagonal.org_dest(&quad_arena);
+        assert!((org, dest) == (points[1], points[2]) || (org, dest) == (points[2], points[1]));
+        assert!(diagonal.get_flag(&quad_arena, VISITED));
+        assert!(diagonal.sym().get_flag(&quad_arena, VISITED));
+        assert!(diagonal.rot().get_flag(&quad_arena, VISITED));
+        assert!(!diagonal.get_flag(&quad_arena, BOUNDARY));
+        assert!(!side.get_flag(&quad_arena, VISITED | BOUNDARY));
+    }
+
+    #[test]
+    fn test_dump_mesh() {
+        let mut quad_arena = QuadEdgeArena::new();
+        let points = [
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 1., y: -2. },
+            Point2 { x: 1., y: 2. },
+            Point2 { x: 2.5, y: 0. },
+        ];
+        compute_delaunay(&mut quad_arena, &points);
+        assert_eq!(
+            dump_mesh(&quad_arena),
+            "Q0: (0.0, 0.0)->(1.0, -2.0)  onext=Q2.2 sym.onext=Q4.2 [hull]\n\
+             Q1: (1.0, 2.0)->(2.5, 0.0)  onext=Q2.0 sym.onext=Q4.0 [hull]\n\
+             Q2: (1.0, 2.0)->(0.0, 0.0)  onext=Q1.0 sym.onext=Q3.2 [hull]\n\
+             Q3: (2.5, 0.0)->(0.0, 0.0)  onext=Q1.2 sym.onext=Q0.0\n\
+             Q4: (2.5, 0.0)->(1.0, -2.0)  onext=Q3.0 sym.onext=Q0.2 [hull]\n\
+             0 deleted\n"
+        );
+
+        // Without the diagonal, the quad is not a triangle anymore
+        let diagonal = quad_arena
+            .iter()
+            .find(|(_, quad_edge)| {
+                quad_edge.get_points(&quad_arena) == Some((points[3], points[0]))
+            })
+            .map(|(quad_id, _)| EdgeRef::new(quad_id, 0))
+            .unwrap();
+        delete_edge(&mut quad_arena, diagonal);
+        let side = quad_arena
+            .iter()
+            .find(|(_, quad_edge)| {
+                quad_edge.get_points(&quad_arena) == Some((points[0], points[1]))
+            })
+            .map(|(quad_id, _)| EdgeRef::new(quad_id, 0))
+            .unwrap();
+        assert_eq!(
+            MeshDebug::around(&quad_arena, side).to_string(),
+            "Q0: (0.0, 0.0)->(1.0, -2.0)  onext=Q2.2 sym.onext=Q4.2 [hull]\n\
+             Q2: (1.0, 2.0)->(0.0, 0.0)  onext=Q1.0 sym.onext=Q0.0 [hull]\n\
+             Q4: (2.5, 0.0)->(1.0, -2.0)  onext=Q1.2 sym.onext=Q0.2 [hull]\n\
+             0 deleted\n"
+        );
+        assert!(dump_mesh(&quad_arena).ends_with("\n1 deleted\n"));
+
+        // A ring that never comes back to its start still prints
+        let other = side.onext(&quad_arena);
+        other.set_onext(&mut quad_arena, other);
+        assert!(MeshDebug::around(&quad_arena, side)
+            .to_string()
+            .starts_with("Q0: "));
+    }
 }
@@ -0,0 +1,241 @@
+/// Exact rational predicates, behind the `exact` feature
+///
+/// Every finite f64 is a rational number, so the orientation and in-circle determinants can be
+/// evaluated with big rationals without any rounding. This is much slower than the adaptive
+/// `robust` predicates and only meant to validate them: `with_predicate_backend` switches every
+/// predicate of the crate over, triangulations and queries alike.
+use std::cell::Cell;
+use std::cmp::Ordering;
+
+use num_rational::BigRational;
+use num_traits::{ToPrimitive, Zero};
+
+use crate::robust_float::Point2;
+
+/// Arithmetic the predicates of the crate use
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PredicateBackend {
+    /// Adaptive floating point predicates of the `robust` crate, exact signs in the f64 range
+    #[default]
+    Robust,
+    /// Big rational arithmetic, exact but slow
+    Exact,
+}
+
+thread_local! {
+    static BACKEND: Cell<PredicateBackend> = const { Cell::new(PredicateBackend::Robust) };
+}
+
+/// Backend of the predicates on the current thread
+pub fn predicate_backend() -> PredicateBackend {
+    BACKEND.with(Cell::get)
+}
+
+/// Run `f` with the predicates of the current thread using `backend`, the previous backend is
+/// back afterwards, even when `f` panics. The switch is per thread: threads spawned by `f`, like
+/// the `rayon` workers, keep their own backend.
+pub fn with_predicate_backend<R>(backend: PredicateBackend, f: impl FnOnce() -> R) -> R {
+    struct Restore(PredicateBackend);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            BACKEND.with(|current| current.set(self.0));
+        }
+    }
+
+    let _restore = Restore(BACKEND.with(|current| current.replace(backend)));
+    f()
+}
+
+/// Exact value of a coordinate
+///
+/// Panics on NaN or infinite coordinates, they have no rational value.
+fn rational(value: f64) -> BigRational {
+    BigRational::from_float(value).expect("Exact predicates need finite coordinates")
+}
+
+/// Exact orientation determinant, with `robust::orient2d`'s sign
+fn orient2d(a: &Point2, b: &Point2, c: &Point2) -> BigRational {
+    let (cx, cy) = (rational(c.x), rational(c.y));
+    let (acx, acy) = (rational(a.x) - &cx, rational(a.y) - &cy);
+    let (bcx, bcy) = (rational(b.x) - cx, rational(b.y) - cy);
+    acx * bcy - acy * bcx
+}
+
+/// Exact in-circle determinant, with `robust::incircle`'s sign
+fn incircle(a: &Point2, b: &Point2, c: &Point2, d: &Point2) -> BigRational {
+    let (dx, dy) = (rational(d.x), rational(d.y));
+    let [(adx, ady), (bdx, bdy), (cdx, cdy)] =
+        [a, b, c].map(|point| (rational(point.x) - &dx, rational(point.y) - &dy));
+    let lift = |x: &BigRational, y: &BigRational| x * x + y * y;
+    let (alift, blift, clift) = (lift(&adx, &ady), lift(&bdx, &bdy), lift(&cdx, &cdy));
+    alift * (&bdx * &cdy - &cdx * &bdy)
+        + blift * (&cdx * &ady - &adx * &cdy)
+        + clift * (&adx * &bdy - &bdx * &ady)
+}
+
+/// Nearest f64 of an exact value, never rounded to zero or across it
+fn to_value(exact: BigRational) -> f64 {
+    let approx = exact.to_f64().unwrap_or(0.);
+    match exact.cmp(&BigRational::zero()) {
+        Ordering::Equal => 0.,
+        Ordering::Greater if approx > 0. => approx,
+        Ordering::Greater => f64::MIN_POSITIVE,
+        Ordering::Less if approx < 0. => approx,
+        Ordering::Less => -f64::MIN_POSITIVE,
+    }
+}
+
+/// Sign of the orientation determinant of the three points, compared to zero: the sign of
+/// `orient2d_value`, so `Less` when they turn `counter_clockwise`
+pub fn orient2d_exact(a: &Point2, b: &Point2, c: &Point2) -> Ordering {
+    orient2d(a, b, c).cmp(&BigRational::zero())
+}
+
+/// Sign of the in-circle determinant of the four points, compared to zero: the sign of
+/// `incircle_value`
+pub fn incircle_exact(a: &Point2, b: &Point2, c: &Point2, d: &Point2) -> Ordering {
+    incircle(a, b, c, d).cmp(&BigRational::zero())
+}
+
+/// `in_circle` with exact arithmetic
+pub fn in_circle_exact(a: &Point2, b: &Point2, c: &Point2, d: &Point2) -> bool {
+    incircle_exact(a, b, c, d) == Ordering::Less
+}
+
+/// `orient2d_value` of the exact backend
+pub(crate) fn orient2d_value(a: &Point2, b: &Point2, c: &Point2) -> f64 {
+    to_value(orient2d(a, b, c))
+}
+
+/// `incircle_value` of the exact backend
+pub(crate) fn incircle_value(a: &Point2, b: &Point2, c: &Point2, d: &Point2) -> f64 {
+    to_value(incircle(a, b, c, d))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gns_delaunay::triangulate;
+    use crate::robust_float::{in_circle, incircle_value, orient2d_value};
+    use crate::test_util::xorshift_bits;
+
+    #[test]
+    fn test_backends_agree_near_degeneracies() {
+        let mut bits = xorshift_bits(101);
+        let mut next = move || bits() >> 11;
+        // Move a coordinate by a few ulps either way
+        let nudge = |value: f64, random: u64| {
+            let steps = (random % 7) as i64 - 3;
+            let bits = value.to_bits() as i64;
+            if value == 0. {
+                value
+            } else {
+                f64::from_bits((bits + steps) as u64)
+            }
+        };
+        let unit = |value: u64| (value % 1_000_000) as f64 / 997.;
+        let mut checked = [0; 3];
+        for round in 0..500 {
+            // Points rounded onto a line, or onto a circle, then moved by ulps
+            let (a, b) = (
+                Point2 {
+                    x: unit(next()),
+                    y: unit(next()),
+                },
+                Point2 {
+                    x: unit(next()),
+                    y: unit(next()),
+                },
+            );
+            let t = unit(next()) / 1000.;
+            let on_line = Point2 {
+                x: nudge(a.x + t * (b.x - a.x), next()),
+                y: nudge(a.y + t * (b.y - a.y), next()),
+            };
+            let center = Point2 {
+                x: unit(next()),
+                y: unit(next()),
+            };
+            let radius = 1. + unit(next());
+            let on_circle: Vec<Point2> = (0..4)
+                .map(|_| {
+                    let angle = unit(next());
+                    Point2 {
+                        x: nudge(center.x + radius * angle.cos(), next()),
+                        y: nudge(center.y + radius * angle.sin(), next()),
+                    }
+                })
+                .collect();
+            // Exactly cocircular, from integer points of the circle of radius 5, scaled by 2^-k
+            let scale = (-((round % 40) as f64)).exp2();
+            let grid: Vec<Point2> = [(5., 0.), (3., 4.), (-4., 3.), (0., -5.)]
+                .into_iter()
+                .map(|(x, y)| Point2 {
+                    x: x * scale + center.x.floor(),
+                    y: y * scale + center.y.floor(),
+                })
+                .collect();
+
+            for [p, q, r] in [
+                [a, b, on_line],
+                [on_line, a, b],
+                [on_circle[0], on_circle[1], on_circle[2]],
+                [grid[0], grid[1], grid[2]],
+            ] {
+                let sign = orient2d_value(&p, &q, &r).partial_cmp(&0.).unwrap();
+                assert_eq!(sign, orient2d_exact(&p, &q, &r));
+                checked[(sign as i8 + 1) as usize] += 1;
+            }
+            for [p, q, r, s] in [
+                [on_circle[0], on_circle[1], on_circle[2], on_circle[3]],
+                [on_circle[3], on_circle[1], on_circle[0], on_circle[2]],
+                [grid[0], grid[1], grid[2], grid[3]],
+                [a, b, on_line, on_circle[0]],
+            ] {
+                let sign = incircle_value(&p, &q, &r, &s).partial_cmp(&0.).unwrap();
+                assert_eq!(sign, incircle_exact(&p, &q, &r, &s));
+                assert_eq!(in_circle(&p, &q, &r, &s), in_circle_exact(&p, &q, &r, &s));
+                checked[(sign as i8 + 1) as usize] += 1;
+            }
+        }
+        // Both signs and the degenerate cases were hit
+        assert!(checked.iter().all(|&count| count > 100));
+    }
+
+    #[test]
+    fn test_with_predicate_backend() {
+        let mut points: Vec<Point2> = (0..200)
+            .map(|idx| Point2 {
+                x: (idx % 15) as f64 * 0.1,
+                y: (idx / 15) as f64 * 0.1,
+            })
+            .collect();
+        let robust = triangulate(&mut points.clone());
+        let exact = with_predicate_backend(PredicateBackend::Exact, || {
+            assert_eq!(predicate_backend(), PredicateBackend::Exact);
+            triangulate(&mut points)
+        });
+        assert_eq!(exact, robust);
+        assert_eq!(predicate_backend(), PredicateBackend::Robust);
+
+        // The backend is back after a panic too
+        let result = std::panic::catch_unwind(|| {
+            with_predicate_backend(PredicateBackend::Exact, || {
+                panic!("Inside the exact backend")
+            })
+        });
+        assert!(result.is_err());
+        assert_eq!(predicate_backend(), PredicateBackend::Robust);
+
+        let (a, b, c) = (
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 0., y: 2. },
+            Point2 { x: 2., y: 0. },
+        );
+        with_predicate_backend(PredicateBackend::Exact, || {
+            assert_eq!(orient2d_value(&a, &b, &c), -4.);
+            assert_eq!(incircle_value(&a, &b, &c, &Point2 { x: 2., y: 2. }), 0.);
+        });
+    }
+}
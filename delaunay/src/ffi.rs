@@ -0,0 +1,162 @@
+/// C ABI for embedding the triangulation in non-Rust programs (C, C++, Python ctypes, ...)
+///
+/// ```c
+/// int delaunay_triangulate(const double* xy, size_t n, double** out, size_t* out_len);
+/// void delaunay_free(double* out, size_t out_len);
+/// ```
+use std::panic;
+use std::ptr;
+use std::slice;
+
+use crate::gns_delaunay::triangulate;
+use crate::robust_float::Point2;
+
+pub const DELAUNAY_OK: i32 = 0;
+/// A required pointer argument was null
+pub const DELAUNAY_NULL_POINTER: i32 = -1;
+/// `2 * n` doubles don't fit in memory, `n` is wrong
+pub const DELAUNAY_TOO_MANY_POINTS: i32 = -2;
+/// The triangulation panicked, a bug of the library. The panic doesn't unwind into the caller.
+pub const DELAUNAY_PANIC: i32 = -3;
+
+/// Triangulate the `n` points stored as `[x0, y0, x1, y1, ...]` in `xy` (so `2 * n` doubles).
+///
+/// On success returns `DELAUNAY_OK`, stores in `*out` a buffer of `*out_len` doubles holding the
+/// lines as `[org.x, org.y, dest.x, dest.y, ...]`, four doubles per line. The buffer is owned by
+/// the caller and must be released with `delaunay_free(*out, *out_len)`, never with `free`. When
+/// there are no lines (less than two distinct points, including `n == 0`), `*out` is set to null
+/// and `*out_len` to 0, and there is nothing to free.
+///
+/// Returns `DELAUNAY_NULL_POINTER` without touching anything if `out` or `out_len` is null, or if
+/// `xy` is null while `n` is not 0. Returns `DELAUNAY_TOO_MANY_POINTS` when `2 * n` doubles
+/// can't be addressed, and `DELAUNAY_PANIC` if the triangulation panics; `*out` is then null
+/// and `*out_len` 0.
+///
+/// # Safety
+///
+/// `xy` must point to `2 * n` readable doubles, `out` and `out_len` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn delaunay_triangulate(
+    xy: *const f64,
+    n: usize,
+    out: *mut *mut f64,
+    out_len: *mut usize,
+) -> i32 {
+    // Unwinding into the frames of the caller is undefined behavior
+    panic::catch_unwind(|| triangulate_raw(xy, n, out, out_len)).unwrap_or(DELAUNAY_PANIC)
+}
+
+/// Body of `delaunay_triangulate`, with the same safety requirements
+unsafe fn triangulate_raw(
+    xy: *const f64,
+    n: usize,
+    out: *mut *mut f64,
+    out_len: *mut usize,
+) -> i32 {
+    if out.is_null() || out_len.is_null() || (xy.is_null() && n != 0) {
+        return DELAUNAY_NULL_POINTER;
+    }
+    *out = ptr::null_mut();
+    *out_len = 0;
+    // A slice can't take more than isize::MAX bytes
+    let Some(len) = n
+        .checked_mul(2)
+        .filter(|&len| len <= isize::MAX as usize / std::mem::size_of::<f64>())
+    else {
+        return DELAUNAY_TOO_MANY_POINTS;
+    };
+    if n == 0 {
+        return DELAUNAY_OK;
+    }
+
+    let coords = slice::from_raw_parts(xy, len);
+    let mut points: Vec<Point2> = coords
+        .chunks_exact(2)
+        .map(|xy| Point2 { x: xy[0], y: xy[1] })
+        .collect();
+    let lines = triangulate(&mut points);
+    let mut flat = Vec::with_capacity(lines.len() * 4);
+    for (org, dest) in lines {
+        flat.extend_from_slice(&[org.x, org.y, dest.x, dest.y]);
+    }
+
+    // Boxed slice so the allocation is exactly out_len long, delaunay_free rebuilds it
+    let flat = flat.into_boxed_slice();
+    *out_len = flat.len();
+    *out = Box::into_raw(flat) as *mut f64;
+    DELAUNAY_OK
+}
+
+/// Release a buffer returned by `delaunay_triangulate`. Null pointers are ignored.
+///
+/// # Safety
+///
+/// `out` and `out_len` must be exactly the values written by one `delaunay_triangulate` call,
+/// and the buffer must not be freed twice.
+#[no_mangle]
+pub unsafe extern "C" fn delaunay_free(out: *mut f64, out_len: usize) {
+    if out.is_null() {
+        return;
+    }
+    drop(Box::from_raw(ptr::slice_from_raw_parts_mut(out, out_len)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ffi_square() {
+        let xy = [0., 0., 1., 0., 1., 1., 0., 1.];
+        let mut out = ptr::null_mut();
+        let mut out_len = 0;
+        let status = unsafe { delaunay_triangulate(xy.as_ptr(), 4, &mut out, &mut out_len) };
+        assert_eq!(status, DELAUNAY_OK);
+        // 4 sides and 1 diagonal
+        assert_eq!(out_len, 5 * 4);
+
+        let flat = unsafe { slice::from_raw_parts(out, out_len) };
+        let square = [
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 1., y: 0. },
+            Point2 { x: 1., y: 1. },
+            Point2 { x: 0., y: 1. },
+        ];
+        for line in flat.chunks_exact(4) {
+            assert!(square.contains(&Point2 {
+                x: line[0],
+                y: line[1]
+            }));
+            assert!(square.contains(&Point2 {
+                x: line[2],
+                y: line[3]
+            }));
+        }
+        unsafe { delaunay_free(out, out_len) };
+    }
+
+    #[test]
+    fn test_ffi_bad_input() {
+        let mut out = ptr::null_mut();
+        let mut out_len = 7;
+        let status = unsafe { delaunay_triangulate(ptr::null(), 3, &mut out, &mut out_len) };
+        assert_eq!(status, DELAUNAY_NULL_POINTER);
+        let status = unsafe { delaunay_triangulate(ptr::null(), 0, &mut out, &mut out_len) };
+        assert_eq!(status, DELAUNAY_OK);
+        assert!(out.is_null());
+        assert_eq!(out_len, 0);
+
+        let xy = [0., 0.];
+        let status = unsafe { delaunay_triangulate(xy.as_ptr(), 1, ptr::null_mut(), &mut out_len) };
+        assert_eq!(status, DELAUNAY_NULL_POINTER);
+        // 2 * n overflows, or takes more than isize::MAX bytes
+        for n in [usize::MAX, usize::MAX / 2 + 1, usize::MAX / 16 + 1] {
+            out_len = 7;
+            let status = unsafe { delaunay_triangulate(xy.as_ptr(), n, &mut out, &mut out_len) };
+            assert_eq!(status, DELAUNAY_TOO_MANY_POINTS);
+            assert!(out.is_null());
+            assert_eq!(out_len, 0);
+        }
+        unsafe { delaunay_free(ptr::null_mut(), 0) };
+    }
+}
@@ -0,0 +1,147 @@
+/// Furthest-point Delaunay triangulation
+///
+/// The furthest-point Voronoi cell of a site holds the places it is the furthest site from. Only
+/// the strictly convex hull vertices have a cell, and the dual triangulation is the one of the
+/// hull polygon where every circumcircle holds all the sites, the reverse of the empty circle of
+/// the Delaunay triangulation.
+use crate::hull::monotone_chain;
+use crate::robust_float::{cmp_points, incircle_value, orient2d_value, Point2};
+
+/// Return true if `d` is strictly outside of the circle through `a`, `b` and `c`, whatever the
+/// winding of the triangle
+fn outside(a: &Point2, b: &Point2, c: &Point2, d: &Point2) -> bool {
+    let lifted = incircle_value(a, b, c, d);
+    let turn = orient2d_value(a, b, c);
+    (lifted < 0. && turn > 0.) || (lifted > 0. && turn < 0.)
+}
+
+/// Indices of the strictly convex hull vertices, turning counter clockwise as
+/// `counter_clockwise` defines it. Exactly equal points give their first index.
+fn convex_hull(points: &[Point2]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    order.sort_by(|&a, &b| cmp_points(&points[a], &points[b]).then(a.cmp(&b)));
+    order.dedup_by(|a, b| points[*a] == points[*b]);
+    if order.len() < 3 {
+        return order;
+    }
+    monotone_chain(points, &order, false)
+}
+
+/// Triangulate the polygon `hull[lo..=hi]` on the side of the chord from `hull[lo]` to
+/// `hull[hi]`. The apex of the chord's triangle is the vertex whose circle through the chord
+/// holds all the others, circles through a chord being ordered on one side of it.
+fn split(points: &[Point2], hull: &[usize], lo: usize, hi: usize, out: &mut Vec<[usize; 3]>) {
+    if hi - lo < 2 {
+        return;
+    }
+    let (a, b) = (&points[hull[lo]], &points[hull[hi]]);
+    let mut apex = lo + 1;
+    for candidate in lo + 2..hi {
+        if outside(a, &points[hull[apex]], b, &points[hull[candidate]]) {
+            apex = candidate;
+        }
+    }
+    out.push([hull[lo], hull[apex], hull[hi]]);
+    split(points, hull, lo, apex, out);
+    split(points, hull, apex, hi, out);
+}
+
+/// Furthest-point Delaunay triangulation of the points, as triangles of indices into `points`,
+/// counter clockwise as `counter_clockwise` defines it. Points inside of the hull or on its sides
+/// are never part of it, and fewer than 3 hull vertices give no triangle.
+///
+/// The hull is found in O(n log n), then triangulated chord by chord in O(h^2) worst case for h
+/// hull vertices.
+pub fn triangulate_furthest(points: &[Point2]) -> Vec<[usize; 3]> {
+    let hull = convex_hull(points);
+    let mut triangles = vec![];
+    if hull.len() >= 3 {
+        split(points, &hull, 0, hull.len() - 1, &mut triangles);
+    }
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robust_float::counter_clockwise;
+    use crate::test_util::xorshift;
+    use crate::triangulation::Triangulation;
+
+    /// Triangles tile the hull and every circumcircle holds all the points
+    fn check_furthest(points: &[Point2], triangles: &[[usize; 3]], hull_size: usize) {
+        assert_eq!(triangles.len(), hull_size - 2);
+        for &[a, b, c] in triangles {
+            let (a, b, c) = (&points[a], &points[b], &points[c]);
+            assert!(counter_clockwise(a, b, c));
+            assert!(points.iter().all(|point| !outside(a, b, c, point)));
+        }
+        let area: f64 = triangles
+            .iter()
+            .map(|&[a, b, c]| orient2d_value(&points[a], &points[b], &points[c]).abs() / 2.)
+            .sum();
+        let hull_area = Triangulation::new(points).hull_area();
+        assert!((area - hull_area).abs() < 1e-9 * hull_area.max(1.));
+    }
+
+    #[test]
+    fn test_triangulate_furthest() {
+        let mut next = xorshift(61);
+        for n in [3, 5, 20, 200] {
+            let points: Vec<Point2> = (0..n)
+                .map(|_| Point2 {
+                    x: next() * 10.,
+                    y: next() * 10.,
+                })
+                .collect();
+            let hull = convex_hull(&points);
+            check_furthest(&points, &triangulate_furthest(&points), hull.len());
+        }
+
+        // The center of a square has no furthest cell, neither do the middles of its sides
+        let square = [
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 2., y: 0. },
+            Point2 { x: 1., y: 1. },
+            Point2 { x: 2., y: 2. },
+            Point2 { x: 0., y: 2. },
+            Point2 { x: 1., y: 0. },
+        ];
+        let triangles = triangulate_furthest(&square);
+        check_furthest(&square, &triangles, 4);
+        assert!(triangles.iter().flatten().all(|&idx| idx != 2 && idx != 5));
+
+        let line = [
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 1., y: 1. },
+            Point2 { x: 2., y: 2. },
+        ];
+        assert!(triangulate_furthest(&line).is_empty());
+    }
+
+    #[test]
+    fn test_cocircular_points() {
+        // Integer points of the circle of radius 5, exactly cocircular
+        let points: Vec<Point2> = [(5, 0), (4, 3), (3, 4), (0, 5), (-3, 4), (-4, 3)]
+            .into_iter()
+            .flat_map(|(x, y)| [(x, y), (-x, -y)])
+            .map(|(x, y)| Point2 {
+                x: x as f64,
+                y: y as f64,
+            })
+            .collect();
+        let triangles = triangulate_furthest(&points);
+        check_furthest(&points, &triangles, 12);
+        // Every circle is the same, so the triangulation is a nearest point one too
+        for &[a, b, c] in &triangles {
+            let corners = [&points[a], &points[b], &points[c]];
+            assert!(points
+                .iter()
+                .all(|point| { incircle_value(corners[0], corners[1], corners[2], point) == 0. }));
+        }
+        assert_eq!(
+            Triangulation::new(&points).triangles().len(),
+            triangles.len()
+        );
+    }
+}
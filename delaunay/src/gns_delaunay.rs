@@ -1,128 +1,1267 @@
 /// Guibas and Stolfi implementation of the delaunay triangulation
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
 use crate::edge::*;
-use crate::robust_float::{counter_clockwise, in_circle, sanitize_points_vec, Point2};
+use crate::robust_float::{
+    cmp_points, counter_clockwise, sanitize_points_vec_with, sort_points, InCircleBase,
+    OrderedPoint2, Point2, SortOrder,
+};
 
-fn compute_delaunay(quad_arena: &mut QuadEdgeArena, points: &[Point2]) -> (EdgeRef, EdgeRef) {
-    if points.len() < 2 {
-        panic!("Not enough points in vec!")
-    }
-    if points.len() == 2 {
-        // println!(
-        //     "Points length = 2, origin: {:?}, dest: {:?}",
-        //     points[0], points[1]
-        // );
-        let mut a = make_edge(quad_arena);
-        a.set_org(quad_arena, points[0]);
-        a.set_dest(quad_arena, points[1]);
-        return (a, a.sym());
-    } else if points.len() == 3 {
-        let mut a = make_edge(quad_arena);
-        let mut b = make_edge(quad_arena);
-
-        splice(quad_arena, a.sym(), b);
-        a.set_org(quad_arena, points[0]);
-        b.set_org(quad_arena, points[1]);
-        a.set_dest(quad_arena, points[1]);
-        b.set_dest(quad_arena, points[2]);
-
-        if counter_clockwise(&points[0], &points[1], &points[2]) {
-            let _ = connect(quad_arena, a, b);
-            return (a, b.sym());
-        } else if counter_clockwise(&points[0], &points[2], &points[1]) {
-            let c = connect(quad_arena, b, a);
-            return (c.sym(), c);
-        } else {
-            return (a, b.sym());
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DelaunayError {
+    /// Fewer than 2 distinct points are left once sanitized, holds how many: all the points
+    /// were coincident, or there were none
+    NotEnoughPoints(usize),
+}
+
+impl fmt::Display for DelaunayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DelaunayError::NotEnoughPoints(count) => write!(
+                f,
+                "{} distinct point(s) left once sanitized, at least 2 are needed",
+                count
+            ),
         }
+    }
+}
+
+/// Step of the divide and conquer a `MeshEvent` happens in
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// Meshing two or three sites
+    BaseCase,
+    /// Joining two halves: the lowest common tangent, then the edges climbing from it
+    Merge,
+    /// Deleting an edge of a half that fails the in-circle test of the merge
+    CandidateRejection,
+}
+
+/// Change of the mesh while it is built, see `Triangulation::with_recorder`. Edges are given by
+/// their origin and destination.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MeshEvent {
+    /// A lone edge was made
+    MakeEdge(Point2, Point2, Phase),
+    /// An edge was made between two edges of the mesh
+    Connect(Point2, Point2, Phase),
+    /// An edge was deleted
+    DeleteEdge(Point2, Point2, Phase),
+}
+
+/// Where the events of a build go, nothing is recorded without a sink
+pub(crate) type Recorder<'a> = Option<&'a mut dyn FnMut(MeshEvent)>;
+
+/// Send the event `kind` of `edge` to the sink, if any
+#[inline]
+fn record(
+    recorder: &mut Recorder<'_>,
+    quad_arena: &QuadEdgeArena,
+    kind: fn(Point2, Point2, Phase) -> MeshEvent,
+    edge: EdgeRef,
+    phase: Phase,
+) {
+    if let Some(sink) = recorder {
+        let (org, dest) = edge.org_dest(quad_arena);
+        sink(kind(org, dest, phase));
+    }
+}
+
+/// Smallest merge whose two candidate scans may run in parallel with the `rayon` feature. Tests
+/// lower it so that small inputs take the parallel path too.
+#[cfg(feature = "rayon")]
+const PARALLEL_MERGE_MIN: usize = if cfg!(test) { 64 } else { 1 << 16 };
+
+/// Candidates deleted by the previous step of a merge above which the next step scans in
+/// parallel. Most steps delete a couple of edges, far too little work to pay for a `rayon::join`.
+#[cfg(feature = "rayon")]
+const PARALLEL_SCAN_MIN: usize = if cfg!(test) { 1 } else { 64 };
+
+/// Merge steps that scanned their candidates with `join_scans`, for tests checking the parallel
+/// path is taken
+#[cfg(all(test, feature = "rayon"))]
+pub(crate) static PARALLEL_SCANS: std::sync::atomic::AtomicUsize =
+    std::sync::atomic::AtomicUsize::new(0);
+
+/// Run the two candidate scans of a merge step with `rayon::join`. The predicate backend is a
+/// thread local, the worker thread gets the one of the calling thread.
+#[cfg(feature = "rayon")]
+fn join_scans(
+    left: impl FnOnce() -> usize + Send,
+    right: impl FnOnce() -> usize + Send,
+) -> (usize, usize) {
+    #[cfg(test)]
+    PARALLEL_SCANS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    #[cfg(feature = "exact")]
+    {
+        use crate::exact::{predicate_backend, with_predicate_backend};
+        let backend = predicate_backend();
+        rayon::join(
+            || with_predicate_backend(backend, left),
+            || with_predicate_backend(backend, right),
+        )
+    }
+    #[cfg(not(feature = "exact"))]
+    rayon::join(left, right)
+}
+
+/// Predicates of the divide and conquer, on vertices of the vertex table of the arena. The
+/// float kernel reads the coordinates of the table, other kernels can keep their own.
+pub(crate) trait Kernel: Sync {
+    /// Two vertices fixed for many `in_circle` tests
+    type Base: Sync;
+
+    /// `counter_clockwise` of the three vertices
+    fn ccw(&self, quad_arena: &QuadEdgeArena, a: u32, b: u32, c: u32) -> bool;
+
+    fn base(&self, quad_arena: &QuadEdgeArena, a: u32, b: u32) -> Self::Base;
+
+    /// `in_circle` of the two vertices of `base` and `c`, `d`
+    fn in_circle(&self, quad_arena: &QuadEdgeArena, base: &Self::Base, c: u32, d: u32) -> bool;
+}
+
+/// Kernel of the f64 points, with the robust predicates
+struct FloatKernel;
+
+impl Kernel for FloatKernel {
+    type Base = InCircleBase;
+
+    #[inline(always)]
+    fn ccw(&self, quad_arena: &QuadEdgeArena, a: u32, b: u32, c: u32) -> bool {
+        counter_clockwise(
+            &quad_arena.vertex(a),
+            &quad_arena.vertex(b),
+            &quad_arena.vertex(c),
+        )
+    }
+
+    #[inline(always)]
+    fn base(&self, quad_arena: &QuadEdgeArena, a: u32, b: u32) -> InCircleBase {
+        InCircleBase::new(&quad_arena.vertex(a), &quad_arena.vertex(b))
+    }
+
+    #[inline(always)]
+    fn in_circle(&self, quad_arena: &QuadEdgeArena, base: &InCircleBase, c: u32, d: u32) -> bool {
+        base.in_circle(&quad_arena.vertex(c), &quad_arena.vertex(d))
+    }
+}
+
+/// `valid` with the predicates of the kernel: the destination of `edge` is strictly right of
+/// `basel`
+#[inline(always)]
+fn above<K: Kernel>(quad_arena: &QuadEdgeArena, kernel: &K, edge: EdgeRef, basel: EdgeRef) -> bool {
+    kernel.ccw(
+        quad_arena,
+        edge.dest_vertex(quad_arena),
+        basel.dest_vertex(quad_arena),
+        basel.org_vertex(quad_arena),
+    )
+}
+
+/// Number of candidates the merge deletes on one side of `basel`, starting at `cand` and going
+/// around its origin with `next`. Deleting a candidate leaves the ring of the ones after it as
+/// is, so the scan only reads the mesh and the deletions can follow.
+fn doomed_candidates<K: Kernel>(
+    quad_arena: &QuadEdgeArena,
+    kernel: &K,
+    base: &K::Base,
+    basel: EdgeRef,
+    mut cand: EdgeRef,
+    next: fn(&EdgeRef, &QuadEdgeArena) -> EdgeRef,
+) -> usize {
+    if !above(quad_arena, kernel, cand, basel) {
+        return 0;
+    }
+    let mut count = 0;
+    while kernel.in_circle(
+        quad_arena,
+        base,
+        cand.dest_vertex(quad_arena),
+        next(&cand, quad_arena).dest_vertex(quad_arena),
+    ) {
+        cand = next(&cand, quad_arena);
+        count += 1;
+    }
+    count
+}
+
+/// Quad edges of the triangulation of `sites` sites, an upper bound: a planar graph on n >= 3
+/// vertices has at most 3n - 6 edges, and the deleted quad edges are reused while building
+pub fn quad_capacity(sites: usize) -> usize {
+    3 * sites
+}
+
+pub(crate) fn compute_delaunay(
+    quad_arena: &mut QuadEdgeArena,
+    points: &[Point2],
+) -> (EdgeRef, EdgeRef) {
+    compute_delaunay_recorded(quad_arena, points, &mut None)
+}
+
+/// `compute_delaunay`, sending every change of the mesh to `recorder`
+pub(crate) fn compute_delaunay_recorded(
+    quad_arena: &mut QuadEdgeArena,
+    points: &[Point2],
+    recorder: &mut Recorder<'_>,
+) -> (EdgeRef, EdgeRef) {
+    if points.len() < 2 {
+        panic!("At least 2 distinct points are needed to triangulate")
+    }
+    debug_assert!(
+        points.windows(2).all(|pair| pair[0] != pair[1]),
+        "Two points are coincident"
+    );
+    // Every edge leaving a point shares its entry of the vertex table
+    let first = quad_arena.vertices().len() as u32;
+    for point in points {
+        quad_arena.add_vertex(*point);
+    }
+    divide(
+        quad_arena,
+        &FloatKernel,
+        first,
+        points.len() as u32,
+        recorder,
+    )
+}
+
+/// Mesh of the segment between the vertices `a` and `b` of the table, the base case of two
+/// sites. Returns the edge from `a` to `b` and its symmetric: like every step of the recursion,
+/// the counter clockwise hull edge leaving the first site and the clockwise one leaving the last.
+pub fn base_edge(quad_arena: &mut QuadEdgeArena, a: u32, b: u32) -> (EdgeRef, EdgeRef) {
+    let edge = make_edge(quad_arena);
+    edge.set_org_vertex(quad_arena, a);
+    edge.set_dest_vertex(quad_arena, b);
+    (edge, edge.sym())
+}
+
+/// Mesh of the vertices `a`, `b` and `c` of the table, sorted like `cmp_points`, the base case of
+/// three sites: a triangle, or two edges when they are collinear. Returns the hull edges of
+/// `base_edge`, leaving `a` and leaving `c`.
+pub fn base_triangle(quad_arena: &mut QuadEdgeArena, a: u32, b: u32, c: u32) -> (EdgeRef, EdgeRef) {
+    base_triangle_with(quad_arena, &FloatKernel, a, b, c, &mut None)
+}
+
+fn base_triangle_with<K: Kernel>(
+    quad_arena: &mut QuadEdgeArena,
+    kernel: &K,
+    a: u32,
+    b: u32,
+    c: u32,
+    recorder: &mut Recorder<'_>,
+) -> (EdgeRef, EdgeRef) {
+    let (first, _) = base_edge(quad_arena, a, b);
+    record(
+        recorder,
+        quad_arena,
+        MeshEvent::MakeEdge,
+        first,
+        Phase::BaseCase,
+    );
+    let (second, _) = base_edge(quad_arena, b, c);
+    record(
+        recorder,
+        quad_arena,
+        MeshEvent::MakeEdge,
+        second,
+        Phase::BaseCase,
+    );
+    splice(quad_arena, first.sym(), second);
+
+    if kernel.ccw(quad_arena, a, b, c) {
+        let third = connect(quad_arena, second, first);
+        record(
+            recorder,
+            quad_arena,
+            MeshEvent::Connect,
+            third,
+            Phase::BaseCase,
+        );
+        (first, second.sym())
+    } else if kernel.ccw(quad_arena, a, c, b) {
+        let third = connect(quad_arena, second, first);
+        record(
+            recorder,
+            quad_arena,
+            MeshEvent::Connect,
+            third,
+            Phase::BaseCase,
+        );
+        (third.sym(), third)
+    } else {
+        (first, second.sym())
+    }
+}
+
+/// Triangulate the `count` vertices of the table starting at `first`, sorted and unique, with
+/// the predicates of `kernel`, sending every change of the mesh to `recorder`
+pub(crate) fn divide<K: Kernel>(
+    quad_arena: &mut QuadEdgeArena,
+    kernel: &K,
+    first: u32,
+    count: u32,
+    recorder: &mut Recorder<'_>,
+) -> (EdgeRef, EdgeRef) {
+    // Merges reuse the quad edges they delete, so the arena never holds more quad edges than
+    // the largest planar graph on the sites, 3 per site
+    let hull = divide_range(quad_arena, kernel, first, count, recorder);
+    quad_arena.end_recycling();
+    hull
+}
+
+fn divide_range<K: Kernel>(
+    quad_arena: &mut QuadEdgeArena,
+    kernel: &K,
+    first: u32,
+    count: u32,
+    recorder: &mut Recorder<'_>,
+) -> (EdgeRef, EdgeRef) {
+    if count == 2 {
+        let hull = base_edge(quad_arena, first, first + 1);
+        record(
+            recorder,
+            quad_arena,
+            MeshEvent::MakeEdge,
+            hull.0,
+            Phase::BaseCase,
+        );
+        hull
+    } else if count == 3 {
+        base_triangle_with(quad_arena, kernel, first, first + 1, first + 2, recorder)
     } else {
-        // points.len() >= 4
-        // Find the base left oriented edge
-        let (mut ldo, mut ldi) = compute_delaunay(quad_arena, &points[..points.len() / 2]);
-        let (mut rdi, mut rdo) = compute_delaunay(quad_arena, &points[points.len() / 2..]);
+        // count >= 4
+        // The sites are sorted by x then y, so the index midpoint is the (x, y) median: sites of
+        // the left half sharing their x with right half sites are below them, and a line turned
+        // slightly from the vertical parts the halves. The tangent search relies on it, halves
+        // split on x alone could both hold sites of a vertical line and overlap.
+        let half = count / 2;
+        debug_assert!(
+            !cmp_points(
+                &quad_arena.vertices()[(first + half - 1) as usize],
+                &quad_arena.vertices()[(first + half) as usize]
+            )
+            .is_gt(),
+            "The halves overlap"
+        );
+        let (mut ldo, mut ldi) = divide_range(quad_arena, kernel, first, half, recorder);
+        let (mut rdi, mut rdo) =
+            divide_range(quad_arena, kernel, first + half, count - half, recorder);
+        // Find the base left oriented edge, the lowest common tangent of the two hulls
         loop {
-            if left_of(quad_arena, &rdi.org(&quad_arena), ldi) {
+            let (left_org, right_org) = (ldi.org_vertex(quad_arena), rdi.org_vertex(quad_arena));
+            if kernel.ccw(quad_arena, right_org, left_org, ldi.dest_vertex(quad_arena)) {
                 ldi = ldi.lnext(quad_arena);
-            } else if right_of(quad_arena, &ldi.org(&quad_arena), rdi) {
+            } else if kernel.ccw(quad_arena, left_org, rdi.dest_vertex(quad_arena), right_org) {
                 rdi = rdi.rprev(quad_arena);
             } else {
                 break;
             }
         }
         let mut basel = connect(quad_arena, rdi.sym(), ldi);
-        if ldi.org(&quad_arena) == ldo.org(&quad_arena) {
+        record(
+            recorder,
+            quad_arena,
+            MeshEvent::Connect,
+            basel,
+            Phase::Merge,
+        );
+        if ldi.org_vertex(quad_arena) == ldo.org_vertex(quad_arena) {
             ldo = basel.sym()
         }
-        if rdi.org(&quad_arena) == rdo.org(&quad_arena) {
+        if rdi.org_vertex(quad_arena) == rdo.org_vertex(quad_arena) {
             rdo = basel
         }
 
         // Merge loop start
+        #[cfg(feature = "rayon")]
+        let mut deleted = 0;
         loop {
-            let mut lcand = basel.sym().onext(&quad_arena);
-            if valid(quad_arena, lcand, basel) {
-                while in_circle(
-                    &basel.dest(&quad_arena),
-                    &basel.org(&quad_arena),
-                    &lcand.dest(&quad_arena),
-                    &lcand.onext(&quad_arena).dest(&quad_arena),
-                ) {
-                    let t = lcand.onext(&quad_arena);
-                    delete_edge(quad_arena, lcand);
-                    lcand = t;
-                }
+            // basel stays the same while candidates are deleted, prepare its endpoints once
+            let base = kernel.base(
+                quad_arena,
+                basel.dest_vertex(quad_arena),
+                basel.org_vertex(quad_arena),
+            );
+            let mut lcand = basel.sym().onext(quad_arena);
+            let mut rcand = basel.oprev(quad_arena);
+            // Only reads the mesh, the two sides walk rings around different ends of basel
+            let arena = &*quad_arena;
+            let scan_left =
+                || doomed_candidates(arena, kernel, &base, basel, lcand, EdgeRef::onext);
+            let scan_right =
+                || doomed_candidates(arena, kernel, &base, basel, rcand, EdgeRef::oprev);
+            #[cfg(feature = "rayon")]
+            let (left, right) =
+                if count as usize >= PARALLEL_MERGE_MIN && deleted >= PARALLEL_SCAN_MIN {
+                    join_scans(scan_left, scan_right)
+                } else {
+                    (scan_left(), scan_right())
+                };
+            #[cfg(feature = "rayon")]
+            {
+                deleted = left + right;
             }
+            #[cfg(not(feature = "rayon"))]
+            let (left, right) = (scan_left(), scan_right());
 
-            let mut rcand = basel.oprev(quad_arena);
-            if valid(quad_arena, rcand, basel) {
-                while in_circle(
-                    &basel.dest(&quad_arena),
-                    &basel.org(&quad_arena),
-                    &rcand.dest(&quad_arena),
-                    &rcand.oprev(&quad_arena).dest(&quad_arena),
-                ) {
-                    let t = rcand.oprev(&quad_arena);
-                    delete_edge(quad_arena, lcand);
-                    rcand = t;
-                }
+            for _ in 0..left {
+                let t = lcand.onext(quad_arena);
+                record(
+                    recorder,
+                    quad_arena,
+                    MeshEvent::DeleteEdge,
+                    lcand,
+                    Phase::CandidateRejection,
+                );
+                recycle_edge(quad_arena, lcand);
+                lcand = t;
+            }
+            for _ in 0..right {
+                let t = rcand.oprev(quad_arena);
+                record(
+                    recorder,
+                    quad_arena,
+                    MeshEvent::DeleteEdge,
+                    rcand,
+                    Phase::CandidateRejection,
+                );
+                recycle_edge(quad_arena, rcand);
+                rcand = t;
             }
 
-            if !valid(&quad_arena, lcand, basel) && !valid(quad_arena, rcand, basel) {
+            let (left_valid, right_valid) = (
+                above(quad_arena, kernel, lcand, basel),
+                above(quad_arena, kernel, rcand, basel),
+            );
+            if !left_valid && !right_valid {
                 break;
             }
 
-            if !valid(quad_arena, lcand, basel)
-                || (valid(quad_arena, rcand, basel)
-                    && in_circle(
-                        &lcand.dest(quad_arena),
-                        &lcand.org(&quad_arena),
-                        &rcand.org(&quad_arena),
-                        &rcand.dest(&quad_arena),
-                    ))
+            if !left_valid
+                || (right_valid && {
+                    let base = kernel.base(
+                        quad_arena,
+                        lcand.dest_vertex(quad_arena),
+                        lcand.org_vertex(quad_arena),
+                    );
+                    kernel.in_circle(
+                        quad_arena,
+                        &base,
+                        rcand.org_vertex(quad_arena),
+                        rcand.dest_vertex(quad_arena),
+                    )
+                })
             {
                 basel = connect(quad_arena, rcand, basel.sym());
             } else {
                 basel = connect(quad_arena, basel.sym(), lcand.sym());
             }
+            record(
+                recorder,
+                quad_arena,
+                MeshEvent::Connect,
+                basel,
+                Phase::Merge,
+            );
+        }
+        (ldo, rdo)
+    }
+}
+
+/// Triangulate the points into `quad_arena`, the low level entry point behind `triangulate`.
+///
+/// The points must be sorted with `cmp_points`, without duplicates, and at least 2 of them, as
+/// `sanitize_points_vec` leaves them. This is only checked in debug builds.
+///
+/// Returns the two hull edges of Guibas and Stolfi, `(ldo, rdo)`:
+/// - `ldo` leaves the leftmost point, the first one, and is the counter clockwise hull edge
+///   there: the triangles are on its left, as `counter_clockwise` defines it.
+/// - `rdo` leaves the rightmost point, the last one, and is the clockwise hull edge there: the
+///   triangles are on its right.
+///
+/// Following `lnext` from `ldo.sym()` walks the whole hull, with the outer face on the left.
+pub fn build(
+    quad_arena: &mut QuadEdgeArena,
+    sorted_unique_points: &[Point2],
+) -> (EdgeRef, EdgeRef) {
+    debug_assert!(
+        sorted_unique_points.len() >= 2,
+        "At least 2 points are needed"
+    );
+    debug_assert!(
+        sorted_unique_points
+            .windows(2)
+            .all(|pair| cmp_points(&pair[0], &pair[1]).is_lt()),
+        "The points must be sorted and unique"
+    );
+    compute_delaunay(quad_arena, sorted_unique_points)
+}
+
+/// Panic on the first live edge of the arena whose two triangles fail the in-circle test, like
+/// `Triangulation::assert_delaunay`. Tests check every mesh of the entry points with it.
+#[cfg(test)]
+fn assert_delaunay(quad_arena: &QuadEdgeArena) {
+    let left_corner = |edge: EdgeRef| {
+        let second = edge.lnext(quad_arena);
+        let (a, b) = edge.org_dest(quad_arena);
+        let c = second.dest(quad_arena);
+        (second.lnext(quad_arena).lnext(quad_arena) == edge && counter_clockwise(&a, &b, &c))
+            .then_some(c)
+    };
+    for (quad_id, quad_edge) in quad_arena.iter() {
+        if quad_edge.is_deleted() {
+            continue;
+        }
+        let edge = quad_arena.edge_ref(quad_id, 0);
+        if let (Some(c), Some(d)) = (left_corner(edge), left_corner(edge.sym())) {
+            let (a, b) = edge.org_dest(quad_arena);
+            assert!(
+                !crate::robust_float::in_circle(&a, &b, &c, &d),
+                "Edge {} from {} to {} isn't Delaunay",
+                edge,
+                a,
+                b
+            );
+        }
+    }
+}
+
+/// Triangulate sites sanitized in `order`. The merge needs x-sorted halves: y-major sites are
+/// turned a quarter, triangulated, and turned back, rotations and the exact predicates commute.
+fn compute_delaunay_in_order(quad_arena: &mut QuadEdgeArena, points: &[Point2], order: SortOrder) {
+    match order {
+        SortOrder::XMajor => {
+            compute_delaunay(quad_arena, points);
+        }
+        SortOrder::YMajor => {
+            let mut turned: Vec<Point2> = points
+                .iter()
+                .map(|point| Point2 {
+                    x: point.y,
+                    y: -point.x,
+                })
+                .collect();
+            sort_points(&mut turned);
+            let first = quad_arena.vertices().len();
+            compute_delaunay(quad_arena, &turned);
+            for point in &mut quad_arena.vertices_mut()[first..] {
+                *point = Point2 {
+                    x: -point.y,
+                    y: point.x,
+                };
+            }
         }
-        return (ldo, rdo);
     }
+    #[cfg(test)]
+    assert_delaunay(quad_arena);
 }
 
-/// Triangulate function returns a list of all lines
+/// Triangulate function returns a list of all lines.
+///
+/// Every undirected segment of the triangulation appears exactly once, as `(smaller, larger)`
+/// endpoints, points being compared with `cmp_points`. Lines come in a canonical order,
+/// independent of the arena allocation order: sorted by their smaller endpoint, then by their
+/// larger one. Fewer than 2 points left once sanitized give no line.
 pub fn triangulate(points: &mut Vec<Point2>) -> Vec<(Point2, Point2)> {
+    triangulate_with(points, SortOrder::XMajor)
+}
+
+/// Like `triangulate`, telling degenerate input apart from a triangulation without lines: fails
+/// when fewer than 2 distinct points are left once sanitized, like when they all coincide.
+pub fn try_triangulate(points: &mut Vec<Point2>) -> Result<Vec<(Point2, Point2)>, DelaunayError> {
+    let lines = triangulate(points);
+    if points.len() < 2 {
+        return Err(DelaunayError::NotEnoughPoints(points.len()));
+    }
+    Ok(lines)
+}
+
+/// Like `triangulate`, the points being sanitized and left sorted in `order`. `YMajor` splits
+/// the recursion along y. The lines are the same whatever the order, unless four sites are
+/// cocircular.
+pub fn triangulate_with(points: &mut Vec<Point2>, order: SortOrder) -> Vec<(Point2, Point2)> {
+    let mut lines = vec![];
+    let mut quad_arena = QuadEdgeArena::with_capacity(quad_capacity(points.len()));
+    triangulate_into_with(points, order, &mut quad_arena, &mut lines);
+    lines
+}
+
+/// Like `triangulate`, with room for `capacity` quad edges in the arena instead of
+/// `quad_capacity` of the points. The arena grows past a smaller capacity, and never uses more.
+pub fn triangulate_with_capacity(
+    points: &mut Vec<Point2>,
+    capacity: usize,
+) -> Vec<(Point2, Point2)> {
+    let mut lines = vec![];
+    let mut quad_arena = QuadEdgeArena::with_capacity(capacity);
+    triangulate_into_with(points, SortOrder::XMajor, &mut quad_arena, &mut lines);
+    lines
+}
+
+/// Memory `triangulate_into` keeps from one call to the next: the lines of the last call, and
+/// the quad edges and vertex table of its triangulation, which the next call reuses.
+#[derive(Debug, Default)]
+pub struct LineBuffer {
+    lines: Vec<(Point2, Point2)>,
+    quad_arena: QuadEdgeArena,
+}
+
+impl LineBuffer {
+    pub fn new() -> LineBuffer {
+        LineBuffer::default()
+    }
+
+    /// Lines written by the last `triangulate_into`, in the order of `triangulate`
+    pub fn lines(&self) -> &[(Point2, Point2)] {
+        &self.lines
+    }
+}
+
+/// Like `triangulate`, writing the lines into `out` instead of a new vector. The lines and the
+/// quad edges of the previous call are reused, so calls on inputs of a same size stop
+/// allocating after the first one, unless rayon joins are made from outside a rayon pool.
+/// Returns the number of lines written.
+pub fn triangulate_into(points: &mut Vec<Point2>, out: &mut LineBuffer) -> usize {
+    let capacity = quad_capacity(points.len());
+    if out.quad_arena.is_empty() {
+        out.quad_arena = QuadEdgeArena::with_capacity(capacity);
+    }
+    out.quad_arena.recycle_all(capacity);
+    let count = triangulate_into_with(
+        points,
+        SortOrder::XMajor,
+        &mut out.quad_arena,
+        &mut out.lines,
+    );
+    // Recycled now, the free list of the next call already has room for every quad edge
+    out.quad_arena.recycle_all(capacity);
+    count
+}
+
+/// Triangulate in a new or recycled arena, see `QuadEdgeArena::recycle_all`
+fn triangulate_into_with(
+    points: &mut Vec<Point2>,
+    order: SortOrder,
+    quad_arena: &mut QuadEdgeArena,
+    out: &mut Vec<(Point2, Point2)>,
+) -> usize {
     // Steps:
     // 1- Sort points
     // 2- Delete near-equal points
-    sanitize_points_vec(points);
+    sanitize_points_vec_with(points, order);
+    out.clear();
+    // A single point left, or none, has no line
+    if points.len() < 2 {
+        return 0;
+    }
     // 3- triangulate
-    let mut quad_arena = QuadEdgeArena::with_capacity(points.len() * 4); // Random ass big value
-    compute_delaunay(&mut quad_arena, points);
+    compute_delaunay_in_order(quad_arena, points, order);
     // 4- parse return values into data
-    let mut lines = vec![];
+    // Every live quad edge is one undirected segment, given smaller endpoint first
     for (_, quad_edge) in quad_arena.iter() {
-        if let Some((org, dest)) = quad_edge.get_points() {
-            lines.push((org, dest));
+        if let Some((org, dest)) = quad_edge.get_points(quad_arena) {
+            out.push(match cmp_points(&org, &dest) {
+                core::cmp::Ordering::Greater => (dest, org),
+                _ => (org, dest),
+            });
         }
     }
-    lines
+    // Lines are unique, the unstable sort gives the same order without a scratch buffer
+    out.sort_unstable_by(|a, b| cmp_points(&a.0, &b.0).then(cmp_points(&a.1, &b.1)));
+    out.len()
+}
+
+/// How `triangulate_indexed` numbers the vertices
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum IndexOrder {
+    /// In the order the walk over the arena meets them
+    #[default]
+    Arena,
+    /// In the order of the sanitized points, so the same points always get the same indices.
+    /// Triangles start at their smallest index and come sorted.
+    Sanitized,
+}
+
+/// Triangulate the points into a vertex buffer and triangles of indices into it, counter
+/// clockwise as `counter_clockwise` defines it. The points are sanitized like `triangulate` does.
+///
+/// With `IndexOrder::Sanitized` the vertex buffer is the sanitized points, and the whole output
+/// only depends on the set of points, not on their input order or on the arena layout.
+pub fn triangulate_indexed(
+    points: &mut Vec<Point2>,
+    order: IndexOrder,
+) -> (Vec<Point2>, Vec<[usize; 3]>) {
+    sanitize_points_vec_with(points, SortOrder::XMajor);
+    let mut vertices: Vec<Point2> = vec![];
+    let mut triangles = vec![];
+    if points.len() < 2 {
+        return (points.clone(), triangles);
+    }
+    let mut quad_arena = QuadEdgeArena::with_capacity(quad_capacity(points.len()));
+    compute_delaunay(&mut quad_arena, points);
+    #[cfg(test)]
+    assert_delaunay(&quad_arena);
+
+    let mut indices: BTreeMap<OrderedPoint2, usize> = BTreeMap::new();
+    if order == IndexOrder::Sanitized {
+        vertices.clone_from(points);
+        indices.extend(
+            points
+                .iter()
+                .enumerate()
+                .map(|(idx, point)| (point.ordered(), idx)),
+        );
+    }
+    for (quad_id, quad_edge) in quad_arena.iter() {
+        if quad_edge.is_deleted() {
+            continue;
+        }
+        for edge in [
+            quad_arena.edge_ref(quad_id, 0),
+            quad_arena.edge_ref(quad_id, 2),
+        ] {
+            let second = edge.lnext(&quad_arena);
+            let third = second.lnext(&quad_arena);
+            // A face is given by its smallest edge only
+            if third.lnext(&quad_arena) != edge || second < edge || third < edge {
+                continue;
+            }
+            let corners = [edge, second, third].map(|side| side.org(&quad_arena));
+            if !counter_clockwise(&corners[0], &corners[1], &corners[2]) {
+                continue;
+            }
+            triangles.push(corners.map(|corner| {
+                *indices.entry(corner.ordered()).or_insert_with(|| {
+                    vertices.push(corner);
+                    vertices.len() - 1
+                })
+            }));
+        }
+    }
+    if order == IndexOrder::Sanitized {
+        for triangle in &mut triangles {
+            let smallest = (0..3).min_by_key(|&idx| triangle[idx]).unwrap();
+            triangle.rotate_left(smallest);
+        }
+        triangles.sort_unstable();
+    }
+    (vertices, triangles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robust_float::{in_circle, sanitize_points_vec};
+    use crate::test_util::{xorshift, xorshift_bits};
+
+    #[test]
+    fn test_triangulate_edge_count() {
+        // A triangulation of n points with h of them on the hull has 3n - 3 - h edges
+        let mut points = vec![
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 4., y: 0.5 },
+            Point2 { x: 1., y: 3. },
+            Point2 { x: 3.5, y: 4. },
+            Point2 { x: 2., y: 1.5 },
+        ];
+        assert_eq!(triangulate(&mut points).len(), 8);
+
+        // Points on y = x^2 with x >= 0 are in convex position and never cocircular
+        for n in 3..40 {
+            let mut points: Vec<Point2> = (0..n)
+                .map(|i| Point2 {
+                    x: i as f64,
+                    y: (i * i) as f64,
+                })
+                .collect();
+            assert_eq!(triangulate(&mut points).len(), 2 * n - 3);
+        }
+    }
+
+    #[test]
+    fn test_triangulate_duplicates() {
+        let origin = Point2 { x: 0., y: 0. };
+        let mut points = vec![origin, origin, Point2 { x: 1., y: 1. }];
+        assert_eq!(
+            triangulate(&mut points),
+            vec![(origin, Point2 { x: 1., y: 1. })]
+        );
+        assert_eq!(points.len(), 2);
+
+        let mut points = vec![origin, origin];
+        assert!(triangulate(&mut points).is_empty());
+        assert_eq!(points, vec![origin]);
+        assert!(triangulate(&mut vec![]).is_empty());
+        let mut out = LineBuffer::new();
+        triangulate_into(&mut vec![origin, Point2 { x: 1., y: 1. }], &mut out);
+        assert_eq!(triangulate_into(&mut vec![origin], &mut out), 0);
+        assert!(out.lines().is_empty());
+    }
+
+    #[test]
+    fn test_triangulate_coincident() {
+        let point = Point2 { x: 3., y: -4. };
+        let mut points = vec![point; 10];
+        assert!(triangulate(&mut points.clone()).is_empty());
+        assert_eq!(
+            try_triangulate(&mut points),
+            Err(DelaunayError::NotEnoughPoints(1))
+        );
+        assert_eq!(points, vec![point]);
+        // Near-equal points sanitize down to one too
+        let mut points = vec![
+            point,
+            Point2 {
+                x: 3. + f64::EPSILON,
+                y: -4.,
+            },
+        ];
+        assert_eq!(
+            try_triangulate(&mut points),
+            Err(DelaunayError::NotEnoughPoints(1))
+        );
+        assert_eq!(
+            try_triangulate(&mut vec![]),
+            Err(DelaunayError::NotEnoughPoints(0))
+        );
+        assert_eq!(
+            try_triangulate(&mut vec![point, Point2 { x: 0., y: 0. }]).map(|lines| lines.len()),
+            Ok(1)
+        );
+
+        let triangulation = crate::triangulation::Triangulation::new(&[point; 10]);
+        assert_eq!(triangulation.points(), &[point]);
+        assert!(triangulation.edges().is_empty() && triangulation.triangles().is_empty());
+    }
+
+    #[test]
+    fn test_triangulate_output_order() {
+        let mut next = xorshift_bits(3);
+        let points: Vec<Point2> = (0..200)
+            .map(|_| Point2 {
+                x: (next() >> 11) as f64 / (1u64 << 53) as f64,
+                y: (next() >> 11) as f64 / (1u64 << 53) as f64,
+            })
+            .collect();
+        let mut shuffled = points.clone();
+        for idx in (1..shuffled.len()).rev() {
+            shuffled.swap(idx, next() as usize % (idx + 1));
+        }
+        let lines = triangulate(&mut points.clone());
+        assert_eq!(lines, triangulate(&mut shuffled));
+        assert!(lines
+            .windows(2)
+            .all(|pair| cmp_points(&pair[0].0, &pair[1].0).is_le()));
+    }
+
+    #[test]
+    fn test_triangulate_unique_segments() {
+        use crate::triangulation::Triangulation;
+        use std::collections::HashSet;
+
+        let mut next = xorshift(5);
+        for n in [3, 4, 10, 57, 300] {
+            let mut points: Vec<Point2> = (0..n)
+                .map(|_| Point2 {
+                    x: next() * 10.,
+                    y: next() * 10.,
+                })
+                .collect();
+            let triangulation = Triangulation::new(&points);
+            let hull = (0..n)
+                .filter(|&vertex| triangulation.is_hull_vertex(vertex))
+                .count();
+            let lines = triangulate(&mut points);
+
+            let key = |a: &Point2, b: &Point2| {
+                [a.x.to_bits(), a.y.to_bits(), b.x.to_bits(), b.y.to_bits()]
+            };
+            let mut seen = HashSet::new();
+            for (a, b) in &lines {
+                assert!(cmp_points(a, b).is_lt());
+                assert!(seen.insert(key(a, b)));
+                assert!(!seen.contains(&key(b, a)));
+            }
+            assert!(lines.len() <= 3 * n - 6);
+            assert_eq!(lines.len(), 3 * n - 3 - hull);
+        }
+    }
+
+    #[test]
+    fn test_triangulate_sort_orders() {
+        let mut next = xorshift(11);
+        // A square and a long east west corridor
+        for (width, height) in [(10., 10.), (1000., 1.)] {
+            let points: Vec<Point2> = (0..500)
+                .map(|_| Point2 {
+                    x: next() * width,
+                    y: next() * height,
+                })
+                .collect();
+            let mut by_y = points.clone();
+            let lines = triangulate(&mut points.clone());
+            assert_eq!(triangulate_with(&mut by_y, SortOrder::YMajor), lines);
+            assert!(by_y.windows(2).all(|pair| pair[0].y <= pair[1].y));
+
+            // Every face of the turned back mesh is ccw and locally Delaunay
+            let mut quad_arena = QuadEdgeArena::new();
+            compute_delaunay_in_order(&mut quad_arena, &by_y, SortOrder::YMajor);
+            let mut faces = 0;
+            for (quad_id, quad_edge) in quad_arena.iter() {
+                if quad_edge.is_deleted() {
+                    continue;
+                }
+                for edge in [
+                    quad_arena.edge_ref(quad_id, 0),
+                    quad_arena.edge_ref(quad_id, 2),
+                ] {
+                    let next = edge.lnext(&quad_arena);
+                    let (a, b) = edge.org_dest(&quad_arena);
+                    let c = next.dest(&quad_arena);
+                    if next.lnext(&quad_arena).lnext(&quad_arena) != edge
+                        || !counter_clockwise(&a, &b, &c)
+                    {
+                        continue;
+                    }
+                    faces += 1;
+                    let other = edge.sym().lnext(&quad_arena).dest(&quad_arena);
+                    assert!(!in_circle(&a, &b, &c, &other));
+                }
+            }
+            // Every triangle is seen from its three edges
+            let hull = 3 * by_y.len() - 3 - lines.len();
+            assert_eq!(faces, 3 * (2 * by_y.len() - 2 - hull));
+        }
+    }
+
+    #[test]
+    fn test_base_edge() {
+        let mut quad_arena = QuadEdgeArena::new();
+        let a = quad_arena.add_vertex(Point2 { x: 0., y: 0. });
+        let b = quad_arena.add_vertex(Point2 { x: 1., y: 2. });
+        let (left, right) = base_edge(&mut quad_arena, a, b);
+        assert_eq!(quad_arena.len(), 1);
+        assert_eq!(right, left.sym());
+        assert_eq!(
+            (left.org_vertex(&quad_arena), left.dest_vertex(&quad_arena)),
+            (a, b)
+        );
+        // Alone in the rings of both ends, the only face is the outer one
+        assert_eq!(left.onext(&quad_arena), left);
+        assert_eq!(right.onext(&quad_arena), right);
+        assert_eq!(left.lnext(&quad_arena), right);
+    }
+
+    #[test]
+    fn test_base_triangle() {
+        let corners = |bottom: f64| {
+            [
+                Point2 { x: 0., y: 0. },
+                Point2 { x: 1., y: bottom },
+                Point2 { x: 2., y: 0. },
+            ]
+        };
+        for (bottom, edges) in [(1., 3), (-1., 3), (0., 2)] {
+            let mut quad_arena = QuadEdgeArena::new();
+            let [a, b, c] = corners(bottom).map(|point| quad_arena.add_vertex(point));
+            let (left, right) = base_triangle(&mut quad_arena, a, b, c);
+            assert_eq!(quad_arena.len(), edges);
+            assert_eq!(left.org_vertex(&quad_arena), a);
+            assert_eq!(right.org_vertex(&quad_arena), c);
+
+            // The triangle is on the left of the hull edge leaving a, on the right of the other
+            let face = left.lnext(&quad_arena);
+            let closed = face.lnext(&quad_arena).lnext(&quad_arena) == left;
+            assert_eq!(closed, edges == 3);
+            if closed {
+                let (org, dest) = left.org_dest(&quad_arena);
+                assert!(counter_clockwise(&org, &dest, &face.dest(&quad_arena)));
+                let inner = right.sym();
+                assert_eq!(
+                    inner
+                        .lnext(&quad_arena)
+                        .lnext(&quad_arena)
+                        .lnext(&quad_arena),
+                    inner
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_quad_capacity() {
+        let mut next = xorshift(61);
+        for count in [2, 3, 10, 100, 1000, 20_000] {
+            let uniform: Vec<Point2> = (0..count)
+                .map(|_| Point2 {
+                    x: next(),
+                    y: next(),
+                })
+                .collect();
+            let grid: Vec<Point2> = (0..count)
+                .map(|idx| Point2 {
+                    x: (idx % 50) as f64,
+                    y: (idx / 50) as f64,
+                })
+                .collect();
+            let circle: Vec<Point2> = (0..count)
+                .map(|_| {
+                    let angle = next() * std::f64::consts::TAU;
+                    Point2 {
+                        x: angle.cos(),
+                        y: angle.sin(),
+                    }
+                })
+                .collect();
+            for mut points in [uniform, grid, circle] {
+                sanitize_points_vec(&mut points);
+                let hint = quad_capacity(points.len());
+                let mut quad_arena = QuadEdgeArena::with_capacity(hint);
+                compute_delaunay(&mut quad_arena, &points);
+                // The deleted quad edges were reused, the arena never grew past the hint
+                assert!(quad_arena.len() <= hint);
+                let live = quad_arena
+                    .iter()
+                    .filter(|(_, quad_edge)| !quad_edge.is_deleted())
+                    .count();
+                assert!(live <= 3 * points.len() - 3);
+                assert_eq!(
+                    triangulate_with_capacity(&mut points.clone(), 0),
+                    triangulate(&mut points)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_wide_merge_front() {
+        let mut next = xorshift(5);
+        // Two tall strips far apart, the last merge zips along all of them, with parallel scans in
+        // the `rayon` builds
+        let points: Vec<Point2> = (0..4000)
+            .map(|idx| Point2 {
+                x: next() + if idx % 2 == 0 { 0. } else { 100. },
+                y: next() * 1000.,
+            })
+            .collect();
+        let key = |(a, b): (Point2, Point2)| {
+            let (a, b) = if cmp_points(&a, &b).is_lt() {
+                (a, b)
+            } else {
+                (b, a)
+            };
+            [a.x, a.y, b.x, b.y].map(f64::to_bits)
+        };
+        let mut merged: Vec<[u64; 4]> = triangulate(&mut points.clone())
+            .into_iter()
+            .map(key)
+            .collect();
+        // The points are in general position, the incremental triangulation is the same one
+        let mut inserted: Vec<[u64; 4]> = crate::triangulation::Triangulation::new(&points)
+            .edges_iter()
+            .map(key)
+            .collect();
+        merged.sort_unstable();
+        inserted.sort_unstable();
+        assert_eq!(merged, inserted);
+        #[cfg(feature = "rayon")]
+        assert!(PARALLEL_SCANS.load(std::sync::atomic::Ordering::Relaxed) > 0);
+    }
+
+    #[test]
+    fn test_equal_x_split() {
+        let mut next = xorshift(31);
+        let mut column = |x: f64| Point2 {
+            x,
+            y: next() * 100.,
+        };
+        // A tall column holding the median, with a few sites on both sides
+        let mut tall: Vec<Point2> = (0..1001).map(|_| column(5.)).collect();
+        tall.extend((0..40).map(|idx| column(if idx % 2 == 0 { 0. } else { 10. })));
+        // Three columns, every split falls inside of one
+        let columns: Vec<Point2> = (0..900).map(|idx| column((idx % 3) as f64)).collect();
+        // A column and a site on its right, every triangle is in the fan around that site
+        let mut fan: Vec<Point2> = (0..513).map(|_| column(0.)).collect();
+        fan.push(Point2 { x: 1., y: 50. });
+
+        let edge_set = |triangulation: &crate::triangulation::Triangulation| {
+            let mut edges: Vec<[u64; 4]> = triangulation
+                .edges_iter()
+                .map(|(a, b)| {
+                    if cmp_points(&a, &b).is_lt() {
+                        (a, b)
+                    } else {
+                        (b, a)
+                    }
+                })
+                .map(|(a, b)| [a.x, a.y, b.x, b.y].map(f64::to_bits))
+                .collect();
+            edges.sort_unstable();
+            edges
+        };
+        for points in [tall, columns, fan] {
+            let mut sorted = points.clone();
+            sanitize_points_vec(&mut sorted);
+            let mut quad_arena = QuadEdgeArena::with_capacity(quad_capacity(sorted.len()));
+            let (ldo, _) = build(&mut quad_arena, &sorted);
+            // The hull found by the merges is convex: no site is outside of a hull edge
+            let mut edge = ldo.sym();
+            loop {
+                let (org, dest) = edge.org_dest(&quad_arena);
+                assert!(sorted
+                    .iter()
+                    .all(|point| !counter_clockwise(&org, &dest, point)));
+                edge = edge.lnext(&quad_arena);
+                if edge == ldo.sym() {
+                    break;
+                }
+            }
+
+            // Every edge is Delaunay, and the mesh is the one of inserting the sites one by one
+            let merged = crate::triangulation::Triangulation::new(&points);
+            assert!(merged.is_delaunay());
+            let mut inserted = crate::triangulation::Triangulation::new(&[]);
+            inserted.insert_points(&points);
+            assert_eq!(edge_set(&merged), edge_set(&inserted));
+        }
+    }
+
+    #[test]
+    fn test_build_hull_edges() {
+        let mut next = xorshift(13);
+        for n in [3, 4, 9, 100, 1000] {
+            let mut points: Vec<Point2> = (0..n)
+                .map(|_| Point2 {
+                    x: next() * 10.,
+                    y: next() * 10.,
+                })
+                .collect();
+            sanitize_points_vec(&mut points);
+            let mut quad_arena = QuadEdgeArena::new();
+            let (ldo, rdo) = build(&mut quad_arena, &points);
+            assert_eq!(ldo.org(&quad_arena), points[0]);
+            assert_eq!(rdo.org(&quad_arena), points[n - 1]);
+
+            // Monotone chain hull, in the same turning direction as the outer face walk
+            let mut hull: Vec<Point2> = vec![];
+            for pass in [points.clone(), points.iter().rev().copied().collect()] {
+                let start = hull.len();
+                for point in pass {
+                    while hull.len() >= start + 2
+                        && !counter_clockwise(&hull[hull.len() - 2], &hull[hull.len() - 1], &point)
+                    {
+                        hull.pop();
+                    }
+                    hull.push(point);
+                }
+                hull.pop();
+            }
+
+            let mut walked = vec![];
+            let mut edge = ldo.sym();
+            loop {
+                walked.push(edge.dest(&quad_arena));
+                edge = edge.lnext(&quad_arena);
+                if edge == ldo.sym() {
+                    break;
+                }
+            }
+            hull.reverse();
+            hull.rotate_right(1);
+            assert_eq!(walked, hull);
+            // rdo's sym has the outer face on its left too
+            assert!(walked.contains(&rdo.dest(&quad_arena)));
+        }
+    }
+
+    #[test]
+    fn test_triangulate_into_reuses_buffer() {
+        use crate::triangulation::counting_alloc::ALLOCATED;
+        use std::cell::Cell;
+
+        let frames = || {
+            let mut next = xorshift(17);
+            let mut frame = |shift: f64| -> Vec<Point2> {
+                (0..2000)
+                    .map(|_| Point2 {
+                        x: next() * 100. + shift,
+                        y: next() * 100.,
+                    })
+                    .collect()
+            };
+            let mut out = LineBuffer::new();
+            let count = triangulate_into(&mut frame(0.), &mut out);
+            assert_eq!(count, out.lines().len());
+
+            // Same sized frames reuse the lines and the quad edges of the previous one
+            for shift in [0.5, 1., 1.5] {
+                let mut points = frame(shift);
+                let before = ALLOCATED.with(Cell::get);
+                let count = triangulate_into(&mut points, &mut out);
+                assert_eq!(ALLOCATED.with(Cell::get) - before, 0);
+                assert_eq!(count, out.lines().len());
+                assert_eq!(out.lines(), triangulate(&mut points.clone()));
+            }
+        };
+        // Joins made outside a rayon pool allocate the jobs they inject into it
+        #[cfg(feature = "rayon")]
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap()
+            .install(frames);
+        #[cfg(not(feature = "rayon"))]
+        frames();
+    }
+
+    #[test]
+    fn test_triangulate_indexed() {
+        let mut next = xorshift_bits(71);
+        let points: Vec<Point2> = (0..300)
+            .map(|_| Point2 {
+                x: (next() >> 11) as f64 / (1u64 << 53) as f64,
+                y: (next() >> 11) as f64 / (1u64 << 53) as f64,
+            })
+            .collect();
+        let mut shuffled = points.clone();
+        for idx in (1..shuffled.len()).rev() {
+            shuffled.swap(idx, next() as usize % (idx + 1));
+        }
+        let mut sanitized = points.clone();
+        let (vertices, triangles) = triangulate_indexed(&mut sanitized, IndexOrder::Sanitized);
+        assert_eq!(vertices, sanitized);
+        assert_eq!(
+            triangulate_indexed(&mut shuffled.clone(), IndexOrder::Sanitized),
+            (vertices.clone(), triangles.clone())
+        );
+        assert!(triangles.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(triangles.iter().all(|&[a, b, c]| a < b
+            && a < c
+            && counter_clockwise(&vertices[a], &vertices[b], &vertices[c])));
+        let edges = triangulate(&mut points.clone()).len();
+        let hull = 3 * vertices.len() - 3 - edges;
+        assert_eq!(triangles.len(), 2 * vertices.len() - 2 - hull);
+
+        // The arena order gives the same triangles, numbered differently
+        let (arena_vertices, arena_triangles) =
+            triangulate_indexed(&mut points.clone(), IndexOrder::Arena);
+        let mut renumbered: Vec<[usize; 3]> = arena_triangles
+            .iter()
+            .map(|triangle| {
+                let mut triangle = triangle.map(|idx| {
+                    vertices
+                        .binary_search_by(|point| cmp_points(point, &arena_vertices[idx]))
+                        .unwrap()
+                });
+                let smallest = (0..3).min_by_key(|&idx| triangle[idx]).unwrap();
+                triangle.rotate_left(smallest);
+                triangle
+            })
+            .collect();
+        renumbered.sort_unstable();
+        assert_eq!(renumbered, triangles);
+    }
 }
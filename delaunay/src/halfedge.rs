@@ -0,0 +1,224 @@
+/// Flat halfedge layout, as used by delaunator and the d3-delaunay ports
+///
+/// Halfedge `e` belongs to triangle `e / 3` and goes from `triangles[e]` to `triangles[next(e)]`,
+/// `next(e)` being `e + 1` except for the last halfedge of a triangle, which wraps to its first.
+/// `halfedges[e]` is the opposite halfedge in the neighbor triangle, or -1 on the hull.
+use std::collections::HashMap;
+
+use crate::edge::EdgeRef;
+use crate::triangulation::Triangulation;
+
+/// Triangulation flattened to the delaunator arrays
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct HalfedgeMesh {
+    /// Site indices, three per triangle, counter clockwise
+    pub triangles: Vec<usize>,
+    /// Opposite halfedge of every halfedge, -1 for hull halfedges
+    pub halfedges: Vec<i64>,
+    /// Site indices of the convex hull, counter clockwise
+    pub hull: Vec<usize>,
+}
+
+/// Next halfedge in the same triangle
+pub fn next_halfedge(edge: usize) -> usize {
+    if edge % 3 == 2 {
+        edge - 2
+    } else {
+        edge + 1
+    }
+}
+
+/// Previous halfedge in the same triangle
+pub fn prev_halfedge(edge: usize) -> usize {
+    if edge.is_multiple_of(3) {
+        edge + 2
+    } else {
+        edge - 1
+    }
+}
+
+impl Triangulation {
+    /// Convert to the delaunator halfedge layout. Site indices are the indices of `points()`. A
+    /// mesh without triangles (less than 3 sites, or collinear sites) gives empty arrays.
+    ///
+    /// The output order is canonical: every triangle starts at its smallest site and triangles
+    /// are sorted by their sorted sites, the hull starts at its smallest site.
+    pub fn to_halfedges(&self) -> HalfedgeMesh {
+        let arena = self.quad_arena();
+        let vertex = |point| self.vertex_index(&point).unwrap();
+        let mut faces: Vec<([usize; 3], [EdgeRef; 3])> = vec![];
+        for edge in self.live_edges() {
+            for edge in [edge, edge.sym()] {
+                if self.left_triangle(edge).is_none() {
+                    continue;
+                }
+                let second = edge.lnext(arena);
+                let third = second.lnext(arena);
+                if edge < second && edge < third {
+                    let mut sides = [edge, second, third];
+                    let mut corners = sides.map(|side| vertex(side.org(arena)));
+                    // Start every triangle at its smallest site, keeping the winding
+                    let first = (0..3).min_by_key(|&idx| corners[idx]).unwrap();
+                    sides.rotate_left(first);
+                    corners.rotate_left(first);
+                    faces.push((corners, sides));
+                }
+            }
+        }
+        // Canonical order, independent of the arena: triangles sorted by their sorted sites
+        faces.sort_by_cached_key(|(corners, _)| {
+            let mut sorted = *corners;
+            sorted.sort_unstable();
+            sorted
+        });
+
+        let mut mesh = HalfedgeMesh::default();
+        let mut halfedge_of = HashMap::new();
+        for (corners, sides) in faces {
+            for (corner, side) in corners.into_iter().zip(sides) {
+                halfedge_of.insert(side, mesh.triangles.len());
+                mesh.triangles.push(corner);
+            }
+        }
+
+        mesh.halfedges = vec![-1; mesh.triangles.len()];
+        let mut hull_edge = None;
+        for (&edge, &halfedge) in &halfedge_of {
+            match halfedge_of.get(&edge.sym()) {
+                Some(&opposite) => mesh.halfedges[halfedge] = opposite as i64,
+                None => hull_edge = Some(edge),
+            }
+        }
+
+        // Walk the outer face, it goes clockwise, the other way around the hull
+        if let Some(hull_edge) = hull_edge {
+            let start = hull_edge.sym();
+            let mut edge = start;
+            loop {
+                mesh.hull.push(vertex(edge.org(arena)));
+                edge = edge.lnext(arena);
+                if edge == start {
+                    break;
+                }
+            }
+            mesh.hull.reverse();
+            let first = (0..mesh.hull.len())
+                .min_by_key(|&idx| mesh.hull[idx])
+                .unwrap();
+            mesh.hull.rotate_left(first);
+        }
+        mesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robust_float::{counter_clockwise, Point2};
+    use crate::test_util::random_points;
+
+    #[test]
+    fn test_halfedge_invariants() {
+        let triangulation = Triangulation::new(&random_points(17, 200, 100.));
+        let points = triangulation.points();
+        let mesh = triangulation.to_halfedges();
+        assert_eq!(mesh.triangles.len() % 3, 0);
+        assert_eq!(mesh.halfedges.len(), mesh.triangles.len());
+
+        for triangle in mesh.triangles.chunks_exact(3) {
+            assert!(counter_clockwise(
+                &points[triangle[0]],
+                &points[triangle[1]],
+                &points[triangle[2]]
+            ));
+        }
+        let mut hull_halfedges = 0;
+        for (edge, &opposite) in mesh.halfedges.iter().enumerate() {
+            if opposite < 0 {
+                hull_halfedges += 1;
+                continue;
+            }
+            let opposite = opposite as usize;
+            assert_eq!(mesh.halfedges[opposite], edge as i64);
+            assert_eq!(
+                mesh.triangles[edge],
+                mesh.triangles[next_halfedge(opposite)]
+            );
+            assert_eq!(
+                mesh.triangles[next_halfedge(edge)],
+                mesh.triangles[opposite]
+            );
+        }
+        assert_eq!(hull_halfedges, mesh.hull.len());
+        // 2n - 2 - h triangles
+        assert_eq!(mesh.triangles.len() / 3, 2 * 200 - 2 - mesh.hull.len());
+        for (idx, &vertex) in mesh.hull.iter().enumerate() {
+            let next = mesh.hull[(idx + 1) % mesh.hull.len()];
+            let after = mesh.hull[(idx + 2) % mesh.hull.len()];
+            assert!(triangulation.is_hull_vertex(vertex));
+            assert!(!counter_clockwise(
+                &points[after],
+                &points[next],
+                &points[vertex]
+            ));
+        }
+    }
+
+    #[test]
+    fn test_small_input() {
+        // Square around an off center site, delaunator gives the same four triangles, up to
+        // their order and the halfedge each of them starts with
+        let points = [
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 10., y: 0. },
+            Point2 { x: 10., y: 10. },
+            Point2 { x: 0., y: 10. },
+            Point2 { x: 4., y: 6. },
+        ];
+        let triangulation = Triangulation::new(&points);
+        let mesh = triangulation.to_halfedges();
+        let site = |idx: usize| triangulation.vertex_index(&points[idx]).unwrap();
+        let canonical = |triangle: &[usize]| {
+            let start = (0..3).min_by_key(|&idx| triangle[idx]).unwrap();
+            [0, 1, 2].map(|offset| triangle[(start + offset) % 3])
+        };
+        let mut triangles: Vec<[usize; 3]> =
+            mesh.triangles.chunks_exact(3).map(canonical).collect();
+        triangles.sort_unstable();
+        let mut expected: Vec<[usize; 3]> = [[0, 4, 1], [1, 4, 2], [2, 4, 3], [3, 4, 0]]
+            .iter()
+            .map(|triangle| canonical(&triangle.map(site)))
+            .collect();
+        expected.sort_unstable();
+        assert_eq!(triangles, expected);
+
+        let start = mesh
+            .hull
+            .iter()
+            .position(|&vertex| vertex == site(0))
+            .unwrap();
+        let hull: Vec<usize> = (0..4).map(|idx| mesh.hull[(start + idx) % 4]).collect();
+        assert_eq!(hull, [0, 3, 2, 1].map(site));
+    }
+
+    #[test]
+    fn test_canonical_order() {
+        let points = random_points(17, 150, 100.);
+        let mut reversed = points.clone();
+        reversed.reverse();
+        let mesh = Triangulation::new(&points).to_halfedges();
+        assert_eq!(mesh, Triangulation::new(&reversed).to_halfedges());
+        let sorted: Vec<[usize; 3]> = mesh
+            .triangles
+            .chunks_exact(3)
+            .map(|triangle| {
+                assert!(triangle[0] < triangle[1] && triangle[0] < triangle[2]);
+                let mut sorted = [triangle[0], triangle[1], triangle[2]];
+                sorted.sort_unstable();
+                sorted
+            })
+            .collect();
+        assert!(sorted.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(mesh.hull[0], *mesh.hull.iter().min().unwrap());
+    }
+}
@@ -0,0 +1,165 @@
+/// Convex hull of points without triangulating them
+///
+/// Andrew's monotone chain: the points sorted with `cmp_points` are swept once left to right for
+/// one chain and once right to left for the other, popping the corners that don't turn
+/// `counter_clockwise`. O(n log n) for the sort, then linear.
+use crate::robust_float::{orient2d_value, sanitize_points_vec, Point2};
+
+/// Indices of the hull corners of `points`, turning counter clockwise as `counter_clockwise`
+/// defines it, from the first index of `order`. `order` holds distinct points sorted with
+/// `cmp_points`, at least 3 of them. Corners in the middle of a side are kept when
+/// `include_collinear` is set, the points must then not be all collinear.
+pub(crate) fn monotone_chain(
+    points: &[Point2],
+    order: &[usize],
+    include_collinear: bool,
+) -> Vec<usize> {
+    // orient2d_value is negative when the turn is counter clockwise
+    let keeps = |turn: f64| turn < 0. || (include_collinear && turn == 0.);
+    let mut hull: Vec<usize> = vec![];
+    for pass in [
+        order.to_vec(),
+        order.iter().rev().copied().collect::<Vec<usize>>(),
+    ] {
+        let start = hull.len();
+        for idx in pass {
+            while hull.len() >= start + 2 {
+                let (a, b) = (hull[hull.len() - 2], hull[hull.len() - 1]);
+                if keeps(orient2d_value(&points[a], &points[b], &points[idx])) {
+                    break;
+                }
+                hull.pop();
+            }
+            hull.push(idx);
+        }
+        hull.pop();
+    }
+    hull
+}
+
+/// Convex hull of the points, counter clockwise as `counter_clockwise` defines it from the
+/// smallest point in `cmp_points` order, the points being sanitized like `Triangulation::new`
+/// does. Points in the middle of a side are left out. Collinear points give their two extremes,
+/// fewer than 3 points are given as sanitized.
+pub fn convex_hull_only(points: &[Point2]) -> Vec<Point2> {
+    convex_hull_only_with(points, false)
+}
+
+/// Like `convex_hull_only`, keeping the points in the middle of the sides when
+/// `include_collinear` is set, which gives the corners of `Triangulation::hull_edges`. Collinear
+/// points are then all given, sorted.
+pub fn convex_hull_only_with(points: &[Point2], include_collinear: bool) -> Vec<Point2> {
+    let mut points = points.to_vec();
+    sanitize_points_vec(&mut points);
+    if points.len() < 3 {
+        return points;
+    }
+    let (first, last) = (points[0], points[points.len() - 1]);
+    if points
+        .iter()
+        .all(|point| orient2d_value(&first, &last, point) == 0.)
+    {
+        if !include_collinear {
+            points = vec![first, last];
+        }
+        return points;
+    }
+    let order: Vec<usize> = (0..points.len()).collect();
+    monotone_chain(&points, &order, include_collinear)
+        .into_iter()
+        .map(|idx| points[idx])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robust_float::{cmp_points, counter_clockwise};
+    use crate::test_util::xorshift;
+    use crate::triangulation::Triangulation;
+
+    /// Corners of the hull edges of the triangulation, counter clockwise from the smallest one
+    fn triangulation_hull(points: &[Point2]) -> Vec<Point2> {
+        let triangulation = Triangulation::new(points);
+        let arena = triangulation.quad_arena();
+        // Hull edges have the outside on their left, the reverse edges turn counter clockwise
+        let next: Vec<(Point2, Point2)> = triangulation
+            .hull_edges()
+            .map(|edge| {
+                let (org, dest) = edge.org_dest(arena);
+                (dest, org)
+            })
+            .collect();
+        let start = next
+            .iter()
+            .map(|&(from, _)| from)
+            .min_by(cmp_points)
+            .unwrap();
+        let mut hull = vec![start];
+        loop {
+            let current = *hull.last().unwrap();
+            let (_, to) = *next.iter().find(|&&(from, _)| from == current).unwrap();
+            if to == start {
+                return hull;
+            }
+            hull.push(to);
+        }
+    }
+
+    #[test]
+    fn test_matches_triangulation() {
+        let mut next = xorshift(101);
+        for count in [3, 10, 100, 2000] {
+            let points: Vec<Point2> = (0..count)
+                .map(|_| Point2 {
+                    x: next() * 100.,
+                    y: next() * 100.,
+                })
+                .collect();
+            let hull = convex_hull_only(&points);
+            assert_eq!(hull, triangulation_hull(&points));
+            assert_eq!(hull, convex_hull_only_with(&points, true));
+            for idx in 0..hull.len() {
+                let after = (idx + 1) % hull.len();
+                let turn = (idx + 2) % hull.len();
+                assert!(counter_clockwise(&hull[idx], &hull[after], &hull[turn]));
+            }
+        }
+
+        // A grid has sites in the middle of its sides
+        let grid: Vec<Point2> = (0..20)
+            .map(|idx| Point2 {
+                x: (idx % 5) as f64,
+                y: (idx / 5) as f64,
+            })
+            .collect();
+        let with_sides = convex_hull_only_with(&grid, true);
+        assert_eq!(with_sides.len(), 14);
+        assert_eq!(with_sides, triangulation_hull(&grid));
+        let corners = convex_hull_only(&grid);
+        assert_eq!(corners.len(), 4);
+        assert!(corners.iter().all(|corner| with_sides.contains(corner)));
+        assert_eq!(corners[0], Point2 { x: 0., y: 0. });
+    }
+
+    #[test]
+    fn test_degenerate() {
+        assert!(convex_hull_only(&[]).is_empty());
+        let pair = [Point2 { x: 3., y: 1. }, Point2 { x: 1., y: 1. }];
+        assert_eq!(convex_hull_only(&pair), [pair[1], pair[0]]);
+        // Near-equal points are merged
+        let near = Point2 {
+            x: 1. + f64::EPSILON,
+            y: 1.,
+        };
+        assert_eq!(convex_hull_only(&[pair[0], pair[1], near]).len(), 2);
+
+        let line: Vec<Point2> = [3., 0., 4., 1., 2.]
+            .iter()
+            .map(|&t| Point2 { x: t, y: 2. * t })
+            .collect();
+        assert_eq!(convex_hull_only(&line), [line[1], line[2]]);
+        let all = convex_hull_only_with(&line, true);
+        assert_eq!(all, [line[1], line[3], line[4], line[0], line[2]]);
+    }
+}
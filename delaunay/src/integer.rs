@@ -0,0 +1,329 @@
+/// Delaunay triangulation of integer points, with exact integer predicates
+///
+/// Pixel and grid coordinates don't need any tolerance: the orientation determinant is computed
+/// in i128 and the in-circle one in 256 bits, so every decision is exact and duplicates are the
+/// exactly equal points. The divide and conquer is the one of `gns_delaunay`, with integer
+/// predicates.
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::edge::QuadEdgeArena;
+use crate::gns_delaunay::{divide, quad_capacity, Kernel};
+use crate::robust_float::Point2;
+
+/// Largest magnitude of a coordinate. Differences then fit in 63 bits, the orientation
+/// determinant in i128 and the in-circle one in 253 bits.
+pub const MAX_COORD: i64 = 1 << 61;
+
+/// Point with integer coordinates, in the frame of `Point2`. Points are ordered x first, then y,
+/// like `cmp_points` orders `Point2`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point2I {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Point2I {
+    /// Nearest `Point2`, exact while both coordinates are below 2^53 in magnitude
+    pub fn to_f64(&self) -> Point2 {
+        Point2 {
+            x: self.x as f64,
+            y: self.y as f64,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CoordinateError {
+    /// A coordinate is beyond `MAX_COORD` in magnitude, holds the index of the point
+    OutOfRange(usize),
+}
+
+impl fmt::Display for CoordinateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoordinateError::OutOfRange(idx) => write!(
+                f,
+                "Point {} has a coordinate beyond {} in magnitude",
+                idx, MAX_COORD
+            ),
+        }
+    }
+}
+
+fn in_range(point: &Point2I) -> bool {
+    point.x.unsigned_abs() <= MAX_COORD as u64 && point.y.unsigned_abs() <= MAX_COORD as u64
+}
+
+/// Two's complement 256 bits integer, just enough for the in-circle determinant
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct I256 {
+    hi: u128,
+    lo: u128,
+}
+
+impl I256 {
+    /// Exact product of two i128
+    fn mul(a: i128, b: i128) -> I256 {
+        let (a_abs, b_abs) = (a.unsigned_abs(), b.unsigned_abs());
+        let (a0, a1) = (a_abs as u64 as u128, a_abs >> 64);
+        let (b0, b1) = (b_abs as u64 as u128, b_abs >> 64);
+        let (mid, mid_carry) = (a0 * b1).overflowing_add(a1 * b0);
+        let (lo, lo_carry) = (a0 * b0).overflowing_add(mid << 64);
+        let hi = a1 * b1 + (mid >> 64) + ((mid_carry as u128) << 64) + lo_carry as u128;
+        let product = I256 { hi, lo };
+        if (a < 0) != (b < 0) {
+            product.neg()
+        } else {
+            product
+        }
+    }
+
+    fn neg(self) -> I256 {
+        let (lo, carry) = (!self.lo).overflowing_add(1);
+        I256 {
+            hi: (!self.hi).wrapping_add(carry as u128),
+            lo,
+        }
+    }
+
+    fn add(self, other: I256) -> I256 {
+        let (lo, carry) = self.lo.overflowing_add(other.lo);
+        I256 {
+            hi: self.hi.wrapping_add(other.hi).wrapping_add(carry as u128),
+            lo,
+        }
+    }
+
+    /// Sign, compared to zero
+    fn signum(&self) -> Ordering {
+        if (self.hi as i128) < 0 {
+            Ordering::Less
+        } else if self.hi == 0 && self.lo == 0 {
+            Ordering::Equal
+        } else {
+            Ordering::Greater
+        }
+    }
+}
+
+/// Exact orientation determinant, with the sign of `orient2d_value`: negative when the points
+/// turn `counter_clockwise`, zero when they are collinear. Twice the area of the triangle.
+///
+/// Coordinates must be within `MAX_COORD`, checked in debug builds.
+pub fn orient2d_i64(a: &Point2I, b: &Point2I, c: &Point2I) -> i128 {
+    debug_assert!([a, b, c].into_iter().all(in_range));
+    let (acx, acy) = (a.x as i128 - c.x as i128, a.y as i128 - c.y as i128);
+    let (bcx, bcy) = (b.x as i128 - c.x as i128, b.y as i128 - c.y as i128);
+    acx * bcy - acy * bcx
+}
+
+/// Sign of the exact in-circle determinant, the sign of `incircle_value`: `Less` when `d` is
+/// inside of the circle through `a`, `b`, `c` turning `counter_clockwise`, `Equal` when the four
+/// points are cocircular.
+///
+/// Coordinates must be within `MAX_COORD`, checked in debug builds.
+pub fn incircle_i64(a: &Point2I, b: &Point2I, c: &Point2I, d: &Point2I) -> Ordering {
+    debug_assert!([a, b, c, d].into_iter().all(in_range));
+    let [(adx, ady), (bdx, bdy), (cdx, cdy)] =
+        [a, b, c].map(|point| (point.x as i128 - d.x as i128, point.y as i128 - d.y as i128));
+    let lift = |x: i128, y: i128| x * x + y * y;
+    I256::mul(lift(adx, ady), bdx * cdy - cdx * bdy)
+        .add(I256::mul(lift(bdx, bdy), cdx * ady - adx * cdy))
+        .add(I256::mul(lift(cdx, cdy), adx * bdy - bdx * ady))
+        .signum()
+}
+
+/// Predicates of the divide and conquer on the integer points, `points[vertex - first]`
+struct IntKernel<'a> {
+    points: &'a [Point2I],
+    first: u32,
+}
+
+impl IntKernel<'_> {
+    fn point(&self, vertex: u32) -> &Point2I {
+        &self.points[(vertex - self.first) as usize]
+    }
+}
+
+impl Kernel for IntKernel<'_> {
+    type Base = (Point2I, Point2I);
+
+    fn ccw(&self, _: &QuadEdgeArena, a: u32, b: u32, c: u32) -> bool {
+        orient2d_i64(self.point(a), self.point(b), self.point(c)) < 0
+    }
+
+    fn base(&self, _: &QuadEdgeArena, a: u32, b: u32) -> (Point2I, Point2I) {
+        (*self.point(a), *self.point(b))
+    }
+
+    fn in_circle(&self, _: &QuadEdgeArena, base: &(Point2I, Point2I), c: u32, d: u32) -> bool {
+        incircle_i64(&base.0, &base.1, self.point(c), self.point(d)) == Ordering::Less
+    }
+}
+
+/// Delaunay triangulation of integer points, the segments of `triangulate` with the smaller
+/// endpoint first, sorted. Duplicates are the exactly equal points, and fewer than 2 distinct
+/// points have no segment.
+///
+/// Returns an error if a coordinate is beyond `MAX_COORD` in magnitude.
+pub fn triangulate_i64(points: &[Point2I]) -> Result<Vec<(Point2I, Point2I)>, CoordinateError> {
+    if let Some(idx) = points.iter().position(|point| !in_range(point)) {
+        return Err(CoordinateError::OutOfRange(idx));
+    }
+    let mut sorted = points.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    if sorted.len() < 2 {
+        return Ok(vec![]);
+    }
+
+    // The vertex table holds the nearest floats, only the integer points are read
+    let mut quad_arena = QuadEdgeArena::with_capacity(quad_capacity(sorted.len()));
+    for point in &sorted {
+        quad_arena.add_vertex(point.to_f64());
+    }
+    let kernel = IntKernel {
+        points: &sorted,
+        first: 0,
+    };
+    divide(&mut quad_arena, &kernel, 0, sorted.len() as u32, &mut None);
+
+    let mut lines: Vec<(Point2I, Point2I)> = quad_arena
+        .iter()
+        .filter(|(_, quad_edge)| !quad_edge.is_deleted())
+        .map(|(quad_id, _)| {
+            let edge = quad_arena.edge_ref(quad_id, 0);
+            let org = *kernel.point(edge.org_vertex(&quad_arena));
+            let dest = *kernel.point(edge.dest_vertex(&quad_arena));
+            (org.min(dest), org.max(dest))
+        })
+        .collect();
+    lines.sort_unstable();
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gns_delaunay::triangulate;
+    use crate::robust_float::{incircle_value, orient2d_value};
+
+    #[test]
+    fn test_i256() {
+        let big = (1i128 << 125) - 3;
+        for (a, b) in [(big, big), (big, -big), (-big, 7), (0, -big), (-1, 1)] {
+            let product = I256::mul(a, b);
+            assert_eq!(product.signum(), (a.signum() * b.signum()).cmp(&0));
+            // Adding the opposite product gives zero
+            assert_eq!(product.add(I256::mul(-a, b)).signum(), Ordering::Equal);
+        }
+        assert_eq!(I256::mul(1 << 100, 1 << 100), I256 { hi: 1 << 72, lo: 0 });
+        assert_eq!(I256::mul(-1, 1), I256 { hi: !0, lo: !0 });
+    }
+
+    #[test]
+    fn test_integer_predicates() {
+        let points = [(5, 0), (3, 4), (-4, 3), (0, -5), (4, -3), (1, 1), (7, 7)]
+            .map(|(x, y)| Point2I { x, y });
+        // Integer points of the circle of radius 5 are exactly cocircular
+        assert_eq!(
+            incircle_i64(&points[0], &points[1], &points[2], &points[3]),
+            Ordering::Equal
+        );
+        assert_eq!(
+            incircle_i64(&points[4], &points[1], &points[2], &points[3]),
+            Ordering::Equal
+        );
+        for [a, b, c, d] in [[0, 1, 2, 5], [0, 2, 1, 5], [0, 1, 2, 6], [2, 1, 0, 6]] {
+            let [a, b, c, d] = [a, b, c, d].map(|idx| points[idx]);
+            let expected = incircle_value(&a.to_f64(), &b.to_f64(), &c.to_f64(), &d.to_f64());
+            assert_eq!(
+                incircle_i64(&a, &b, &c, &d),
+                expected.partial_cmp(&0.).unwrap()
+            );
+            let expected = orient2d_value(&a.to_f64(), &b.to_f64(), &c.to_f64());
+            assert_eq!(orient2d_i64(&a, &b, &c) as f64, expected);
+        }
+
+        // Near the bound, where f64 can't tell the points apart: collinear for integers only
+        let max = MAX_COORD;
+        let (a, b, c) = (
+            Point2I { x: -max, y: -max },
+            Point2I { x: max, y: max },
+            Point2I { x: 1, y: 1 },
+        );
+        assert_eq!(orient2d_i64(&a, &b, &c), 0);
+        assert_eq!(
+            orient2d_i64(&a, &b, &Point2I { x: 1, y: 2 }),
+            2 * max as i128
+        );
+        let far = Point2I { x: max, y: -max };
+        assert_eq!(
+            incircle_i64(&a, &far, &b, &Point2I { x: -max, y: max }),
+            Ordering::Equal
+        );
+        assert_eq!(
+            incircle_i64(&a, &far, &b, &Point2I { x: 0, y: 0 }),
+            incircle_value(
+                &a.to_f64(),
+                &far.to_f64(),
+                &b.to_f64(),
+                &Point2 { x: 0., y: 0. }
+            )
+            .partial_cmp(&0.)
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_triangulate_i64() {
+        // A grid is full of cocircular quadruples, the output must still be the same every time
+        let grid: Vec<Point2I> = (0..12 * 9)
+            .map(|idx| Point2I {
+                x: (idx % 12) * 3 - 10,
+                y: (idx / 12) * 2 - 7,
+            })
+            .collect();
+        let lines = triangulate_i64(&grid).unwrap();
+        let mut shuffled: Vec<Point2I> = grid.iter().rev().copied().collect();
+        shuffled.extend_from_slice(&grid[..20]);
+        assert_eq!(triangulate_i64(&shuffled).unwrap(), lines);
+        // 3n - 3 - h edges, h = 38 points on the hull
+        assert_eq!(lines.len(), 3 * grid.len() - 3 - 38);
+
+        // Same decisions as the float path where both are exact
+        let mut floats: Vec<Point2> = grid.iter().map(Point2I::to_f64).collect();
+        let mut float_lines: Vec<(Point2I, Point2I)> = triangulate(&mut floats)
+            .into_iter()
+            .map(|(a, b)| {
+                let [a, b] = [a, b].map(|point| Point2I {
+                    x: point.x as i64,
+                    y: point.y as i64,
+                });
+                (a.min(b), a.max(b))
+            })
+            .collect();
+        float_lines.sort_unstable();
+        assert_eq!(float_lines, lines);
+
+        // Points 1 apart near the bound stay apart
+        let max = MAX_COORD;
+        let huge = [
+            Point2I { x: max, y: max },
+            Point2I { x: max - 1, y: max },
+            Point2I { x: max, y: max - 1 },
+            Point2I { x: -max, y: 0 },
+        ];
+        assert_eq!(triangulate_i64(&huge).unwrap().len(), 5);
+
+        assert_eq!(
+            triangulate_i64(&[Point2I { x: 0, y: 0 }, Point2I { x: 0, y: max + 1 }]),
+            Err(CoordinateError::OutOfRange(1))
+        );
+        assert_eq!(
+            triangulate_i64(&[Point2I { x: 2, y: 2 }, Point2I { x: 2, y: 2 }]),
+            Ok(vec![])
+        );
+    }
+}
@@ -0,0 +1,135 @@
+/// Convex layers of a point set, also known as onion peeling
+///
+/// The first layer is the convex hull of the points, the next one the hull of the points left
+/// once the first layer is taken out, and so on. The layers are peeled off a triangulation:
+/// taking a hull site out with `remove_vertex` only retriangulates the hole it leaves.
+use std::collections::HashMap;
+
+use crate::robust_float::{cmp_points, Point2};
+use crate::triangulation::{Triangulation, VertexHandle};
+
+/// Convex layers of the points, outermost first. A layer is the sites on the hull of the points
+/// left, its corners and the sites on its sides, counter clockwise as `counter_clockwise`
+/// defines it from its smallest site in `cmp_points` order.
+///
+/// When the points left have no triangle, collinear or fewer than 3, they make a last degenerate
+/// layer, sorted with `cmp_points`. Near-equal points are merged first, like `Triangulation::new`
+/// does. Every layer walks the edges left in the mesh, so peeling costs O(n) per layer.
+pub fn convex_layers(points: &[Point2]) -> Vec<Vec<Point2>> {
+    let mut triangulation = Triangulation::new(points);
+    let mut left = triangulation.points().len();
+    let mut layers = vec![];
+    while left > 0 {
+        if triangulation.triangles_iter().next().is_none() {
+            let mut rest: Vec<Point2> = (0..triangulation.points().len())
+                .filter(|&vertex| !triangulation.is_removed(vertex))
+                .map(|vertex| triangulation.points()[vertex])
+                .collect();
+            rest.sort_by(cmp_points);
+            layers.push(rest);
+            break;
+        }
+        // Hull edges have the outside on their left, going back along them turns counter
+        // clockwise
+        let arena = triangulation.quad_arena();
+        let previous: HashMap<VertexHandle, VertexHandle> = triangulation
+            .hull_edges()
+            .map(|edge| {
+                let (org, dest) = edge.org_dest(arena);
+                (
+                    triangulation.vertex_index(&dest).unwrap(),
+                    triangulation.vertex_index(&org).unwrap(),
+                )
+            })
+            .collect();
+        let points = triangulation.points();
+        let start = *previous
+            .keys()
+            .min_by(|&&a, &&b| cmp_points(&points[a], &points[b]))
+            .unwrap();
+        let mut ring = vec![start];
+        let mut vertex = previous[&start];
+        while vertex != start {
+            ring.push(vertex);
+            vertex = previous[&vertex];
+        }
+        layers.push(ring.iter().map(|&vertex| points[vertex]).collect());
+        for vertex in ring {
+            triangulation.remove_vertex(vertex);
+        }
+        left -= layers.last().unwrap().len();
+    }
+    layers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robust_float::counter_clockwise;
+    use crate::test_util::xorshift;
+
+    #[test]
+    fn test_convex_layers() {
+        // Concentric squares with a point in the middle of every side, and a center
+        let mut points = vec![Point2 { x: 0., y: 0. }];
+        for size in 1..=4 {
+            let size = size as f64;
+            for (x, y) in [(-1., -1.), (0., -1.), (1., -1.), (1., 0.)] {
+                for (x, y) in [(x, y), (-x, -y)] {
+                    points.push(Point2 {
+                        x: x * size,
+                        y: y * size,
+                    });
+                }
+            }
+        }
+        let layers = convex_layers(&points);
+        assert_eq!(layers.len(), 5);
+        for (idx, layer) in layers[..4].iter().enumerate() {
+            let size = (4 - idx) as f64;
+            assert_eq!(layer.len(), 8);
+            assert_eq!(layer[0], Point2 { x: -size, y: -size });
+            assert!(layer
+                .iter()
+                .all(|point| point.x.abs() == size || point.y.abs() == size));
+            // The sides turn one way, the middles of the sides are straight
+            for corner in 0..8 {
+                let [a, b, c] = [0, 1, 2].map(|step| layer[(corner + step) % 8]);
+                if corner % 2 == 0 {
+                    assert!(!counter_clockwise(&a, &b, &c) && !counter_clockwise(&a, &c, &b));
+                } else {
+                    assert!(counter_clockwise(&a, &b, &c));
+                }
+            }
+        }
+        assert_eq!(layers[4], vec![Point2 { x: 0., y: 0. }]);
+
+        let mut next = xorshift(73);
+        let points: Vec<Point2> = (0..500)
+            .map(|_| Point2 {
+                x: next() * 10.,
+                y: next() * 10.,
+            })
+            .collect();
+        let layers = convex_layers(&points);
+        let mut peeled: Vec<Point2> = layers.concat();
+        let mut expected = points.clone();
+        peeled.sort_by(cmp_points);
+        expected.sort_by(cmp_points);
+        assert_eq!(peeled, expected);
+        assert!(layers.len() > 5);
+
+        // A collinear leftover is one last layer
+        let layers = convex_layers(&[
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 10., y: 0. },
+            Point2 { x: 5., y: 10. },
+            Point2 { x: 6., y: 3. },
+            Point2 { x: 4., y: 3. },
+            Point2 { x: 5., y: 3. },
+        ]);
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[1].len(), 3);
+        assert_eq!(layers[1][0], Point2 { x: 4., y: 3. });
+    }
+}
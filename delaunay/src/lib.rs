@@ -1,3 +1,85 @@
-mod edge;
+#![cfg_attr(all(feature = "no_std", not(test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(all(
+    feature = "no_std",
+    not(test),
+    any(
+        feature = "cabi",
+        feature = "exact",
+        feature = "rand",
+        feature = "rayon"
+    )
+))]
+compile_error!("The no_std feature can't be combined with the cabi, exact, rand or rayon features");
+
+// Modules using hash maps or float functions of std are left out of no_std builds, tests always
+// have std
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod adjacency;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod buffers;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod builder;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod cluster;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod constraints;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod dcel;
+pub mod edge;
+#[cfg(feature = "exact")]
+pub mod exact;
+#[cfg(feature = "cabi")]
+pub mod ffi;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod furthest;
 pub mod gns_delaunay;
-mod robust_float;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod halfedge;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod hull;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod integer;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod layers;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod locate;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod medial;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod nearest;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod periodic;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod polygon;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod preprocess;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod quality;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod range;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod regions;
+pub mod robust_float;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod sample;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod sphere;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod stream;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod strips;
+#[cfg(test)]
+pub(crate) mod test_util;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod tin;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod triangulation;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod voronoi;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(any(not(feature = "no_std"), test))]
+pub mod weighted;
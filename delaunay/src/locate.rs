@@ -0,0 +1,692 @@
+/// Point location with hints, for runs of nearby queries
+///
+/// Locating walks from a start triangle towards the point, the walk is short when the start is
+/// close. Without a hint, a few sites are sampled and the walk starts from the one nearest to the
+/// point (jump and walk). `Locator` keeps the last located triangle as the hint of the next query.
+/// For scattered queries, `TriangleIndex` buckets the triangles in a grid over their bounding
+/// boxes and skips the walk, and `LocatorHierarchy` walks down coarser and coarser samples of the
+/// sites (Delaunay hierarchy).
+use crate::edge::EdgeRef;
+use crate::robust_float::{nearly_equals, point_in_triangle, Point2, Rect, Region};
+use crate::triangulation::{splitmix64, Location, Triangulation};
+
+/// One site in this many goes up to the next level of a `LocatorHierarchy`
+pub const HIERARCHY_RATIO: u64 = 30;
+
+/// Levels stop once they have at most this many sites
+const HIERARCHY_TOP: usize = 2 * HIERARCHY_RATIO as usize;
+
+/// Walks crossing more than this many times the square root of the number of sites are long,
+/// see `Triangulation::is_long_walk`
+pub const LONG_WALK_FACTOR: f64 = 4.;
+
+#[cfg(test)]
+thread_local! {
+    /// Faces `TriangleIndex::locate` tested the point against, for tests measuring work
+    pub(crate) static CANDIDATE_FACES: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+fn squared_distance(a: &Point2, b: &Point2) -> f64 {
+    (a.x - b.x).powi(2) + (a.y - b.y).powi(2)
+}
+
+impl Triangulation {
+    /// Locate `point`, starting the walk from the nearest of about `n^(1/3)` sampled sites. A
+    /// walk that doesn't end, in a corrupt or not Delaunay mesh, falls back to a linear scan of
+    /// the faces, counted by `walk_fallbacks`.
+    ///
+    /// Panics if the triangulation has no triangle.
+    pub fn locate(&self, point: Point2) -> Location {
+        let start = self
+            .jump_start(point)
+            .or_else(|| self.any_triangle_edge())
+            .expect("The triangulation has no triangle");
+        self.walk(&point, start)
+    }
+
+    /// Locate `point`, starting the walk from the triangle on either side of `hint`. A hint that
+    /// isn't a live edge of this mesh, or that has no triangle around it, is ignored and the walk
+    /// starts like `locate`.
+    ///
+    /// Panics if the triangulation has no triangle.
+    pub fn locate_from(&self, hint: EdgeRef, point: Point2) -> Location {
+        if !hint.is_live(self.quad_arena()) {
+            return self.locate(point);
+        }
+        match [hint, hint.sym()]
+            .into_iter()
+            .find(|&edge| self.left_triangle(edge).is_some())
+        {
+            Some(start) => self.walk(&point, start),
+            None => self.locate(point),
+        }
+    }
+
+    /// Like `locate`, also returning the edges the walk crossed, in order, each as seen from the
+    /// triangle it leads to. For debugging and drawing walks. A long walk, see `is_long_walk`,
+    /// only returns its first `long_walk_length` edges, and is logged as a warning with the
+    /// `log` feature.
+    ///
+    /// Panics if the triangulation has no triangle.
+    pub fn locate_traced(&self, point: Point2) -> (Location, Vec<EdgeRef>) {
+        let start = self
+            .jump_start(point)
+            .or_else(|| self.any_triangle_edge())
+            .expect("The triangulation has no triangle");
+        let mut path = vec![];
+        let location = self.walk_traced(&point, start, Some(&mut path));
+        (location, path)
+    }
+
+    /// Like `locate_from`, also returning the edges the walk crossed like `locate_traced`
+    ///
+    /// Panics if the triangulation has no triangle.
+    pub fn locate_traced_from(&self, hint: EdgeRef, point: Point2) -> (Location, Vec<EdgeRef>) {
+        let start = [hint, hint.sym()]
+            .into_iter()
+            .find(|&edge| edge.is_live(self.quad_arena()) && self.left_triangle(edge).is_some());
+        let Some(start) = start else {
+            return self.locate_traced(point);
+        };
+        let mut path = vec![];
+        let location = self.walk_traced(&point, start, Some(&mut path));
+        (location, path)
+    }
+
+    /// Whether a walk crossing `path` is suspiciously long: more than `LONG_WALK_FACTOR` times
+    /// the square root of the number of sites, the length of a straight walk across a mesh of
+    /// evenly spread sites. Long walks come from far hints, or from long and thin triangles.
+    pub fn is_long_walk(&self, path: &[EdgeRef]) -> bool {
+        path.len() >= self.long_walk_length()
+    }
+
+    /// Length of the shortest long walk, where `locate_traced` cuts the path
+    pub fn long_walk_length(&self) -> usize {
+        (LONG_WALK_FACTOR * (self.points().len() as f64).sqrt()) as usize + 1
+    }
+
+    /// Edge with a triangle on its left, leaving the sampled site nearest to `point`
+    fn jump_start(&self, point: Point2) -> Option<EdgeRef> {
+        let count = self.points().len();
+        let samples = (count as f64).cbrt() as usize + 1;
+        let seed = splitmix64(point.x.to_bits() ^ splitmix64(point.y.to_bits()));
+        (0..samples as u64)
+            .map(|idx| (splitmix64(seed.wrapping_add(idx)) % count.max(1) as u64) as usize)
+            .filter(|&vertex| vertex < count && !self.is_removed(vertex))
+            .filter_map(|vertex| {
+                let edge = self
+                    .onext_ring(vertex)
+                    .find(|&edge| self.left_triangle(edge).is_some())?;
+                Some((squared_distance(&self.points()[vertex], &point), edge))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, edge)| edge)
+    }
+}
+
+/// Locates points one after the other, starting every walk where the previous one ended
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Locator {
+    last: Option<EdgeRef>,
+}
+
+impl Locator {
+    pub fn new() -> Locator {
+        Locator::default()
+    }
+
+    /// Locate `point`, walking from the last located triangle. The locator can be used with a
+    /// single triangulation at a time, edits of the mesh are fine.
+    ///
+    /// Panics if the triangulation has no triangle.
+    pub fn locate(&mut self, triangulation: &Triangulation, point: Point2) -> Location {
+        let location = match self.last {
+            Some(hint) => triangulation.locate_from(hint, point),
+            None => triangulation.locate(point),
+        };
+        match location {
+            Location::Inside(edge) | Location::OnEdge(edge) => self.last = Some(edge),
+            Location::Outside(edge) => self.last = Some(edge.sym()),
+            Location::Vertex(_) => {}
+        }
+        location
+    }
+}
+
+/// Uniform grid over the bounding boxes of the triangles of a triangulation, see
+/// `Triangulation::build_index`
+#[derive(Clone, Debug)]
+pub struct TriangleIndex {
+    /// Every triangle, as the edge it is on the left of and its corners
+    faces: Vec<(EdgeRef, [Point2; 3])>,
+    bounds: Rect,
+    /// Number of columns and rows of the grid
+    side: usize,
+    /// Faces of cell `i` are `entries[starts[i]..starts[i + 1]]`
+    starts: Vec<usize>,
+    entries: Vec<usize>,
+}
+
+impl TriangleIndex {
+    /// Cells covered by the rectangle, as column and row ranges
+    fn cell_range(&self, min: &Point2, max: &Point2) -> (usize, usize, usize, usize) {
+        let column = |x: f64| {
+            let t = (x - self.bounds.min.x) / self.bounds.width();
+            ((t * self.side as f64) as usize).min(self.side - 1)
+        };
+        let row = |y: f64| {
+            let t = (y - self.bounds.min.y) / self.bounds.height();
+            ((t * self.side as f64) as usize).min(self.side - 1)
+        };
+        (column(min.x), column(max.x), row(min.y), row(max.y))
+    }
+
+    /// Locate `point` in `triangulation`, which must be the one the index was built from.
+    ///
+    /// Points inside of a triangle or on one of its corners are answered from the grid. Points on
+    /// an edge, outside of every triangle, or in a triangle changed since the index was built
+    /// are located with `Triangulation::locate`, so edits of the mesh only make the index slower.
+    ///
+    /// Panics if the triangulation has no triangle.
+    pub fn locate(&self, triangulation: &Triangulation, point: Point2) -> Location {
+        let inside_bounds = point.x >= self.bounds.min.x
+            && point.x <= self.bounds.max.x
+            && point.y >= self.bounds.min.y
+            && point.y <= self.bounds.max.y;
+        if inside_bounds && !self.faces.is_empty() {
+            let (column, _, row, _) = self.cell_range(&point, &point);
+            let cell = row * self.side + column;
+            for &face in &self.entries[self.starts[cell]..self.starts[cell + 1]] {
+                let (edge, [a, b, c]) = self.faces[face];
+                #[cfg(test)]
+                CANDIDATE_FACES.with(|count| count.set(count.get() + 1));
+                if point_in_triangle(point, a, b, c) != Region::Inside {
+                    continue;
+                }
+                if triangulation.left_triangle(edge) != Some([a, b, c])
+                    || !edge.is_live(triangulation.quad_arena())
+                {
+                    break;
+                }
+                // The walk snaps near-equal points to the site
+                if let Some(corner) = [a, b, c]
+                    .into_iter()
+                    .find(|corner| nearly_equals(corner, &point))
+                {
+                    return Location::Vertex(triangulation.vertex_index(&corner).unwrap());
+                }
+                return Location::Inside(edge);
+            }
+        }
+        triangulation.locate(point)
+    }
+}
+
+/// Triangulations of nested random samples of the sites of a triangulation, see
+/// `Triangulation::build_hierarchy`
+#[derive(Default)]
+pub struct LocatorHierarchy {
+    /// Coarsest level last, every level holds about one site in `HIERARCHY_RATIO` of the one
+    /// below, the first one of the triangulation itself
+    levels: Vec<Triangulation>,
+}
+
+impl LocatorHierarchy {
+    /// Sample the live sites of `triangulation` level after level, see
+    /// `Triangulation::build_hierarchy`
+    pub(crate) fn new(triangulation: &Triangulation) -> LocatorHierarchy {
+        let mut levels: Vec<Triangulation> = vec![];
+        let mut sites: Vec<Point2> = (0..triangulation.points().len())
+            .filter(|&vertex| !triangulation.is_removed(vertex))
+            .map(|vertex| triangulation.points()[vertex])
+            .filter(|&point| !triangulation.is_ghost(point))
+            .collect();
+        for depth in 0u64.. {
+            let sample: Vec<Point2> = sites
+                .iter()
+                .filter(|point| {
+                    let key = point.x.to_bits() ^ splitmix64(point.y.to_bits() ^ depth);
+                    splitmix64(key).is_multiple_of(HIERARCHY_RATIO)
+                })
+                .copied()
+                .collect();
+            if sites.len() <= HIERARCHY_TOP || sample.len() < 3 {
+                break;
+            }
+            levels.push(Triangulation::new(&sample));
+            sites = sample;
+        }
+        LocatorHierarchy { levels }
+    }
+
+    /// Number of levels above the triangulation
+    pub fn levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Locate `point` in `triangulation`, which must be the one the hierarchy belongs to.
+    ///
+    /// The point is located in the coarsest level, then every level walks from the site of the
+    /// level above nearest to the point, which is a few triangles away, down to the triangulation.
+    ///
+    /// Panics if the triangulation has no triangle.
+    pub fn locate(&self, triangulation: &Triangulation, point: Point2) -> Location {
+        let mut nearest = None;
+        for level in self.levels.iter().rev().chain([triangulation]) {
+            let hint = nearest.and_then(|site: Point2| {
+                let vertex = level.vertex_index(&site)?;
+                level.onext_ring(vertex).next()
+            });
+            let location = match hint {
+                Some(hint) => level.locate_from(hint, point),
+                None if level.any_triangle_edge().is_some() => level.locate(point),
+                None => continue,
+            };
+            if std::ptr::eq(level, triangulation) {
+                return location;
+            }
+            nearest = Some(nearest_corner(level, location, &point));
+        }
+        triangulation.locate(point)
+    }
+}
+
+/// Corner of the located triangle or hull edge nearest to `point`
+fn nearest_corner(triangulation: &Triangulation, location: Location, point: &Point2) -> Point2 {
+    let arena = triangulation.quad_arena();
+    let corners = match location {
+        Location::Vertex(vertex) => return triangulation.points()[vertex],
+        Location::Inside(edge) | Location::OnEdge(edge) => {
+            match triangulation.left_triangle(edge) {
+                Some(corners) => corners.to_vec(),
+                None => vec![edge.org(arena), edge.dest(arena)],
+            }
+        }
+        Location::Outside(edge) => vec![edge.org(arena), edge.dest(arena)],
+    };
+    corners
+        .into_iter()
+        .min_by(|a, b| squared_distance(a, point).total_cmp(&squared_distance(b, point)))
+        .unwrap()
+}
+
+impl Triangulation {
+    /// Build a `TriangleIndex` over the triangles of the mesh, with about one triangle per grid
+    /// cell. Build it again after editing the mesh, stale triangles are only detected.
+    pub fn build_index(&self) -> TriangleIndex {
+        let faces: Vec<(EdgeRef, [Point2; 3])> = self
+            .live_edges()
+            .flat_map(|edge| [edge, edge.sym()])
+            .filter_map(|edge| {
+                let corners = self.left_triangle(edge)?;
+                let second = edge.lnext(self.quad_arena());
+                (edge < second && edge < second.lnext(self.quad_arena())).then_some((edge, corners))
+            })
+            .collect();
+        let corners = || faces.iter().flat_map(|(_, corners)| corners.iter());
+        let bounds = Rect {
+            min: Point2 {
+                x: corners().map(|p| p.x).fold(f64::INFINITY, f64::min),
+                y: corners().map(|p| p.y).fold(f64::INFINITY, f64::min),
+            },
+            max: Point2 {
+                x: corners().map(|p| p.x).fold(f64::NEG_INFINITY, f64::max),
+                y: corners().map(|p| p.y).fold(f64::NEG_INFINITY, f64::max),
+            },
+        };
+        let side = ((faces.len() as f64).sqrt() as usize).max(1);
+        let mut index = TriangleIndex {
+            faces: vec![],
+            bounds,
+            side,
+            starts: vec![0; side * side + 1],
+            entries: vec![],
+        };
+        // Count the faces of every cell, then fill the cells in place
+        let ranges: Vec<(usize, usize, usize, usize)> = faces
+            .iter()
+            .map(|(_, [a, b, c])| {
+                let min = Point2 {
+                    x: a.x.min(b.x).min(c.x),
+                    y: a.y.min(b.y).min(c.y),
+                };
+                let max = Point2 {
+                    x: a.x.max(b.x).max(c.x),
+                    y: a.y.max(b.y).max(c.y),
+                };
+                index.cell_range(&min, &max)
+            })
+            .collect();
+        for &(first_column, last_column, first_row, last_row) in &ranges {
+            for row in first_row..=last_row {
+                for column in first_column..=last_column {
+                    index.starts[row * side + column + 1] += 1;
+                }
+            }
+        }
+        for cell in 0..side * side {
+            index.starts[cell + 1] += index.starts[cell];
+        }
+        let mut fill = index.starts.clone();
+        index.entries = vec![0; index.starts[side * side]];
+        for (face, &(first_column, last_column, first_row, last_row)) in ranges.iter().enumerate() {
+            for row in first_row..=last_row {
+                for column in first_column..=last_column {
+                    let cell = row * side + column;
+                    index.entries[fill[cell]] = face;
+                    fill[cell] += 1;
+                }
+            }
+        }
+        index.faces = faces;
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robust_float::{sort_points, ORIENTATION_TESTS};
+    #[cfg(feature = "log")]
+    use crate::test_util::take_warnings;
+    use crate::test_util::xorshift;
+
+    /// Located triangle or site, independent of the edge the walk ended on
+    fn normalize(triangulation: &Triangulation, location: Location) -> Vec<Point2> {
+        let mut corners = match location {
+            Location::Vertex(vertex) => return vec![triangulation.points()[vertex]],
+            Location::Inside(edge) | Location::OnEdge(edge) => {
+                triangulation.left_triangle(edge).unwrap().to_vec()
+            }
+            Location::Outside(edge) => {
+                let (org, dest) = edge.org_dest(triangulation.quad_arena());
+                vec![org, dest]
+            }
+        };
+        sort_points(&mut corners);
+        corners
+    }
+
+    #[test]
+    fn test_hinted_sweep() {
+        let mut next = xorshift(23);
+        let points: Vec<Point2> = (0..100_000)
+            .map(|_| Point2 {
+                x: next() * 1000.,
+                y: next() * 1000.,
+            })
+            .collect();
+        let triangulation = Triangulation::new(&points);
+        // Serpentine path over the square, 100 rows of 100 queries
+        let queries: Vec<Point2> = (0..10_000)
+            .map(|idx| {
+                let (row, col) = (idx / 100, idx % 100);
+                let col = if row % 2 == 0 { col } else { 99 - col };
+                Point2 {
+                    x: col as f64 * 10. + 5.,
+                    y: row as f64 * 10. + 5.,
+                }
+            })
+            .collect();
+
+        let start = triangulation.any_triangle_edge().unwrap();
+        let before = ORIENTATION_TESTS.with(|count| count.get());
+        let plain: Vec<Vec<Point2>> = queries
+            .iter()
+            .map(|query| normalize(&triangulation, triangulation.walk(query, start)))
+            .collect();
+        let plain_tests = ORIENTATION_TESTS.with(|count| count.get()) - before;
+
+        let mut locator = Locator::new();
+        let before = ORIENTATION_TESTS.with(|count| count.get());
+        let hinted: Vec<Vec<Point2>> = queries
+            .iter()
+            .map(|&query| normalize(&triangulation, locator.locate(&triangulation, query)))
+            .collect();
+        let hinted_tests = ORIENTATION_TESTS.with(|count| count.get()) - before;
+        assert_eq!(hinted, plain);
+        assert!(hinted_tests * 20 < plain_tests);
+
+        // The jump alone beats the plain walk too, on every tenth query
+        let before = ORIENTATION_TESTS.with(|count| count.get());
+        for query in queries.iter().step_by(10) {
+            triangulation.locate(*query);
+        }
+        let jump_tests = ORIENTATION_TESTS.with(|count| count.get()) - before;
+        assert!(jump_tests * 10 * 5 < plain_tests);
+    }
+
+    #[test]
+    fn test_locate_traced() {
+        let mut next = xorshift(47);
+        let points: Vec<Point2> = (0..4000)
+            .map(|_| Point2 {
+                x: next() * 100.,
+                y: next() * 100.,
+            })
+            .collect();
+        let triangulation = Triangulation::new(&points);
+        let target = Point2 { x: 90.5, y: 90.5 };
+        let near = triangulation.locate_traced_from(
+            triangulation.nearest_face(Point2 { x: 90., y: 90. }),
+            target,
+        );
+        let corner = triangulation.nearest_face(Point2 { x: 1., y: 1. });
+        let far = triangulation.locate_traced_from(corner, target);
+        assert_eq!(near.0, triangulation.locate(target));
+        assert_eq!(
+            normalize(&triangulation, far.0),
+            normalize(&triangulation, near.0)
+        );
+        assert!(near.1.len() < 5);
+        assert!(far.1.len() > 50);
+        assert!(!triangulation.is_long_walk(&far.1));
+
+        // The path goes from triangle to triangle, and ends in the located one
+        let mut face = corner;
+        for &crossed in &far.1 {
+            let arena = triangulation.quad_arena();
+            let second = face.lnext(arena);
+            assert!([face, second, second.lnext(arena)].contains(&crossed.sym()));
+            face = crossed;
+        }
+        assert_eq!(
+            normalize(&triangulation, Location::Inside(face)),
+            normalize(&triangulation, far.0)
+        );
+        let (location, path) = triangulation.locate_traced(target);
+        assert_eq!(location, triangulation.locate(target));
+        assert!(!triangulation.is_long_walk(&path));
+    }
+
+    #[test]
+    fn test_long_walk_cut() {
+        // A strip of thin triangles, walked end to end
+        let points: Vec<Point2> = (0..1_000)
+            .map(|i| Point2 {
+                x: (i / 2) as f64,
+                y: (i % 2) as f64,
+            })
+            .collect();
+        let triangulation = Triangulation::new(&points);
+        let target = Point2 { x: 498.5, y: 0.5 };
+        let start = triangulation.nearest_face(Point2 { x: 0.5, y: 0.5 });
+        #[cfg(feature = "log")]
+        take_warnings();
+        let (location, path) = triangulation.locate_traced_from(start, target);
+        assert_eq!(
+            normalize(&triangulation, location),
+            normalize(&triangulation, triangulation.locate(target))
+        );
+        assert_eq!(path.len(), triangulation.long_walk_length());
+        assert!(triangulation.is_long_walk(&path));
+        #[cfg(feature = "log")]
+        assert_eq!(take_warnings().len(), 1);
+        assert_eq!(triangulation.walk_fallbacks(), 0);
+    }
+
+    #[test]
+    fn test_stale_hint() {
+        let points: Vec<Point2> = (0..25)
+            .map(|i| Point2 {
+                x: (i % 5) as f64,
+                y: (i / 5) as f64,
+            })
+            .collect();
+        let mut triangulation = Triangulation::new(&points);
+        let query = Point2 { x: 3.2, y: 1.7 };
+        let hint = triangulation.onext_ring(12).next().unwrap();
+        let expected = normalize(&triangulation, triangulation.locate(query));
+        assert_eq!(
+            normalize(&triangulation, triangulation.locate_from(hint, query)),
+            expected
+        );
+        // The hint's quad edge is deleted with the site, it's ignored
+        triangulation.remove_vertex(12);
+        assert!(!hint.is_live(triangulation.quad_arena()));
+        let location = triangulation.locate_from(hint, query);
+        assert_eq!(
+            normalize(&triangulation, location),
+            normalize(&triangulation, triangulation.locate(query))
+        );
+    }
+
+    #[test]
+    fn test_hierarchy() {
+        let mut next = xorshift(89);
+        let mut points = |count: usize| -> Vec<Point2> {
+            (0..count)
+                .map(|_| Point2 {
+                    x: next() * 1000.,
+                    y: next() * 1000.,
+                })
+                .collect()
+        };
+        let small = Triangulation::new(&points(2_000));
+        let large = Triangulation::new(&points(100_000));
+        // Scattered queries, some of them outside of the hull or on a site
+        let mut queries: Vec<Point2> = points(100_000)
+            .into_iter()
+            .map(|point| Point2 {
+                x: point.x * 1.1 - 50.,
+                y: point.y * 1.1 - 50.,
+            })
+            .collect();
+        queries.extend_from_slice(&large.points()[..100]);
+
+        // Located triangle or site, outside of the hull any hull edge facing the point will do
+        let located = |triangulation: &Triangulation, location: Location| match location {
+            Location::Outside(_) => vec![],
+            location => normalize(triangulation, location),
+        };
+
+        // Same locations as the flat walk, and orientation tests over every 10th query from a
+        // fixed start, over every query through the hierarchy
+        let mut costs = vec![];
+        for triangulation in [&small, &large] {
+            let hierarchy = triangulation.build_hierarchy();
+            assert!(hierarchy.levels() >= 1);
+            // Built once
+            assert!(std::ptr::eq(hierarchy, triangulation.build_hierarchy()));
+            for &query in &queries {
+                assert_eq!(
+                    located(triangulation, hierarchy.locate(triangulation, query)),
+                    located(triangulation, triangulation.locate(query))
+                );
+            }
+            let start = triangulation.any_triangle_edge().unwrap();
+            let before = ORIENTATION_TESTS.with(|count| count.get());
+            for query in queries.iter().step_by(10) {
+                triangulation.walk(query, start);
+            }
+            let walk_tests = ORIENTATION_TESTS.with(|count| count.get()) - before;
+            let before = ORIENTATION_TESTS.with(|count| count.get());
+            for query in &queries {
+                hierarchy.locate(triangulation, *query);
+            }
+            let hierarchy_tests = ORIENTATION_TESTS.with(|count| count.get()) - before;
+            let walk_per_query = walk_tests as f64 / queries.iter().step_by(10).len() as f64;
+            let per_query = hierarchy_tests as f64 / queries.len() as f64;
+            // A walk of a few dozen tests on each level, and log n / log HIERARCHY_RATIO levels
+            let sites = triangulation.points().len() as f64;
+            let log_bound = 40. * (sites.ln() / (HIERARCHY_RATIO as f64).ln() + 1.);
+            assert!(per_query < log_bound, "{} tests a query", per_query);
+            costs.push((walk_per_query, per_query));
+        }
+        let (small_walk, _) = costs[0];
+        let (large_walk, large_hierarchy) = costs[1];
+        assert!(large_hierarchy * 10. < large_walk);
+        // 50 times the sites: the walk grows like the square root
+        assert!(large_walk > small_walk * 5.);
+
+        // Removals drop the levels, the next use samples the remaining sites again
+        let mut triangulation = small;
+        assert!(triangulation.build_hierarchy().levels() >= 1);
+        for vertex in (0..2_000).step_by(3) {
+            triangulation.remove_vertex(vertex);
+        }
+        let hierarchy = triangulation.build_hierarchy();
+        for level in &hierarchy.levels {
+            for site in level.points() {
+                let vertex = triangulation.vertex_index(site).unwrap();
+                assert!(!triangulation.is_removed(vertex));
+            }
+        }
+        for query in queries.iter().take(2000) {
+            assert_eq!(
+                located(&triangulation, hierarchy.locate(&triangulation, *query)),
+                located(&triangulation, triangulation.locate(*query))
+            );
+        }
+    }
+
+    #[test]
+    fn test_triangle_index() {
+        let mut next = xorshift(79);
+        let points: Vec<Point2> = (0..5_000)
+            .map(|_| Point2 {
+                x: next() * 1000.,
+                y: next() * 1000.,
+            })
+            .collect();
+        let mut triangulation = Triangulation::new(&points);
+        let index = triangulation.build_index();
+        // Scattered queries, some of them outside of the hull or on a site
+        let mut queries: Vec<Point2> = (0..100_000)
+            .map(|_| Point2 {
+                x: next() * 1100. - 50.,
+                y: next() * 1100. - 50.,
+            })
+            .collect();
+        queries.extend_from_slice(&points[..100]);
+
+        let before = ORIENTATION_TESTS.with(|count| count.get());
+        let walked: Vec<Vec<Point2>> = queries
+            .iter()
+            .map(|&query| normalize(&triangulation, triangulation.locate(query)))
+            .collect();
+        let walk_tests = ORIENTATION_TESTS.with(|count| count.get()) - before;
+        let before =
+            ORIENTATION_TESTS.with(|count| count.get()) + CANDIDATE_FACES.with(|count| count.get());
+        let indexed: Vec<Vec<Point2>> = queries
+            .iter()
+            .map(|&query| normalize(&triangulation, index.locate(&triangulation, query)))
+            .collect();
+        // Faces tested in the grid cells, plus the walks of the points it doesn't answer
+        let index_tests = ORIENTATION_TESTS.with(|count| count.get())
+            + CANDIDATE_FACES.with(|count| count.get())
+            - before;
+        assert_eq!(indexed, walked);
+        assert!(index_tests * 3 < walk_tests);
+
+        // Stale triangles fall back to the walk
+        let vertex = triangulation.vertex_index(&points[0]).unwrap();
+        triangulation.remove_vertex(vertex);
+        for query in queries.iter().take(2000) {
+            assert_eq!(
+                normalize(&triangulation, index.locate(&triangulation, *query)),
+                normalize(&triangulation, triangulation.locate(*query))
+            );
+        }
+    }
+}
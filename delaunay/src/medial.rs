@@ -0,0 +1,393 @@
+/// Approximate medial axis of a polygon, for the centerline of road or river outlines
+///
+/// The boundary is sampled densely and triangulated. The Voronoi vertices of the samples lying
+/// inside the polygon approach its medial axis as the samples get closer, so the Voronoi edges
+/// with both ends strictly inside the polygon are kept. Samples near a corner add short branches
+/// going to it, `prune_branches` cuts them.
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::cluster::UnionFind;
+use crate::robust_float::{orient2d_value, OrderedPoint2, Point2};
+use crate::triangulation::Triangulation;
+use crate::voronoi::Segment;
+
+/// Segments shorter than this, relative to the largest coordinate, join their two ends into one
+/// node. Cocircular samples, like the ones facing each other across a strip, give Voronoi edges
+/// whose ends only differ by the rounding of their circumcenters.
+const CONTRACT_TOLERANCE: f64 = 1e-9;
+
+/// Points every `spacing` or closer along the sides of the polygon, its vertices included
+fn resample(polygon: &[Point2], spacing: f64) -> Vec<Point2> {
+    let mut samples = vec![];
+    for (idx, a) in polygon.iter().enumerate() {
+        let b = &polygon[(idx + 1) % polygon.len()];
+        let pieces = (a.distance(b) / spacing).ceil().max(1.) as usize;
+        samples.extend((0..pieces).map(|piece| {
+            let t = piece as f64 / pieces as f64;
+            Point2 {
+                x: a.x + (b.x - a.x) * t,
+                y: a.y + (b.y - a.y) * t,
+            }
+        }));
+    }
+    samples
+}
+
+/// Return true if `point` is inside the polygon and not on its boundary, with the crossing
+/// number of a ray going along +x. Sides are tested with exact orientations.
+fn strictly_inside(polygon: &[Point2], point: &Point2) -> bool {
+    let mut inside = false;
+    for (idx, a) in polygon.iter().enumerate() {
+        let b = &polygon[(idx + 1) % polygon.len()];
+        let side = orient2d_value(a, b, point);
+        let between = point.x >= a.x.min(b.x)
+            && point.x <= a.x.max(b.x)
+            && point.y >= a.y.min(b.y)
+            && point.y <= a.y.max(b.y);
+        if side == 0. && between {
+            return false;
+        }
+        // The ray crosses the side when the point is on its left going up, on its right going
+        // down, as `orient2d_value` sees them in a y-up frame
+        if (a.y > point.y) != (b.y > point.y) && (side > 0.) == (b.y > a.y) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Approximate medial axis of the simple polygon bounded by `polygon`, given in either winding
+/// without repeating its first vertex: the Voronoi edges of samples taken every
+/// `sample_spacing` or closer along the boundary, kept when both of their ends are strictly
+/// inside the polygon. Smaller spacings follow the axis closer, with more and shorter segments.
+///
+/// Near convex corners the axis gets short branches going to them, `prune_branches` removes
+/// them, then `stitch_polylines` joins the segments.
+///
+/// Panics if `sample_spacing` isn't positive.
+pub fn medial_axis(polygon: &[Point2], sample_spacing: f64) -> Vec<Segment> {
+    assert!(sample_spacing > 0., "The sample spacing must be positive");
+    let triangulation = Triangulation::new(&resample(polygon, sample_spacing));
+    let Some(bounds) = triangulation.bounds() else {
+        return vec![];
+    };
+    // Hull rays end on the bounds of the samples, which are never strictly inside
+    let (_, dual) = triangulation.primal_and_dual(bounds);
+    dual.into_iter()
+        .filter(|(start, end)| strictly_inside(polygon, start) && strictly_inside(polygon, end))
+        .collect()
+}
+
+/// Segments as a graph of their ends
+struct AxisGraph {
+    /// Position of every node
+    nodes: Vec<Point2>,
+    /// Neighbors of every node, with the index of the segment going there
+    adjacency: Vec<Vec<(usize, usize)>>,
+    /// Nodes of the ends of every segment, the same one for contracted segments
+    ends: Vec<(usize, usize)>,
+}
+
+impl AxisGraph {
+    /// Graph of the segments, with ends of equal coordinates as one node and the ends of
+    /// segments shorter than `CONTRACT_TOLERANCE` joined
+    fn new(segments: &[Segment]) -> AxisGraph {
+        let scale = segments
+            .iter()
+            .flat_map(|(a, b)| [a.x.abs(), a.y.abs(), b.x.abs(), b.y.abs()])
+            .fold(0., f64::max);
+        let mut ids: HashMap<OrderedPoint2, usize> = HashMap::new();
+        let mut points = vec![];
+        let mut id = |point: Point2| {
+            *ids.entry(point.ordered()).or_insert_with(|| {
+                points.push(point);
+                points.len() - 1
+            })
+        };
+        let raw_ends: Vec<(usize, usize)> = segments.iter().map(|&(a, b)| (id(a), id(b))).collect();
+
+        let mut sets = UnionFind::new(points.len());
+        for ((a, b), &(start, end)) in segments.iter().zip(&raw_ends) {
+            if a.distance(b) <= scale * CONTRACT_TOLERANCE {
+                sets.union(start, end);
+            }
+        }
+        let mut node_of = vec![usize::MAX; points.len()];
+        let mut nodes = vec![];
+        for raw in 0..points.len() {
+            let root = sets.find(raw);
+            if node_of[root] == usize::MAX {
+                node_of[root] = nodes.len();
+                nodes.push(points[root]);
+            }
+        }
+        let ends: Vec<(usize, usize)> = raw_ends
+            .iter()
+            .map(|&(start, end)| (node_of[sets.find(start)], node_of[sets.find(end)]))
+            .collect();
+        let mut adjacency = vec![vec![]; nodes.len()];
+        for (segment, &(start, end)) in ends.iter().enumerate() {
+            if start != end {
+                adjacency[start].push((end, segment));
+                adjacency[end].push((start, segment));
+            }
+        }
+        AxisGraph {
+            nodes,
+            adjacency,
+            ends,
+        }
+    }
+
+    fn degree(&self, node: usize) -> usize {
+        self.adjacency[node].len()
+    }
+
+    /// Chains of segments between nodes of another degree than 2, then the closed loops left,
+    /// which start and end at the same node. Contracted segments are in no branch.
+    fn branches(&self) -> Vec<Branch> {
+        let mut visited = vec![false; self.ends.len()];
+        let mut branches = vec![];
+        let ends = (0..self.nodes.len()).filter(|&node| self.degree(node) != 2);
+        let loops = (0..self.nodes.len()).filter(|&node| self.degree(node) == 2);
+        for start in ends.chain(loops) {
+            for &(next, segment) in &self.adjacency[start] {
+                if visited[segment] {
+                    continue;
+                }
+                let mut branch = Branch {
+                    nodes: vec![start],
+                    segments: vec![],
+                    length: 0.,
+                };
+                let mut step = Some((next, segment));
+                while let Some((node, segment)) = step {
+                    visited[segment] = true;
+                    let last = *branch.nodes.last().unwrap();
+                    branch.length += self.nodes[last].distance(&self.nodes[node]);
+                    branch.nodes.push(node);
+                    branch.segments.push(segment);
+                    step = if self.degree(node) == 2 {
+                        self.adjacency[node]
+                            .iter()
+                            .copied()
+                            .find(|&(_, segment)| !visited[segment])
+                    } else {
+                        None
+                    };
+                }
+                branches.push(branch);
+            }
+        }
+        branches
+    }
+}
+
+/// Chain of segments of an `AxisGraph`
+struct Branch {
+    nodes: Vec<usize>,
+    segments: Vec<usize>,
+    length: f64,
+}
+
+/// Remove the spurs of the axis: the trees hanging from the rest of it whose farthest free end
+/// is less than `min_branch_length` away. A tree holding the whole part of the axis it's in,
+/// like a chain without junction, keeps its longest path instead.
+///
+/// Trees are peeled from their free ends inward, each node measuring the longest way to a free
+/// end below it, so a corner spur splitting in smaller ones goes as a whole.
+pub fn prune_branches(segments: &[Segment], min_branch_length: f64) -> Vec<Segment> {
+    let graph = AxisGraph::new(segments);
+    let branches = graph.branches();
+    let far_end = |branch: usize, node: usize| {
+        let nodes = &branches[branch].nodes;
+        if nodes[0] == node {
+            *nodes.last().unwrap()
+        } else {
+            nodes[0]
+        }
+    };
+    // Branches at the ends of every branch, a loop is there twice
+    let mut incident: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (idx, branch) in branches.iter().enumerate() {
+        incident.entry(branch.nodes[0]).or_default().push(idx);
+        incident
+            .entry(*branch.nodes.last().unwrap())
+            .or_default()
+            .push(idx);
+    }
+
+    let mut pending: HashMap<usize, usize> = incident
+        .iter()
+        .map(|(&node, branches)| (node, branches.len()))
+        .collect();
+    let mut queue: VecDeque<usize> = pending
+        .iter()
+        .filter(|(_, &count)| count == 1)
+        .map(|(&node, _)| node)
+        .collect();
+    let mut peeled: HashSet<usize> = HashSet::new();
+    // Longest way to a free end below every peeled node, and the branch going down it
+    let mut height: HashMap<usize, (f64, Option<usize>)> = HashMap::new();
+    let mut parents: Vec<(usize, usize)> = vec![];
+    let mut roots = vec![];
+    while let Some(node) = queue.pop_front() {
+        peeled.insert(node);
+        let up = incident[&node]
+            .iter()
+            .copied()
+            .find(|&branch| !peeled.contains(&far_end(branch, node)));
+        let Some(up) = up else {
+            // Every neighbor is peeled, the whole tree is
+            roots.push(node);
+            continue;
+        };
+        parents.push((node, up));
+        let parent = far_end(up, node);
+        let reach = height.get(&node).map_or(0., |&(reach, _)| reach) + branches[up].length;
+        let entry = height.entry(parent).or_insert((0., None));
+        if entry.1.is_none() || reach > entry.0 {
+            *entry = (reach, Some(up));
+        }
+        let count = pending.get_mut(&parent).unwrap();
+        *count -= 1;
+        if *count == 1 {
+            queue.push_back(parent);
+        }
+    }
+
+    let mut dropped = vec![false; branches.len()];
+    for &(node, up) in &parents {
+        let below = height.get(&node).map_or(0., |&(reach, _)| reach);
+        dropped[up] = below + branches[up].length < min_branch_length;
+    }
+    for root in roots {
+        if incident[&root].iter().all(|&branch| dropped[branch]) {
+            let mut node = root;
+            while let Some(&(_, Some(branch))) = height.get(&node) {
+                dropped[branch] = false;
+                node = far_end(branch, node);
+            }
+        }
+    }
+
+    let mut keep = vec![false; segments.len()];
+    let mut kept_nodes = HashSet::new();
+    for (branch, _) in branches
+        .iter()
+        .zip(&dropped)
+        .filter(|(_, &dropped)| !dropped)
+    {
+        for &segment in &branch.segments {
+            keep[segment] = true;
+        }
+        kept_nodes.extend(branch.nodes.iter().copied());
+    }
+    // Contracted segments stay with the node they are in
+    for (segment, &(start, end)) in graph.ends.iter().enumerate() {
+        if start == end && kept_nodes.contains(&start) {
+            keep[segment] = true;
+        }
+    }
+    segments
+        .iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(&segment, _)| segment)
+        .collect()
+}
+
+/// Join the segments into polylines going from a free end or a junction to the next one. Ends
+/// closer than the rounding of the Voronoi vertices are joined, and closed loops repeat their
+/// first point at the end.
+pub fn stitch_polylines(segments: &[Segment]) -> Vec<Vec<Point2>> {
+    let graph = AxisGraph::new(segments);
+    graph
+        .branches()
+        .iter()
+        .map(|branch| branch.nodes.iter().map(|&node| graph.nodes[node]).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_medial_axis() {
+        let rectangle = [
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 100., y: 0. },
+            Point2 { x: 100., y: 10. },
+            Point2 { x: 0., y: 10. },
+        ];
+        let axis = medial_axis(&rectangle, 0.5);
+        assert!(!axis.is_empty());
+        for (start, end) in &axis {
+            assert!(strictly_inside(&rectangle, start) && strictly_inside(&rectangle, end));
+        }
+        // The branches to the corners are 5 * sqrt(2) long
+        let polylines = stitch_polylines(&prune_branches(&axis, 10.));
+        assert_eq!(polylines.len(), 1);
+        let centerline = &polylines[0];
+        assert!(centerline.iter().all(|point| (point.y - 5.).abs() < 1e-6));
+        let (first, last) = (centerline[0], *centerline.last().unwrap());
+        let (left, right) = if first.x < last.x {
+            (first, last)
+        } else {
+            (last, first)
+        };
+        assert!((left.x - 5.).abs() < 0.5 && (right.x - 95.).abs() < 0.5);
+        // Without pruning the corner branches stay
+        assert!(stitch_polylines(&prune_branches(&axis, 1.)).len() > 1);
+
+        // Either winding
+        let mut reversed = rectangle;
+        reversed.reverse();
+        assert_eq!(medial_axis(&reversed, 0.5).len(), axis.len());
+        assert!(medial_axis(&[], 1.).is_empty());
+    }
+
+    #[test]
+    fn test_prune_and_stitch() {
+        let point = |x, y| Point2 { x, y };
+        let segments = [
+            (point(0., 0.), point(10., 0.)),
+            (point(20., 0.), point(10., 0.)),
+            (point(10., 0.), point(10., 1.)),
+            (point(10., 1.), point(10., 1.5)),
+            // Contracted, like the Voronoi edge of cocircular sites
+            (point(20., 0.), point(20., 1e-12)),
+        ];
+        let pruned = prune_branches(&segments, 2.);
+        assert_eq!(pruned, vec![segments[0], segments[1], segments[4]]);
+        let polylines = stitch_polylines(&pruned);
+        assert_eq!(polylines.len(), 1);
+        let mut line = polylines[0].clone();
+        if line[0].x > 0. {
+            line.reverse();
+        }
+        assert_eq!(line, vec![point(0., 0.), point(10., 0.), point(20., 0.)]);
+        // The spur is long enough to stay
+        assert_eq!(prune_branches(&segments, 1.).len(), segments.len());
+        assert_eq!(stitch_polylines(&segments).len(), 3);
+
+        // A star of short branches keeps its longest one
+        let star = [
+            (point(0., 0.), point(1., 0.)),
+            (point(0., 0.), point(0., 1.5)),
+            (point(0., 0.), point(-1., 0.)),
+        ];
+        assert_eq!(prune_branches(&star, 5.), vec![star[1]]);
+
+        let square = [
+            (point(0., 0.), point(1., 0.)),
+            (point(1., 0.), point(1., 1.)),
+            (point(1., 1.), point(0., 0.)),
+        ];
+        let loops = stitch_polylines(&square);
+        assert_eq!(loops.len(), 1);
+        assert_eq!(loops[0].len(), 4);
+        assert_eq!(loops[0][0], loops[0][3]);
+    }
+}
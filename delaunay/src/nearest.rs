@@ -0,0 +1,172 @@
+/// Nearest site queries walking the Delaunay graph
+///
+/// The nearest site of a point is reached by greedily moving to a closer neighbor, and the i-th
+/// nearest site is always a Delaunay neighbor of one of the i - 1 nearer ones. A best-first
+/// expansion from the nearest site thus finds the k nearest sites exactly while only looking at
+/// their neighborhoods.
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::robust_float::Point2;
+use crate::triangulation::Triangulation;
+
+/// Site reached by the expansion, the nearest one pops first
+struct Neighbor {
+    distance: f64,
+    vertex: usize,
+}
+
+impl PartialEq for Neighbor {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Neighbor {}
+
+impl PartialOrd for Neighbor {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Neighbor {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.distance.total_cmp(&self.distance)
+    }
+}
+
+fn squared_distance(a: &Point2, b: &Point2) -> f64 {
+    (a.x - b.x).powi(2) + (a.y - b.y).powi(2)
+}
+
+impl Triangulation {
+    /// Neighbors of a site in the mesh
+    fn neighbors(&self, vertex: usize) -> impl Iterator<Item = usize> + '_ {
+        self.onext_ring(vertex)
+            .map(|edge| self.vertex_index(&edge.dest(self.quad_arena())).unwrap())
+    }
+
+    /// Site nearest to `point`, None if the triangulation has no site left. Of sites at the same
+    /// distance, the one of lowest index.
+    pub fn nearest_site(&self, point: Point2) -> Option<usize> {
+        let start = if self.any_triangle_edge().is_some() {
+            let edge = self.nearest_face(point);
+            self.vertex_index(&edge.org(self.quad_arena())).unwrap()
+        } else {
+            (0..self.points().len()).find(|&vertex| !self.is_removed(vertex))?
+        };
+        Some(self.nearest_site_from(start, point))
+    }
+
+    /// Site nearest to `point`, greedily walking the Delaunay graph from the site `start`, then
+    /// going over the sites at the same distance for the one of lowest index. Sites tied for
+    /// nearest lie on an empty circle around `point`, the edges between them along the circle
+    /// are Delaunay edges.
+    pub(crate) fn nearest_site_from(&self, start: usize, point: Point2) -> usize {
+        let distance = |vertex: usize| squared_distance(&self.points()[vertex], &point);
+        let mut current = start;
+        loop {
+            let closer = self
+                .neighbors(current)
+                .min_by(|&a, &b| distance(a).total_cmp(&distance(b)))
+                .filter(|&neighbor| distance(neighbor) < distance(current));
+            match closer {
+                Some(neighbor) => current = neighbor,
+                None => break,
+            }
+        }
+        let nearest = distance(current);
+        let mut tied = vec![current];
+        let mut stack = vec![current];
+        while let Some(vertex) = stack.pop() {
+            for neighbor in self.neighbors(vertex) {
+                if distance(neighbor) == nearest && !tied.contains(&neighbor) {
+                    tied.push(neighbor);
+                    stack.push(neighbor);
+                }
+            }
+        }
+        tied.into_iter().min().unwrap()
+    }
+
+    /// The `k` sites nearest to `point`, nearest first. Less than `k` sites are returned when the
+    /// triangulation doesn't have that many.
+    pub fn k_nearest(&self, point: Point2, k: usize) -> Vec<usize> {
+        let mut nearest = Vec::with_capacity(k);
+        let Some(start) = self.nearest_site(point).filter(|_| k > 0) else {
+            return nearest;
+        };
+        let distance = |vertex: usize| squared_distance(&self.points()[vertex], &point);
+        let mut reached = vec![false; self.points().len()];
+        reached[start] = true;
+        let mut frontier = BinaryHeap::from([Neighbor {
+            distance: distance(start),
+            vertex: start,
+        }]);
+        while let Some(Neighbor { vertex, .. }) = frontier.pop() {
+            nearest.push(vertex);
+            if nearest.len() == k {
+                break;
+            }
+            for neighbor in self.neighbors(vertex) {
+                if !reached[neighbor] {
+                    reached[neighbor] = true;
+                    frontier.push(Neighbor {
+                        distance: distance(neighbor),
+                        vertex: neighbor,
+                    });
+                }
+            }
+        }
+        nearest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::xorshift;
+
+    #[test]
+    fn test_k_nearest_brute_force() {
+        let mut next = xorshift(31);
+        let points: Vec<Point2> = (0..300)
+            .map(|_| Point2 {
+                x: next() * 100.,
+                y: next() * 100.,
+            })
+            .collect();
+        let triangulation = Triangulation::new(&points);
+        let sites = triangulation.points();
+
+        for _ in 0..30 {
+            // Some queries fall outside of the hull
+            let query = Point2 {
+                x: next() * 140. - 20.,
+                y: next() * 140. - 20.,
+            };
+            let mut brute: Vec<usize> = (0..sites.len()).collect();
+            brute.sort_by(|&a, &b| {
+                squared_distance(&sites[a], &query).total_cmp(&squared_distance(&sites[b], &query))
+            });
+            for k in [1, 5, 17, 300, 400] {
+                let expected = &brute[..k.min(brute.len())];
+                assert_eq!(triangulation.k_nearest(query, k), expected);
+            }
+        }
+        assert!(triangulation.k_nearest(points[0], 0).is_empty());
+    }
+
+    #[test]
+    fn test_k_nearest_collinear() {
+        let points: Vec<Point2> = (0..10).map(|i| Point2 { x: i as f64, y: 0. }).collect();
+        let triangulation = Triangulation::new(&points);
+        let nearest = triangulation.k_nearest(Point2 { x: 6.2, y: 3. }, 3);
+        let nearest: Vec<f64> = nearest
+            .into_iter()
+            .map(|vertex| triangulation.points()[vertex].x)
+            .collect();
+        assert_eq!(nearest, [6., 7., 5.]);
+    }
+}
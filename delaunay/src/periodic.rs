@@ -0,0 +1,204 @@
+/// Triangulation of a periodic (toroidal) rectangular domain, where the left and right sides,
+/// and the top and bottom sides, are glued together
+///
+/// Uses the 3x3 tiling approach: the sites are copied in the 8 tiles around the domain, the
+/// tiling is triangulated, and only the edges with at least one end in the central tile are kept.
+/// This is exact as long as the sites are dense enough for every circumcircle of the central
+/// tile to stay within the tiling.
+use crate::robust_float::{sanitize_points_vec, Point2, Rect, EPSILON};
+use crate::triangulation::Triangulation;
+
+/// Edge of a periodic triangulation, going from site `from` to the copy of site `to` shifted by
+/// `offset` domain widths and heights, each within -1..=1
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PeriodicEdge {
+    pub from: usize,
+    pub to: usize,
+    pub offset: (i8, i8),
+}
+
+pub struct PeriodicTriangulation {
+    domain: Rect,
+    points: Vec<Point2>,
+    edges: Vec<PeriodicEdge>,
+}
+
+/// Wrap a coordinate in [min, max). Coordinates within the dedup tolerance of `max` are the same
+/// site as the ones at `min` once glued, so they wrap to `min`.
+fn wrap(value: f64, min: f64, max: f64) -> f64 {
+    let wrapped = min + (value - min).rem_euclid(max - min);
+    if max - wrapped <= EPSILON {
+        min
+    } else {
+        wrapped
+    }
+}
+
+/// Triangulate the points on the torus made by gluing the sides of `domain`. Points outside the
+/// domain are wrapped into it, then sanitized like `sanitize_points_vec` does.
+pub fn triangulate_periodic(points: &[Point2], domain: Rect) -> PeriodicTriangulation {
+    let mut sites: Vec<Point2> = points
+        .iter()
+        .map(|point| Point2 {
+            x: wrap(point.x, domain.min.x, domain.max.x),
+            y: wrap(point.y, domain.min.y, domain.max.y),
+        })
+        .collect();
+    sanitize_points_vec(&mut sites);
+
+    let mut tiled = Vec::with_capacity(sites.len() * 9);
+    let mut origins = Vec::with_capacity(sites.len() * 9);
+    for di in -1..=1i8 {
+        for dj in -1..=1i8 {
+            for (site, point) in sites.iter().enumerate() {
+                tiled.push(Point2 {
+                    x: point.x + di as f64 * domain.width(),
+                    y: point.y + dj as f64 * domain.height(),
+                });
+                origins.push((site, di, dj));
+            }
+        }
+    }
+    let triangulation = Triangulation::new(&tiled);
+    // Site and tile of every vertex of the tiled triangulation
+    let mut tile_of = vec![(0, 0, 0); triangulation.points().len()];
+    for (point, origin) in tiled.iter().zip(origins) {
+        let vertex = triangulation
+            .vertex_index(point)
+            .expect("Tiles are far enough apart to never be merged");
+        tile_of[vertex] = origin;
+    }
+
+    let mut edges = vec![];
+    for (_, quad_edge) in triangulation.quad_arena().iter() {
+        let Some((org, dest)) = quad_edge.get_points(triangulation.quad_arena()) else {
+            continue;
+        };
+        let (from, from_i, from_j) = tile_of[triangulation.vertex_index(&org).unwrap()];
+        let (to, to_i, to_j) = tile_of[triangulation.vertex_index(&dest).unwrap()];
+        if (from_i, from_j) != (0, 0) && (to_i, to_j) != (0, 0) {
+            continue;
+        }
+        let offset = (to_i - from_i, to_j - from_j);
+        // The same torus edge goes from `to` to `from` shifted by -offset, keep one of them
+        let reversed = (-offset.0, -offset.1);
+        edges.push(if (from, offset) <= (to, reversed) {
+            PeriodicEdge { from, to, offset }
+        } else {
+            PeriodicEdge {
+                from: to,
+                to: from,
+                offset: reversed,
+            }
+        });
+    }
+    edges.sort_unstable();
+    edges.dedup();
+
+    PeriodicTriangulation {
+        domain,
+        points: sites,
+        edges,
+    }
+}
+
+impl PeriodicTriangulation {
+    pub fn domain(&self) -> Rect {
+        self.domain
+    }
+
+    /// Sites wrapped into the domain and sanitized, indexed like the edges
+    pub fn points(&self) -> &[Point2] {
+        &self.points
+    }
+
+    /// Every edge of the torus once
+    pub fn edges(&self) -> &[PeriodicEdge] {
+        &self.edges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::random_points;
+
+    /// Random points stretched over `DOMAIN`
+    fn domain_points(n: usize) -> Vec<Point2> {
+        random_points(11, n, 1.)
+            .into_iter()
+            .map(|point| Point2 {
+                x: point.x * 4.,
+                y: point.y * 3.,
+            })
+            .collect()
+    }
+
+    const DOMAIN: Rect = Rect {
+        min: Point2 { x: 0., y: 0. },
+        max: Point2 { x: 4., y: 3. },
+    };
+
+    #[test]
+    fn test_torus_euler() {
+        let periodic = triangulate_periodic(&domain_points(60), DOMAIN);
+        assert_eq!(periodic.points().len(), 60);
+        // V - E + F = 0 on the torus, and F = 2E / 3
+        assert_eq!(periodic.edges().len(), 3 * 60);
+    }
+
+    #[test]
+    fn test_degree_matches_copies() {
+        let periodic = triangulate_periodic(&domain_points(60), DOMAIN);
+        let mut degrees = vec![0; periodic.points().len()];
+        for edge in periodic.edges() {
+            degrees[edge.from] += 1;
+            degrees[edge.to] += 1;
+        }
+
+        // Away from the outer border of a 5x5 tiling, every copy of a site has its torus degree
+        let mut tiled = vec![];
+        for di in -2..=2 {
+            for dj in -2..=2 {
+                tiled.extend(periodic.points().iter().map(|point| Point2 {
+                    x: point.x + di as f64 * DOMAIN.width(),
+                    y: point.y + dj as f64 * DOMAIN.height(),
+                }));
+            }
+        }
+        let triangulation = Triangulation::new(&tiled);
+        for di in -1..=1 {
+            for dj in -1..=1 {
+                for (site, point) in periodic.points().iter().enumerate() {
+                    let copy = Point2 {
+                        x: point.x + di as f64 * DOMAIN.width(),
+                        y: point.y + dj as f64 * DOMAIN.height(),
+                    };
+                    let vertex = triangulation.vertex_index(&copy).unwrap();
+                    assert_eq!(triangulation.onext_ring(vertex).count(), degrees[site]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_points_on_domain_border() {
+        let mut points = domain_points(40);
+        // Same site once the sides are glued
+        points.push(Point2 { x: 0., y: 1.5 });
+        points.push(Point2 { x: 4., y: 1.5 });
+        points.push(Point2 {
+            x: 4. - f64::EPSILON,
+            y: 1.5,
+        });
+        points.push(Point2 { x: 6., y: -1. });
+        let periodic = triangulate_periodic(&points, DOMAIN);
+        assert_eq!(periodic.points().len(), 42);
+        assert!(periodic.points().contains(&Point2 { x: 2., y: 2. }));
+        assert!(periodic
+            .points()
+            .iter()
+            .all(|point| point.x >= 0. && point.x < 4. && point.y >= 0. && point.y < 3.));
+        assert_eq!(periodic.edges().len(), 3 * 42);
+    }
+}
@@ -0,0 +1,475 @@
+/// Triangulation of simple polygons in O(n log n), by splitting them in y-monotone pieces with a
+/// sweep line and triangulating every piece with a stack (de Berg et al., chapter 3)
+///
+/// The sweep goes from the top (largest y, smallest x first) to the bottom. Turns are tested in a
+/// y-up frame internally: the boundary is walked so that `orientation` is positive at its convex
+/// vertices.
+use std::collections::HashSet;
+
+use crate::robust_float::{orient2d_value, shoelace, Point2};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum VertexKind {
+    Start,
+    Split,
+    End,
+    Merge,
+    Regular,
+}
+
+/// Sweep order, top to bottom then left to right
+fn sweep_cmp(a: &Point2, b: &Point2) -> std::cmp::Ordering {
+    b.y.total_cmp(&a.y).then(a.x.total_cmp(&b.x))
+}
+
+/// Return true if `a` comes before `b` in the sweep
+fn above(a: &Point2, b: &Point2) -> bool {
+    sweep_cmp(a, b).is_lt()
+}
+
+/// Angular order of directions from `center`, counter clockwise in a y-up frame starting at the
+/// positive x axis
+fn angle_cmp(center: &Point2, a: &Point2, b: &Point2) -> std::cmp::Ordering {
+    let upper = |p: &Point2| p.y > center.y || (p.y == center.y && p.x > center.x);
+    match (upper(a), upper(b)) {
+        (true, false) => std::cmp::Ordering::Less,
+        (false, true) => std::cmp::Ordering::Greater,
+        _ => 0f64.total_cmp(&orient2d_value(center, a, b)),
+    }
+}
+
+/// Sweep line status: the edges crossing the sweep line with the polygon on their right, from
+/// left to right, with their helper vertex
+struct Status<'a> {
+    vertices: &'a [Point2],
+    /// (edge, helper), edge `i` going from vertex `i` to vertex `i + 1`
+    edges: Vec<(usize, usize)>,
+}
+
+impl Status<'_> {
+    /// Abscissa of the edge on the horizontal line at `y`, a horizontal edge gives its leftmost
+    /// end
+    fn x_at(&self, edge: usize, y: f64) -> f64 {
+        let a = self.vertices[edge];
+        let b = self.vertices[(edge + 1) % self.vertices.len()];
+        if a.y == b.y {
+            a.x.min(b.x)
+        } else {
+            a.x + (y - a.y) * (b.x - a.x) / (b.y - a.y)
+        }
+    }
+
+    /// Position of the first edge not strictly on the left of `point`
+    fn position(&self, point: &Point2) -> usize {
+        self.edges
+            .partition_point(|&(edge, _)| self.x_at(edge, point.y) < point.x)
+    }
+
+    fn insert(&mut self, edge: usize, helper: usize) {
+        let pos = self.position(&self.vertices[edge]);
+        self.edges.insert(pos, (edge, helper));
+    }
+
+    /// Remove the edge, at the sweep line's `point`, returning its helper
+    fn remove(&mut self, edge: usize, point: &Point2) -> usize {
+        let guess = self.position(point);
+        let pos = (guess.saturating_sub(1)..(guess + 2).min(self.edges.len()))
+            .find(|&pos| self.edges[pos].0 == edge)
+            .or_else(|| self.edges.iter().position(|&(other, _)| other == edge))
+            .expect("The edge is crossing the sweep line");
+        self.edges.remove(pos).1
+    }
+
+    /// Position of the edge directly on the left of `point`
+    fn left_of(&self, point: &Point2) -> usize {
+        self.position(point)
+            .checked_sub(1)
+            .expect("An edge bounds the polygon on the left")
+    }
+}
+
+/// Diagonals splitting the counter clockwise (y-up) polygon in y-monotone pieces
+fn monotone_diagonals(vertices: &[Point2]) -> Vec<(usize, usize)> {
+    let n = vertices.len();
+    let prev = |i: usize| (i + n - 1) % n;
+    let next = |i: usize| (i + 1) % n;
+    let kinds: Vec<VertexKind> = (0..n)
+        .map(|i| {
+            let (p, v, q) = (&vertices[prev(i)], &vertices[i], &vertices[next(i)]);
+            let convex = orient2d_value(p, v, q) > 0.;
+            match (above(v, p), above(v, q)) {
+                (true, true) if convex => VertexKind::Start,
+                (true, true) => VertexKind::Split,
+                (false, false) if convex => VertexKind::End,
+                (false, false) => VertexKind::Merge,
+                _ => VertexKind::Regular,
+            }
+        })
+        .collect();
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&a, &b| sweep_cmp(&vertices[a], &vertices[b]));
+
+    let mut status = Status {
+        vertices,
+        edges: vec![],
+    };
+    let mut diagonals = vec![];
+    for v in order {
+        let point = &vertices[v];
+        match kinds[v] {
+            VertexKind::Start => status.insert(v, v),
+            VertexKind::End => {
+                let helper = status.remove(prev(v), point);
+                if kinds[helper] == VertexKind::Merge {
+                    diagonals.push((v, helper));
+                }
+            }
+            VertexKind::Split => {
+                let left = status.left_of(point);
+                diagonals.push((v, status.edges[left].1));
+                status.edges[left].1 = v;
+                status.insert(v, v);
+            }
+            VertexKind::Merge => {
+                let helper = status.remove(prev(v), point);
+                if kinds[helper] == VertexKind::Merge {
+                    diagonals.push((v, helper));
+                }
+                let left = status.left_of(point);
+                let helper = status.edges[left].1;
+                if kinds[helper] == VertexKind::Merge {
+                    diagonals.push((v, helper));
+                }
+                status.edges[left].1 = v;
+            }
+            VertexKind::Regular if above(&vertices[prev(v)], point) => {
+                // Going down the left side, the polygon is on the right of the vertex
+                let helper = status.remove(prev(v), point);
+                if kinds[helper] == VertexKind::Merge {
+                    diagonals.push((v, helper));
+                }
+                status.insert(v, v);
+            }
+            VertexKind::Regular => {
+                let left = status.left_of(point);
+                let helper = status.edges[left].1;
+                if kinds[helper] == VertexKind::Merge {
+                    diagonals.push((v, helper));
+                }
+                status.edges[left].1 = v;
+            }
+        }
+    }
+    diagonals
+}
+
+/// Faces of the polygon cut by the diagonals, as counter clockwise (y-up) vertex cycles
+fn pieces(vertices: &[Point2], diagonals: &[(usize, usize)]) -> Vec<Vec<usize>> {
+    let n = vertices.len();
+    let mut neighbors: Vec<Vec<usize>> =
+        (0..n).map(|i| vec![(i + n - 1) % n, (i + 1) % n]).collect();
+    for &(a, b) in diagonals {
+        neighbors[a].push(b);
+        neighbors[b].push(a);
+    }
+    for (v, around) in neighbors.iter_mut().enumerate() {
+        around.sort_by(|a, b| angle_cmp(&vertices[v], &vertices[*a], &vertices[*b]));
+    }
+
+    let half_edges = (0..n)
+        .map(|i| (i, (i + 1) % n))
+        .chain(diagonals.iter().flat_map(|&(a, b)| [(a, b), (b, a)]));
+    let mut visited = HashSet::new();
+    let mut faces = vec![];
+    for start in half_edges {
+        if visited.contains(&start) {
+            continue;
+        }
+        let mut face = vec![];
+        let (mut from, mut to) = start;
+        while visited.insert((from, to)) {
+            face.push(from);
+            // The face continues with the edge just clockwise of the one coming back
+            let around = &neighbors[to];
+            let back = around.iter().position(|&other| other == from).unwrap();
+            let after = around[(back + around.len() - 1) % around.len()];
+            (from, to) = (to, after);
+        }
+        faces.push(face);
+    }
+    faces
+}
+
+/// Triangles of a y-monotone counter clockwise (y-up) polygon, counter clockwise (y-up) too
+fn triangulate_monotone(vertices: &[Point2], face: &[usize], triangles: &mut Vec<[usize; 3]>) {
+    let n = face.len();
+    if n < 3 {
+        return;
+    }
+    let mut sorted: Vec<usize> = (0..n).collect();
+    sorted.sort_by(|&a, &b| sweep_cmp(&vertices[face[a]], &vertices[face[b]]));
+    // Going counter clockwise from the top walks down the left chain
+    let mut on_left = vec![false; n];
+    let mut pos = sorted[0];
+    while pos != sorted[n - 1] {
+        on_left[pos] = true;
+        pos = (pos + 1) % n;
+    }
+
+    let mut emit = |a: usize, b: usize, c: usize| {
+        let [a, b, c] = [face[a], face[b], face[c]];
+        let turn = orient2d_value(&vertices[a], &vertices[b], &vertices[c]);
+        if turn > 0. {
+            triangles.push([a, b, c]);
+        } else if turn < 0. {
+            triangles.push([a, c, b]);
+        }
+    };
+    let mut stack = vec![sorted[0], sorted[1]];
+    for &current in &sorted[2..n - 1] {
+        let top = *stack.last().unwrap();
+        if on_left[current] != on_left[top] {
+            while stack.len() > 1 {
+                let a = stack.pop().unwrap();
+                emit(current, a, *stack.last().unwrap());
+            }
+            stack.pop();
+            stack.push(top);
+            stack.push(current);
+        } else {
+            let mut last = stack.pop().unwrap();
+            while let Some(&before) = stack.last() {
+                let (b, l, c) = (
+                    &vertices[face[before]],
+                    &vertices[face[last]],
+                    &vertices[face[current]],
+                );
+                // The chain is convex at `last`, in the polygon order of the chain
+                let convex = if on_left[current] {
+                    orient2d_value(b, l, c) > 0.
+                } else {
+                    orient2d_value(c, l, b) > 0.
+                };
+                if !convex {
+                    break;
+                }
+                emit(current, last, before);
+                last = stack.pop().unwrap();
+            }
+            stack.push(last);
+            stack.push(current);
+        }
+    }
+    let last = sorted[n - 1];
+    while stack.len() > 1 {
+        let a = stack.pop().unwrap();
+        emit(last, a, *stack.last().unwrap());
+    }
+}
+
+/// Triangulate the simple polygon bounded by `boundary`, given in either winding without
+/// repeating its first vertex. Triangles are counter clockwise as `counter_clockwise` defines
+/// it, zero area triangles of collinear boundary vertices are left out.
+///
+/// Convex boundaries are fanned from their first vertex. Other ones are split in y-monotone
+/// pieces by a sweep, then each piece is triangulated in linear time, O(n log n) overall.
+pub fn triangulate_polygon_monotone(boundary: &[Point2]) -> Vec<[Point2; 3]> {
+    if boundary.len() < 3 {
+        return vec![];
+    }
+    let mut vertices = boundary.to_vec();
+    let doubled_area = shoelace(&vertices);
+    if doubled_area < 0. {
+        vertices.reverse();
+    }
+    let n = vertices.len();
+    let output = |[a, b, c]: [usize; 3]| [vertices[a], vertices[c], vertices[b]];
+    let convex = (0..n).all(|i| {
+        orient2d_value(&vertices[i], &vertices[(i + 1) % n], &vertices[(i + 2) % n]) >= 0.
+    });
+    if convex {
+        // The input's first vertex, which the reversal moved to the end
+        let first = if doubled_area < 0. { n - 1 } else { 0 };
+        return (1..n - 1)
+            .map(|step| [first, (first + step) % n, (first + step + 1) % n])
+            .filter(|&[a, b, c]| orient2d_value(&vertices[a], &vertices[b], &vertices[c]) > 0.)
+            .map(output)
+            .collect();
+    }
+
+    let diagonals = monotone_diagonals(&vertices);
+    let mut triangles = vec![];
+    for face in pieces(&vertices, &diagonals) {
+        triangulate_monotone(&vertices, &face, &mut triangles);
+    }
+    triangles.into_iter().map(output).collect()
+}
+
+/// Reverse `boundary` if it winds clockwise, so it winds counter clockwise as
+/// `counter_clockwise` defines it, seen on a screen with the crate's y-down axis. The winding is
+/// the sign of its `shoelace` area, a boundary of zero area is left as it is.
+pub fn ensure_ccw(boundary: &mut [Point2]) {
+    // The y-up shoelace sign is negative for the crate's counter clockwise
+    if shoelace(boundary) > 0. {
+        boundary.reverse();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robust_float::counter_clockwise;
+    use crate::test_util::xorshift;
+    use std::collections::HashMap;
+
+    fn doubled_area(polygon: &[Point2]) -> f64 {
+        (0..polygon.len())
+            .map(|i| {
+                let (a, b) = (&polygon[i], &polygon[(i + 1) % polygon.len()]);
+                a.x * b.y - b.x * a.y
+            })
+            .sum::<f64>()
+            .abs()
+    }
+
+    /// The triangles tile the polygon: same area, boundary edges used once and diagonals twice
+    fn check_tiling(polygon: &[Point2], triangles: &[[Point2; 3]]) {
+        assert_eq!(triangles.len(), polygon.len() - 2);
+        let key = |p: &Point2| (p.x.to_bits(), p.y.to_bits());
+        let mut directed = HashMap::new();
+        let mut area = 0.;
+        for [a, b, c] in triangles {
+            assert!(counter_clockwise(a, b, c));
+            area += doubled_area(&[*a, *b, *c]);
+            for (from, to) in [(a, b), (b, c), (c, a)] {
+                *directed.entry((key(from), key(to))).or_insert(0) += 1;
+            }
+        }
+        let relative = (area - doubled_area(polygon)).abs() / doubled_area(polygon);
+        assert!(relative < 1e-9);
+        let boundary: HashSet<_> = (0..polygon.len())
+            .flat_map(|i| {
+                let (a, b) = (key(&polygon[i]), key(&polygon[(i + 1) % polygon.len()]));
+                [(a, b), (b, a)]
+            })
+            .collect();
+        for (&(from, to), &count) in &directed {
+            assert_eq!(count, 1);
+            if !boundary.contains(&(from, to)) {
+                // A diagonal is shared with the triangle on its other side
+                assert_eq!(directed.get(&(to, from)), Some(&1));
+            }
+        }
+    }
+
+    fn star(count: usize, seed: u64) -> Vec<Point2> {
+        let mut next = xorshift(seed);
+        (0..count)
+            .map(|i| {
+                let angle = i as f64 * std::f64::consts::TAU / count as f64;
+                let radius = 10. + next() * 90.;
+                Point2 {
+                    x: radius * angle.cos(),
+                    y: radius * angle.sin(),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ensure_ccw() {
+        let is_ccw = |polygon: &[Point2]| {
+            let n = polygon.len();
+            // The triangle at the lowest vertex turns like the polygon
+            let low = (0..n)
+                .max_by(|&a, &b| polygon[a].y.total_cmp(&polygon[b].y))
+                .unwrap();
+            let (before, after) = (polygon[(low + n - 1) % n], polygon[(low + 1) % n]);
+            counter_clockwise(&before, &polygon[low], &after)
+        };
+        for seed in 1..5 {
+            let polygon = star(30, seed);
+            let reversed: Vec<Point2> = polygon.iter().rev().copied().collect();
+            assert_ne!(is_ccw(&polygon), is_ccw(&reversed));
+            let (mut ccw, mut cw) = if is_ccw(&polygon) {
+                (polygon, reversed)
+            } else {
+                (reversed, polygon)
+            };
+            let unchanged = ccw.clone();
+            ensure_ccw(&mut ccw);
+            assert_eq!(ccw, unchanged);
+            ensure_ccw(&mut cw);
+            assert_eq!(cw, unchanged);
+            assert!(is_ccw(&cw));
+        }
+
+        // Screen corners: bottom-left, bottom-right, top-right is counter clockwise
+        let mut square = vec![
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 1., y: 0. },
+            Point2 { x: 1., y: 1. },
+            Point2 { x: 0., y: 1. },
+        ];
+        ensure_ccw(&mut square);
+        assert_eq!(square[0], Point2 { x: 0., y: 1. });
+        assert!(counter_clockwise(&square[0], &square[1], &square[2]));
+
+        let mut flat = vec![Point2 { x: 0., y: 0. }, Point2 { x: 1., y: 1. }];
+        ensure_ccw(&mut flat);
+        assert_eq!(flat[0], Point2 { x: 0., y: 0. });
+    }
+
+    #[test]
+    fn test_convex_fan() {
+        let hexagon: Vec<Point2> = (0..6)
+            .map(|i| {
+                let angle = i as f64 * std::f64::consts::TAU / 6.;
+                Point2 {
+                    x: angle.cos(),
+                    y: angle.sin(),
+                }
+            })
+            .collect();
+        for polygon in [hexagon.clone(), hexagon.iter().rev().copied().collect()] {
+            let triangles = triangulate_polygon_monotone(&polygon);
+            check_tiling(&polygon, &triangles);
+            assert!(triangles
+                .iter()
+                .all(|triangle| triangle.contains(&polygon[0])));
+        }
+        assert!(triangulate_polygon_monotone(&hexagon[..2]).is_empty());
+    }
+
+    #[test]
+    fn test_non_convex() {
+        // A comb, with horizontal edges and a merge vertex between every two teeth
+        let mut comb = vec![Point2 { x: 0., y: 0. }, Point2 { x: 20., y: 0. }];
+        for tooth in (0..10).rev() {
+            let x = tooth as f64 * 2.;
+            comb.push(Point2 { x: x + 2., y: 3. });
+            comb.push(Point2 { x: x + 1., y: 3. });
+            comb.push(Point2 { x: x + 1., y: 1. });
+            comb.push(Point2 { x, y: 1. });
+        }
+        comb[1].y = -2.;
+        check_tiling(&comb, &triangulate_polygon_monotone(&comb));
+        let reversed: Vec<Point2> = comb.iter().rev().copied().collect();
+        check_tiling(&reversed, &triangulate_polygon_monotone(&reversed));
+
+        for seed in 1..20 {
+            let polygon = star(60, seed);
+            check_tiling(&polygon, &triangulate_polygon_monotone(&polygon));
+        }
+    }
+
+    #[test]
+    fn test_large_boundary() {
+        let polygon = star(20_000, 77);
+        let start = std::time::Instant::now();
+        let triangles = triangulate_polygon_monotone(&polygon);
+        // Generous, it takes well under a second in a debug build
+        assert!(start.elapsed().as_secs() < 10);
+        check_tiling(&polygon, &triangles);
+    }
+}
@@ -0,0 +1,270 @@
+/// Preprocessing of the sites before triangulating
+///
+/// Oversampled inputs, like GPS traces, put many sites in a few meters. Thinning keeps one site
+/// per cell of a square grid, which makes the triangulation smaller without changing its shape
+/// at scales above the cell size. Duplicates can be found first, to decide what to do with them
+/// before the triangulation merges them.
+use std::collections::HashMap;
+
+use crate::cluster::UnionFind;
+use crate::robust_float::{sanitize_points_vec, Point2};
+
+/// How `thin_points` picks the site kept in a cell
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ThinStrategy {
+    /// The first point of the cell, in input order
+    First,
+    /// The centroid of the points of the cell, which may not be one of them
+    Centroid,
+    /// The point of the cell nearest to its centroid, the first one on ties
+    NearestToCentroid,
+}
+
+/// Grid cell of a point. Cells are closed on their top-left sides: a point on the line between
+/// two cells belongs to the one of larger coordinates.
+fn cell(point: &Point2, cell_size: f64) -> (i64, i64) {
+    (
+        (point.x / cell_size).floor() as i64,
+        (point.y / cell_size).floor() as i64,
+    )
+}
+
+/// Keep one point per cell of a grid of `cell_size` squares aligned on the origin, picked with
+/// `strategy`. Cells come in the order of their first point in `points`.
+///
+/// Panics if `cell_size` isn't a positive finite number.
+pub fn thin_points(points: &[Point2], cell_size: f64, strategy: ThinStrategy) -> Vec<Point2> {
+    assert!(
+        cell_size > 0. && cell_size.is_finite(),
+        "The cell size must be positive and finite, got {}",
+        cell_size
+    );
+    let mut cells: HashMap<(i64, i64), usize> = HashMap::new();
+    // Points of every cell, cells in order of appearance
+    let mut members: Vec<Vec<Point2>> = vec![];
+    for point in points {
+        let slot = *cells.entry(cell(point, cell_size)).or_insert_with(|| {
+            members.push(vec![]);
+            members.len() - 1
+        });
+        members[slot].push(*point);
+    }
+    members
+        .into_iter()
+        .map(|members| {
+            if strategy == ThinStrategy::First {
+                return members[0];
+            }
+            let count = members.len() as f64;
+            let centroid = Point2 {
+                x: members.iter().map(|point| point.x).sum::<f64>() / count,
+                y: members.iter().map(|point| point.y).sum::<f64>() / count,
+            };
+            match strategy {
+                ThinStrategy::NearestToCentroid => *members
+                    .iter()
+                    .min_by(|a, b| a.distance(&centroid).total_cmp(&b.distance(&centroid)))
+                    .unwrap(),
+                _ => centroid,
+            }
+        })
+        .collect()
+}
+
+/// Groups of indices of points within `tol` of each other, directly or through a chain of
+/// points. Points without a duplicate are left out, `points` is left alone. Indices are sorted in
+/// a group, and groups by their first index.
+///
+/// Points are bucketed in a grid of `tol` squares, so only the 9 cells around a point are looked
+/// at. A `tol` of 0 finds the exactly equal points.
+pub fn find_duplicates(points: &[Point2], tol: f64) -> Vec<Vec<usize>> {
+    let key = |point: &Point2| {
+        if tol > 0. {
+            cell(point, tol)
+        } else {
+            // Exact buckets, -0 and 0 merged
+            (
+                (point.x + 0.).to_bits() as i64,
+                (point.y + 0.).to_bits() as i64,
+            )
+        }
+    };
+    let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    let mut sets = UnionFind::new(points.len());
+    let mut duplicated = vec![false; points.len()];
+    for (idx, point) in points.iter().enumerate() {
+        let (x, y) = key(point);
+        for cell in [-1, 0, 1]
+            .into_iter()
+            .flat_map(|dx| [-1, 0, 1].map(|dy| (x.wrapping_add(dx), y.wrapping_add(dy))))
+        {
+            for &other in grid.get(&cell).into_iter().flatten() {
+                if points[other].distance(point) <= tol {
+                    sets.union(idx, other);
+                    duplicated[idx] = true;
+                    duplicated[other] = true;
+                }
+            }
+        }
+        grid.entry((x, y)).or_default().push(idx);
+    }
+
+    let mut slots: HashMap<usize, usize> = HashMap::new();
+    let mut groups: Vec<Vec<usize>> = vec![];
+    for idx in (0..points.len()).filter(|&idx| duplicated[idx]) {
+        let slot = *slots.entry(sets.find(idx)).or_insert_with(|| {
+            groups.push(vec![]);
+            groups.len() - 1
+        });
+        groups[slot].push(idx);
+    }
+    groups
+}
+
+/// Round the coordinates to the nearest multiple of `cell`, then sanitize the points like
+/// `sanitize_points_vec`. Noisy points around a grid node collapse into the node, and the
+/// predicates work on well-spaced sites.
+///
+/// Panics if `cell` isn't a positive finite number.
+pub fn snap_to_grid(points: &mut Vec<Point2>, cell: f64) {
+    assert!(
+        cell > 0. && cell.is_finite(),
+        "The cell size must be positive and finite, got {}",
+        cell
+    );
+    // Adding 0 turns the -0 of small negative coordinates into 0
+    let snap = |value: f64| (value / cell).round() * cell + 0.;
+    for point in points.iter_mut() {
+        *point = Point2 {
+            x: snap(point.x),
+            y: snap(point.y),
+        };
+    }
+    sanitize_points_vec(points);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::xorshift;
+
+    #[test]
+    fn test_thin_points() {
+        let mut next = xorshift(43);
+        // A dense cluster around the origin, half of it on negative coordinates
+        let points: Vec<Point2> = (0..5000)
+            .map(|_| Point2 {
+                x: next() * 6. - 3.,
+                y: next() * 4. - 2.,
+            })
+            .collect();
+        for strategy in [
+            ThinStrategy::First,
+            ThinStrategy::Centroid,
+            ThinStrategy::NearestToCentroid,
+        ] {
+            let thinned = thin_points(&points, 1., strategy);
+            assert_eq!(thinned.len(), 24);
+            let mut seen: Vec<(i64, i64)> = thinned.iter().map(|point| cell(point, 1.)).collect();
+            seen.sort();
+            seen.dedup();
+            assert_eq!(seen.len(), 24);
+            if strategy != ThinStrategy::Centroid {
+                assert!(thinned.iter().all(|point| points.contains(point)));
+            }
+        }
+        assert_eq!(thin_points(&points, 1., ThinStrategy::First)[0], points[0]);
+
+        let points = [
+            Point2 { x: -0.5, y: 0.25 },
+            Point2 { x: -0.25, y: 0.75 },
+            Point2 { x: 0., y: 0. },
+            Point2 { x: -1., y: 0.5 },
+            Point2 { x: 1., y: 1. },
+        ];
+        // The origin is on a corner, it belongs to the cell of larger coordinates
+        assert_eq!(cell(&points[2], 1.), (0, 0));
+        assert_eq!(
+            thin_points(&points, 1., ThinStrategy::Centroid),
+            vec![
+                Point2 {
+                    x: -1.75 / 3.,
+                    y: 0.5
+                },
+                Point2 { x: 0., y: 0. },
+                Point2 { x: 1., y: 1. },
+            ]
+        );
+        assert_eq!(
+            thin_points(&points, 1., ThinStrategy::NearestToCentroid),
+            vec![points[0], points[2], points[4]]
+        );
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let points = [
+            Point2 { x: 2., y: 2. },
+            Point2 { x: -1., y: 0.5 },
+            Point2 { x: 2., y: 2. },
+            Point2 { x: 5., y: 5. },
+            Point2 { x: 2., y: 2. },
+            Point2 { x: -1., y: 0.5 },
+            Point2 { x: 5.05, y: 5. },
+        ];
+        assert_eq!(
+            find_duplicates(&points, 0.),
+            vec![vec![0, 2, 4], vec![1, 5]]
+        );
+        assert_eq!(
+            find_duplicates(&points, 0.1),
+            vec![vec![0, 2, 4], vec![1, 5], vec![3, 6]]
+        );
+        assert_eq!(find_duplicates(&points[..4], 0.), vec![vec![0, 2]]);
+        assert!(find_duplicates(&points[3..], 0.01).is_empty());
+
+        // A chain of points closer than the tolerance is one group, across cells
+        let chain: Vec<Point2> = (0..10)
+            .map(|idx| Point2 {
+                x: idx as f64 * 0.09 - 0.5,
+                y: -0.3,
+            })
+            .collect();
+        assert_eq!(
+            find_duplicates(&chain, 0.1),
+            vec![(0..10).collect::<Vec<usize>>()]
+        );
+        assert!(find_duplicates(&chain, 0.05).is_empty());
+    }
+
+    #[test]
+    fn test_snap_to_grid() {
+        let mut next = xorshift(47);
+        // Noise of less than half a cell around 9 nodes of a 0.1 grid, the origin among them
+        let mut points: Vec<Point2> = (0..900)
+            .map(|idx| Point2 {
+                x: (idx % 3) as f64 * 0.1 - 0.1 + (next() - 0.5) * 0.099,
+                y: (idx / 3 % 3) as f64 * 0.1 + (next() - 0.5) * 0.099,
+            })
+            .collect();
+        snap_to_grid(&mut points, 0.1);
+        assert_eq!(points.len(), 9);
+        assert!(points.contains(&Point2 { x: 0., y: 0. }));
+        // No -0 left from the noise below the origin
+        assert!(!points
+            .iter()
+            .any(|point| point.x == 0. && point.x.is_sign_negative()));
+        let mut expected: Vec<Point2> = (0..9)
+            .map(|idx| Point2 {
+                x: ((idx % 3) as f64 - 1.) * 0.1,
+                y: (idx / 3) as f64 * 0.1,
+            })
+            .collect();
+        sanitize_points_vec(&mut expected);
+        assert_eq!(points, expected);
+
+        let mut points = vec![Point2 { x: 1.2, y: -0.7 }, Point2 { x: 0.8, y: -1.4 }];
+        snap_to_grid(&mut points, 1.);
+        assert_eq!(points, vec![Point2 { x: 1., y: -1. }]);
+    }
+}
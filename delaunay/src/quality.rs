@@ -0,0 +1,468 @@
+/// Triangle and edge quality measures, to find the triangles a refinement or a sliver cleanup
+/// should process first, and length filtered views dropping the long edges outliers bring in
+use std::collections::HashSet;
+
+use crate::edge::{left_of, EdgeRef};
+use crate::robust_float::{circumcenter, cmp_points, shoelace, Point2};
+use crate::triangulation::Triangulation;
+
+/// Halvings of a `smooth_laplacian` step leaving the kernel of the star before giving up
+const KERNEL_HALVINGS: usize = 8;
+
+/// Radius of the circle going through the three corners, infinite when they are collinear
+pub fn circumradius(corners: &[Point2; 3]) -> f64 {
+    let [a, b, c] = corners;
+    let center = circumcenter(a, b, c);
+    let radius = ((center.x - a.x).powi(2) + (center.y - a.y).powi(2)).sqrt();
+    if radius.is_nan() {
+        f64::INFINITY
+    } else {
+        radius
+    }
+}
+
+/// Sum of the lengths of the lines, each undirected segment counted once whatever the direction
+/// it's given in. The minimum weight triangulation minimizes it, the Delaunay triangulation
+/// usually comes close without reaching it.
+pub fn total_edge_length(lines: &[(Point2, Point2)]) -> f64 {
+    let mut seen = HashSet::new();
+    lines
+        .iter()
+        .filter(|(a, b)| {
+            let (first, second) = match cmp_points(a, b) {
+                std::cmp::Ordering::Greater => (b, a),
+                _ => (a, b),
+            };
+            seen.insert((first.ordered(), second.ordered()))
+        })
+        .map(|(a, b)| a.distance(b))
+        .sum()
+}
+
+impl Triangulation {
+    /// Length of every edge of the mesh, in the order of `live_edges`
+    pub fn edge_lengths(&self) -> Vec<f64> {
+        self.live_edges()
+            .map(|edge| {
+                let (org, dest) = edge.org_dest(self.quad_arena());
+                org.distance(&dest)
+            })
+            .collect()
+    }
+
+    /// Number of edges in each of `bins` equal width length ranges, from the shortest edge to
+    /// the longest one, both included. Every edge falls in the first bin when they all have the
+    /// same length.
+    pub fn length_histogram(&self, bins: usize) -> Vec<usize> {
+        let mut histogram = vec![0; bins];
+        let lengths = self.edge_lengths();
+        if bins == 0 || lengths.is_empty() {
+            return histogram;
+        }
+        let min = lengths.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = lengths.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let width = (max - min) / bins as f64;
+        for length in lengths {
+            let bin = if width > 0. {
+                (((length - min) / width) as usize).min(bins - 1)
+            } else {
+                0
+            };
+            histogram[bin] += 1;
+        }
+        histogram
+    }
+
+    /// Edges no longer than `max_len`, as (origin, destination) pairs. The mesh isn't modified.
+    pub fn edges_filtered(&self, max_len: f64) -> Vec<(Point2, Point2)> {
+        self.edges_iter()
+            .filter(|(org, dest)| org.distance(dest) <= max_len)
+            .collect()
+    }
+
+    /// Same as `for_each_face`, skipping the faces with an edge longer than `max_len`
+    pub fn for_each_face_filtered<F: FnMut([Point2; 3])>(&self, max_len: f64, mut f: F) {
+        self.for_each_face(|corners| {
+            let [a, b, c] = &corners;
+            if a.distance(b) <= max_len && b.distance(c) <= max_len && c.distance(a) <= max_len {
+                f(corners);
+            }
+        });
+    }
+
+    /// Every bounded face with its circumradius, the largest radius first.
+    ///
+    /// Zero area faces are never part of the mesh. Faces so flat their circumcenter overflows
+    /// get an infinite radius and come first, they are the worst slivers.
+    pub fn faces_by_circumradius(&self) -> Vec<([Point2; 3], f64)> {
+        let mut faces = vec![];
+        self.for_each_face(|corners| faces.push((corners, circumradius(&corners))));
+        faces.sort_by(|a, b| b.1.total_cmp(&a.1));
+        faces
+    }
+
+    /// Laplacian smoothing: move every site that isn't `is_boundary` to the centroid of its
+    /// neighbors, `iterations` times. This is `smooth_laplacian` with a factor of 1: targets are
+    /// computed for all sites before any moves, then the sites are moved one by one with
+    /// `move_vertex` so the mesh stays Delaunay and handles stay valid. A site whose target is
+    /// near-equal to another site stays.
+    ///
+    /// In ghost mode the ghosts and the real sites next to them are held, the ghosts are left out
+    /// of the centroids.
+    pub fn smooth(&mut self, iterations: usize) {
+        self.smooth_laplacian(iterations, 1.);
+    }
+
+    /// Damped `smooth`: every site goes `factor` of the way to the centroid of its neighbors, and
+    /// never leaves the kernel of its star, the region from which it sees every face around it
+    /// counter clockwise. A step leaving it is halved until it fits, or dropped, so no face is
+    /// ever inverted and the edges around the site are only legalized with flips. Returns the
+    /// largest displacement of the last pass, 0 without iterations.
+    pub fn smooth_laplacian(&mut self, iterations: usize, factor: f64) -> f64 {
+        let mut largest = 0.;
+        for _ in 0..iterations {
+            largest = 0.;
+            for (vertex, centroid) in self.smoothing_targets() {
+                let start = self.points()[vertex];
+                let (mut dx, mut dy) = (
+                    (centroid.x - start.x) * factor,
+                    (centroid.y - start.y) * factor,
+                );
+                let links: Vec<EdgeRef> = self
+                    .onext_ring(vertex)
+                    .map(|edge| edge.lnext(self.quad_arena()))
+                    .collect();
+                for _ in 0..KERNEL_HALVINGS {
+                    let target = Point2 {
+                        x: start.x + dx,
+                        y: start.y + dy,
+                    };
+                    let in_kernel = links
+                        .iter()
+                        .all(|&link| left_of(self.quad_arena(), &target, link));
+                    if in_kernel {
+                        if self.move_vertex(vertex, target).is_ok() {
+                            largest = f64::max(largest, start.distance(&target));
+                        }
+                        break;
+                    }
+                    dx /= 2.;
+                    dy /= 2.;
+                }
+            }
+        }
+        largest
+    }
+
+    /// Sites `smooth` moves, with the centroid of their neighbors
+    fn smoothing_targets(&self) -> Vec<(usize, Point2)> {
+        (0..self.points().len())
+            .filter(|&vertex| !self.is_removed(vertex) && !self.is_boundary(vertex))
+            .filter_map(|vertex| {
+                let neighbors: Vec<Point2> = self
+                    .onext_ring(vertex)
+                    .map(|edge| edge.dest(self.quad_arena()))
+                    .collect();
+                if neighbors.is_empty() {
+                    return None;
+                }
+                let count = neighbors.len() as f64;
+                let centroid = Point2 {
+                    x: neighbors.iter().map(|point| point.x).sum::<f64>() / count,
+                    y: neighbors.iter().map(|point| point.y).sum::<f64>() / count,
+                };
+                Some((vertex, centroid))
+            })
+            .collect()
+    }
+
+    /// Area of the convex hull of the sites, the shoelace formula over `hull_edges` taken
+    /// relative to a hull site like `shoelace` does
+    pub fn hull_area(&self) -> f64 {
+        let mut origin = None;
+        let doubled: f64 = self
+            .hull_edges()
+            .map(|edge| {
+                let (org, dest) = edge.org_dest(self.quad_arena());
+                let origin = *origin.get_or_insert(org);
+                shoelace(&[origin, org, dest])
+            })
+            .sum();
+        doubled.abs() / 2.
+    }
+
+    /// Length of the boundary of the convex hull, summed over `hull_edges`. Collinear sites
+    /// count as a flat polygon, every segment twice.
+    pub fn hull_perimeter(&self) -> f64 {
+        self.hull_edges()
+            .map(|edge| {
+                let (org, dest) = edge.org_dest(self.quad_arena());
+                org.distance(&dest)
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::xorshift;
+
+    #[test]
+    fn test_total_edge_length() {
+        let mut square = vec![
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 2., y: 0. },
+            Point2 { x: 2., y: 2. },
+            Point2 { x: 0., y: 2. },
+        ];
+        let mut lines = crate::gns_delaunay::triangulate(&mut square);
+        // Four sides and a diagonal
+        let expected = 4. * 2. + 8f64.sqrt();
+        assert_eq!(lines.len(), 5);
+        assert!((total_edge_length(&lines) - expected).abs() < 1e-12);
+        // Repeated and reversed lines count once
+        let reversed: Vec<(Point2, Point2)> = lines.iter().map(|&(a, b)| (b, a)).collect();
+        lines.extend(reversed);
+        lines.push(lines[0]);
+        assert!((total_edge_length(&lines) - expected).abs() < 1e-12);
+        assert_eq!(total_edge_length(&[]), 0.);
+    }
+
+    #[test]
+    fn test_faces_by_circumradius() {
+        // A sliver along the hull under two fat triangles
+        let points = [
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 10., y: 0. },
+            Point2 { x: 5., y: 8.66 },
+            Point2 { x: 5., y: 0.2 },
+        ];
+        let triangulation = Triangulation::new(&points);
+        let faces = triangulation.faces_by_circumradius();
+        let mut count = 0;
+        triangulation.for_each_face(|_| count += 1);
+        assert_eq!(faces.len(), count);
+        assert_eq!(count, 3);
+        assert!(faces.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+
+        // The worst face is the sliver (0, 0), (5, 0.2), (10, 0)
+        let mut worst = faces[0].0;
+        worst.sort_by(crate::robust_float::cmp_points);
+        assert_eq!(worst, [points[0], points[3], points[1]]);
+        assert!((faces[0].1 - (25. + 0.04) / 0.4).abs() < 1e-9);
+        assert!(faces[1..].iter().all(|(_, radius)| *radius < 6.));
+        for (corners, radius) in &faces {
+            let center = circumcenter(&corners[0], &corners[1], &corners[2]);
+            for corner in corners {
+                let distance =
+                    ((center.x - corner.x).powi(2) + (center.y - corner.y).powi(2)).sqrt();
+                assert!((distance - radius).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_length_filter() {
+        // A unit cluster with one far outlier, whose edges are the long ones
+        let mut next = xorshift(11);
+        let mut points: Vec<Point2> = (0..100)
+            .map(|_| Point2 {
+                x: next(),
+                y: next(),
+            })
+            .collect();
+        points.push(Point2 { x: 50., y: 30. });
+        let triangulation = Triangulation::new(&points);
+        let lengths = triangulation.edge_lengths();
+        assert_eq!(lengths.len(), triangulation.live_edges().count());
+
+        let histogram = triangulation.length_histogram(10);
+        assert_eq!(histogram.iter().sum::<usize>(), lengths.len());
+        // The outlier edges are alone in the last bin
+        assert!(histogram[0] > 0 && histogram[9] > 0);
+        assert!(triangulation.length_histogram(0).is_empty());
+
+        let max_len = 2.;
+        let edges = triangulation.edges_filtered(max_len);
+        assert!(edges.iter().all(|(a, b)| a.distance(b) <= max_len));
+        assert_eq!(
+            edges.len(),
+            lengths.iter().filter(|&&length| length <= max_len).count()
+        );
+        let mut kept = 0;
+        triangulation.for_each_face_filtered(max_len, |corners| {
+            kept += 1;
+            assert!(corners
+                .iter()
+                .all(|corner| corner.x <= 1. && corner.y <= 1.));
+        });
+        let mut all = 0;
+        triangulation.for_each_face(|_| all += 1);
+        assert!(0 < kept && kept < all);
+        // The mesh is left alone
+        assert_eq!(triangulation.edge_lengths(), lengths);
+    }
+
+    #[test]
+    fn test_hull_area_and_perimeter() {
+        let square = [
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 1., y: 0. },
+            Point2 { x: 1., y: 1. },
+            Point2 { x: 0., y: 1. },
+            Point2 { x: 0.3, y: 0.6 },
+        ];
+        let triangulation = Triangulation::new(&square);
+        assert_eq!(triangulation.hull_edges().count(), 4);
+        assert_eq!(triangulation.hull_area(), 1.);
+        assert_eq!(triangulation.hull_perimeter(), 4.);
+
+        // Equal to the area of the triangles, the ghosts left out
+        let mut next = xorshift(41);
+        let points: Vec<Point2> = (0..300)
+            .map(|_| Point2 {
+                x: next() * 10.,
+                y: next() * 5.,
+            })
+            .collect();
+        for triangulation in [
+            Triangulation::new(&points),
+            Triangulation::with_ghosts(&points),
+        ] {
+            let total: f64 = triangulation
+                .triangles_iter()
+                .map(|[a, b, c]| ((b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y)).abs() / 2.)
+                .sum();
+            assert!((triangulation.hull_area() - total).abs() < 1e-9);
+            assert!(triangulation.hull_perimeter() < 30.);
+        }
+
+        let line = Triangulation::new(&[
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 1., y: 1. },
+            Point2 { x: 2., y: 2. },
+        ]);
+        assert_eq!(line.hull_area(), 0.);
+        assert!((line.hull_perimeter() - 4. * 2f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_smooth() {
+        let mut next = xorshift(53);
+        let points: Vec<Point2> = (0..400)
+            .map(|idx| Point2 {
+                x: (idx % 20) as f64 + (next() - 0.5) * 0.6,
+                y: (idx / 20) as f64 + (next() - 0.5) * 0.6,
+            })
+            .collect();
+        let variance = |lengths: Vec<f64>| {
+            let mean = lengths.iter().sum::<f64>() / lengths.len() as f64;
+            lengths
+                .iter()
+                .map(|length| (length - mean).powi(2))
+                .sum::<f64>()
+                / lengths.len() as f64
+        };
+        let mut triangulation = Triangulation::new(&points);
+        let hull: Vec<(usize, Point2)> = (0..triangulation.points().len())
+            .filter(|&vertex| triangulation.is_hull_vertex(vertex))
+            .map(|vertex| (vertex, triangulation.points()[vertex]))
+            .collect();
+        let before = variance(triangulation.edge_lengths());
+        triangulation.smooth(3);
+        triangulation.assert_delaunay();
+        assert!(variance(triangulation.edge_lengths()) < before * 0.9);
+        for (vertex, point) in hull {
+            assert_eq!(triangulation.points()[vertex], point);
+            assert!(triangulation.is_hull_vertex(vertex));
+        }
+
+        // In ghost mode the hull is made of ghosts, the real sites next to them are pinned too.
+        // `smooth` is `smooth_laplacian` without damping.
+        let mut ghosted = Triangulation::with_ghosts(&points);
+        let boundary: Vec<(usize, Point2)> = (0..ghosted.points().len())
+            .filter(|&vertex| ghosted.is_boundary(vertex))
+            .map(|vertex| (vertex, ghosted.points()[vertex]))
+            .collect();
+        assert!(boundary.len() > 3);
+        assert!(boundary
+            .iter()
+            .any(|&(vertex, point)| !ghosted.is_hull_vertex(vertex) && !ghosted.is_ghost(point)));
+        let mut damped = Triangulation::with_ghosts(&points);
+        ghosted.smooth(2);
+        damped.smooth_laplacian(2, 1.);
+        assert_eq!(ghosted.points(), damped.points());
+        for (vertex, point) in boundary {
+            assert_eq!(ghosted.points()[vertex], point);
+        }
+    }
+
+    #[test]
+    fn test_smooth_laplacian() {
+        let mut next = xorshift(59);
+        // Parallelogram of a triangular lattice, every site off its sides is the centroid of its
+        // six neighbors
+        let side = 15;
+        let lattice: Vec<Point2> = (0..side * side)
+            .map(|idx| Point2 {
+                x: (idx % side) as f64 + (idx / side) as f64 * 0.5,
+                y: (idx / side) as f64,
+            })
+            .collect();
+        let inner = |idx: usize| {
+            (1..side - 1).contains(&(idx % side)) && (1..side - 1).contains(&(idx / side))
+        };
+        let points: Vec<Point2> = lattice
+            .iter()
+            .enumerate()
+            .map(|(idx, point)| {
+                if inner(idx) {
+                    Point2 {
+                        x: point.x + (next() - 0.5) * 0.2,
+                        y: point.y + (next() - 0.5) * 0.2,
+                    }
+                } else {
+                    *point
+                }
+            })
+            .collect();
+        let mut triangulation = Triangulation::new(&points);
+        let handles: Vec<usize> = points
+            .iter()
+            .map(|point| triangulation.vertex_index(point).unwrap())
+            .collect();
+        // Farthest site from its lattice node
+        let error = |triangulation: &Triangulation| {
+            handles
+                .iter()
+                .zip(&lattice)
+                .map(|(&vertex, node)| triangulation.points()[vertex].distance(node))
+                .fold(0., f64::max)
+        };
+        let before = error(&triangulation);
+        assert!(before > 0.08);
+
+        assert_eq!(triangulation.smooth_laplacian(0, 0.5), 0.);
+        let first = triangulation.smooth_laplacian(1, 0.5);
+        let last = triangulation.smooth_laplacian(100, 0.5);
+        assert!(last < first * 0.01);
+        assert!(error(&triangulation) < before * 0.1);
+        triangulation.assert_delaunay();
+        for (idx, (&vertex, node)) in handles.iter().zip(&lattice).enumerate() {
+            if !inner(idx) {
+                assert_eq!(triangulation.points()[vertex], *node);
+            }
+        }
+
+        // Overshooting steps are cut at the kernel of the star, no face is ever flipped over
+        // (`triangles` drops the faces that aren't counter clockwise, their count would shrink)
+        let mut triangulation = Triangulation::new(&points);
+        let faces = triangulation.triangles().len();
+        for _ in 0..5 {
+            triangulation.smooth_laplacian(1, 3.);
+            assert_eq!(triangulation.triangles().len(), faces);
+        }
+        triangulation.assert_delaunay();
+    }
+}
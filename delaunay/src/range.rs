@@ -0,0 +1,311 @@
+/// Window queries, answered by flood filling the triangles meeting the window instead of scanning
+/// every site, and clipping of the mesh to a viewport
+use std::collections::HashSet;
+
+use crate::edge::EdgeRef;
+use crate::robust_float::{counter_clockwise, point_in_triangle, Point2, Rect, Region};
+use crate::triangulation::Triangulation;
+
+/// Rectangle spanned by two opposite corners, in any order
+fn window_rect(window: (Point2, Point2)) -> Rect {
+    let (a, b) = window;
+    Rect {
+        min: Point2 {
+            x: a.x.min(b.x),
+            y: a.y.min(b.y),
+        },
+        max: Point2 {
+            x: a.x.max(b.x),
+            y: a.y.max(b.y),
+        },
+    }
+}
+
+fn in_rect(rect: &Rect, point: &Point2) -> bool {
+    point.x >= rect.min.x && point.x <= rect.max.x && point.y >= rect.min.y && point.y <= rect.max.y
+}
+
+/// Return true if the counter clockwise triangle and the rectangle share at least a point
+fn triangle_meets_rect(corners: &[Point2; 3], rect: &Rect) -> bool {
+    let below = |get: fn(&Point2) -> f64, limit: f64| corners.iter().all(|c| get(c) < limit);
+    let above = |get: fn(&Point2) -> f64, limit: f64| corners.iter().all(|c| get(c) > limit);
+    if below(|p| p.x, rect.min.x)
+        || above(|p| p.x, rect.max.x)
+        || below(|p| p.y, rect.min.y)
+        || above(|p| p.y, rect.max.y)
+    {
+        return false;
+    }
+    let rect_corners = [
+        rect.min,
+        Point2 {
+            x: rect.max.x,
+            y: rect.min.y,
+        },
+        rect.max,
+        Point2 {
+            x: rect.min.x,
+            y: rect.max.y,
+        },
+    ];
+    // Separated when the whole rectangle is strictly outside of one of the triangle sides
+    (0..3).all(|idx| {
+        let (org, dest) = (&corners[idx], &corners[(idx + 1) % 3]);
+        !rect_corners
+            .iter()
+            .all(|corner| counter_clockwise(corner, dest, org))
+    })
+}
+
+/// Part of the segment inside the rectangle, borders included, with Liang-Barsky clipping
+pub(crate) fn clip_segment(a: Point2, b: Point2, rect: &Rect) -> Option<(Point2, Point2)> {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let (mut enter, mut exit) = (0f64, 1f64);
+    for (p, q) in [
+        (-dx, a.x - rect.min.x),
+        (dx, rect.max.x - a.x),
+        (-dy, a.y - rect.min.y),
+        (dy, rect.max.y - a.y),
+    ] {
+        if p == 0. {
+            // Parallel to this border, either always inside of it or never
+            if q < 0. {
+                return None;
+            }
+        } else if p < 0. {
+            enter = enter.max(q / p);
+        } else {
+            exit = exit.min(q / p);
+        }
+    }
+    if enter > exit {
+        return None;
+    }
+    // Keep the original endpoints exactly, and don't let rounding go past the borders
+    let at = |t: f64| {
+        if t == 0. {
+            a
+        } else if t == 1. {
+            b
+        } else {
+            Point2 {
+                x: (a.x + t * dx).clamp(rect.min.x, rect.max.x),
+                y: (a.y + t * dy).clamp(rect.min.y, rect.max.y),
+            }
+        }
+    };
+    Some((at(enter), at(exit)))
+}
+
+impl Triangulation {
+    /// Sites inside the axis aligned window spanned by two opposite corners, borders included,
+    /// sorted by index.
+    ///
+    /// The triangles meeting the window are connected, the search starts from the one holding the
+    /// window center, or from the hull edges crossing the window when the center is outside of
+    /// the hull.
+    pub fn sites_in_rect(&self, window: (Point2, Point2)) -> Vec<usize> {
+        let rect = window_rect(window);
+        if self.any_triangle_edge().is_none() {
+            return (0..self.points().len())
+                .filter(|&vertex| {
+                    !self.is_removed(vertex) && in_rect(&rect, &self.points()[vertex])
+                })
+                .collect();
+        }
+
+        let arena = self.quad_arena();
+        let center = Point2 {
+            x: (rect.min.x + rect.max.x) / 2.,
+            y: (rect.min.y + rect.max.y) / 2.,
+        };
+        let start = self.nearest_face(center);
+        let [a, b, c] = self.left_triangle(start).unwrap();
+        let mut stack: Vec<EdgeRef> = vec![];
+        if point_in_triangle(center, a, b, c) != Region::Outside {
+            stack.push(start);
+        } else {
+            let outer = start.sym();
+            let mut edge = outer;
+            loop {
+                if triangle_meets_rect(&self.left_triangle(edge.sym()).unwrap(), &rect) {
+                    stack.push(edge.sym());
+                }
+                edge = edge.lnext(arena);
+                if edge == outer {
+                    break;
+                }
+            }
+        }
+
+        let mut visited = HashSet::new();
+        let mut found = vec![false; self.points().len()];
+        while let Some(edge) = stack.pop() {
+            let second = edge.lnext(arena);
+            let sides = [edge, second, second.lnext(arena)];
+            if !visited.insert(*sides.iter().min().unwrap()) {
+                continue;
+            }
+            let corners = self.left_triangle(edge).unwrap();
+            if !triangle_meets_rect(&corners, &rect) {
+                continue;
+            }
+            for (side, corner) in sides.iter().zip(corners) {
+                if in_rect(&rect, &corner) {
+                    found[self.vertex_index(&corner).unwrap()] = true;
+                }
+                if self.left_triangle(side.sym()).is_some() {
+                    stack.push(side.sym());
+                }
+            }
+        }
+        (0..found.len()).filter(|&vertex| found[vertex]).collect()
+    }
+
+    /// Edges trimmed to the rectangle, borders included, as (origin, destination) pairs. Edges
+    /// missing the rectangle are dropped, and an edge only touching it gives a single point
+    /// segment.
+    pub fn clip_edges(&self, rect: Rect) -> Vec<(Point2, Point2)> {
+        self.live_edges()
+            .filter_map(|edge| {
+                let (org, dest) = edge.org_dest(self.quad_arena());
+                clip_segment(org, dest, &rect)
+            })
+            .collect()
+    }
+
+    /// Faces whose bounding box meets the rectangle, borders included, not clipped. The corners
+    /// are in the `for_each_face` order.
+    pub fn triangles_in_rect(&self, rect: Rect) -> Vec<[Point2; 3]> {
+        let mut triangles = vec![];
+        self.for_each_face(|corners| {
+            let min =
+                |get: fn(&Point2) -> f64| corners.iter().map(get).fold(f64::INFINITY, f64::min);
+            let max =
+                |get: fn(&Point2) -> f64| corners.iter().map(get).fold(f64::NEG_INFINITY, f64::max);
+            if min(|p| p.x) <= rect.max.x
+                && max(|p| p.x) >= rect.min.x
+                && min(|p| p.y) <= rect.max.y
+                && max(|p| p.y) >= rect.min.y
+            {
+                triangles.push(corners);
+            }
+        });
+        triangles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::xorshift;
+
+    #[test]
+    fn test_sites_in_rect_grid() {
+        let mut next = xorshift(41);
+        let grid: Vec<Point2> = (0..900)
+            .map(|i| Point2 {
+                x: (i % 30) as f64,
+                y: (i / 30) as f64,
+            })
+            .collect();
+        let triangulation = Triangulation::new(&grid);
+        let brute = |window: (Point2, Point2)| -> Vec<usize> {
+            let rect = window_rect(window);
+            (0..triangulation.points().len())
+                .filter(|&vertex| in_rect(&rect, &triangulation.points()[vertex]))
+                .collect()
+        };
+
+        let mut windows = vec![
+            // Straddling the hull, whole mesh, outside, thin and on grid lines
+            (Point2 { x: -5., y: 10.5 }, Point2 { x: 3.5, y: 14.5 }),
+            (Point2 { x: 40., y: 40. }, Point2 { x: -10., y: -10. }),
+            (Point2 { x: 31., y: 0. }, Point2 { x: 40., y: 29. }),
+            (Point2 { x: -3., y: 5. }, Point2 { x: 35., y: 5. }),
+            (Point2 { x: 2., y: 2. }, Point2 { x: 2., y: 2. }),
+            (Point2 { x: 25., y: -8. }, Point2 { x: 45., y: 1. }),
+        ];
+        for _ in 0..50 {
+            let a = Point2 {
+                x: next() * 40. - 5.,
+                y: next() * 40. - 5.,
+            };
+            let b = Point2 {
+                x: a.x + next() * 8.,
+                y: a.y + next() * 8.,
+            };
+            windows.push((a, b));
+        }
+        for window in windows {
+            assert_eq!(triangulation.sites_in_rect(window), brute(window));
+        }
+    }
+
+    #[test]
+    fn test_clip_edges() {
+        let mut next = xorshift(47);
+        let points: Vec<Point2> = (0..200)
+            .map(|_| Point2 {
+                x: next() * 100.,
+                y: next() * 100.,
+            })
+            .collect();
+        let triangulation = Triangulation::new(&points);
+        let arena = triangulation.quad_arena();
+        let everything = Rect {
+            min: Point2 { x: -1., y: -1. },
+            max: Point2 { x: 101., y: 101. },
+        };
+        let all: Vec<(Point2, Point2)> = triangulation
+            .live_edges()
+            .map(|edge| edge.org_dest(arena))
+            .collect();
+        assert_eq!(triangulation.clip_edges(everything), all);
+        let mut count = 0;
+        triangulation.for_each_face(|_| count += 1);
+        assert_eq!(triangulation.triangles_in_rect(everything).len(), count);
+
+        // A tiny window around a site only keeps the edges leaving it, cut short
+        let site = triangulation.points()[100];
+        let tiny = Rect {
+            min: Point2 {
+                x: site.x - 1e-3,
+                y: site.y - 1e-3,
+            },
+            max: Point2 {
+                x: site.x + 1e-3,
+                y: site.y + 1e-3,
+            },
+        };
+        let clipped = triangulation.clip_edges(tiny);
+        assert_eq!(clipped.len(), triangulation.onext_ring(100).count());
+        for (a, b) in &clipped {
+            assert!(*a == site || *b == site);
+            assert!(in_rect(&tiny, a) && in_rect(&tiny, b));
+        }
+        let triangles = triangulation.triangles_in_rect(tiny);
+        assert!(triangles.iter().all(|corners| corners.contains(&site)));
+
+        // Degenerate rectangles and edges along the border don't give NaN
+        let line = Rect {
+            min: Point2 { x: 50., y: -10. },
+            max: Point2 { x: 50., y: 110. },
+        };
+        for (a, b) in triangulation.clip_edges(line) {
+            assert!(a.x == 50. && b.x == 50. && !a.y.is_nan() && !b.y.is_nan());
+        }
+        let border = Rect {
+            min: Point2 { x: 0., y: 0. },
+            max: Point2 { x: 10., y: 10. },
+        };
+        assert_eq!(
+            clip_segment(Point2 { x: -5., y: 0. }, Point2 { x: 5., y: 0. }, &border),
+            Some((Point2 { x: 0., y: 0. }, Point2 { x: 5., y: 0. }))
+        );
+        assert_eq!(
+            clip_segment(Point2 { x: -5., y: -1. }, Point2 { x: 5., y: -1. }, &border),
+            None
+        );
+    }
+}
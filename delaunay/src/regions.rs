@@ -0,0 +1,354 @@
+/// Region attributes of the faces and holes, flooded from seed points like Triangle's `-A`
+/// switch and hole list
+///
+/// The mesh has no constrained edge insertion: the constraints, pairs of site indices like
+/// `check_constraints` takes, must already be edges of the triangulation. A flood starts at the
+/// face holding its seed and spreads to the neighboring faces across every edge that isn't a
+/// constraint.
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::edge::EdgeRef;
+use crate::robust_float::Point2;
+use crate::triangulation::{Location, Triangulation};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RegionError {
+    /// The constraint at this position isn't an edge of the triangulation
+    MissingConstraint(usize),
+    /// The seed at this position is outside of the hull
+    Outside(usize),
+    /// The seed at this position lies on a constraint, or on one of its endpoints
+    OnConstraint(usize),
+    /// Floods of these two region ids reached the same face, the first one got there first
+    Conflict(u32, u32),
+}
+
+impl fmt::Display for RegionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegionError::MissingConstraint(idx) => {
+                write!(f, "Constraint {} isn't an edge of the triangulation", idx)
+            }
+            RegionError::Outside(idx) => write!(f, "Seed {} is outside of the hull", idx),
+            RegionError::OnConstraint(idx) => write!(f, "Seed {} lies on a constraint", idx),
+            RegionError::Conflict(a, b) => {
+                write!(f, "Regions {} and {} flood the same faces", a, b)
+            }
+        }
+    }
+}
+
+/// Where a flood starts
+enum Seed {
+    /// Face on the left of the edge
+    Face(EdgeRef),
+    /// Outside of the hull, or in a face with a ghost corner
+    Outside,
+}
+
+/// Bounded faces of the mesh without a ghost corner, indexed like `triangles()`, and the
+/// constraint edges the floods don't cross
+struct Floods {
+    /// Every edge of a face leads to its index
+    faces: HashMap<EdgeRef, usize>,
+    count: usize,
+    /// Constraint edges, in both directions
+    walls: HashSet<EdgeRef>,
+}
+
+impl Floods {
+    fn new(
+        triangulation: &Triangulation,
+        constraints: &[(usize, usize)],
+    ) -> Result<Floods, RegionError> {
+        let quad_arena = triangulation.quad_arena();
+        let mut walls: HashSet<EdgeRef> = HashSet::new();
+        for (idx, &(a, b)) in constraints.iter().enumerate() {
+            let dest = triangulation.points().get(b).filter(|_| a != b);
+            let edge = dest.and_then(|dest| {
+                triangulation
+                    .onext_ring(a)
+                    .find(|edge| edge.dest(quad_arena) == *dest)
+            });
+            let edge = edge.ok_or(RegionError::MissingConstraint(idx))?;
+            walls.extend([edge, edge.sym()]);
+        }
+
+        // Walk the faces in `triangles_iter` order
+        let mut faces: HashMap<EdgeRef, usize> = HashMap::new();
+        let mut count = 0;
+        for edge in triangulation
+            .live_edges()
+            .flat_map(|edge| [edge, edge.sym()])
+        {
+            let Some(corners) = triangulation.left_triangle(edge) else {
+                continue;
+            };
+            let second = edge.lnext(quad_arena);
+            let third = second.lnext(quad_arena);
+            if edge < second
+                && edge < third
+                && !corners.iter().any(|&corner| triangulation.is_ghost(corner))
+            {
+                faces.extend([(edge, count), (second, count), (third, count)]);
+                count += 1;
+            }
+        }
+        Ok(Floods {
+            faces,
+            count,
+            walls,
+        })
+    }
+
+    /// Face holding the seed at position `idx`
+    fn locate(
+        &self,
+        triangulation: &Triangulation,
+        idx: usize,
+        point: Point2,
+    ) -> Result<Seed, RegionError> {
+        if self.count == 0 {
+            return Ok(Seed::Outside);
+        }
+        let start = match triangulation.locate(point) {
+            Location::Outside(_) => return Ok(Seed::Outside),
+            Location::Vertex(vertex) => {
+                if triangulation
+                    .onext_ring(vertex)
+                    .any(|edge| self.walls.contains(&edge))
+                {
+                    return Err(RegionError::OnConstraint(idx));
+                }
+                triangulation
+                    .onext_ring(vertex)
+                    .find(|edge| self.faces.contains_key(edge))
+            }
+            Location::OnEdge(edge) if self.walls.contains(&edge) => {
+                return Err(RegionError::OnConstraint(idx))
+            }
+            Location::Inside(edge) | Location::OnEdge(edge) => {
+                Some(edge).filter(|edge| self.faces.contains_key(edge))
+            }
+        };
+        Ok(start.map_or(Seed::Outside, Seed::Face))
+    }
+
+    /// Label the faces reached from the face on the left of `start` with `id`, failing on a face
+    /// labeled with another id
+    fn flood(
+        &self,
+        triangulation: &Triangulation,
+        labels: &mut [Option<u32>],
+        start: EdgeRef,
+        id: u32,
+    ) -> Result<(), RegionError> {
+        let quad_arena = triangulation.quad_arena();
+        let mut stack = vec![];
+        let mut visit = |edge: EdgeRef, stack: &mut Vec<EdgeRef>| {
+            let Some(&face) = self.faces.get(&edge) else {
+                return Ok(());
+            };
+            match labels[face] {
+                Some(other) if other == id => Ok(()),
+                Some(other) => Err(RegionError::Conflict(other, id)),
+                None => {
+                    labels[face] = Some(id);
+                    stack.push(edge);
+                    Ok(())
+                }
+            }
+        };
+        visit(start, &mut stack)?;
+        while let Some(edge) = stack.pop() {
+            let second = edge.lnext(quad_arena);
+            for side in [edge, second, second.lnext(quad_arena)] {
+                if !self.walls.contains(&side) {
+                    visit(side.sym(), &mut stack)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Triangulation {
+    /// Label the faces with the region id of the seed whose flood reaches them, without crossing
+    /// the `constraints`. Faces are indexed like `triangles()`, the ones no flood reaches are
+    /// left unlabeled. Several seeds of the same id may share a region.
+    pub fn assign_regions(
+        &self,
+        constraints: &[(usize, usize)],
+        seeds: &[(Point2, u32)],
+    ) -> Result<Vec<Option<u32>>, RegionError> {
+        let floods = Floods::new(self, constraints)?;
+        let mut labels: Vec<Option<u32>> = vec![None; floods.count];
+        for (idx, &(point, id)) in seeds.iter().enumerate() {
+            match floods.locate(self, idx, point)? {
+                Seed::Face(start) => floods.flood(self, &mut labels, start, id)?,
+                Seed::Outside => return Err(RegionError::Outside(idx)),
+            }
+        }
+        Ok(labels)
+    }
+
+    /// Remove the faces reached from the `hole_seeds` without crossing the `constraints`, and
+    /// return how many were removed. A seed outside of the hull carves from the outside in,
+    /// through every hull edge that isn't a constraint, which trims the hull of a concave domain.
+    ///
+    /// Edges with a removed or the outer face on both sides are deleted, sites left without
+    /// edges count as removed. A carved face only bounded by constraints keeps its edges, so it
+    /// still reads as a triangle. Queries walking the mesh (`locate`, insertions) expect a convex
+    /// mesh: carve once the mesh is done.
+    pub fn carve_holes(
+        &mut self,
+        constraints: &[(usize, usize)],
+        hole_seeds: &[Point2],
+    ) -> Result<usize, RegionError> {
+        let floods = Floods::new(self, constraints)?;
+        let mut carved: Vec<Option<u32>> = vec![None; floods.count];
+        for (idx, &point) in hole_seeds.iter().enumerate() {
+            match floods.locate(self, idx, point)? {
+                Seed::Face(start) => floods.flood(self, &mut carved, start, 0)?,
+                Seed::Outside => {
+                    for edge in self.hull_edges() {
+                        if !floods.walls.contains(&edge) {
+                            floods.flood(self, &mut carved, edge.sym(), 0)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        let gone = |edge: EdgeRef| match floods.faces.get(&edge) {
+            Some(&face) => carved[face].is_some(),
+            None => self.left_triangle(edge).is_none(),
+        };
+        let doomed: Vec<EdgeRef> = self
+            .live_edges()
+            .filter(|&edge| gone(edge) && gone(edge.sym()))
+            .collect();
+        self.delete_edges(&doomed);
+        Ok(carved.iter().flatten().count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assign_regions() {
+        // A square with one corner pulled out, so the constrained diagonal is a Delaunay edge
+        let points = [
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 4., y: 0. },
+            Point2 { x: 0., y: 4. },
+            Point2 { x: 5., y: 5. },
+        ];
+        let triangulation = Triangulation::new(&points);
+        let index = |x, y| triangulation.vertex_index(&Point2 { x, y }).unwrap();
+        let diagonal = [(index(4., 0.), index(0., 4.))];
+        let triangles = triangulation.triangles();
+        assert_eq!(triangles.len(), 2);
+
+        let seeds = [(Point2 { x: 1., y: 1. }, 7), (Point2 { x: 3., y: 3. }, 9)];
+        let labels = triangulation.assign_regions(&diagonal, &seeds).unwrap();
+        for (corners, label) in triangles.iter().zip(&labels) {
+            let expected = if corners.contains(&Point2 { x: 0., y: 0. }) {
+                7
+            } else {
+                9
+            };
+            assert_eq!(*label, Some(expected));
+        }
+
+        // One seed floods its half only
+        let labels = triangulation
+            .assign_regions(&diagonal, &seeds[..1])
+            .unwrap();
+        assert_eq!(labels.iter().flatten().count(), 1);
+        // Without the constraint both seeds reach both faces
+        assert_eq!(
+            triangulation.assign_regions(&[], &seeds),
+            Err(RegionError::Conflict(7, 9))
+        );
+        assert_eq!(
+            triangulation.assign_regions(&[], &[seeds[0], (seeds[1].0, 7)]),
+            Ok(vec![Some(7); 2])
+        );
+
+        assert_eq!(
+            triangulation.assign_regions(&diagonal, &[(Point2 { x: 2., y: 2. }, 1)]),
+            Err(RegionError::OnConstraint(0))
+        );
+        assert_eq!(
+            triangulation.assign_regions(&diagonal, &[seeds[0], (Point2 { x: 4., y: 0. }, 1)]),
+            Err(RegionError::OnConstraint(1))
+        );
+        assert_eq!(
+            triangulation.assign_regions(&diagonal, &[(Point2 { x: -1., y: 2. }, 1)]),
+            Err(RegionError::Outside(0))
+        );
+        // The other diagonal isn't an edge
+        assert_eq!(
+            triangulation.assign_regions(&[(index(0., 0.), index(5., 5.))], &seeds),
+            Err(RegionError::MissingConstraint(0))
+        );
+    }
+
+    #[test]
+    fn test_carve_holes() {
+        // Donut: a 6x6 square around a 2x2 square hole, with an extra site in the hole
+        let outer = [(0., 0.), (6., 0.), (6., 6.), (0., 6.)];
+        let inner = [(2., 2.), (4., 2.), (4., 4.), (2., 4.)];
+        let mut points: Vec<Point2> = outer
+            .iter()
+            .chain(&inner)
+            .map(|&(x, y)| Point2 { x, y })
+            .collect();
+        points.push(Point2 { x: 3., y: 3.1 });
+        let donut = Triangulation::new(&points);
+        let index = |&(x, y): &(f64, f64)| donut.vertex_index(&Point2 { x, y }).unwrap();
+        let ring: Vec<(usize, usize)> = (0..4)
+            .map(|side| (index(&inner[side]), index(&inner[(side + 1) % 4])))
+            .collect();
+        let total = donut.triangles().len();
+
+        let mut carved = Triangulation::new(&points);
+        let removed = carved
+            .carve_holes(&ring, &[Point2 { x: 2.5, y: 3. }])
+            .unwrap();
+        assert_eq!(removed, 4);
+        // Nothing left inside of the hole, the 8 faces of the frame are there
+        let triangles = carved.triangles();
+        assert_eq!(triangles.len(), total - 4);
+        assert_eq!(triangles.len(), 8);
+        for corners in &triangles {
+            let center = Point2 {
+                x: corners.iter().map(|point| point.x).sum::<f64>() / 3.,
+                y: corners.iter().map(|point| point.y).sum::<f64>() / 3.,
+            };
+            assert!(!(2. ..=4.).contains(&center.x) || !(2. ..=4.).contains(&center.y));
+        }
+        let middle = carved.vertex_index(&Point2 { x: 3., y: 3.1 }).unwrap();
+        assert!(carved.is_removed(middle));
+        assert_eq!(carved.live_edges().count(), 16);
+        // The hole is a face of the arena but not of the counts
+        assert_eq!(carved.counts(), (8, 16, 9));
+
+        // Carving from the outside, the frame goes and the hole stays
+        let mut inside = Triangulation::new(&points);
+        let removed = inside
+            .carve_holes(&ring, &[Point2 { x: -1., y: -1. }])
+            .unwrap();
+        assert_eq!((removed, inside.triangles().len()), (8, 4));
+        assert!(inside.is_removed(index(&outer[0])));
+
+        assert_eq!(
+            Triangulation::new(&points).carve_holes(&ring, &[Point2 { x: 3., y: 2. }]),
+            Err(RegionError::OnConstraint(0))
+        );
+    }
+}
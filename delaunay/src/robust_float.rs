@@ -1,10 +1,13 @@
+use core::cmp::Ordering;
 use core::f64;
-use robust;
-use std::fmt::Display;
+use core::fmt::Display;
+use core::hash::{Hash, Hasher};
 
-const EPSILON: f64 = f64::EPSILON * 2.;
+use alloc::vec::Vec;
 
-/// Points have top-right x,y coordinates:
+pub(crate) const EPSILON: f64 = f64::EPSILON * 2.;
+
+/// Points have screen x,y coordinates, from the top-left corner with y growing downward:
 /// 0,0 ------- 1,0
 ///  |           |
 ///  |           |
@@ -16,12 +19,107 @@ pub struct Point2 {
     pub y: f64,
 }
 
+impl Point2 {
+    /// Euclidean distance to `other`
+    #[cfg(any(not(feature = "no_std"), test))]
+    pub fn distance(&self, other: &Point2) -> f64 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+
+    /// The point as a key of maps and sets, see `OrderedPoint2`
+    pub fn ordered(self) -> OrderedPoint2 {
+        OrderedPoint2(self)
+    }
+}
+
 impl Display for Point2 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "(x: {},y: {})", self.x, self.y)
     }
 }
 
+/// `Point2` with a total order, `Eq` and `Hash`, to key `HashMap`s and `BTreeSet`s by points.
+/// Ordered like `cmp_points`, x first then y.
+///
+/// Coordinates are compared exactly, with -0 the same as 0. Every NaN is the same coordinate,
+/// greater than every number and infinity. The wrapped point is kept as given.
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(transparent)]
+pub struct OrderedPoint2(pub Point2);
+
+/// The coordinate with -0 turned into 0 and every NaN into the same positive one
+#[inline(always)]
+fn canonical(value: f64) -> f64 {
+    if value.is_nan() {
+        f64::NAN
+    } else {
+        value + 0.
+    }
+}
+
+impl OrderedPoint2 {
+    fn key(&self) -> (u64, u64) {
+        (canonical(self.0.x).to_bits(), canonical(self.0.y).to_bits())
+    }
+}
+
+impl From<Point2> for OrderedPoint2 {
+    fn from(point: Point2) -> OrderedPoint2 {
+        OrderedPoint2(point)
+    }
+}
+
+impl From<OrderedPoint2> for Point2 {
+    fn from(point: OrderedPoint2) -> Point2 {
+        point.0
+    }
+}
+
+impl PartialEq for OrderedPoint2 {
+    fn eq(&self, other: &OrderedPoint2) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for OrderedPoint2 {}
+
+impl Hash for OrderedPoint2 {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl PartialOrd for OrderedPoint2 {
+    fn partial_cmp(&self, other: &OrderedPoint2) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedPoint2 {
+    fn cmp(&self, other: &OrderedPoint2) -> Ordering {
+        canonical(self.0.x)
+            .total_cmp(&canonical(other.0.x))
+            .then(canonical(self.0.y).total_cmp(&canonical(other.0.y)))
+    }
+}
+
+/// Axis aligned rectangle, `min` is its top-left corner and `max` its bottom-right corner
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub struct Rect {
+    pub min: Point2,
+    pub max: Point2,
+}
+
+impl Rect {
+    pub fn width(&self) -> f64 {
+        self.max.x - self.min.x
+    }
+
+    pub fn height(&self) -> f64 {
+        self.max.y - self.min.y
+    }
+}
+
 impl From<&Point2> for robust::Coord<f64> {
     fn from(p: &Point2) -> robust::Coord<f64> {
         robust::Coord::<f64> { x: p.x, y: p.y }
@@ -33,29 +131,231 @@ pub fn nearly_equals(a: &Point2, b: &Point2) -> bool {
     (a.x - b.x).abs() <= EPSILON && (a.y - b.y).abs() <= EPSILON
 }
 
+/// Return true if the current thread evaluates the predicates with `PredicateBackend::Exact`
+#[cfg(feature = "exact")]
+#[inline(always)]
+fn exact_backend() -> bool {
+    crate::exact::predicate_backend() == crate::exact::PredicateBackend::Exact
+}
+
 #[inline(always)]
 pub fn in_circle(a: &Point2, b: &Point2, c: &Point2, d: &Point2) -> bool {
-    robust::incircle(a.into(), b.into(), c.into(), d.into()) < 0.
+    incircle_value(a, b, c, d) < 0.
+}
+
+/// `in_circle` with the first two points fixed, the base edge the merge tests its candidates
+/// against. Converting them once doesn't measurably speed up a triangulation, this is the shape
+/// of the `Base` of the float kernel.
+pub(crate) struct InCircleBase {
+    a: robust::Coord<f64>,
+    b: robust::Coord<f64>,
 }
 
+impl InCircleBase {
+    #[inline(always)]
+    pub(crate) fn new(a: &Point2, b: &Point2) -> InCircleBase {
+        InCircleBase {
+            a: a.into(),
+            b: b.into(),
+        }
+    }
+
+    /// Same as `in_circle(a, b, c, d)`
+    #[inline(always)]
+    pub(crate) fn in_circle(&self, c: &Point2, d: &Point2) -> bool {
+        #[cfg(feature = "exact")]
+        if exact_backend() {
+            let [a, b] = [self.a, self.b].map(|coord| Point2 {
+                x: coord.x,
+                y: coord.y,
+            });
+            return in_circle(&a, &b, c, d);
+        }
+        robust::incircle(self.a, self.b, c.into(), d.into()) < 0.
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Calls to `counter_clockwise` made by the current thread, for tests measuring work
+    pub(crate) static ORIENTATION_TESTS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Return true if a, b, c turn strictly counter clockwise, as seen on a screen with the crate's
+/// y-down axis: `robust::orient2d` is negative. In a y-up (math) frame the same triangle turns
+/// clockwise, flip one axis or swap two corners when handing triangles to such a renderer.
+///
+/// Every triangle the crate outputs follows this orientation, and zero area triangles are never
+/// output.
 #[inline(always)]
 pub fn counter_clockwise(a: &Point2, b: &Point2, c: &Point2) -> bool {
-    robust::orient2d(a.into(), b.into(), c.into()) < 0.
+    #[cfg(test)]
+    ORIENTATION_TESTS.with(|count| count.set(count.get() + 1));
+    orient2d_value(a, b, c) < 0.
 }
 
-pub fn sort_points(points: &mut Vec<Point2>) {
-    points.sort_by(|a, b| match a.x.partial_cmp(&b.x) {
-        Some(ord) => match ord {
-            std::cmp::Ordering::Equal => a.y.partial_cmp(&b.y).unwrap(),
-            x => x,
-        },
-        None => a.y.partial_cmp(&b.y).unwrap(),
+/// Turning direction of three points, as seen on a screen with the crate's y-down axis
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Winding {
+    /// The turn of `counter_clockwise`, the one of every triangle the crate outputs
+    CounterClockwise,
+    Clockwise,
+    Collinear,
+}
+
+/// Exact winding of a, b, c, see `Winding`
+pub fn winding(a: &Point2, b: &Point2, c: &Point2) -> Winding {
+    let value = orient2d_value(a, b, c);
+    if value < 0. {
+        Winding::CounterClockwise
+    } else if value > 0. {
+        Winding::Clockwise
+    } else {
+        Winding::Collinear
+    }
+}
+
+/// Area of the triangle a, b, c, positive when they turn `counter_clockwise`, negative when they
+/// turn clockwise. The opposite of the usual y-up formula, which the y-down axis mirrors.
+pub fn signed_area(a: &Point2, b: &Point2, c: &Point2) -> f64 {
+    -orient2d_value(a, b, c) / 2.
+}
+
+/// Twice the signed area of the polygon, with the sign of the y-up shoelace formula: the sum of
+/// `a.x * b.y - b.x * a.y` over its sides. The sides are taken relative to the first vertex, so
+/// coordinates far from the origin, like UTM ones, don't cancel out in the products.
+pub fn shoelace(polygon: &[Point2]) -> f64 {
+    let Some(origin) = polygon.first() else {
+        return 0.;
+    };
+    (0..polygon.len())
+        .map(|idx| {
+            let (a, b) = (&polygon[idx], &polygon[(idx + 1) % polygon.len()]);
+            (a.x - origin.x) * (b.y - origin.y) - (b.x - origin.x) * (a.y - origin.y)
+        })
+        .sum()
+}
+
+/// Position of a point relative to a triangle
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Region {
+    Inside,
+    OnBoundary,
+    Outside,
+}
+
+/// Locate `q` relative to the triangle `a`, `b`, `c` with exact orientation tests, whatever the
+/// triangle winding. A zero area triangle has no inside, its boundary is the segment it covers.
+pub fn point_in_triangle(q: Point2, a: Point2, b: Point2, c: Point2) -> Region {
+    let orient = |p: &Point2, r: &Point2| orient2d_value(&q, p, r);
+    let sides = [orient(&a, &b), orient(&b, &c), orient(&c, &a)];
+    if orient2d_value(&a, &b, &c) == 0. {
+        let between = |p: &Point2, r: &Point2| {
+            q.x >= p.x.min(r.x) && q.x <= p.x.max(r.x) && q.y >= p.y.min(r.y) && q.y <= p.y.max(r.y)
+        };
+        let on_side = sides[0] == 0. && between(&a, &b)
+            || sides[1] == 0. && between(&b, &c)
+            || sides[2] == 0. && between(&c, &a);
+        return if on_side {
+            Region::OnBoundary
+        } else {
+            Region::Outside
+        };
+    }
+    // Inside points see every side turning the same way, the triangle's winding
+    if sides.iter().all(|&side| side < 0.) || sides.iter().all(|&side| side > 0.) {
+        Region::Inside
+    } else if sides.iter().all(|&side| side <= 0.) || sides.iter().all(|&side| side >= 0.) {
+        Region::OnBoundary
+    } else {
+        Region::Outside
+    }
+}
+
+/// Exact `robust::orient2d` of the three points: positive when they turn counter clockwise in a
+/// y-up frame, so negative when `counter_clockwise` is true, zero when they are collinear. The
+/// magnitude is twice the area of the triangle.
+///
+/// This and `incircle_value` are the predicates every other one goes through, so they are where
+/// the `exact` feature switches backends, see `exact::with_predicate_backend`.
+#[inline(always)]
+pub fn orient2d_value(a: &Point2, b: &Point2, c: &Point2) -> f64 {
+    #[cfg(feature = "exact")]
+    if exact_backend() {
+        return crate::exact::orient2d_value(a, b, c);
+    }
+    robust::orient2d(a.into(), b.into(), c.into())
+}
+
+/// Exact `robust::incircle` of the four points: positive when `d` is inside of the circle through
+/// `a`, `b` and `c` turning counter clockwise in a y-up frame, so negative when `in_circle` is
+/// true, zero when the four points are cocircular. The sign flips with the winding of `a`, `b`,
+/// `c`.
+#[inline(always)]
+pub fn incircle_value(a: &Point2, b: &Point2, c: &Point2, d: &Point2) -> f64 {
+    #[cfg(feature = "exact")]
+    if exact_backend() {
+        return crate::exact::incircle_value(a, b, c, d);
+    }
+    robust::incircle(a.into(), b.into(), c.into(), d.into())
+}
+
+/// Center of the circle going through the three points, with infinite or NaN coordinates when
+/// they are collinear
+pub fn circumcenter(a: &Point2, b: &Point2, c: &Point2) -> Point2 {
+    let (bx, by) = (b.x - a.x, b.y - a.y);
+    let (cx, cy) = (c.x - a.x, c.y - a.y);
+    let det = 2. * (bx * cy - by * cx);
+    let (b_norm, c_norm) = (bx * bx + by * by, cx * cx + cy * cy);
+    Point2 {
+        x: a.x + (cy * b_norm - by * c_norm) / det,
+        y: a.y + (bx * c_norm - cx * b_norm) / det,
+    }
+}
+
+/// Lexicographic ordering of points, x first then y, used to sort the sites before
+/// triangulating. The order of `OrderedPoint2`, NaN coordinates included.
+pub fn cmp_points(a: &Point2, b: &Point2) -> Ordering {
+    a.ordered().cmp(&b.ordered())
+}
+
+/// `cmp_points`, then the bits of the coordinates for the points it finds equal, like 0 and -0
+pub(crate) fn cmp_points_bits(a: &Point2, b: &Point2) -> Ordering {
+    cmp_points(a, b)
+        .then(a.x.to_bits().cmp(&b.x.to_bits()))
+        .then(a.y.to_bits().cmp(&b.y.to_bits()))
+}
+
+/// Sort the points with `cmp_points`, the points it finds equal by the bits of their
+/// coordinates: the order is deterministic without a stable sort, which allocates.
+pub fn sort_points(points: &mut [Point2]) {
+    points.sort_unstable_by(cmp_points_bits);
+}
+
+/// Sort the points lexicographically by the key, first member first
+pub fn sort_points_by(points: &mut [Point2], key: impl Fn(&Point2) -> (f64, f64)) {
+    points.sort_by(|a, b| {
+        let (a, b) = (key(a), key(b));
+        a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1))
     });
 }
 
-fn remove_near_equal_points(points: &mut Vec<Point2>) {
+/// Order the sites are left in once sanitized, which also picks the axis the divide and conquer
+/// splits the sites along. Other orders, like `sort_points_by` gives, can't split the sites: the
+/// merge needs halves a line separates.
+#[derive(Copy, Clone, Debug, Default)]
+pub enum SortOrder {
+    /// x first then y, like `cmp_points`. The recursion splits the sites along x.
+    #[default]
+    XMajor,
+    /// y first then x. The recursion splits the sites along y, which balances it better on
+    /// sites spread along x.
+    YMajor,
+}
+
+pub(crate) fn remove_near_equal_points(points: &mut Vec<Point2>) {
     let mut idx = 0;
-    while idx < points.len() - 1 {
+    while idx + 1 < points.len() {
         if nearly_equals(&points[idx], &points[idx + 1]) {
             points.remove(idx);
         } else {
@@ -65,13 +365,22 @@ fn remove_near_equal_points(points: &mut Vec<Point2>) {
 }
 
 pub fn sanitize_points_vec(points: &mut Vec<Point2>) {
-    sort_points(points);
+    sanitize_points_vec_with(points, SortOrder::XMajor);
+}
+
+/// Sort the points in the given order and remove the near-equal ones
+pub fn sanitize_points_vec_with(points: &mut Vec<Point2>, order: SortOrder) {
+    match order {
+        SortOrder::XMajor => sort_points(points),
+        SortOrder::YMajor => sort_points_by(points, |point| (point.y, point.x)),
+    }
     remove_near_equal_points(points);
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::xorshift_bits;
 
     #[test]
     fn test_in_circle() {
@@ -80,8 +389,8 @@ mod tests {
         let c = Point2 { x: 1., y: 0. };
         let d = Point2 { x: 1., y: 1. };
         let e = Point2 { x: 0.5, y: 0.5 };
-        assert_eq!(in_circle(&a, &b, &c, &d), false);
-        assert_eq!(in_circle(&a, &b, &c, &e), true);
+        assert!(!in_circle(&a, &b, &c, &d));
+        assert!(in_circle(&a, &b, &c, &e));
     }
 
     #[test]
@@ -91,12 +400,119 @@ mod tests {
         let c = Point2 { x: 1., y: 0. };
         let d = Point2 { x: 1., y: 1. };
         let e = Point2 { x: 0.5, y: 0.5 };
-        assert_eq!(counter_clockwise(&a, &b, &c), true);
-        assert_eq!(counter_clockwise(&a, &c, &b), false);
-        assert_eq!(counter_clockwise(&c, &b, &a), false);
-        assert_eq!(counter_clockwise(&b, &c, &a), true);
-        assert_eq!(counter_clockwise(&e, &c, &a), true);
-        assert_eq!(counter_clockwise(&e, &d, &a), false);
+        assert!(counter_clockwise(&a, &b, &c));
+        assert!(!counter_clockwise(&a, &c, &b));
+        assert!(!counter_clockwise(&c, &b, &a));
+        assert!(counter_clockwise(&b, &c, &a));
+        assert!(counter_clockwise(&e, &c, &a));
+        assert!(!counter_clockwise(&e, &d, &a));
+    }
+
+    #[test]
+    fn test_screen_winding() {
+        // Top-left, top-right, bottom-right of a screen: the eye sees a clockwise turn
+        let top_left = Point2 { x: 0., y: 0. };
+        let top_right = Point2 { x: 4., y: 0. };
+        let bottom_right = Point2 { x: 4., y: 3. };
+        assert_eq!(
+            winding(&top_left, &top_right, &bottom_right),
+            Winding::Clockwise
+        );
+        assert!(!counter_clockwise(&top_left, &top_right, &bottom_right));
+        assert_eq!(signed_area(&top_left, &top_right, &bottom_right), -6.);
+        // Going down first turns the other way
+        assert_eq!(
+            winding(&top_left, &bottom_right, &top_right),
+            Winding::CounterClockwise
+        );
+        assert!(counter_clockwise(&top_left, &bottom_right, &top_right));
+        assert_eq!(signed_area(&top_left, &bottom_right, &top_right), 6.);
+        assert_eq!(
+            winding(&top_left, &top_right, &Point2 { x: 8., y: 0. }),
+            Winding::Collinear
+        );
+        assert_eq!(
+            signed_area(&top_left, &top_right, &Point2 { x: 8., y: 0. }),
+            0.
+        );
+    }
+
+    #[test]
+    fn test_raw_values() {
+        let a = Point2 { x: 0., y: 0. };
+        let b = Point2 { x: 0., y: 2. };
+        let c = Point2 { x: 2., y: 0. };
+        let inside = Point2 { x: 1., y: 1. };
+        let outside = Point2 { x: 3., y: 3. };
+        // Twice the area, with the sign opposite to `counter_clockwise`
+        assert_eq!(orient2d_value(&a, &b, &c), -4.);
+        assert_eq!(orient2d_value(&a, &c, &b), 4.);
+        assert_eq!(orient2d_value(&a, &b, &Point2 { x: 0., y: 5. }), 0.);
+        for (p, q, r) in [(&a, &b, &c), (&a, &c, &b), (&inside, &a, &b)] {
+            assert_eq!(counter_clockwise(p, q, r), orient2d_value(p, q, r) < 0.);
+        }
+        for d in [&inside, &outside, &Point2 { x: 2., y: 2. }] {
+            for (p, q, r) in [(&a, &b, &c), (&a, &c, &b)] {
+                assert_eq!(in_circle(p, q, r, d), incircle_value(p, q, r, d) < 0.);
+            }
+        }
+        assert!(incircle_value(&a, &b, &c, &inside) < 0.);
+        assert!(incircle_value(&a, &c, &b, &inside) > 0.);
+        assert_eq!(incircle_value(&a, &b, &c, &Point2 { x: 2., y: 2. }), 0.);
+    }
+
+    #[test]
+    fn test_in_circle_base() {
+        let mut bits = xorshift_bits(19);
+        let mut next = move || {
+            // Coarse coordinates, to hit the cocircular cases too
+            ((bits() >> 11) % 8) as f64
+        };
+        for _ in 0..2000 {
+            let [a, b, c, d] = [(); 4].map(|_| Point2 {
+                x: next(),
+                y: next(),
+            });
+            let base = InCircleBase::new(&a, &b);
+            assert_eq!(base.in_circle(&c, &d), in_circle(&a, &b, &c, &d));
+        }
+    }
+
+    #[test]
+    fn test_point_in_triangle() {
+        let a = Point2 { x: 0., y: 0. };
+        let b = Point2 { x: 4., y: 0. };
+        let c = Point2 { x: 0., y: 4. };
+        let cases = [
+            (Point2 { x: 1., y: 1. }, Region::Inside),
+            (Point2 { x: 3., y: 3. }, Region::Outside),
+            (Point2 { x: -1., y: 0. }, Region::Outside),
+            (Point2 { x: 2., y: 2. }, Region::OnBoundary),
+            (Point2 { x: 2., y: 0. }, Region::OnBoundary),
+            (b, Region::OnBoundary),
+        ];
+        for (q, region) in cases {
+            // Both windings
+            assert_eq!(point_in_triangle(q, a, b, c), region);
+            assert_eq!(point_in_triangle(q, a, c, b), region);
+        }
+
+        let flat = Point2 { x: 8., y: 0. };
+        let q = Point2 { x: 6., y: 0. };
+        assert_eq!(point_in_triangle(q, a, b, flat), Region::OnBoundary);
+        let beyond = Point2 { x: 9., y: 0. };
+        assert_eq!(point_in_triangle(beyond, a, b, flat), Region::Outside);
+        assert_eq!(point_in_triangle(c, a, b, flat), Region::Outside);
+    }
+
+    #[test]
+    fn test_circumcenter() {
+        let a = Point2 { x: 1., y: 0. };
+        let b = Point2 { x: -1., y: 0. };
+        let c = Point2 { x: 0., y: 1. };
+        assert_eq!(circumcenter(&a, &b, &c), Point2 { x: 0., y: 0. });
+        let far = circumcenter(&a, &b, &Point2 { x: 3., y: 0. });
+        assert!(!far.x.is_finite() || !far.y.is_finite());
     }
 
     #[test]
@@ -121,4 +537,94 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_sanitize_sort_orders() {
+        let points = vec![
+            Point2 { x: 3., y: 1. },
+            Point2 { x: 0., y: 2. },
+            Point2 { x: 1., y: 1. },
+            Point2 { x: 0., y: 2. },
+            Point2 { x: 2., y: 0. },
+        ];
+        let mut by_y = points;
+        sanitize_points_vec_with(&mut by_y, SortOrder::YMajor);
+        assert_eq!(
+            by_y,
+            vec![
+                Point2 { x: 2., y: 0. },
+                Point2 { x: 1., y: 1. },
+                Point2 { x: 3., y: 1. },
+                Point2 { x: 0., y: 2. },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ordered_point() {
+        use crate::triangulation::Triangulation;
+        use std::collections::{BTreeSet, HashMap, HashSet};
+
+        let zero = Point2 { x: 0., y: 1. };
+        let negative_zero = Point2 { x: -0., y: 1. };
+        assert_eq!(zero.ordered(), negative_zero.ordered());
+        assert_eq!(
+            zero.ordered().cmp(&negative_zero.ordered()),
+            Ordering::Equal
+        );
+        let set: HashSet<OrderedPoint2> = [zero.ordered(), negative_zero.ordered()].into();
+        assert_eq!(set.len(), 1);
+        // The wrapped point is kept as given
+        assert!(Point2::from(negative_zero.ordered()).x.is_sign_negative());
+
+        // NaNs are equal, past infinity
+        let nan = Point2 { x: f64::NAN, y: 0. }.ordered();
+        let other_nan = Point2 {
+            x: -f64::NAN,
+            y: 0.,
+        }
+        .ordered();
+        let infinity = Point2 {
+            x: f64::INFINITY,
+            y: 0.,
+        }
+        .ordered();
+        assert_eq!(nan, other_nan);
+        assert!(infinity < nan);
+
+        let mut bits = xorshift_bits(41);
+        let mut next = move || {
+            // Few distinct values, so coordinates repeat
+            ((bits() >> 11) % 9) as f64 - 4.
+        };
+        let mut points: Vec<Point2> = (0..300)
+            .map(|_| Point2 {
+                x: next() * 0.5,
+                y: next(),
+            })
+            .collect();
+        points.push(negative_zero);
+        let mut ordered: Vec<OrderedPoint2> = points.iter().map(|&point| point.into()).collect();
+        ordered.sort();
+        sort_points(&mut points);
+        let unwrapped: Vec<Point2> = ordered.into_iter().map(Point2::from).collect();
+        assert_eq!(unwrapped, points);
+        let distinct: BTreeSet<OrderedPoint2> = points.iter().map(|&point| point.into()).collect();
+        assert!(distinct.len() < points.len());
+
+        // Join the sites of a triangulation back to their index
+        let triangulation = Triangulation::new(&points);
+        let index: HashMap<OrderedPoint2, usize> = triangulation
+            .points()
+            .iter()
+            .enumerate()
+            .map(|(idx, point)| (point.ordered(), idx))
+            .collect();
+        assert_eq!(index.len(), triangulation.points().len());
+        for (org, dest) in triangulation.edges() {
+            for point in [org, dest] {
+                assert_eq!(triangulation.points()[index[&point.ordered()]], point);
+            }
+        }
+    }
 }
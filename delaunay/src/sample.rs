@@ -0,0 +1,160 @@
+/// Uniform random points over the triangulated domain, for Monte Carlo integration or seeding
+/// a point process on the mesh
+///
+/// `sample_uniform` draws from a generator of the `rand` crate, behind the `rand` feature.
+/// `sample_uniform_with` takes the draws from a closure instead, for other generators.
+use crate::robust_float::{signed_area, Point2};
+use crate::triangulation::Triangulation;
+
+/// Cumulative area of the triangles of a mesh, cached on the triangulation until it changes
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SampleTable {
+    triangles: Vec<[Point2; 3]>,
+    /// Area of the triangles up to each one, included
+    cumulative: Vec<f64>,
+}
+
+impl SampleTable {
+    /// Table of the triangles of `triangulation`, without the zero area ones that can't be drawn
+    pub(crate) fn new(triangulation: &Triangulation) -> SampleTable {
+        let mut table = SampleTable::default();
+        let mut total = 0.;
+        for corners in triangulation.triangles_iter() {
+            let area = signed_area(&corners[0], &corners[1], &corners[2]);
+            if area > 0. {
+                total += area;
+                table.triangles.push(corners);
+                table.cumulative.push(total);
+            }
+        }
+        table
+    }
+
+    /// Triangle holding the area fraction `draw` of the total
+    fn pick(&self, draw: f64) -> &[Point2; 3] {
+        let total = self.cumulative.last().copied().unwrap_or_default();
+        let target = draw * total;
+        let idx = self.cumulative.partition_point(|&area| area <= target);
+        // A draw rounding up to the total lands past the last triangle
+        &self.triangles[idx.min(self.triangles.len() - 1)]
+    }
+}
+
+/// Point of the triangle for the uniform draws `r1` and `r2`. Taking the square root of `r1`
+/// spreads the points evenly instead of crowding them at the first corner.
+fn point_in(corners: &[Point2; 3], r1: f64, r2: f64) -> Point2 {
+    let [a, b, c] = corners;
+    let s = r1.sqrt();
+    let (wa, wb, wc) = (1. - s, s * (1. - r2), s * r2);
+    Point2 {
+        x: wa * a.x + wb * b.x + wc * c.x,
+        y: wa * a.y + wb * b.y + wc * c.y,
+    }
+}
+
+impl Triangulation {
+    /// Draw `n` points uniformly over the triangles of the mesh: each triangle is picked with a
+    /// probability proportional to its area, by a binary search in the cumulative areas, then a
+    /// point is drawn uniformly inside it with the square root barycentric formula.
+    ///
+    /// The cumulative areas are computed on the first call and kept until the mesh changes.
+    /// Empty when the mesh has no triangle, as with less than 3 sites or collinear ones.
+    #[cfg(feature = "rand")]
+    pub fn sample_uniform(&self, n: usize, rng: &mut impl rand::Rng) -> Vec<Point2> {
+        self.sample_uniform_with(n, || rng.gen())
+    }
+
+    /// `sample_uniform` taking its draws from `uniform`, which must give numbers in [0, 1).
+    /// Three are used per point.
+    pub fn sample_uniform_with(&self, n: usize, mut uniform: impl FnMut() -> f64) -> Vec<Point2> {
+        let table = self.sample_table();
+        if table.triangles.is_empty() {
+            return vec![];
+        }
+        (0..n)
+            .map(|_| {
+                let corners = table.pick(uniform());
+                let (r1, r2) = (uniform(), uniform());
+                point_in(corners, r1, r2)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robust_float::{point_in_triangle, Region};
+    use crate::test_util::xorshift;
+    use crate::triangulation::Location;
+
+    /// Check the points `sample` draws over a mesh: the share of every triangle and that they
+    /// stay in the mesh as it changes
+    fn check_sampling(mut sample: impl FnMut(&Triangulation, usize) -> Vec<Point2>) {
+        // Two triangles on the diagonal from 0, 0 to 4, 0, of area 2 and 6
+        let kite = Triangulation::new(&[
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 1., y: 1. },
+            Point2 { x: 4., y: 0. },
+            Point2 { x: 0.5, y: -3. },
+        ]);
+        let triangles = kite.triangles();
+        assert_eq!(triangles.len(), 2);
+        let small = triangles
+            .iter()
+            .find(|corners| signed_area(&corners[0], &corners[1], &corners[2]) == 2.)
+            .unwrap();
+        let count = 40_000;
+        let samples = sample(&kite, count);
+        assert_eq!(samples.len(), count);
+        let in_small = samples
+            .iter()
+            .filter(|&&point| {
+                point_in_triangle(point, small[0], small[1], small[2]) != Region::Outside
+            })
+            .count();
+        assert!((in_small as f64 / count as f64 - 0.25).abs() < 0.01);
+
+        let points: Vec<Point2> = (0..300)
+            .map(|idx| Point2 {
+                x: (idx as f64 * 0.618_034).fract() * 100.,
+                y: (idx as f64 * 0.754_877).fract() * 50.,
+            })
+            .collect();
+        let mut triangulation = Triangulation::new(&points);
+        for point in sample(&triangulation, 2000) {
+            assert!(!matches!(triangulation.locate(point), Location::Outside(_)));
+        }
+        // The cached areas follow the mesh as it grows
+        triangulation.insert_delaunay(Point2 { x: 300., y: 25. });
+        let samples = sample(&triangulation, 2000);
+        assert!(samples.iter().any(|point| point.x > 100.));
+        assert!(samples
+            .iter()
+            .all(|&point| !matches!(triangulation.locate(point), Location::Outside(_))));
+
+        let line = Triangulation::new(&[
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 1., y: 1. },
+            Point2 { x: 2., y: 2. },
+        ]);
+        assert!(sample(&line, 10).is_empty());
+        assert!(sample(&Triangulation::new(&[]), 10).is_empty());
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_sample_uniform() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let mut rng = StdRng::seed_from_u64(101);
+        check_sampling(|triangulation, n| triangulation.sample_uniform(n, &mut rng));
+    }
+
+    #[test]
+    fn test_sample_uniform_with() {
+        let mut next = xorshift(101);
+        check_sampling(|triangulation, n| triangulation.sample_uniform_with(n, &mut next));
+    }
+}
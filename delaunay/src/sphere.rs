@@ -0,0 +1,338 @@
+/// Delaunay triangulation of sites on the unit sphere
+///
+/// The Delaunay triangulation on the sphere is the convex hull of the sites. It is computed by
+/// stereographically projecting the sites from a pole away from every site: circles not going
+/// through the pole stay circles, so the planar Delaunay triangulation of the projection gives
+/// every hull face whose empty cap doesn't hold the pole. The faces left, around the pole, cover
+/// the outside of the planar convex hull and close the sphere.
+use std::collections::HashMap;
+
+use crate::robust_float::Point2;
+use crate::triangulation::{sanitize_with, Triangulation};
+
+type Vec3 = [f64; 3];
+
+/// Candidate projection poles tried besides the antipode of the sites' mean
+const POLE_CANDIDATES: usize = 256;
+
+fn dot(a: &Vec3, b: &Vec3) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: &Vec3, b: &Vec3) -> Vec3 {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(a: Vec3) -> Vec3 {
+    let norm = dot(&a, &a).sqrt();
+    a.map(|coord| coord / norm)
+}
+
+fn to_coord(a: &Vec3) -> robust::Coord3D<f64> {
+    robust::Coord3D {
+        x: a[0],
+        y: a[1],
+        z: a[2],
+    }
+}
+
+/// Positive when `d` is on the same side of the plane through `a`, `b` and `c` as the sphere
+/// center, for a triangle counter clockwise seen from outside the sphere
+fn orient(a: &Vec3, b: &Vec3, c: &Vec3, d: &Vec3) -> f64 {
+    robust::orient3d(to_coord(a), to_coord(b), to_coord(c), to_coord(d))
+}
+
+/// Unit vector of a (longitude, latitude) pair in degrees
+fn to_unit(lon: f64, lat: f64) -> Vec3 {
+    let (lon, lat) = (lon.to_radians(), lat.to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+/// Direction whose nearest site is the farthest, among a Fibonacci spread of candidates and the
+/// antipode of the sites' mean
+fn projection_pole(sites: &[Vec3]) -> Vec3 {
+    let mut candidates: Vec<Vec3> = (0..POLE_CANDIDATES)
+        .map(|i| {
+            let z = 1. - (2 * i + 1) as f64 / POLE_CANDIDATES as f64;
+            let angle = i as f64 * std::f64::consts::PI * (3. - 5f64.sqrt());
+            let radius = (1. - z * z).sqrt();
+            [radius * angle.cos(), radius * angle.sin(), z]
+        })
+        .collect();
+    let sum = sites.iter().fold([0.; 3], |sum, site| {
+        [sum[0] + site[0], sum[1] + site[1], sum[2] + site[2]]
+    });
+    if dot(&sum, &sum) > 1e-12 {
+        candidates.push(normalize(sum.map(|coord| -coord)));
+    }
+    // The nearest site is the one with the largest dot product
+    let nearest = |candidate: &Vec3| {
+        sites
+            .iter()
+            .map(|site| dot(site, candidate))
+            .fold(f64::NEG_INFINITY, f64::max)
+    };
+    candidates
+        .into_iter()
+        .map(|candidate| (nearest(&candidate), candidate))
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .unwrap()
+        .1
+}
+
+/// Triangulate sites given as (longitude, latitude) pairs in degrees on the sphere.
+///
+/// Returns triangles of indices into `lonlat`, counter clockwise seen from outside the sphere,
+/// each starting at its smallest index, sorted by their sorted indices.
+/// When several sites are at the same place only one of them appears in the triangles. Less than
+/// 4 distinct sites don't enclose anything and give no triangle.
+pub fn triangulate_sphere(lonlat: &[(f64, f64)]) -> Vec<[usize; 3]> {
+    let sites: Vec<Vec3> = lonlat.iter().map(|&(lon, lat)| to_unit(lon, lat)).collect();
+    if sites.len() < 4 {
+        return vec![];
+    }
+    let pole = projection_pole(&sites);
+    let helper = if pole[0].abs() < 0.9 {
+        [1., 0., 0.]
+    } else {
+        [0., 1., 0.]
+    };
+    let u = normalize(cross(&helper, &pole));
+    let v = cross(&pole, &u);
+    let projected: Vec<Point2> = sites
+        .iter()
+        .map(|site| {
+            let scale = 1. - dot(site, &pole);
+            Point2 {
+                x: dot(site, &u) / scale,
+                y: dot(site, &v) / scale,
+            }
+        })
+        .collect();
+    let inputs: Vec<usize> = (0..sites.len()).collect();
+    let (projected, inputs) = sanitize_with(&projected, &inputs);
+    if projected.len() < 4 {
+        return vec![];
+    }
+    let triangulation = Triangulation::new(&projected);
+    let input_of = |point: &Point2| inputs[triangulation.vertex_index(point).unwrap()];
+
+    let center = [0.; 3];
+    // Corners of a face in the outward order: the center on their left. Faces through the center,
+    // like the flat face of sites on a great circle, go by the pole instead, which is outside of
+    // the faces around it and inside of the others.
+    let outward = |[a, b, c]: [usize; 3], around_pole: bool| {
+        let (p, q, r) = (&sites[a], &sites[b], &sites[c]);
+        let side = match orient(p, q, r, &center) {
+            0. if around_pole => -orient(p, q, r, &pole),
+            0. => orient(p, q, r, &pole),
+            side => side,
+        };
+        if side > 0. {
+            [a, b, c]
+        } else {
+            [a, c, b]
+        }
+    };
+    let mut triangles = vec![];
+    triangulation
+        .for_each_face(|corners| triangles.push(outward(corners.map(|c| input_of(&c)), false)));
+
+    // Outer face of the planar triangulation: the polygon around the pole
+    let mut next = HashMap::new();
+    for edge in triangulation.live_edges() {
+        for edge in [edge, edge.sym()] {
+            if triangulation.left_triangle(edge).is_none()
+                && triangulation.left_triangle(edge.sym()).is_some()
+            {
+                let (org, dest) = edge.org_dest(triangulation.quad_arena());
+                next.insert(input_of(&org), input_of(&dest));
+            }
+        }
+    }
+    let Some(&start) = next.keys().min() else {
+        return triangles;
+    };
+    let mut polygon = vec![start];
+    while let Some(&vertex) = next.get(polygon.last().unwrap()) {
+        if vertex == start {
+            break;
+        }
+        polygon.push(vertex);
+    }
+
+    // Cut the polygon in hull faces. A face around the pole has the pole outside of its plane
+    // and every site inside: its circle in the projection holds every site. The corners of the
+    // polygon are enough to check, the circle then holds their convex hull and the sites in it.
+    let corners = polygon.clone();
+    // Least orientation of the pole, negated, and of the corners against the plane of the ear.
+    // At most 0, the corners of the ear being on its plane, and 0 for a hull face.
+    let clearance = |ear: [usize; 3]| {
+        let [a, b, c] = outward(ear, true);
+        let (a, b, c) = (&sites[a], &sites[b], &sites[c]);
+        corners
+            .iter()
+            .map(|&corner| orient(a, b, c, &sites[corner]))
+            .fold(-orient(a, b, c, &pole), f64::min)
+    };
+    while polygon.len() > 3 {
+        let ear_at = |pos: usize| {
+            [
+                polygon[pos],
+                polygon[(pos + 1) % polygon.len()],
+                polygon[(pos + 2) % polygon.len()],
+            ]
+        };
+        // The first hull face in polygon order. Rounding in the projection can leave none, the
+        // ear with the largest clearance, the first one on ties, is cut then.
+        let (mut pos, mut best) = (0, f64::NEG_INFINITY);
+        for candidate in 0..polygon.len() {
+            let clearance = clearance(ear_at(candidate));
+            if clearance > best {
+                (pos, best) = (candidate, clearance);
+            }
+            if clearance >= 0. {
+                break;
+            }
+        }
+        #[cfg(feature = "log")]
+        if best < 0. {
+            log::warn!(
+                "No hull face around the pole among {} polygon corners, cutting the ear {:?}",
+                polygon.len(),
+                ear_at(pos)
+            );
+        }
+        triangles.push(outward(ear_at(pos), true));
+        polygon.remove((pos + 1) % polygon.len());
+    }
+    if polygon.len() == 3 {
+        triangles.push(outward([polygon[0], polygon[1], polygon[2]], true));
+    }
+    // Canonical order: every triangle starts at its smallest index, triangles are sorted by their
+    // sorted indices
+    for triangle in triangles.iter_mut() {
+        let first = (0..3).min_by_key(|&idx| triangle[idx]).unwrap();
+        triangle.rotate_left(first);
+    }
+    triangles.sort_unstable_by_key(|&triangle| {
+        let mut sorted = triangle;
+        sorted.sort_unstable();
+        sorted
+    });
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::xorshift;
+
+    fn random_lonlat(n: usize) -> Vec<(f64, f64)> {
+        let mut next = xorshift(5);
+        (0..n)
+            .map(|_| {
+                let lon = next() * 360. - 180.;
+                let lat = (next() * 2. - 1.).asin().to_degrees();
+                (lon, lat)
+            })
+            .collect()
+    }
+
+    fn assert_closed(triangles: &[[usize; 3]], vertices: usize) {
+        let mut directed = HashMap::new();
+        for &[a, b, c] in triangles {
+            for edge in [(a, b), (b, c), (c, a)] {
+                *directed.entry(edge).or_insert(0) += 1;
+            }
+        }
+        // Every edge is used once in each direction by its two triangles
+        for (&(a, b), &count) in &directed {
+            assert_eq!(count, 1);
+            assert_eq!(directed.get(&(b, a)), Some(&1));
+        }
+        let edges = directed.len() / 2;
+        assert_eq!(vertices as i64 - edges as i64 + triangles.len() as i64, 2);
+    }
+
+    #[test]
+    fn test_random_sphere_is_closed() {
+        let lonlat = random_lonlat(300);
+        let triangles = triangulate_sphere(&lonlat);
+        assert_closed(&triangles, lonlat.len());
+        assert_eq!(triangles.len(), 2 * lonlat.len() - 4);
+
+        // Every face is a convex hull face
+        let sites: Vec<Vec3> = lonlat.iter().map(|&(lon, lat)| to_unit(lon, lat)).collect();
+        for [a, b, c] in triangles {
+            for site in &sites {
+                assert!(orient(&sites[a], &sites[b], &sites[c], site) >= 0.);
+            }
+        }
+    }
+
+    #[test]
+    fn test_duplicate_and_antipodal_sites() {
+        let mut lonlat = random_lonlat(50);
+        lonlat.push(lonlat[3]);
+        lonlat.push((lonlat[7].0 + 180., -lonlat[7].1));
+        let triangles = triangulate_sphere(&lonlat);
+        assert_closed(&triangles, lonlat.len() - 1);
+        // The later duplicate is kept
+        assert!(triangles.iter().all(|triangle| !triangle.contains(&3)));
+    }
+
+    #[test]
+    fn test_cocircular_pole_polygon() {
+        // The equator ring is the polygon around the projection pole, every ear of it is a hull
+        // face of the flat octagon
+        let mut lonlat: Vec<(f64, f64)> = (0..8).map(|idx| (idx as f64 * 45., 0.)).collect();
+        lonlat.push((0., 90.));
+        let triangles = triangulate_sphere(&lonlat);
+        assert_closed(&triangles, lonlat.len());
+        assert_eq!(triangles.len(), 2 * lonlat.len() - 4);
+        let sites: Vec<Vec3> = lonlat.iter().map(|&(lon, lat)| to_unit(lon, lat)).collect();
+        for &[a, b, c] in &triangles {
+            for site in &sites {
+                assert!(orient(&sites[a], &sites[b], &sites[c], site) >= 0.);
+            }
+        }
+        assert_eq!(triangulate_sphere(&lonlat), triangles);
+    }
+
+    #[test]
+    fn test_octahedron() {
+        let lonlat = [
+            (0., 0.),
+            (90., 0.),
+            (180., 0.),
+            (-90., 0.),
+            (0., 90.),
+            (0., -90.),
+        ];
+        let mut faces: Vec<[usize; 3]> = triangulate_sphere(&lonlat)
+            .into_iter()
+            .map(|mut face| {
+                face.sort_unstable();
+                face
+            })
+            .collect();
+        faces.sort_unstable();
+        let mut expected = vec![];
+        for x in [0, 2] {
+            for y in [1, 3] {
+                for z in [4, 5] {
+                    let mut face = [x, y, z];
+                    face.sort_unstable();
+                    expected.push(face);
+                }
+            }
+        }
+        expected.sort_unstable();
+        assert_eq!(faces, expected);
+    }
+}
@@ -0,0 +1,344 @@
+/// Triangulation of points coming from an iterator, like a file reader
+///
+/// Collecting the points before calling `Triangulation::new` holds them twice, the raw copy and
+/// the sanitized one. Here the points are staged in chunks: every chunk is sorted into a run as
+/// it arrives, dropping its duplicates, and the runs are merged in one pass at the end.
+///
+/// `triangulate_iter` goes further and inserts every window of points into the mesh as it
+/// arrives, holding one window and the mesh.
+use std::cmp::Ordering;
+use std::collections::binary_heap::{BinaryHeap, PeekMut};
+use std::fmt;
+
+use crate::robust_float::{cmp_points_bits, remove_near_equal_points, sort_points, Point2};
+use crate::triangulation::Triangulation;
+
+/// Points staged at once by `triangulate_stream`, 16 MiB of them
+pub const STREAM_CHUNK: usize = 1 << 20;
+
+/// Points inserted at once by `triangulate_iter`
+pub const STREAM_WINDOW: usize = 1 << 12;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StreamError {
+    /// A coordinate is NaN or infinite, holds the position of the point in the stream
+    NonFinite(usize),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::NonFinite(idx) => write!(f, "Point {} has a non-finite coordinate", idx),
+        }
+    }
+}
+
+/// Next point of a sorted run in `merge_runs`
+struct RunHead {
+    point: Point2,
+    run: usize,
+    idx: usize,
+}
+
+impl Ord for RunHead {
+    /// Reversed so `BinaryHeap` pops the smallest point first, and of equal points the one of the
+    /// earliest run
+    fn cmp(&self, other: &Self) -> Ordering {
+        cmp_points_bits(&other.point, &self.point).then(other.run.cmp(&self.run))
+    }
+}
+
+impl PartialOrd for RunHead {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for RunHead {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for RunHead {}
+
+/// Merge the sorted runs of `staged`, starting at `starts`, with a heap of their heads. Points
+/// are ordered like `sort_points` orders them, as if the runs were appended and sorted.
+fn merge_runs(staged: &[Point2], starts: &[usize]) -> Vec<Point2> {
+    let end = |run: usize| starts.get(run + 1).copied().unwrap_or(staged.len());
+    let mut heads: BinaryHeap<RunHead> = starts
+        .iter()
+        .enumerate()
+        .map(|(run, &idx)| RunHead {
+            point: staged[idx],
+            run,
+            idx,
+        })
+        .collect();
+    let mut sites = Vec::with_capacity(staged.len());
+    while let Some(mut head) = heads.peek_mut() {
+        sites.push(head.point);
+        head.idx += 1;
+        if head.idx < end(head.run) {
+            head.point = staged[head.idx];
+        } else {
+            PeekMut::pop(head);
+        }
+    }
+    sites
+}
+
+/// Stage the points `chunk_len` at a time and return them sanitized, like `sanitize_points_vec`
+/// leaves them.
+///
+/// Chunks only drop their exactly equal points, keeping the last one like the sanitizing does.
+/// Near-equal points are only dropped once every run is merged: whether a point goes depends on
+/// the point following it in the whole sorted sequence, which can come from a later chunk.
+///
+/// Merging every chunk into the sites as it arrives would move the sites merged so far each time,
+/// quadratic in the number of chunks. The runs are kept side by side instead and merged once, at
+/// a heap operation per point. That merge holds the runs and the merged sites, never the raw
+/// points on top of them.
+fn stage_sorted(
+    points: impl IntoIterator<Item = Point2>,
+    chunk_len: usize,
+) -> Result<Vec<Point2>, StreamError> {
+    let mut points = points.into_iter().enumerate();
+    let mut staged: Vec<Point2> = Vec::with_capacity(points.size_hint().0);
+    let mut starts: Vec<usize> = vec![];
+    let mut chunk: Vec<Point2> = Vec::with_capacity(chunk_len);
+    loop {
+        chunk.clear();
+        for (idx, point) in points.by_ref().take(chunk_len) {
+            if !point.x.is_finite() || !point.y.is_finite() {
+                return Err(StreamError::NonFinite(idx));
+            }
+            chunk.push(point);
+        }
+        if chunk.is_empty() {
+            break;
+        }
+        sort_points(&mut chunk);
+        chunk.dedup_by(|later, kept| {
+            let equal = later == kept;
+            if equal {
+                *kept = *later;
+            }
+            equal
+        });
+        starts.push(staged.len());
+        staged.extend_from_slice(&chunk);
+    }
+    drop(chunk);
+    let mut sites = merge_runs(&staged, &starts);
+    drop(staged);
+    remove_near_equal_points(&mut sites);
+    Ok(sites)
+}
+
+/// Triangulate the points of an iterator, staging them `STREAM_CHUNK` at a time. The result is
+/// the one of `Triangulation::new` on the collected points.
+pub fn triangulate_stream(
+    points: impl IntoIterator<Item = Point2>,
+) -> Result<Triangulation, StreamError> {
+    triangulate_stream_chunked(points, STREAM_CHUNK)
+}
+
+/// `triangulate_stream` staging `chunk_len` points at a time. Every chunk is a sorted run, and
+/// merging the runs takes a log of their number of comparisons a point, so smaller chunks trade
+/// time for memory.
+///
+/// Panics if `chunk_len` is 0.
+pub fn triangulate_stream_chunked(
+    points: impl IntoIterator<Item = Point2>,
+    chunk_len: usize,
+) -> Result<Triangulation, StreamError> {
+    assert!(chunk_len > 0, "Chunks must hold at least one point");
+    Ok(Triangulation::from_sanitized(
+        stage_sorted(points, chunk_len)?,
+        None,
+    ))
+}
+
+/// Triangulate the points of an iterator by inserting them `STREAM_WINDOW` at a time, see
+/// `triangulate_iter_windowed`
+pub fn triangulate_iter(points: impl IntoIterator<Item = Point2>) -> Triangulation {
+    triangulate_iter_windowed(points, STREAM_WINDOW)
+}
+
+/// Triangulate the points of an iterator, buffering `window` points at a time and inserting
+/// every window with `insert_points`, which orders it along a Hilbert curve. The first window
+/// is triangulated from scratch.
+///
+/// Only one window is held next to the mesh, and the sites are never sorted as a whole. Points
+/// are inserted one by one though, which is slower than `triangulate_stream` staging the sorted
+/// sites then dividing and conquering. The mesh is the Delaunay triangulation of the
+/// points like `Triangulation::new` gives, with inserted sites in `points()` after the first
+/// window. Where four sites are cocircular the diagonal may differ, and of near-equal points
+/// the first one is kept.
+///
+/// Points with a NaN or infinite coordinate are skipped, with a warning under the `log` feature:
+/// the mesh of the points before them is already built, so there is no error to return.
+/// `triangulate_stream` rejects them instead.
+///
+/// Panics if `window` is 0.
+pub fn triangulate_iter_windowed(
+    points: impl IntoIterator<Item = Point2>,
+    window: usize,
+) -> Triangulation {
+    assert!(window > 0, "Windows must hold at least one point");
+    let mut points = points.into_iter().enumerate();
+    let mut triangulation = Triangulation::new(&[]);
+    let mut buffer: Vec<Point2> = Vec::with_capacity(window);
+    loop {
+        buffer.clear();
+        for (_idx, point) in points.by_ref() {
+            if !point.x.is_finite() || !point.y.is_finite() {
+                #[cfg(feature = "log")]
+                log::warn!("Skipping point {} with a non-finite coordinate", _idx);
+                continue;
+            }
+            buffer.push(point);
+            if buffer.len() == window {
+                break;
+            }
+        }
+        if buffer.is_empty() {
+            return triangulation;
+        }
+        triangulation.insert_points(&buffer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robust_float::{cmp_points, sanitize_points_vec};
+    #[cfg(feature = "log")]
+    use crate::test_util::take_warnings;
+    use crate::test_util::xorshift;
+    use crate::triangulation::counting_alloc::{LIVE, PEAK};
+    use std::cell::Cell;
+
+    #[test]
+    fn test_triangulate_stream() {
+        let mut next = xorshift(89);
+        // Coarse coordinates for exact duplicates across chunks, and near-equal twins
+        let mut points: Vec<Point2> = (0..20_000)
+            .map(|_| Point2 {
+                x: (next() * 200.).floor(),
+                y: (next() * 200.).floor(),
+            })
+            .collect();
+        for idx in (0..points.len()).step_by(97) {
+            let point = points[idx];
+            points.push(Point2 {
+                x: point.x + f64::EPSILON,
+                y: point.y,
+            });
+        }
+        points.push(Point2 { x: -0., y: 0. });
+        points.push(Point2 { x: 0., y: -0. });
+
+        let mut expected = points.clone();
+        sanitize_points_vec(&mut expected);
+        let batch = Triangulation::new(&points);
+        for chunk_len in [1, 7, 1000, points.len(), STREAM_CHUNK] {
+            let sites = stage_sorted(points.iter().copied(), chunk_len).unwrap();
+            assert_eq!(sites.len(), expected.len());
+            assert!(sites.iter().zip(&expected).all(|(a, b)| {
+                a.x.to_bits() == b.x.to_bits() && a.y.to_bits() == b.y.to_bits()
+            }));
+        }
+        let streamed = triangulate_stream_chunked(points.iter().copied(), 1000).unwrap();
+        assert_eq!(streamed.points(), batch.points());
+        assert_eq!(streamed.triangles(), batch.triangles());
+        assert_eq!(streamed.edges(), batch.edges());
+
+        assert_eq!(
+            triangulate_stream([Point2 { x: 0., y: 0. }, Point2 { x: 1., y: f64::NAN }]).err(),
+            Some(StreamError::NonFinite(1))
+        );
+        assert_eq!(triangulate_stream([]).unwrap().points(), &[]);
+    }
+
+    #[test]
+    fn test_triangulate_iter() {
+        let mut next = xorshift(91);
+        let points: Vec<Point2> = (0..5000)
+            .map(|_| Point2 {
+                x: next() * 100.,
+                y: next() * 100.,
+            })
+            .collect();
+        let batch = Triangulation::new(&points);
+        let sorted = |mut lines: Vec<(Point2, Point2)>| {
+            for line in lines.iter_mut() {
+                if cmp_points(&line.0, &line.1).is_gt() {
+                    *line = (line.1, line.0);
+                }
+            }
+            lines.sort_by(|a, b| cmp_points(&a.0, &b.0).then(cmp_points(&a.1, &b.1)));
+            lines
+        };
+        let expected = sorted(batch.edges());
+        for window in [1, 2, 100, points.len()] {
+            let streamed = triangulate_iter_windowed(points.iter().copied(), window);
+            let mut sites = streamed.points().to_vec();
+            sites.sort_by(cmp_points);
+            assert_eq!(sites, batch.points());
+            assert_eq!(sorted(streamed.edges()), expected);
+            assert!(streamed.is_delaunay());
+        }
+        assert_eq!(
+            sorted(triangulate_iter(points.iter().copied()).edges()),
+            expected
+        );
+
+        // Non-finite points are skipped, a whole window of them included
+        let mut noisy: Vec<Point2> = vec![Point2 { x: f64::NAN, y: 0. }; 3];
+        for (idx, point) in points.iter().enumerate() {
+            noisy.push(*point);
+            if idx % 10 == 0 {
+                noisy.push(Point2 {
+                    x: 1.,
+                    y: f64::INFINITY,
+                });
+            }
+        }
+        #[cfg(feature = "log")]
+        take_warnings();
+        let streamed = triangulate_iter_windowed(noisy, 2);
+        assert_eq!(sorted(streamed.edges()), expected);
+        #[cfg(feature = "log")]
+        assert_eq!(take_warnings().len(), 3 + 500);
+        assert!(triangulate_iter([]).points().is_empty());
+    }
+
+    #[test]
+    fn test_stream_staging_memory() {
+        let mut next = xorshift(97);
+        let chunk_len = 1000;
+        let count = 50_000;
+        let before = LIVE.with(Cell::get);
+        PEAK.with(|peak| peak.set(before));
+        // Points are made on the fly, like a reader would
+        let sites = stage_sorted(
+            (0..count).map(|_| Point2 {
+                x: next(),
+                y: next(),
+            }),
+            chunk_len,
+        )
+        .unwrap();
+        let staged = PEAK.with(Cell::get) - before;
+        let point = std::mem::size_of::<Point2>();
+        assert_eq!(sites.len(), count);
+        // The runs and the merged sites, the chunk is dropped before the merge. A heap entry and
+        // a start per run on top of them.
+        let runs = count / chunk_len;
+        let heads = runs * (std::mem::size_of::<RunHead>() + 2 * std::mem::size_of::<usize>());
+        assert!(staged <= 2 * sites.capacity() * point + heads + 1024);
+        assert!(staged < 2 * count * point + chunk_len * point);
+    }
+}
@@ -0,0 +1,261 @@
+/// Triangle strips and fans, for submitting the mesh to a GPU with fewer vertices
+///
+/// Both are read the usual way. Triangle `i` of a strip is `(s[i], s[i + 1], s[i + 2])` when `i`
+/// is even and `(s[i + 1], s[i], s[i + 2])` when it is odd, so every triangle keeps the counter
+/// clockwise winding of `to_halfedges`. Triangle `i` of a fan is `(f[0], f[i + 1], f[i + 2])`.
+use crate::halfedge::{next_halfedge, prev_halfedge, HalfedgeMesh};
+use crate::triangulation::Triangulation;
+
+/// Neighbor triangle across halfedge `edge` if it's still free
+fn free_neighbor(mesh: &HalfedgeMesh, used: &[bool], edge: usize) -> Option<usize> {
+    let opposite = usize::try_from(mesh.halfedges[edge]).ok()?;
+    (!used[opposite / 3]).then_some(opposite)
+}
+
+/// Halfedges entering the triangles of the strip starting with the triangle of `start`, read
+/// from its origin, taking free triangles until the strip can't go on
+fn walk_strip(mesh: &HalfedgeMesh, used: &mut [bool], start: usize) -> Vec<usize> {
+    let mut entries = vec![start];
+    used[start / 3] = true;
+    let mut entry = start;
+    // Even triangles leave by the side after their entry, odd ones by the side before
+    loop {
+        let exit = if entries.len() % 2 == 1 {
+            next_halfedge(entry)
+        } else {
+            prev_halfedge(entry)
+        };
+        let Some(opposite) = free_neighbor(mesh, used, exit) else {
+            break;
+        };
+        used[opposite / 3] = true;
+        entries.push(opposite);
+        entry = opposite;
+    }
+    entries
+}
+
+/// Greedy strips: seeds are the free triangles with the fewest free neighbors, so the strips
+/// start at the border of the free part and don't cut it into islands. Every seed is tried from
+/// its three sides and keeps the longest strip.
+fn strips(mesh: &HalfedgeMesh) -> Vec<Vec<usize>> {
+    let count = mesh.triangles.len() / 3;
+    let mut used = vec![false; count];
+    let free_neighbors = |used: &[bool], triangle: usize| {
+        (3 * triangle..3 * triangle + 3)
+            .filter(|&edge| free_neighbor(mesh, used, edge).is_some())
+            .count()
+    };
+    // Triangles by number of free neighbors, stale entries are skipped when popped
+    let mut buckets: [Vec<usize>; 4] = Default::default();
+    for triangle in (0..count).rev() {
+        buckets[free_neighbors(&used, triangle)].push(triangle);
+    }
+
+    let mut strips = vec![];
+    while let Some(seed) = buckets
+        .iter_mut()
+        .find_map(|bucket| std::iter::from_fn(|| bucket.pop()).find(|&triangle| !used[triangle]))
+    {
+        let entries = (3 * seed..3 * seed + 3)
+            .map(|start| {
+                let entries = walk_strip(mesh, &mut used, start);
+                for entry in &entries {
+                    used[entry / 3] = false;
+                }
+                entries
+            })
+            .max_by_key(Vec::len)
+            .unwrap();
+
+        let first = entries[0];
+        let mut strip = vec![
+            mesh.triangles[first],
+            mesh.triangles[next_halfedge(first)],
+            mesh.triangles[prev_halfedge(first)],
+        ];
+        for &entry in &entries {
+            used[entry / 3] = true;
+        }
+        for &entry in &entries[1..] {
+            strip.push(mesh.triangles[prev_halfedge(entry)]);
+        }
+        // The free neighbors of the strip lost one free neighbor each
+        for &entry in &entries {
+            for edge in [entry, next_halfedge(entry), prev_halfedge(entry)] {
+                if let Some(opposite) = free_neighbor(mesh, &used, edge) {
+                    let triangle = opposite / 3;
+                    buckets[free_neighbors(&used, triangle)].push(triangle);
+                }
+            }
+        }
+        strips.push(strip);
+    }
+    strips
+}
+
+/// Greedy fans: around every site in order, each run of free triangles in a row becomes a fan
+fn fans(mesh: &HalfedgeMesh, sites: usize) -> Vec<Vec<usize>> {
+    // Where the triangles around a site start: after every gap in them, or anywhere when they
+    // close around it. A halfedge without opposite has no triangle clockwise of it.
+    let mut starts = vec![];
+    let mut inner = vec![None; sites];
+    for (edge, &site) in mesh.triangles.iter().enumerate() {
+        if mesh.halfedges[edge] < 0 {
+            starts.push((site, edge));
+        } else if inner[site].is_none() {
+            inner[site] = Some(edge);
+        }
+    }
+    let open: Vec<bool> = {
+        let mut open = vec![false; sites];
+        for &(site, _) in &starts {
+            open[site] = true;
+        }
+        open
+    };
+    starts.extend(
+        inner
+            .into_iter()
+            .enumerate()
+            .filter_map(|(site, edge)| Some((site, edge?)).filter(|_| !open[site])),
+    );
+    starts.sort_unstable();
+
+    let mut used = vec![false; mesh.triangles.len() / 3];
+    let mut fans = vec![];
+    for (site, start) in starts {
+        // Halfedges leaving the site, counter clockwise
+        let mut ring = vec![start];
+        while let Ok(opposite) =
+            usize::try_from(mesh.halfedges[prev_halfedge(ring[ring.len() - 1])])
+        {
+            if opposite == start {
+                break;
+            }
+            ring.push(opposite);
+        }
+        // A closed ring partly taken starts at a taken triangle, so no run wraps around
+        if !open[site] {
+            if let Some(taken) = ring.iter().position(|edge| used[edge / 3]) {
+                ring.rotate_left(taken);
+            }
+        }
+        let runs: Vec<&[usize]> = ring
+            .split(|edge| used[edge / 3])
+            .filter(|run| !run.is_empty())
+            .collect();
+        for run in runs {
+            let mut fan = vec![site, mesh.triangles[next_halfedge(run[0])]];
+            fan.extend(run.iter().map(|&edge| mesh.triangles[prev_halfedge(edge)]));
+            for edge in run {
+                used[edge / 3] = true;
+            }
+            fans.push(fan);
+        }
+    }
+    fans
+}
+
+impl Triangulation {
+    /// Triangle strips covering every triangle of `to_halfedges` once, as site indices of
+    /// `points()`, read as the module doc says. The strips are built greedily from the triangle
+    /// neighbors; a triangle without a free neighbor is a strip of its own, of 3 indices.
+    pub fn to_strips(&self) -> Vec<Vec<usize>> {
+        strips(&self.to_halfedges())
+    }
+
+    /// Triangle fans covering every triangle of `to_halfedges` once, as site indices of
+    /// `points()`: the center then its neighbors, counter clockwise. A simpler alternative to
+    /// `to_strips`, giving more and shorter primitives. A fan going all the way around its center
+    /// ends with its first neighbor again.
+    pub fn to_fans_around_vertices(&self) -> Vec<Vec<usize>> {
+        fans(&self.to_halfedges(), self.points().len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robust_float::Point2;
+    use crate::test_util::xorshift;
+
+    /// Triangles of strips and fans, every one starting at its smallest site, sorted
+    fn decode(strips: &[Vec<usize>], fans: bool) -> Vec<[usize; 3]> {
+        let mut triangles: Vec<[usize; 3]> = strips
+            .iter()
+            .flat_map(|strip| {
+                (0..strip.len() - 2).map(move |idx| match (fans, idx % 2) {
+                    (true, _) => [strip[0], strip[idx + 1], strip[idx + 2]],
+                    (false, 0) => [strip[idx], strip[idx + 1], strip[idx + 2]],
+                    (false, _) => [strip[idx + 1], strip[idx], strip[idx + 2]],
+                })
+            })
+            .map(|triangle| {
+                let first = (0..3).min_by_key(|&idx| triangle[idx]).unwrap();
+                [0, 1, 2].map(|offset| triangle[(first + offset) % 3])
+            })
+            .collect();
+        triangles.sort_unstable();
+        triangles
+    }
+
+    fn check(triangulation: &Triangulation) -> (Vec<Vec<usize>>, Vec<Vec<usize>>) {
+        let mut expected: Vec<[usize; 3]> = triangulation
+            .to_halfedges()
+            .triangles
+            .chunks_exact(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .collect();
+        expected.sort_unstable();
+        let (strips, fans) = (
+            triangulation.to_strips(),
+            triangulation.to_fans_around_vertices(),
+        );
+        assert_eq!(decode(&strips, false), expected);
+        assert_eq!(decode(&fans, true), expected);
+        (strips, fans)
+    }
+
+    #[test]
+    fn test_strips_and_fans() {
+        let grid: Vec<Point2> = (0..400)
+            .map(|idx| Point2 {
+                x: (idx % 20) as f64,
+                y: (idx / 20) as f64,
+            })
+            .collect();
+        let (strips, fans) = check(&Triangulation::new(&grid));
+        let triangles = 2 * 19 * 19;
+        assert!(triangles / strips.len() > 10, "{} strips", strips.len());
+        assert!(fans.len() <= triangles / 2);
+
+        let mut next = xorshift(23);
+        let points: Vec<Point2> = (0..500)
+            .map(|_| Point2 {
+                x: next() * 100.,
+                y: next() * 100.,
+            })
+            .collect();
+        check(&Triangulation::new(&points));
+
+        // A lone triangle, and two triangles touching at a site once the top and bottom ones
+        // are carved out
+        let lone = [(0., 0.), (1., 0.), (0., 1.)].map(|(x, y)| Point2 { x, y });
+        let (strips, fans) = check(&Triangulation::new(&lone));
+        assert_eq!((strips.len(), strips[0].len(), fans.len()), (1, 3, 1));
+        let bowtie =
+            [(0., 0.), (2., 1.), (0., 2.), (4., 0.), (4., 2.)].map(|(x, y)| Point2 { x, y });
+        let mut triangulation = Triangulation::new(&bowtie);
+        let site = |idx: usize| triangulation.vertex_index(&bowtie[idx]).unwrap();
+        let constraints =
+            [(0, 1), (1, 2), (1, 3), (1, 4), (0, 2), (3, 4)].map(|(a, b)| (site(a), site(b)));
+        let outside = Point2 { x: -1., y: -1. };
+        assert_eq!(triangulation.carve_holes(&constraints, &[outside]), Ok(2));
+        let (strips, fans) = check(&triangulation);
+        assert_eq!(strips.iter().map(Vec::len).collect::<Vec<_>>(), [3, 3]);
+        assert_eq!(fans.len(), 2);
+
+        assert!(check(&Triangulation::new(&[])).0.is_empty());
+    }
+}
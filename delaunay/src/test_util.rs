@@ -0,0 +1,66 @@
+//! Deterministic random input shared by the unit tests
+
+use crate::robust_float::Point2;
+
+/// The raw 64 bit words of a xorshift generator, the same sequence for the same nonzero seed
+pub(crate) fn xorshift_bits(seed: u64) -> impl FnMut() -> u64 {
+    let mut state = seed;
+    move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    }
+}
+
+/// Uniform floats in [0, 1) drawn from the top 53 bits of `xorshift_bits`
+pub(crate) fn xorshift(seed: u64) -> impl FnMut() -> f64 {
+    let mut bits = xorshift_bits(seed);
+    move || (bits() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// `n` points uniform in the square [0, scale)², each drawn as x then y
+pub(crate) fn random_points(seed: u64, n: usize, scale: f64) -> Vec<Point2> {
+    let mut next = xorshift(seed);
+    (0..n)
+        .map(|_| Point2 {
+            x: next() * scale,
+            y: next() * scale,
+        })
+        .collect()
+}
+
+#[cfg(feature = "log")]
+thread_local! {
+    static WARNINGS: std::cell::RefCell<Vec<String>> = const { std::cell::RefCell::new(vec![]) };
+}
+
+/// Logger keeping the warnings of every thread apart, see `take_warnings`
+#[cfg(feature = "log")]
+struct WarningLog;
+
+#[cfg(feature = "log")]
+impl log::Log for WarningLog {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Warn
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            WARNINGS.with(|warnings| warnings.borrow_mut().push(record.args().to_string()));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Warnings logged by the current thread since the last call, the first call installs the logger
+#[cfg(feature = "log")]
+pub(crate) fn take_warnings() -> Vec<String> {
+    static INSTALL: std::sync::Once = std::sync::Once::new();
+    INSTALL.call_once(|| {
+        log::set_logger(&WarningLog).unwrap();
+        log::set_max_level(log::LevelFilter::Warn);
+    });
+    WARNINGS.with(|warnings| warnings.take())
+}
@@ -0,0 +1,1059 @@
+/// Terrain analysis on triangulations carrying a height per site (2.5D TIN)
+///
+/// Heights are a function z = f(x, y) of the crate's coordinates: x grows to the right and y
+/// grows downward, so "north" (up on screen) is the -y direction.
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::locate::Locator;
+use crate::robust_float::{orient2d_value, point_in_triangle, Point2, Region};
+use crate::triangulation::{hole_ears, Location, Triangulation};
+
+/// Queries sharing a walk hint in `interpolate_linear_batch`
+const BATCH_CHUNK: usize = 1024;
+
+/// Earthworks between two surfaces, see `Triangulation::cut_fill`
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct CutFill {
+    /// Volume where the other surface is below this one, material taken away
+    pub cut: f64,
+    /// Volume where the other surface is above this one, material brought in
+    pub fill: f64,
+    /// Area under this surface only, left out of the volumes
+    pub only_self: f64,
+    /// Area under the other surface only, left out of the volumes
+    pub only_other: f64,
+}
+
+/// Value given by `interpolate_linear_extrapolated` where linear interpolation has none, outside
+/// of the hull
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Extrapolation {
+    /// No value, like `interpolate_linear`
+    #[default]
+    None,
+    /// Value of the nearest site
+    NearestVertex,
+    /// Value at the nearest point of the hull, interpolated linearly along the hull edge holding
+    /// it. Continuous across the hull, where it agrees with the interpolation inside.
+    NearestEdge,
+    /// The given value
+    Constant(f64),
+}
+
+/// Triangles of a surface with their corner heights, bucketed in a uniform grid over their
+/// bounding boxes
+struct FaceGrid {
+    faces: Vec<([Point2; 3], [f64; 3])>,
+    min: Point2,
+    cell: f64,
+    side: usize,
+    cells: Vec<Vec<usize>>,
+}
+
+impl FaceGrid {
+    fn new(triangulation: &Triangulation) -> FaceGrid {
+        let heights = triangulation.site_heights();
+        let faces: Vec<([Point2; 3], [f64; 3])> = triangulation
+            .triangles_iter()
+            .map(|corners| {
+                let vertex = |corner: &Point2| triangulation.vertex_index(corner).unwrap();
+                (corners, corners.map(|corner| heights[vertex(&corner)]))
+            })
+            .collect();
+        let corners = || faces.iter().flat_map(|(corners, _)| corners.iter());
+        let min = Point2 {
+            x: corners().map(|p| p.x).fold(f64::INFINITY, f64::min),
+            y: corners().map(|p| p.y).fold(f64::INFINITY, f64::min),
+        };
+        let extent = corners()
+            .map(|p| (p.x - min.x).max(p.y - min.y))
+            .fold(0., f64::max);
+        let side = ((faces.len() as f64).sqrt() as usize).max(1);
+        let cell = if extent > 0. {
+            extent / side as f64
+        } else {
+            1.
+        };
+        let mut grid = FaceGrid {
+            faces: vec![],
+            min,
+            cell,
+            side,
+            cells: vec![vec![]; side * side],
+        };
+        for (face, (corners, _)) in faces.iter().enumerate() {
+            for cell in grid.cells_of(corners) {
+                grid.cells[cell].push(face);
+            }
+        }
+        grid.faces = faces;
+        grid
+    }
+
+    /// Cells the bounding box of the corners overlaps
+    fn cells_of(&self, corners: &[Point2; 3]) -> impl Iterator<Item = usize> {
+        let index = |value: f64, min: f64| {
+            (((value - min) / self.cell).floor().max(0.) as usize).min(self.side - 1)
+        };
+        let (x0, x1) = (
+            index(
+                corners.iter().map(|p| p.x).fold(f64::INFINITY, f64::min),
+                self.min.x,
+            ),
+            index(
+                corners
+                    .iter()
+                    .map(|p| p.x)
+                    .fold(f64::NEG_INFINITY, f64::max),
+                self.min.x,
+            ),
+        );
+        let (y0, y1) = (
+            index(
+                corners.iter().map(|p| p.y).fold(f64::INFINITY, f64::min),
+                self.min.y,
+            ),
+            index(
+                corners
+                    .iter()
+                    .map(|p| p.y)
+                    .fold(f64::NEG_INFINITY, f64::max),
+                self.min.y,
+            ),
+        );
+        let side = self.side;
+        (y0..=y1).flat_map(move |row| (x0..=x1).map(move |column| row * side + column))
+    }
+}
+
+/// Part of the convex `polygon` inside of the triangle, counter clockwise as
+/// `counter_clockwise` defines it, by clipping with the line of every side
+fn clip(mut polygon: Vec<Point2>, triangle: &[Point2; 3]) -> Vec<Point2> {
+    for side in 0..3 {
+        let (a, b) = (triangle[side], triangle[(side + 1) % 3]);
+        // Inside of the side where the orientation is not positive, as for the corners
+        let inside = |point: &Point2| orient2d_value(&a, &b, point);
+        let mut clipped = Vec::with_capacity(polygon.len() + 1);
+        for (idx, current) in polygon.iter().enumerate() {
+            let next = polygon[(idx + 1) % polygon.len()];
+            let (here, there) = (inside(current), inside(&next));
+            if here <= 0. {
+                clipped.push(*current);
+            }
+            if (here < 0. && there > 0.) || (here > 0. && there < 0.) {
+                let t = here / (here - there);
+                clipped.push(Point2 {
+                    x: current.x + t * (next.x - current.x),
+                    y: current.y + t * (next.y - current.y),
+                });
+            }
+        }
+        polygon = clipped;
+        if polygon.len() < 3 {
+            return vec![];
+        }
+    }
+    polygon
+}
+
+/// Gradient (dz/dx, dz/dy) of the plane going through the three corners, with the triangle area
+fn plane_gradient(corners: &[Point2; 3], heights: &[f64; 3]) -> ((f64, f64), f64) {
+    let (dx1, dy1) = (corners[1].x - corners[0].x, corners[1].y - corners[0].y);
+    let (dx2, dy2) = (corners[2].x - corners[0].x, corners[2].y - corners[0].y);
+    let (dz1, dz2) = (heights[1] - heights[0], heights[2] - heights[0]);
+    let det = dx1 * dy2 - dx2 * dy1;
+    let gradient = ((dz1 * dy2 - dz2 * dy1) / det, (dx1 * dz2 - dx2 * dz1) / det);
+    (gradient, det.abs() / 2.)
+}
+
+/// Normal of the triangle in 3D, turned up (z >= 0), twice as long as the triangle area
+fn face_normal(corners: &[Point2; 3], heights: &[f64; 3]) -> [f64; 3] {
+    let [a, b, c] = [0, 1, 2].map(|idx| [corners[idx].x, corners[idx].y, heights[idx]]);
+    let (u, v) = (
+        [0, 1, 2].map(|k| b[k] - a[k]),
+        [0, 1, 2].map(|k| c[k] - a[k]),
+    );
+    let normal = [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ];
+    if normal[2] < 0. {
+        normal.map(|k| -k)
+    } else {
+        normal
+    }
+}
+
+/// `normal` scaled to a unit vector, zero stays zero
+fn unit(normal: [f64; 3]) -> [f64; 3] {
+    let length = normal.iter().map(|k| k * k).sum::<f64>().sqrt();
+    if length > 0. {
+        normal.map(|k| k / length)
+    } else {
+        normal
+    }
+}
+
+/// Volumes above and below zero of the linear function taking the `values` at the corners of a
+/// triangle of `area`. A triangle crossing zero is cut along the zero line: the part on the side
+/// of the lone corner is a smaller triangle, a pyramid under the surface.
+fn split_volumes(area: f64, values: [f64; 3]) -> (f64, f64) {
+    let signed = area * values.iter().sum::<f64>() / 3.;
+    // Volume on the side of the lone corner `tip`, the others on the other side or at zero
+    let tip_volume = |tip: f64, others: [f64; 2]| {
+        area * tip.powi(3) / (3. * (tip - others[0]) * (tip - others[1]))
+    };
+    let above = values.iter().filter(|&&value| value > 0.).count();
+    let below = values.iter().filter(|&&value| value < 0.).count();
+    let lone = |positive: bool| {
+        let tip = (0..3)
+            .find(|&idx| (values[idx] > 0.) == positive && values[idx] != 0.)
+            .unwrap();
+        (values[tip], [values[(tip + 1) % 3], values[(tip + 2) % 3]])
+    };
+    match (above, below) {
+        (_, 0) => (signed, 0.),
+        (0, _) => (0., -signed),
+        (1, _) => {
+            let (tip, others) = lone(true);
+            let up = tip_volume(tip, others);
+            (up, up - signed)
+        }
+        _ => {
+            let (tip, others) = lone(false);
+            let down = -tip_volume(tip, others);
+            (signed + down, down)
+        }
+    }
+}
+
+/// Height at `at` of the plane going through the three corners
+fn plane_height(corners: &[Point2; 3], heights: &[f64; 3], at: &Point2) -> f64 {
+    let ((gx, gy), _) = plane_gradient(corners, heights);
+    heights[0] + gx * (at.x - corners[0].x) + gy * (at.y - corners[0].y)
+}
+
+/// Return true if point is inside or on the border of the triangle
+fn in_triangle(corners: &[Point2; 3], point: &Point2) -> bool {
+    let [a, b, c] = *corners;
+    point_in_triangle(*point, a, b, c) != Region::Outside
+}
+
+/// Site waiting in the simplification queue, the smallest error pops first
+struct Candidate {
+    error: f64,
+    vertex: usize,
+    /// Candidates are pushed again when their neighborhood changes, older versions are stale
+    version: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.error.total_cmp(&self.error)
+    }
+}
+
+impl Triangulation {
+    fn site_heights(&self) -> &[f64] {
+        self.heights()
+            .expect("Terrain analysis needs a triangulation built with heights")
+    }
+
+    /// Estimate the gradient (dz/dx, dz/dy) at every site, indexed like `points()`.
+    ///
+    /// The estimate is the area weighted average of the plane gradients of the triangles around
+    /// the site, found by walking its origin ring. Hull sites only average the triangles they
+    /// have; sites without any triangle (collinear input) get NaN.
+    ///
+    /// Panics if the triangulation was not built with heights.
+    pub fn vertex_gradients(&self) -> Vec<(f64, f64)> {
+        let heights = self.site_heights();
+        let height_of = |point: &Point2| heights[self.vertex_index(point).unwrap()];
+        (0..self.points().len())
+            .map(|vertex| {
+                let (mut gx, mut gy, mut total_area) = (0., 0., 0.);
+                for edge in self.onext_ring(vertex) {
+                    if let Some(corners) = self.left_triangle(edge) {
+                        let corner_heights = [
+                            height_of(&corners[0]),
+                            height_of(&corners[1]),
+                            height_of(&corners[2]),
+                        ];
+                        let ((tx, ty), area) = plane_gradient(&corners, &corner_heights);
+                        gx += tx * area;
+                        gy += ty * area;
+                        total_area += area;
+                    }
+                }
+                if total_area > 0. {
+                    (gx / total_area, gy / total_area)
+                } else {
+                    (f64::NAN, f64::NAN)
+                }
+            })
+            .collect()
+    }
+
+    /// Unit normal of every triangle, in the `triangles_iter` order, turned up: z is positive.
+    /// A triangle too flat for its normal to be computed gets a zero vector.
+    ///
+    /// Panics if the triangulation was not built with heights.
+    pub fn face_normals(&self) -> Vec<[f64; 3]> {
+        let heights = self.site_heights();
+        self.triangles_iter()
+            .map(|corners| {
+                let corner_heights =
+                    corners.map(|corner| heights[self.vertex_index(&corner).unwrap()]);
+                unit(face_normal(&corners, &corner_heights))
+            })
+            .collect()
+    }
+
+    /// Unit normal at every site, indexed like `points()`: the area weighted average of the
+    /// normals of the triangles around the site, found by walking its origin ring. Triangles too
+    /// flat to have a normal add nothing; sites without any triangle get NaN.
+    ///
+    /// Panics if the triangulation was not built with heights.
+    pub fn vertex_normals(&self) -> Vec<[f64; 3]> {
+        let heights = self.site_heights();
+        let height_of = |point: &Point2| heights[self.vertex_index(point).unwrap()];
+        (0..self.points().len())
+            .map(|vertex| {
+                let mut sum = [0.; 3];
+                for edge in self.onext_ring(vertex) {
+                    if let Some(corners) = self.left_triangle(edge) {
+                        // The length of the normal is twice the area, it weights the average
+                        let normal =
+                            face_normal(&corners, &corners.map(|corner| height_of(&corner)));
+                        for k in 0..3 {
+                            sum[k] += normal[k];
+                        }
+                    }
+                }
+                if sum == [0.; 3] {
+                    [f64::NAN; 3]
+                } else {
+                    unit(sum)
+                }
+            })
+            .collect()
+    }
+
+    /// Slope at every site, in degrees from the horizontal (0 is flat, 90 is a vertical wall)
+    pub fn slope_degrees(&self) -> Vec<f64> {
+        self.vertex_gradients()
+            .into_iter()
+            .map(|(gx, gy)| gx.hypot(gy).atan().to_degrees())
+            .collect()
+    }
+
+    /// Vertical distance between the height of `vertex` and the surface that would be
+    /// interpolated at its position if it was removed.
+    ///
+    /// A removed hull site ends up outside the new hull, its height is then compared to the
+    /// linear interpolation along the nearest new hull side.
+    fn removal_error(&self, vertex: usize, heights: &[f64]) -> f64 {
+        let Some((polygon, _, closed)) = self.hole_boundary(vertex) else {
+            return f64::INFINITY;
+        };
+        let at = self.points()[vertex];
+        let polygon_heights: Vec<f64> = polygon
+            .iter()
+            .map(|point| heights[self.vertex_index(point).unwrap()])
+            .collect();
+        let ears = hole_ears(&polygon, closed);
+        for ear in ears.iter() {
+            let corners = ear.map(|idx| polygon[idx]);
+            if in_triangle(&corners, &at) {
+                let surface = plane_height(&corners, &ear.map(|idx| polygon_heights[idx]), &at);
+                return (surface - heights[vertex]).abs();
+            }
+        }
+        if closed {
+            // Only reachable through rounding
+            return f64::INFINITY;
+        }
+
+        let chain: Vec<usize> = (0..polygon.len())
+            .filter(|idx| !ears.iter().any(|ear| ear[1] == *idx))
+            .collect();
+        chain
+            .windows(2)
+            .map(|side| {
+                let (a, b) = (polygon[side[0]], polygon[side[1]]);
+                let (dx, dy) = (b.x - a.x, b.y - a.y);
+                let t =
+                    (((at.x - a.x) * dx + (at.y - a.y) * dy) / (dx * dx + dy * dy)).clamp(0., 1.);
+                let distance = (a.x + t * dx - at.x).hypot(a.y + t * dy - at.y);
+                let surface = polygon_heights[side[0]] * (1. - t) + polygon_heights[side[1]] * t;
+                (distance, (surface - heights[vertex]).abs())
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map_or(f64::INFINITY, |(_, error)| error)
+    }
+
+    fn removal_candidate(
+        &self,
+        vertex: usize,
+        version: usize,
+        heights: &[f64],
+        remove_hull: bool,
+    ) -> Option<Candidate> {
+        if self.is_removed(vertex) || (!remove_hull && self.is_hull_vertex(vertex)) {
+            return None;
+        }
+        Some(Candidate {
+            error: self.removal_error(vertex, heights),
+            vertex,
+            version,
+        })
+    }
+
+    /// Greedily remove the sites whose removal changes the interpolated surface by at most
+    /// `max_error` at their own position, smallest error first, re-evaluating the neighbors of
+    /// every removed site. Hull sites are kept. Returns the number of removed sites.
+    ///
+    /// The error is only measured at the site being removed, successive removals can add up to
+    /// more than `max_error` elsewhere.
+    ///
+    /// Panics if the triangulation was not built with heights.
+    pub fn simplify(&mut self, max_error: f64) -> usize {
+        self.simplify_with(max_error, false)
+    }
+
+    /// Same as `simplify`, also removing hull sites when `remove_hull` is set. At least three
+    /// sites are always kept.
+    pub fn simplify_with(&mut self, max_error: f64, remove_hull: bool) -> usize {
+        let heights = self.site_heights().to_vec();
+        let mut versions = vec![0; self.points().len()];
+        let mut live = (0..self.points().len())
+            .filter(|&vertex| !self.is_removed(vertex))
+            .count();
+        let mut queue: BinaryHeap<Candidate> = (0..self.points().len())
+            .filter_map(|vertex| self.removal_candidate(vertex, 0, &heights, remove_hull))
+            .collect();
+
+        let mut removed = 0;
+        while let Some(candidate) = queue.pop() {
+            if candidate.error > max_error || live <= 3 {
+                break;
+            }
+            if candidate.version != versions[candidate.vertex] || self.is_removed(candidate.vertex)
+            {
+                continue;
+            }
+            let neighbors: Vec<usize> = self
+                .onext_ring(candidate.vertex)
+                .map(|edge| self.vertex_index(&edge.dest(self.quad_arena())).unwrap())
+                .collect();
+            self.remove_vertex(candidate.vertex);
+            live -= 1;
+            removed += 1;
+            for neighbor in neighbors {
+                versions[neighbor] += 1;
+                queue.extend(self.removal_candidate(
+                    neighbor,
+                    versions[neighbor],
+                    &heights,
+                    remove_hull,
+                ));
+            }
+        }
+        removed
+    }
+
+    /// Linear interpolation at `point` of `values`, one per site indexed like `points()`: the
+    /// value of the plane through the corners of the triangle holding the point. None outside of
+    /// the hull, in a triangle with a ghost corner, or without any triangle.
+    ///
+    /// Panics if `values` doesn't have one value per site.
+    pub fn interpolate_linear(&self, point: Point2, values: &[f64]) -> Option<f64> {
+        self.interpolate_with(&mut Locator::new(), point, values)
+    }
+
+    /// Like `interpolate_linear`, falling back on `extrapolation` where it gives no value. The
+    /// fallback can still give None, when there is no site left or no hull edge.
+    ///
+    /// Panics if `values` doesn't have one value per site.
+    pub fn interpolate_linear_extrapolated(
+        &self,
+        point: Point2,
+        values: &[f64],
+        extrapolation: Extrapolation,
+    ) -> Option<f64> {
+        self.interpolate_with(&mut Locator::new(), point, values)
+            .or_else(|| match extrapolation {
+                Extrapolation::None => None,
+                Extrapolation::NearestVertex => {
+                    self.nearest_site(point).map(|vertex| values[vertex])
+                }
+                Extrapolation::NearestEdge => self.hull_projection(point, values),
+                Extrapolation::Constant(value) => Some(value),
+            })
+    }
+
+    /// Value at the point of the hull nearest to `point`, linear along the hull edge holding it
+    fn hull_projection(&self, point: Point2, values: &[f64]) -> Option<f64> {
+        self.hull_edges()
+            .map(|edge| {
+                let (org, dest) = edge.org_dest(self.quad_arena());
+                let (dx, dy) = (dest.x - org.x, dest.y - org.y);
+                let length = dx * dx + dy * dy;
+                let t = if length > 0. {
+                    (((point.x - org.x) * dx + (point.y - org.y) * dy) / length).clamp(0., 1.)
+                } else {
+                    0.
+                };
+                let projection = Point2 {
+                    x: org.x + t * dx,
+                    y: org.y + t * dy,
+                };
+                let value = |corner: &Point2| values[self.vertex_index(corner).unwrap()];
+                let interpolated = (1. - t) * value(&org) + t * value(&dest);
+                (point.distance(&projection), interpolated)
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, value)| value)
+    }
+
+    /// `interpolate_linear` over a batch of queries. Queries go in chunks, each walking from the
+    /// previous query of its chunk, so the results only depend on the queries. With the `rayon`
+    /// feature the chunks are processed in parallel, the mesh is only read.
+    pub fn interpolate_linear_batch(&self, queries: &[Point2], values: &[f64]) -> Vec<Option<f64>> {
+        let chunk = |queries: &[Point2]| {
+            let mut locator = Locator::new();
+            queries
+                .iter()
+                .map(|&query| self.interpolate_with(&mut locator, query, values))
+                .collect::<Vec<Option<f64>>>()
+        };
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            queries
+                .par_chunks(BATCH_CHUNK)
+                .flat_map_iter(chunk)
+                .collect()
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            queries.chunks(BATCH_CHUNK).flat_map(chunk).collect()
+        }
+    }
+
+    fn interpolate_with(
+        &self,
+        locator: &mut Locator,
+        point: Point2,
+        values: &[f64],
+    ) -> Option<f64> {
+        assert_eq!(
+            values.len(),
+            self.points().len(),
+            "Every site needs exactly one value"
+        );
+        self.any_triangle_edge()?;
+        let edge = match locator.locate(self, point) {
+            Location::Vertex(vertex) => return Some(values[vertex]),
+            Location::Inside(edge) | Location::OnEdge(edge) => edge,
+            Location::Outside(_) => return None,
+        };
+        let corners = self.left_triangle(edge)?;
+        if corners.iter().any(|&corner| self.is_ghost(corner)) {
+            return None;
+        }
+        let [a, b, c] = &corners;
+        let area = orient2d_value(a, b, c);
+        let weights = [
+            orient2d_value(&point, b, c) / area,
+            orient2d_value(a, &point, c) / area,
+            orient2d_value(a, b, &point) / area,
+        ];
+        Some(
+            corners
+                .iter()
+                .zip(weights)
+                .map(|(corner, weight)| weight * values[self.vertex_index(corner).unwrap()])
+                .sum(),
+        )
+    }
+
+    /// Volume between the surface and the horizontal plane at height `datum`, where the surface
+    /// is above it. See `datum_volumes`.
+    ///
+    /// Panics if the triangulation was not built with heights.
+    pub fn volume_above(&self, datum: f64) -> f64 {
+        self.datum_volumes(datum).0
+    }
+
+    /// Volumes between the surface and the horizontal plane at height `datum`, where the surface
+    /// is above it and where it is below it, both positive, over the triangles of
+    /// `triangles_iter`. Each triangle is a prism under its plane; triangles crossing the datum
+    /// are cut along the crossing line, so the volumes are exact up to rounding.
+    ///
+    /// Panics if the triangulation was not built with heights.
+    pub fn datum_volumes(&self, datum: f64) -> (f64, f64) {
+        let heights = self.site_heights();
+        self.triangles_iter()
+            .map(|corners| {
+                let values =
+                    corners.map(|corner| heights[self.vertex_index(&corner).unwrap()] - datum);
+                let area = orient2d_value(&corners[0], &corners[1], &corners[2]).abs() / 2.;
+                split_volumes(area, values)
+            })
+            .fold((0., 0.), |(above, below), (up, down)| {
+                (above + up, below + down)
+            })
+    }
+
+    /// Cut and fill from this surface to `other`, both triangulations with heights: the volume
+    /// where `other` is below and where it is above, over the area both cover. Every pair of
+    /// overlapping triangles is intersected; over their intersection both surfaces are planes, so
+    /// the volumes between them are exact up to rounding. The area covered by a single surface
+    /// is left out and reported.
+    ///
+    /// Panics if either triangulation was not built with heights.
+    pub fn cut_fill(&self, other: &Triangulation) -> CutFill {
+        let grid = FaceGrid::new(other);
+        let mut result = CutFill::default();
+        let (mut overlap, mut area) = (0., 0.);
+        // Faces of `other` already met for the current face, by face
+        let mut seen = vec![usize::MAX; grid.faces.len()];
+        for (face, (corners, heights)) in FaceGrid::new(self).faces.into_iter().enumerate() {
+            area += orient2d_value(&corners[0], &corners[1], &corners[2]).abs() / 2.;
+            for cell in grid.cells_of(&corners) {
+                for &candidate in &grid.cells[cell] {
+                    if seen[candidate] == face {
+                        continue;
+                    }
+                    seen[candidate] = face;
+                    let (other_corners, other_heights) = &grid.faces[candidate];
+                    let polygon = clip(corners.to_vec(), other_corners);
+                    // Height of `other` over this surface, linear over the polygon
+                    let rise = |point: &Point2| {
+                        plane_height(other_corners, other_heights, point)
+                            - plane_height(&corners, &heights, point)
+                    };
+                    for idx in 1..polygon.len().saturating_sub(1) {
+                        let fan = [polygon[0], polygon[idx], polygon[idx + 1]];
+                        let fan_area = orient2d_value(&fan[0], &fan[1], &fan[2]).abs() / 2.;
+                        let (fill, cut) = split_volumes(fan_area, fan.map(|point| rise(&point)));
+                        result.fill += fill;
+                        result.cut += cut;
+                        overlap += fan_area;
+                    }
+                }
+            }
+        }
+        let other_area: f64 = grid
+            .faces
+            .iter()
+            .map(|(corners, _)| orient2d_value(&corners[0], &corners[1], &corners[2]).abs() / 2.)
+            .sum();
+        result.only_self = (area - overlap).max(0.);
+        result.only_other = (other_area - overlap).max(0.);
+        result
+    }
+
+    /// Aspect at every site: the compass bearing the downhill direction faces, in degrees within
+    /// [0, 360), clockwise from north. With y growing downward, north is -y, east is +x, south
+    /// is +y and west is -x. Flat sites have no aspect.
+    pub fn aspect_degrees(&self) -> Vec<Option<f64>> {
+        self.vertex_gradients()
+            .into_iter()
+            .map(|(gx, gy)| {
+                if gx == 0. && gy == 0. || gx.is_nan() || gy.is_nan() {
+                    return None;
+                }
+                // Downhill direction is (-gx, -gy), north is (0, -1)
+                Some((-gx).atan2(gy).to_degrees().rem_euclid(360.))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robust_float::counter_clockwise;
+    use crate::test_util::xorshift;
+
+    fn ramp(a: f64, b: f64) -> Triangulation {
+        let mut points = vec![];
+        let mut heights = vec![];
+        for i in 0..6 {
+            for j in 0..6 {
+                // Jitter the grid so triangles have various shapes and sizes
+                let x = i as f64 + 0.1 * ((i * 7 + j * 3) % 5) as f64;
+                let y = j as f64 + 0.1 * ((i * 3 + j * 5) % 4) as f64;
+                points.push(Point2 { x, y });
+                heights.push(a * x + b * y + 3.);
+            }
+        }
+        Triangulation::with_heights(&points, &heights)
+    }
+
+    #[test]
+    fn test_triangulation_is_sync() {
+        fn assert_sync<T: Sync + Send>() {}
+        assert_sync::<Triangulation>();
+    }
+
+    #[test]
+    fn test_interpolate_linear_batch() {
+        let mut next = xorshift(83);
+        let points: Vec<Point2> = (0..2000)
+            .map(|_| Point2 {
+                x: next() * 100.,
+                y: next() * 100.,
+            })
+            .collect();
+        let triangulation = Triangulation::new(&points);
+        let plane = |point: &Point2| 2. * point.x - 0.5 * point.y + 7.;
+        let values: Vec<f64> = triangulation.points().iter().map(plane).collect();
+        // A raster tile over the sites and past them, row by row
+        let queries: Vec<Point2> = (0..100 * 100)
+            .map(|idx| Point2 {
+                x: (idx % 100) as f64 * 1.1 - 2.,
+                y: (idx / 100) as f64 * 1.1 - 2.,
+            })
+            .collect();
+        let batch = triangulation.interpolate_linear_batch(&queries, &values);
+        let serial: Vec<Option<f64>> = queries
+            .chunks(BATCH_CHUNK)
+            .flat_map(|chunk| {
+                let mut locator = Locator::new();
+                chunk
+                    .iter()
+                    .map(|&query| triangulation.interpolate_with(&mut locator, query, &values))
+                    .collect::<Vec<Option<f64>>>()
+            })
+            .collect();
+        assert_eq!(batch, serial);
+        for (query, value) in queries.iter().zip(&batch) {
+            match value {
+                Some(value) => assert!((value - plane(query)).abs() < 1e-9),
+                // Hull edges have the outside on their left, so not strictly inside of the hull
+                None => assert!(!triangulation.hull_edges().all(|edge| {
+                    let (org, dest) = edge.org_dest(triangulation.quad_arena());
+                    counter_clockwise(&dest, &org, query)
+                })),
+            }
+            assert_eq!(
+                value.is_some(),
+                triangulation.interpolate_linear(*query, &values).is_some()
+            );
+        }
+        assert!(batch.iter().filter(|value| value.is_none()).count() > 0);
+        assert_eq!(
+            triangulation.interpolate_linear(points[3], &values),
+            Some(plane(&points[3]))
+        );
+    }
+
+    #[test]
+    fn test_extrapolation() {
+        // Unit square with a center site, hull edges on x = 0, x = 1, y = 0 and y = 1
+        let points = [
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 1., y: 0. },
+            Point2 { x: 1., y: 1. },
+            Point2 { x: 0., y: 1. },
+            Point2 { x: 0.5, y: 0.5 },
+        ];
+        let triangulation = Triangulation::new(&points);
+        let values: Vec<f64> = triangulation
+            .points()
+            .iter()
+            .map(|point| 10. * point.x + point.y * point.y)
+            .collect();
+        let value = |point: Point2| values[triangulation.vertex_index(&point).unwrap()];
+        let outside = Point2 { x: 1.01, y: 0.3 };
+        let interpolate = |point: Point2, extrapolation: Extrapolation| {
+            triangulation.interpolate_linear_extrapolated(point, &values, extrapolation)
+        };
+
+        assert_eq!(interpolate(outside, Extrapolation::None), None);
+        assert_eq!(
+            interpolate(outside, Extrapolation::Constant(-4.)),
+            Some(-4.)
+        );
+        assert_eq!(
+            interpolate(outside, Extrapolation::NearestVertex),
+            Some(value(points[1]))
+        );
+        // Linear along the edge from (1, 0) to (1, 1)
+        let expected = 0.7 * value(points[1]) + 0.3 * value(points[2]);
+        let edge = interpolate(outside, Extrapolation::NearestEdge).unwrap();
+        assert!((edge - expected).abs() < 1e-12);
+        // Past a corner, the corner is the nearest point of the hull
+        let corner = interpolate(Point2 { x: -0.5, y: 1.2 }, Extrapolation::NearestEdge);
+        assert!((corner.unwrap() - value(points[3])).abs() < 1e-12);
+
+        // Continuous across the hull edge, and inside every policy is the interpolation
+        let inside = Point2 {
+            x: 1. - 1e-9,
+            y: 0.3,
+        };
+        let within = triangulation.interpolate_linear(inside, &values).unwrap();
+        let across = interpolate(
+            Point2 {
+                x: 1. + 1e-9,
+                y: 0.3,
+            },
+            Extrapolation::NearestEdge,
+        );
+        assert!((within - across.unwrap()).abs() < 1e-6);
+        for extrapolation in [
+            Extrapolation::None,
+            Extrapolation::NearestVertex,
+            Extrapolation::NearestEdge,
+            Extrapolation::Constant(-4.),
+        ] {
+            assert_eq!(interpolate(inside, extrapolation), Some(within));
+        }
+    }
+
+    #[test]
+    fn test_planar_ramp_gradient() {
+        let (a, b) = (0.3, -1.7);
+        let gradients = ramp(a, b).vertex_gradients();
+        assert_eq!(gradients.len(), 36);
+        for (gx, gy) in gradients {
+            assert!((gx - a).abs() < 1e-9);
+            assert!((gy - b).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_normals() {
+        let (a, b) = (0.3, -1.7);
+        let triangulation = ramp(a, b);
+        let length = (a * a + b * b + 1f64).sqrt();
+        let expected = [-a / length, -b / length, 1. / length];
+        let normals = triangulation.face_normals();
+        assert_eq!(normals.len(), triangulation.triangles().len());
+        for normal in normals.into_iter().chain(triangulation.vertex_normals()) {
+            for k in 0..3 {
+                assert!((normal[k] - expected[k]).abs() < 1e-9);
+            }
+        }
+
+        // Square pyramid, apex in the middle
+        let corners = [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.), (0., 0.)];
+        let points = corners.map(|(x, y)| Point2 { x, y });
+        let pyramid = Triangulation::with_heights(&points, &[0., 0., 0., 0., 2.]);
+        let apex = pyramid.vertex_index(&points[4]).unwrap();
+        assert_eq!(pyramid.vertex_normals()[apex], [0., 0., 1.]);
+        for normal in pyramid.face_normals() {
+            // Every face leans outward, at 2 / sqrt(5) from the vertical
+            assert!((normal[2] - 1. / 5f64.sqrt()).abs() < 1e-12);
+        }
+
+        // Collinear sites have no triangle
+        let line = [0., 1., 2.].map(|x| Point2 { x, y: 0. });
+        let flat = Triangulation::with_heights(&line, &[0.; 3]);
+        assert!(flat.face_normals().is_empty());
+        assert!(flat
+            .vertex_normals()
+            .iter()
+            .all(|normal| normal[0].is_nan()));
+    }
+
+    #[test]
+    fn test_datum_volumes() {
+        // Flat at height 3 over a 4 by 2 rectangle, with a site inside
+        let rectangle =
+            [(0., 0.), (4., 0.), (4., 2.), (0., 2.), (1., 1.)].map(|(x, y)| Point2 { x, y });
+        let flat = Triangulation::with_heights(&rectangle, &[3.; 5]);
+        assert_eq!(flat.datum_volumes(1.), (16., 0.));
+        assert_eq!(flat.datum_volumes(5.), (0., 16.));
+        assert_eq!(flat.volume_above(3.), 0.);
+
+        // Square pyramid of base 2 by 2 and height 2
+        let corners = [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.), (0., 0.)];
+        let points = corners.map(|(x, y)| Point2 { x, y });
+        let pyramid = Triangulation::with_heights(&points, &[0., 0., 0., 0., 2.]);
+        assert!((pyramid.volume_above(0.) - 8. / 3.).abs() < 1e-12);
+        // Cut halfway up: a pyramid of half the size above, the rest of the solid below
+        let (above, below) = pyramid.datum_volumes(1.);
+        assert!((above - 1. / 3.).abs() < 1e-12);
+        assert!((below - (4. - (8. / 3. - 1. / 3.))).abs() < 1e-12);
+
+        // Saddle z = x² - y², over sites kept by quarter turns that negate it
+        let mut next = xorshift(59);
+        let mut points = vec![Point2 { x: 0., y: 0. }];
+        for _ in 0..100 {
+            let mut point = Point2 {
+                x: next() * 2. - 1.,
+                y: next() * 2. - 1.,
+            };
+            for _ in 0..4 {
+                points.push(point);
+                point = Point2 {
+                    x: -point.y,
+                    y: point.x,
+                };
+            }
+        }
+        let heights: Vec<f64> = points.iter().map(|p| p.x * p.x - p.y * p.y).collect();
+        let saddle = Triangulation::with_heights(&points, &heights);
+        let (fill, cut) = saddle.datum_volumes(0.);
+        assert!(fill > 0.1);
+        assert!((fill - cut).abs() < 1e-12);
+        // Raising the datum moves volume from above to below, by the area each unit
+        let area: f64 = saddle
+            .triangles_iter()
+            .map(|[a, b, c]| orient2d_value(&a, &b, &c).abs() / 2.)
+            .sum();
+        let (higher_fill, higher_cut) = saddle.datum_volumes(0.25);
+        assert!((fill - cut - (higher_fill - higher_cut) - 0.25 * area).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_cut_fill() {
+        let mut next = xorshift(61);
+        // Sites over a square, its corners included so both surfaces cover all of it
+        let mut survey = |offset: f64, count: usize| -> Vec<Point2> {
+            let mut points: Vec<Point2> = [(0., 0.), (100., 0.), (100., 100.), (0., 100.)]
+                .map(|(x, y)| Point2 { x: x + offset, y })
+                .to_vec();
+            points.extend((0..count).map(|_| Point2 {
+                x: next() * 100. + offset,
+                y: next() * 100.,
+            }));
+            points
+        };
+        let terrain = |points: &[Point2], height: &dyn Fn(&Point2) -> f64| {
+            let heights: Vec<f64> = points.iter().map(height).collect();
+            Triangulation::with_heights(points, &heights)
+        };
+        let ground = |point: &Point2| 0.02 * point.x - 0.01 * point.y + 5.;
+        let (before_sites, after_sites) = (survey(0., 300), survey(0., 200));
+        let before = terrain(&before_sites, &ground);
+
+        let same = before.cut_fill(&before);
+        assert!(same.cut < 1e-9 && same.fill < 1e-9);
+        assert!(same.only_self < 1e-9 && same.only_other < 1e-9);
+
+        // Raised by 1 everywhere, on other sites
+        let raised = before.cut_fill(&terrain(&after_sites, &|point| ground(point) + 1.));
+        assert!((raised.fill - 10_000.).abs() < 1e-6);
+        assert!(raised.cut < 1e-9);
+
+        // Tilted around x = 50: as much is dug on the left as is filled on the right
+        let tilted = before.cut_fill(&terrain(&after_sites, &|point| {
+            ground(point) + 0.1 * (point.x - 50.)
+        }));
+        assert!((tilted.fill - 12_500.).abs() < 1e-6);
+        assert!((tilted.cut - 12_500.).abs() < 1e-6);
+
+        // Half of the footprints overlap
+        let shifted = before.cut_fill(&terrain(&survey(50., 200), &ground));
+        assert!((shifted.only_self - 5000.).abs() < 1e-6);
+        assert!((shifted.only_other - 5000.).abs() < 1e-6);
+        assert!(shifted.cut < 1e-9 && shifted.fill < 1e-9);
+    }
+
+    #[test]
+    fn test_slope_and_aspect() {
+        // z = x: 45 degrees, going down toward -x, west
+        let triangulation = ramp(1., 0.);
+        for slope in triangulation.slope_degrees() {
+            assert!((slope - 45.).abs() < 1e-9);
+        }
+        for aspect in triangulation.aspect_degrees() {
+            assert!((aspect.unwrap() - 270.).abs() < 1e-9);
+        }
+
+        // z = -2y: going down toward +y, south
+        let triangulation = ramp(0., -2.);
+        for slope in triangulation.slope_degrees() {
+            assert!((slope - 2f64.atan().to_degrees()).abs() < 1e-9);
+        }
+        for aspect in triangulation.aspect_degrees() {
+            assert!((aspect.unwrap() - 180.).abs() < 1e-9);
+        }
+
+        // Flat ground has no aspect
+        let triangulation = ramp(0., 0.);
+        assert!(triangulation.aspect_degrees().iter().all(Option::is_none));
+    }
+
+    /// Height of the simplified surface at `at`, searching every triangle
+    fn surface_at(triangulation: &Triangulation, at: &Point2) -> Option<f64> {
+        let heights = triangulation.heights().unwrap();
+        (0..triangulation.points().len())
+            .flat_map(|vertex| triangulation.onext_ring(vertex))
+            .filter_map(|edge| triangulation.left_triangle(edge))
+            .find(|corners| in_triangle(corners, at))
+            .map(|corners| {
+                let corner_heights =
+                    corners.map(|corner| heights[triangulation.vertex_index(&corner).unwrap()]);
+                plane_height(&corners, &corner_heights, at)
+            })
+    }
+
+    #[test]
+    fn test_simplify_planar() {
+        let mut triangulation = ramp(0.3, -1.7);
+        let hull: Vec<bool> = (0..triangulation.points().len())
+            .map(|vertex| triangulation.is_hull_vertex(vertex))
+            .collect();
+        let interior = hull.iter().filter(|on_hull| !**on_hull).count();
+        assert!(interior > 0);
+        assert_eq!(triangulation.simplify(1e-9), interior);
+        for (vertex, on_hull) in hull.into_iter().enumerate() {
+            assert_eq!(triangulation.is_removed(vertex), !on_hull);
+        }
+    }
+
+    #[test]
+    fn test_simplify_ridge() {
+        // Ridge along x = 4.5, the surface goes down 2 per unit on both sides
+        let height = |point: &Point2| 10. - 2. * (point.x - 4.5).abs();
+        let mut points = vec![];
+        for i in 0..10 {
+            for j in 0..10 {
+                points.push(Point2 {
+                    x: i as f64 + 0.13 * ((i * 7 + j * 3) % 5) as f64,
+                    y: j as f64 + 0.11 * ((i * 3 + j * 5) % 4) as f64,
+                });
+            }
+            points.push(Point2 {
+                x: 4.5,
+                y: i as f64 + 0.5,
+            });
+        }
+        let heights: Vec<f64> = points.iter().map(height).collect();
+        let mut triangulation = Triangulation::with_heights(&points, &heights);
+
+        let tolerance = 0.01;
+        let removed = triangulation.simplify(tolerance);
+        assert!(removed > 50);
+        for step in 0..=80 {
+            let at = Point2 {
+                x: 4.5,
+                y: 1. + step as f64 * 0.1,
+            };
+            let surface = surface_at(&triangulation, &at).unwrap();
+            assert!((surface - height(&at)).abs() <= tolerance);
+        }
+    }
+}
@@ -0,0 +1,2902 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::iter::FusedIterator;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+use crate::edge::*;
+use crate::gns_delaunay::{
+    compute_delaunay, compute_delaunay_recorded, quad_capacity, MeshEvent, Recorder,
+};
+use crate::locate::LocatorHierarchy;
+use crate::robust_float::{
+    cmp_points, counter_clockwise, in_circle, incircle_value, nearly_equals, point_in_triangle,
+    sanitize_points_vec, OrderedPoint2, Point2, Rect, Region,
+};
+use crate::sample::SampleTable;
+
+/// Distance of the ghost sites of `Triangulation::with_ghosts` from the center of the sites, in
+/// multiples of the sites' extent
+const GHOST_DISTANCE: f64 = 1e4;
+
+/// Insertions `undo_last_insert` can take back, the older ones are forgotten
+const UNDO_DEPTH: usize = 64;
+
+/// Handle of a site of a `Triangulation`, its index in `points()`. Handles stay valid when sites
+/// are inserted or removed.
+pub type VertexHandle = usize;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MoveError {
+    /// The site was removed from the triangulation, holds its handle
+    Removed(VertexHandle),
+    /// The new position is on another site, holds the handle of that site
+    Collision(VertexHandle),
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::Removed(vertex) => write!(f, "Site {} was removed", vertex),
+            MoveError::Collision(vertex) => write!(f, "Site {} is already there", vertex),
+        }
+    }
+}
+
+/// Delaunay triangulation that keeps its quad edge structure around, so it can be queried after
+/// construction instead of only being flattened into a list of lines
+pub struct Triangulation {
+    quad_arena: QuadEdgeArena,
+    /// Sites, a site index is its position in this vec. The sites given at construction are
+    /// sanitized and sorted with `cmp_points`, inserted sites are appended.
+    points: Vec<Point2>,
+    /// Index of every site, keyed by its exact coordinates
+    index: HashMap<OrderedPoint2, usize>,
+    /// Optional height of every site, for 2.5D terrain (TIN) use
+    heights: Option<Vec<f64>>,
+    /// One edge leaving every site, None while the site has no edge (less than 2 sites, or
+    /// removed site)
+    vertex_edges: Vec<Option<EdgeRef>>,
+    /// Sites taken out of the mesh by `remove_vertex`, they keep their index
+    removed: Vec<bool>,
+    /// Far away sites wrapping the others, see `with_ghosts`, empty otherwise
+    ghosts: Vec<VertexHandle>,
+    /// Latest insertions, the last one at the back. Any other change of the mesh clears it.
+    undo_log: VecDeque<Insertion>,
+    /// Walks that didn't reach their point and fell back to a scan of the faces, see
+    /// `walk_fallbacks`
+    walk_fallbacks: AtomicUsize,
+    /// Counts, bounds and centroid of the real part of the mesh, see `site_count`
+    extent: Extent,
+    /// Cumulative triangle areas of `sample_uniform`, built on first use and dropped whenever
+    /// the `extent` is updated
+    samples: OnceLock<SampleTable>,
+    /// Levels of `build_hierarchy`, built on first use and dropped along with `samples`
+    hierarchy: OnceLock<LocatorHierarchy>,
+    /// Edge of the hull with the outside on its left, where `hull` starts walking. Insertions
+    /// reaching the hull and removals of hull sites move it when it isn't on the hull anymore.
+    hull_edge: Option<EdgeRef>,
+}
+
+/// How to take an insertion back, see `undo_last_insert`
+#[derive(Clone, Debug)]
+enum Insertion {
+    /// The site was connected to the hole around it, then `flips` were flipped. `split` is the
+    /// edge it was inserted on, deleted first: its origin, destination and the third corner of
+    /// the triangle on its left.
+    Local {
+        vertex: VertexHandle,
+        appended: bool,
+        flips: Vec<EdgeRef>,
+        split: Option<[VertexHandle; 3]>,
+    },
+    /// The mesh was triangulated again with the site
+    Rebuilt {
+        vertex: VertexHandle,
+        appended: bool,
+    },
+}
+
+/// What `site_count`, `edge_count`, `triangle_count`, `bounds` and `centroid` give, counted once
+/// at build time then updated by every change of the mesh
+#[derive(Clone, Debug, Default)]
+struct Extent {
+    sites: usize,
+    edges: usize,
+    triangles: usize,
+    bounds: Option<Rect>,
+    /// Sum of the sites relative to `origin`, one of them, so sites far from 0, 0 keep the
+    /// digits of their mean
+    origin: Point2,
+    sum: (f64, f64),
+}
+
+/// Sort and remove near-equal points like `sanitize_points_vec`, keeping every point's payload
+/// along with it
+pub(crate) fn sanitize_with<T: Copy>(points: &[Point2], payload: &[T]) -> (Vec<Point2>, Vec<T>) {
+    let mut sites: Vec<(Point2, T)> = points
+        .iter()
+        .copied()
+        .zip(payload.iter().copied())
+        .collect();
+    sites.sort_by(|a, b| cmp_points(&a.0, &b.0));
+    let mut idx = 0;
+    while idx + 1 < sites.len() {
+        if nearly_equals(&sites[idx].0, &sites[idx + 1].0) {
+            sites.remove(idx);
+        } else {
+            idx += 1;
+        }
+    }
+    sites.into_iter().unzip()
+}
+
+/// Delaunay triangulation of the hole left by a removed site, as triangles of indices into
+/// `polygon`, in the order their ears should be cut.
+///
+/// `polygon` lists the hole boundary with the hole on the left of every `polygon[i] ->
+/// polygon[i + 1]` side. A closed polygon is cut down to its last triangle, which is returned
+/// last and needs no new edge. An open chain (hole of a hull site, opening on the outer face) is
+/// cut until it is convex.
+pub(crate) fn hole_ears(polygon: &[Point2], closed: bool) -> Vec<[usize; 3]> {
+    let mut remaining: Vec<usize> = (0..polygon.len()).collect();
+    let mut ears = vec![];
+    loop {
+        let min_len = if closed { 4 } else { 3 };
+        if remaining.len() < min_len {
+            break;
+        }
+        let candidates = if closed {
+            remaining.len()
+        } else {
+            remaining.len() - 2
+        };
+        let ear_at = |pos: usize| {
+            [
+                remaining[pos],
+                remaining[(pos + 1) % remaining.len()],
+                remaining[(pos + 2) % remaining.len()],
+            ]
+        };
+        let convex = |ear: &[usize; 3]| {
+            counter_clockwise(&polygon[ear[0]], &polygon[ear[1]], &polygon[ear[2]])
+        };
+        let delaunay = |ear: &[usize; 3]| {
+            (0..polygon.len()).all(|other| {
+                ear.contains(&other)
+                    || !in_circle(
+                        &polygon[ear[0]],
+                        &polygon[ear[1]],
+                        &polygon[ear[2]],
+                        &polygon[other],
+                    )
+            })
+        };
+        let found = (0..candidates)
+            .find(|&pos| convex(&ear_at(pos)) && delaunay(&ear_at(pos)))
+            // Only reachable through rounding, any convex ear keeps the mesh valid
+            .or_else(|| (0..candidates).find(|&pos| convex(&ear_at(pos))));
+        match found {
+            Some(pos) => {
+                ears.push(ear_at(pos));
+                remaining.remove((pos + 1) % remaining.len());
+            }
+            None => break,
+        }
+    }
+    if closed && remaining.len() == 3 {
+        ears.push([remaining[0], remaining[1], remaining[2]]);
+    }
+    ears
+}
+
+/// Where a point lies in a triangulation, relative to the triangles around it
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Location {
+    /// On an existing site
+    Vertex(usize),
+    /// Strictly inside the triangle on the left of the edge
+    Inside(EdgeRef),
+    /// On the edge, which has a triangle on its left
+    OnEdge(EdgeRef),
+    /// Outside of the hull, strictly on the left of the edge, which has the outer face on its left
+    Outside(EdgeRef),
+}
+
+/// Smallest rectangle holding every point, None without points
+pub(crate) fn bounding_rect(points: &[Point2]) -> Option<Rect> {
+    let first = *points.first()?;
+    Some(points.iter().fold(
+        Rect {
+            min: first,
+            max: first,
+        },
+        |bounds, point| Rect {
+            min: Point2 {
+                x: bounds.min.x.min(point.x),
+                y: bounds.min.y.min(point.y),
+            },
+            max: Point2 {
+                x: bounds.max.x.max(point.x),
+                y: bounds.max.y.max(point.y),
+            },
+        },
+    ))
+}
+
+/// Position of `point` along a Hilbert curve filling `bounds`, consecutive positions are close
+/// in the plane
+fn hilbert_index(point: &Point2, bounds: &Rect) -> u64 {
+    const SIDE: u64 = 1 << 16;
+    let cell = |value: f64, min: f64, size: f64| {
+        if size > 0. {
+            ((value - min) / size * (SIDE - 1) as f64) as u64
+        } else {
+            0
+        }
+    };
+    let mut x = cell(point.x, bounds.min.x, bounds.width());
+    let mut y = cell(point.y, bounds.min.y, bounds.height());
+    let mut index = 0;
+    let mut half = SIDE / 2;
+    while half > 0 {
+        let rx = (x & half > 0) as u64;
+        let ry = (y & half > 0) as u64;
+        index += half * half * ((3 * rx) ^ ry);
+        if ry == 0 {
+            if rx == 1 {
+                x = SIDE - 1 - x;
+                y = SIDE - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        half /= 2;
+    }
+    index
+}
+
+/// Finalizer of the splitmix64 generator, a cheap well mixing 64 bits hash
+pub(crate) fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+impl Triangulation {
+    /// Sanitize and triangulate the points
+    pub fn new(points: &[Point2]) -> Triangulation {
+        let mut points = points.to_vec();
+        sanitize_points_vec(&mut points);
+        Triangulation::from_sanitized(points, None)
+    }
+
+    /// Sanitize and triangulate the points, carrying a height per point.
+    /// When near-equal points are merged, the height of the kept point is kept.
+    ///
+    /// Panics if `points` and `heights` don't have the same length.
+    pub fn with_heights(points: &[Point2], heights: &[f64]) -> Triangulation {
+        assert_eq!(
+            points.len(),
+            heights.len(),
+            "Every point needs exactly one height"
+        );
+        let (points, heights) = sanitize_with(points, heights);
+        Triangulation::from_sanitized(points, Some(heights))
+    }
+
+    /// Sanitize and triangulate the points, sending every change of the mesh to `recorder` as it
+    /// happens: edges made by the base cases, joined by the merges, and deleted when they fail
+    /// the in-circle test of a merge. Applying the events in order to an empty set of edges
+    /// gives the edges of the triangulation.
+    pub fn with_recorder(points: &[Point2], mut recorder: impl FnMut(MeshEvent)) -> Triangulation {
+        let mut points = points.to_vec();
+        sanitize_points_vec(&mut points);
+        let capacity = quad_capacity(points.len());
+        Triangulation::from_sanitized_recorded(points, None, capacity, &mut Some(&mut recorder))
+    }
+
+    /// Sanitize and triangulate the points, choosing the diagonal of every quadrilateral with
+    /// four cocircular corners from `seed` rather than from the merge order.
+    ///
+    /// The rule: sort the four corners with `cmp_points`, hash the seed and the bits of their
+    /// coordinates with splitmix64, the diagonal goes through the first corner when the hash is
+    /// even. Only exact predicates and integer hashing are involved, so the mesh is the same on
+    /// every platform. Quadrilaterals are visited once, in the order of their diagonal endpoints.
+    pub fn with_tiebreak(points: &[Point2], seed: u64) -> Triangulation {
+        let mut triangulation = Triangulation::new(points);
+        triangulation.break_ties(seed);
+        triangulation
+    }
+
+    /// Sanitize and triangulate the points, then wrap them in three ghost sites far away, so that
+    /// every site is inside the mesh and the outer face is made of ordinary triangles touching a
+    /// ghost. Walks then never leave the mesh for points inside the ghost triangle.
+    ///
+    /// The ghosts are sites appended after the given ones, `is_ghost` tells them apart. Edges
+    /// between two real sites are never flipped for a ghost edge, so the real part is the same as
+    /// `new` gives. `edges_iter`, `triangles_iter` and the outputs built on them skip everything
+    /// touching a ghost, the lower level views (`live_edges`, `left_triangle`...) don't.
+    pub fn with_ghosts(points: &[Point2]) -> Triangulation {
+        let mut triangulation = Triangulation::new(points);
+        triangulation.add_ghosts();
+        triangulation
+    }
+
+    /// Wrap the sites in the three ghost sites of `with_ghosts`
+    pub(crate) fn add_ghosts(&mut self) {
+        let (center, size) = match bounding_rect(&self.points) {
+            Some(bounds) => (
+                Point2 {
+                    x: (bounds.min.x + bounds.max.x) / 2.,
+                    y: (bounds.min.y + bounds.max.y) / 2.,
+                },
+                bounds.width().max(bounds.height()).max(1.),
+            ),
+            None => (Point2::default(), 1.),
+        };
+        for angle in [90f64, 210., 330.] {
+            let (sin, cos) = angle.to_radians().sin_cos();
+            let ghost = Point2 {
+                x: center.x + GHOST_DISTANCE * size * cos,
+                y: center.y + GHOST_DISTANCE * size * sin,
+            };
+            let handle = self.insert_one(ghost);
+            self.ghosts.push(handle);
+        }
+        self.undo_log.clear();
+        self.recount();
+    }
+
+    /// Return true if `point` is one of the ghost sites added by `with_ghosts`
+    pub fn is_ghost(&self, point: Point2) -> bool {
+        self.ghosts.iter().any(|&ghost| self.points[ghost] == point)
+    }
+
+    /// Corners of the two triangles around `edge` if they lie on a same circle
+    fn cocircular_quad(&self, edge: EdgeRef) -> Option<[Point2; 4]> {
+        let [a, b, c] = self.left_triangle(edge)?;
+        let [_, _, d] = self.left_triangle(edge.sym())?;
+        (incircle_value(&a, &b, &c, &d) == 0.).then_some([a, b, c, d])
+    }
+
+    /// Choose the diagonals of cocircular quadrilaterals from `seed`, see `with_tiebreak`
+    pub(crate) fn break_ties(&mut self, seed: u64) {
+        let mut ties: Vec<(Point2, Point2, EdgeRef)> = self
+            .live_edges()
+            .filter(|&edge| self.cocircular_quad(edge).is_some())
+            .map(|edge| {
+                let (org, dest) = edge.org_dest(&self.quad_arena);
+                match cmp_points(&org, &dest) {
+                    std::cmp::Ordering::Greater => (dest, org, edge),
+                    _ => (org, dest, edge),
+                }
+            })
+            .collect();
+        ties.sort_by(|a, b| cmp_points(&a.0, &b.0).then(cmp_points(&a.1, &b.1)));
+
+        for (first_end, second_end, edge) in ties {
+            // An earlier flip may have changed the triangles around this edge
+            let Some(mut corners) = self.cocircular_quad(edge) else {
+                continue;
+            };
+            corners.sort_by(cmp_points);
+            let hash = corners.iter().fold(splitmix64(seed), |hash, corner| {
+                splitmix64(splitmix64(hash ^ corner.x.to_bits()) ^ corner.y.to_bits())
+            });
+            let through_first = first_end == corners[0] || second_end == corners[0];
+            if through_first != hash.is_multiple_of(2) {
+                self.flip(edge);
+            }
+        }
+    }
+
+    pub(crate) fn from_sanitized(points: Vec<Point2>, heights: Option<Vec<f64>>) -> Triangulation {
+        let capacity = quad_capacity(points.len());
+        Triangulation::from_sanitized_recorded(points, heights, capacity, &mut None)
+    }
+
+    /// Triangulate sanitized points, with room for `capacity` quad edges in the arena
+    pub(crate) fn from_sanitized_recorded(
+        points: Vec<Point2>,
+        heights: Option<Vec<f64>>,
+        capacity: usize,
+        recorder: &mut Recorder<'_>,
+    ) -> Triangulation {
+        let mut triangulation = Triangulation {
+            quad_arena: QuadEdgeArena::with_capacity(capacity),
+            vertex_edges: vec![None; points.len()],
+            removed: vec![false; points.len()],
+            index: points
+                .iter()
+                .enumerate()
+                .map(|(vertex, point)| (point.ordered(), vertex))
+                .collect(),
+            points,
+            heights,
+            ghosts: vec![],
+            undo_log: VecDeque::new(),
+            walk_fallbacks: AtomicUsize::new(0),
+            extent: Extent::default(),
+            samples: OnceLock::new(),
+            hierarchy: OnceLock::new(),
+            hull_edge: None,
+        };
+        if triangulation.points.len() >= 2 {
+            compute_delaunay_recorded(
+                &mut triangulation.quad_arena,
+                &triangulation.points,
+                recorder,
+            );
+        }
+        triangulation.index_vertex_edges();
+        triangulation.recount();
+        #[cfg(test)]
+        triangulation.assert_delaunay();
+        triangulation
+    }
+
+    /// Give the sites back their coordinates before a translation, see
+    /// `TriangulationBuilder::normalize`. `sites` holds the original of every site, in site
+    /// order; the edges are kept.
+    pub(crate) fn restore_sites(&mut self, sites: Vec<Point2>) {
+        for vertex in self.quad_arena.vertices_mut() {
+            if let Some(&site) = self.index.get(&vertex.ordered()) {
+                *vertex = sites[site];
+            }
+        }
+        self.index = sites
+            .iter()
+            .enumerate()
+            .map(|(vertex, point)| (point.ordered(), vertex))
+            .collect();
+        self.points = sites;
+        self.recount();
+    }
+
+    /// Record one edge leaving every site
+    fn index_vertex_edges(&mut self) {
+        let edges: Vec<EdgeRef> = self.live_edges().collect();
+        for edge in edges {
+            for edge in [edge, edge.sym()] {
+                if let Some(vertex) = self.vertex_index(&edge.org(&self.quad_arena)) {
+                    self.vertex_edges[vertex] = Some(edge);
+                }
+            }
+        }
+    }
+
+    pub fn quad_arena(&self) -> &QuadEdgeArena {
+        &self.quad_arena
+    }
+
+    /// Cumulative triangle areas of the mesh, see `sample_uniform`
+    pub(crate) fn sample_table(&self) -> &SampleTable {
+        self.samples.get_or_init(|| SampleTable::new(self))
+    }
+
+    /// The `LocatorHierarchy` of the mesh: levels of nested random samples, each keeping about
+    /// one site in `locate::HIERARCHY_RATIO` of the level below, up to a level of a few dozen
+    /// sites. Locating walks down the levels in O(log n) expected time, where the walk alone
+    /// takes O(n^(1/3)) from its sampled start. Built on first use and dropped whenever the mesh
+    /// changes.
+    pub fn build_hierarchy(&self) -> &LocatorHierarchy {
+        self.hierarchy.get_or_init(|| LocatorHierarchy::new(self))
+    }
+
+    /// Iterate the quad edges still part of the mesh, skipping the deleted ones, as the primal
+    /// edge of index 0 of every quad edge
+    pub fn live_edges(&self) -> impl Iterator<Item = EdgeRef> + '_ {
+        self.quad_arena
+            .iter()
+            .filter(|(_, quad_edge)| !quad_edge.is_deleted())
+            .map(|(quad_id, _)| self.quad_arena.edge_ref(quad_id, 0))
+    }
+
+    /// Sites of the triangulation, indexed the same way as every per-vertex output of this type.
+    /// The sites given at construction come first, sanitized and sorted, then the inserted ones.
+    pub fn points(&self) -> &[Point2] {
+        &self.points
+    }
+
+    /// Height of every site, if the triangulation was built with `with_heights`
+    pub fn heights(&self) -> Option<&[f64]> {
+        self.heights.as_deref()
+    }
+
+    /// Index of the site exactly at `point`, if any
+    pub fn vertex_index(&self, point: &Point2) -> Option<usize> {
+        self.index.get(&point.ordered()).copied()
+    }
+
+    /// Iterate the edges leaving `vertex`, in onext order (counter clockwise in the crate's
+    /// top-left origin, y going down, coordinate system)
+    pub fn onext_ring(&self, vertex: usize) -> impl Iterator<Item = EdgeRef> + '_ {
+        let start = self.vertex_edges.get(vertex).copied().flatten();
+        let mut current = start;
+        std::iter::from_fn(move || {
+            let edge = current?;
+            let next = edge.onext(&self.quad_arena);
+            current = if Some(next) == start {
+                None
+            } else {
+                Some(next)
+            };
+            Some(edge)
+        })
+    }
+
+    /// Edges leaving every site, keyed by site handle, in one pass over the arena. The edges of a
+    /// site are in arena order rather than in `onext_ring` order, removed sites have no entry.
+    pub fn vertex_edge_map(&self) -> HashMap<VertexHandle, Vec<EdgeRef>> {
+        let mut map: HashMap<VertexHandle, Vec<EdgeRef>> = HashMap::new();
+        for edge in self.live_edges().flat_map(|edge| [edge, edge.sym()]) {
+            if let Some(vertex) = self.vertex_index(&edge.org(&self.quad_arena)) {
+                map.entry(vertex).or_default().push(edge);
+            }
+        }
+        map
+    }
+
+    /// Whether the site was taken out of the mesh by `remove_vertex`
+    pub fn is_removed(&self, vertex: usize) -> bool {
+        self.removed[vertex]
+    }
+
+    /// Whether the site lies on the convex hull, that is it touches the unbounded face. Sites
+    /// without triangles around them (collinear input) are all on the hull.
+    pub fn is_hull_vertex(&self, vertex: usize) -> bool {
+        !self.removed[vertex]
+            && self
+                .onext_ring(vertex)
+                .any(|edge| self.left_triangle(edge).is_none())
+    }
+
+    /// Whether the site bounds the mesh of the real sites: it lies on the hull, or in ghost mode
+    /// it is a ghost or a neighbor of one, the ghosts making the hull. Removed sites aren't.
+    pub fn is_boundary(&self, vertex: usize) -> bool {
+        !self.removed[vertex]
+            && (self.is_hull_vertex(vertex)
+                || self.is_ghost(self.points[vertex])
+                || self
+                    .onext_ring(vertex)
+                    .any(|edge| self.is_ghost(edge.dest(&self.quad_arena))))
+    }
+
+    /// Triangle fan around every site, for renderers drawing `GL_TRIANGLE_FAN`s: the site, its
+    /// neighbors in `onext_ring` order and whether the fan is closed. Each face is the site and
+    /// two neighbors in a row, counter clockwise; a closed fan also has the face of the last and
+    /// first neighbors, which the caller draws by repeating the first one. Fans of hull sites are
+    /// open and start right after the outer face.
+    ///
+    /// Faces with a ghost corner are left out like in `triangles_iter`, and sites without faces
+    /// (collinear input, removed or ghost sites) have no fan.
+    pub fn fans(&self) -> impl Iterator<Item = (Point2, Vec<Point2>, bool)> + '_ {
+        (0..self.points.len()).filter_map(|vertex| self.fan(vertex))
+    }
+
+    fn fan(&self, vertex: usize) -> Option<(Point2, Vec<Point2>, bool)> {
+        let center = self.points[vertex];
+        if self.removed[vertex] || self.is_ghost(center) {
+            return None;
+        }
+        let mut ring: Vec<EdgeRef> = self.onext_ring(vertex).collect();
+        let mut faces: Vec<bool> = ring
+            .iter()
+            .map(|&edge| {
+                self.left_triangle(edge)
+                    .is_some_and(|corners| !corners.iter().any(|&corner| self.is_ghost(corner)))
+            })
+            .collect();
+        if !faces.contains(&true) {
+            return None;
+        }
+        // The face on the left of an edge is between it and the next edge of the ring
+        let closed = !faces.contains(&false);
+        if let Some(gap) = faces.iter().rposition(|&face| !face) {
+            ring.rotate_left(gap + 1);
+            faces.rotate_left(gap + 1);
+        }
+        let sides = if closed {
+            ring.len()
+        } else {
+            faces.iter().take_while(|&&face| face).count() + 1
+        };
+        let rim = ring[..sides]
+            .iter()
+            .map(|edge| edge.dest(&self.quad_arena))
+            .collect();
+        Some((center, rim, closed))
+    }
+
+    /// Boundary of the hole `vertex` would leave if removed, as the sites around it and the edges
+    /// going from each of them to the next one, with the hole on their left. The boolean is true
+    /// when the boundary is a closed polygon, false when it's an open chain because the site is on
+    /// the hull. None when there is no triangle around the site.
+    pub(crate) fn hole_boundary(&self, vertex: usize) -> Option<(Vec<Point2>, Vec<EdgeRef>, bool)> {
+        let mut ring: Vec<EdgeRef> = self.onext_ring(vertex).collect();
+        let faces: Vec<bool> = ring
+            .iter()
+            .map(|&edge| self.left_triangle(edge).is_some())
+            .collect();
+        if !faces.contains(&true) {
+            return None;
+        }
+        let gap = faces.iter().position(|&face| !face);
+        let closed = gap.is_none();
+        if let Some(gap) = gap {
+            // Put the edge before the outer face last, the chain goes around the other faces
+            ring.rotate_left(gap + 1);
+        }
+        let polygon: Vec<Point2> = ring
+            .iter()
+            .map(|edge| edge.dest(&self.quad_arena))
+            .collect();
+        let sides = if closed { ring.len() } else { ring.len() - 1 };
+        let boundary = ring[..sides]
+            .iter()
+            .map(|edge| edge.lnext(&self.quad_arena))
+            .collect();
+        Some((polygon, boundary, closed))
+    }
+
+    /// Take a site out of the triangulation, retriangulating the hole it leaves so the mesh stays
+    /// Delaunay. Hull sites can be removed too, the hull then shrinks. The site keeps its index
+    /// in `points()` but has no edge anymore.
+    pub fn remove_vertex(&mut self, vertex: usize) {
+        if self.removed[vertex] {
+            return;
+        }
+        self.undo_log.clear();
+        let ring: Vec<EdgeRef> = self.onext_ring(vertex).collect();
+        let neighbors: Vec<VertexHandle> = ring
+            .iter()
+            .map(|edge| self.vertex_index(&edge.dest(&self.quad_arena)).unwrap())
+            .collect();
+        let hole = self.hole_boundary(vertex);
+        self.removed[vertex] = true;
+        self.vertex_edges[vertex] = None;
+        self.forget_site(self.points[vertex]);
+
+        let Some((polygon, boundary, closed)) = hole else {
+            // Collinear sites: unlink the site and join its two neighbors, if any
+            let ends: Vec<u32> = ring
+                .iter()
+                .map(|edge| edge.dest_vertex(&self.quad_arena))
+                .collect();
+            for &edge in ring.iter() {
+                let neighbor = self.vertex_index(&edge.dest(&self.quad_arena)).unwrap();
+                let other = edge.sym().onext(&self.quad_arena);
+                self.vertex_edges[neighbor] = (other != edge.sym()).then_some(other);
+                self.delete(edge);
+            }
+            if let [first, second] = ends[..] {
+                self.link_collinear(first, second);
+            }
+            self.track_hull(&neighbors);
+            return;
+        };
+        for &edge in ring.iter() {
+            self.delete(edge);
+        }
+
+        // edge_from[i] goes from polygon[i] to the next polygon site still on the hole boundary
+        let mut edge_from: Vec<Option<EdgeRef>> = boundary.into_iter().map(Some).collect();
+        let mut ears = hole_ears(&polygon, closed);
+        if closed {
+            // The last triangle is what is left of the hole, it has all its edges already
+            ears.pop();
+        }
+        for [first, middle, _] in ears {
+            let cut = self.join(edge_from[middle].unwrap(), edge_from[first].unwrap());
+            edge_from[first] = Some(cut.sym());
+        }
+
+        for (idx, point) in polygon.iter().enumerate() {
+            let neighbor = self.vertex_index(point).unwrap();
+            self.vertex_edges[neighbor] = match edge_from.get(idx) {
+                Some(edge) => *edge,
+                // End of an open chain, reached by the edge leaving the previous chain site
+                None => Some(edge_from[idx - 1].unwrap().sym()),
+            };
+        }
+        self.track_hull(&neighbors);
+    }
+
+    /// Delete `edges` from the mesh, without filling the faces they leave. Their sites keep an
+    /// edge if they have any left, the ones left without edges count as removed.
+    pub(crate) fn delete_edges(&mut self, edges: &[EdgeRef]) {
+        self.undo_log.clear();
+        let mut sites: Vec<usize> = edges
+            .iter()
+            .flat_map(|edge| [edge.org(&self.quad_arena), edge.dest(&self.quad_arena)])
+            .filter_map(|point| self.vertex_index(&point))
+            .collect();
+        sites.sort_unstable();
+        sites.dedup();
+        let rings: Vec<Vec<EdgeRef>> = sites
+            .iter()
+            .map(|&vertex| self.onext_ring(vertex).collect())
+            .collect();
+        for &edge in edges {
+            self.delete(edge);
+        }
+        for (&vertex, ring) in sites.iter().zip(rings) {
+            let kept = ring.into_iter().find(|edge| edge.is_live(&self.quad_arena));
+            self.vertex_edges[vertex] = kept;
+            if kept.is_none() && !self.removed[vertex] {
+                self.removed[vertex] = true;
+                self.forget_site(self.points[vertex]);
+            }
+        }
+        self.track_hull(&sites);
+    }
+
+    /// Insert a batch of points, keeping the triangulation Delaunay, and return the handle of
+    /// every point in the order of `new_points`. Existing handles stay valid.
+    ///
+    /// The batch is sorted along a Hilbert curve and every point is located by walking from the
+    /// previous one, then the triangles around it are legalized with flips. A point near-equal to
+    /// a site already in the mesh isn't inserted, the handle of that site is returned instead.
+    /// Inserted sites have a NaN height when the triangulation has heights.
+    pub fn insert_points(&mut self, new_points: &[Point2]) -> Vec<VertexHandle> {
+        let mut order: Vec<usize> = (0..new_points.len()).collect();
+        if let Some(bounds) = bounding_rect(new_points) {
+            order.sort_by_cached_key(|&idx| hilbert_index(&new_points[idx], &bounds));
+        }
+
+        let mut handles = vec![0; new_points.len()];
+        let mut hint = None;
+        for (pos, &idx) in order.iter().enumerate() {
+            let Some(start) = hint.or_else(|| self.any_triangle_edge()) else {
+                // No triangle to walk in yet, triangulate again with the rest of the batch
+                let rest: Vec<Point2> = order[pos..].iter().map(|&idx| new_points[idx]).collect();
+                for (&idx, handle) in order[pos..].iter().zip(self.rebuild_with(&rest)) {
+                    handles[idx] = handle;
+                }
+                break;
+            };
+            let vertex = self.insert_from(new_points[idx], start);
+            handles[idx] = vertex;
+            hint = self
+                .onext_ring(vertex)
+                .find(|&edge| self.left_triangle(edge).is_some());
+        }
+        handles
+    }
+
+    /// An edge with a triangle on its left, if the mesh has any triangle
+    pub(crate) fn any_triangle_edge(&self) -> Option<EdgeRef> {
+        self.live_edges()
+            .flat_map(|edge| [edge, edge.sym()])
+            .find(|&edge| self.left_triangle(edge).is_some())
+    }
+
+    /// Walk from the triangle on the left of `start` to the triangle holding `point`, crossing at
+    /// every step an edge `point` is strictly on the right of
+    pub(crate) fn walk(&self, point: &Point2, start: EdgeRef) -> Location {
+        self.walk_traced(point, start, None)
+    }
+
+    /// `walk`, pushing every crossed edge to `path`, as seen from the triangle it leads to. The
+    /// path stops growing once it is long, see `is_long_walk`, and the walk goes on untraced.
+    ///
+    /// A walk in a mesh that isn't Delaunay, or that is corrupt, can go around in circles. After
+    /// as many steps as the arena has quad edges it gives up and scans the faces instead, see
+    /// `walk_fallbacks`.
+    ///
+    /// With the `log` feature, a long path and a fallback are both logged as warnings.
+    pub(crate) fn walk_traced(
+        &self,
+        point: &Point2,
+        start: EdgeRef,
+        mut path: Option<&mut Vec<EdgeRef>>,
+    ) -> Location {
+        let arena = &self.quad_arena;
+        let long_walk = path.as_ref().map_or(0, |_| self.long_walk_length());
+        let mut edge = start;
+        let mut steps = 0;
+        'walk: loop {
+            let second = edge.lnext(arena);
+            let sides = [edge, second, second.lnext(arena)];
+            for side in sides {
+                let org = side.org(arena);
+                if nearly_equals(&org, point) {
+                    return Location::Vertex(self.vertex_index(&org).unwrap());
+                }
+            }
+            for side in sides {
+                if right_of(arena, point, side) {
+                    if self.left_triangle(side.sym()).is_none() {
+                        return Location::Outside(side.sym());
+                    }
+                    edge = side.sym();
+                    if let Some(path) = path.as_deref_mut().filter(|path| path.len() < long_walk) {
+                        path.push(edge);
+                        #[cfg(feature = "log")]
+                        if path.len() == long_walk {
+                            log::warn!(
+                                "Walk towards {:?} crossed {} edges, more than expected for {} sites",
+                                point,
+                                long_walk,
+                                self.points.len()
+                            );
+                        }
+                    }
+                    steps += 1;
+                    if steps > arena.len() {
+                        #[cfg(feature = "log")]
+                        log::warn!(
+                            "Walk towards {:?} didn't end after {} steps, scanning the faces",
+                            point,
+                            arena.len()
+                        );
+                        self.walk_fallbacks.fetch_add(1, Ordering::Relaxed);
+                        return self.scan_faces(point);
+                    }
+                    continue 'walk;
+                }
+            }
+            return match sides.into_iter().find(|&side| !left_of(arena, point, side)) {
+                Some(side) => Location::OnEdge(side),
+                None => Location::Inside(edge),
+            };
+        }
+    }
+
+    /// Locate `point` by trying every face, in linear time. The fallback of walks that don't end.
+    fn scan_faces(&self, point: &Point2) -> Location {
+        let arena = &self.quad_arena;
+        let edges = || self.live_edges().flat_map(|edge| [edge, edge.sym()]);
+        if let Some(org) = edges()
+            .map(|edge| edge.org(arena))
+            .find(|org| nearly_equals(org, point))
+        {
+            return Location::Vertex(self.vertex_index(&org).unwrap());
+        }
+        let mut hull_edge = None;
+        for edge in edges() {
+            match self.left_triangle(edge) {
+                Some([a, b, c]) => match point_in_triangle(*point, a, b, c) {
+                    Region::Inside => return Location::Inside(edge),
+                    Region::OnBoundary => {
+                        let second = edge.lnext(arena);
+                        let side = [edge, second, second.lnext(arena)]
+                            .into_iter()
+                            .find(|&side| !left_of(arena, point, side))
+                            .unwrap();
+                        return Location::OnEdge(side);
+                    }
+                    Region::Outside => {}
+                },
+                // Prefer a hull edge with `point` on its left, any other does in a broken mesh
+                None if self.left_triangle(edge.sym()).is_some()
+                    && hull_edge.is_none_or(|hull_edge| !left_of(arena, point, hull_edge)) =>
+                {
+                    hull_edge = Some(edge)
+                }
+                None => {}
+            }
+        }
+        Location::Outside(hull_edge.expect("The triangulation has no triangle"))
+    }
+
+    /// Number of walks, as `locate` and the insertions do, that went on for more steps than the
+    /// mesh has edges and fell back to a linear scan of the faces. It stays at 0 unless the mesh
+    /// is corrupt or lost the Delaunay property, which makes it a warning worth watching.
+    /// With the `log` feature, every fallback is also logged as a warning.
+    pub fn walk_fallbacks(&self) -> usize {
+        self.walk_fallbacks.load(Ordering::Relaxed)
+    }
+
+    /// Edge with the triangle nearest to `point` on its left. Inside the hull it's a triangle
+    /// holding `point`. Outside, it's the hull edge nearest to `point`; when that nearest feature
+    /// is a hull vertex rather than the inside of an edge, the returned edge leaves that vertex.
+    ///
+    /// Panics if the triangulation has no triangle.
+    pub fn nearest_face(&self, point: Point2) -> EdgeRef {
+        let start = self
+            .any_triangle_edge()
+            .expect("The triangulation has no triangle");
+        let arena = &self.quad_arena;
+        let hull_edge = match self.walk(&point, start) {
+            Location::Vertex(vertex) => {
+                return self
+                    .onext_ring(vertex)
+                    .find(|&edge| self.left_triangle(edge).is_some())
+                    .unwrap()
+            }
+            Location::Inside(edge) | Location::OnEdge(edge) => return edge,
+            Location::Outside(edge) => edge,
+        };
+
+        // Nearest point of every hull edge, the flag is true when it's the edge origin. Beyond a
+        // hull vertex both edges around it are as near, the one ending there wins the tie.
+        let nearest = |edge: EdgeRef| {
+            let (org, dest) = edge.org_dest(arena);
+            let (dx, dy) = (dest.x - org.x, dest.y - org.y);
+            let t = ((point.x - org.x) * dx + (point.y - org.y) * dy) / (dx * dx + dy * dy);
+            let at = if t <= 0. {
+                org
+            } else if t >= 1. {
+                dest
+            } else {
+                Point2 {
+                    x: org.x + t * dx,
+                    y: org.y + t * dy,
+                }
+            };
+            let distance = (point.x - at.x).powi(2) + (point.y - at.y).powi(2);
+            (distance, t <= 0.)
+        };
+        let mut best = (nearest(hull_edge), hull_edge);
+        let mut edge = hull_edge.lnext(arena);
+        while edge != hull_edge {
+            let candidate = (nearest(edge), edge);
+            if candidate.0 .0 < best.0 .0 || (candidate.0 .0 == best.0 .0 && !candidate.0 .1) {
+                best = candidate;
+            }
+            edge = edge.lnext(arena);
+        }
+        best.1.sym()
+    }
+
+    /// Extend a chain of hull edges, with the outer face on their left, to every consecutive hull
+    /// edge `point` is strictly on the left of
+    fn visible_chain(&self, point: &Point2, mut chain: Vec<EdgeRef>) -> Vec<EdgeRef> {
+        let arena = &self.quad_arena;
+        loop {
+            let before = chain[0].onext(arena).sym();
+            if before == *chain.last().unwrap() || !left_of(arena, point, before) {
+                break;
+            }
+            chain.insert(0, before);
+        }
+        loop {
+            let after = chain.last().unwrap().lnext(arena);
+            if after == chain[0] || !left_of(arena, point, after) {
+                break;
+            }
+            chain.push(after);
+        }
+        chain
+    }
+
+    /// Delete an edge, keeping the per-site edges valid
+    fn unlink(&mut self, edge: EdgeRef) {
+        for end in [edge, edge.sym()] {
+            let vertex = self.vertex_index(&end.org(&self.quad_arena)).unwrap();
+            let other = end.onext(&self.quad_arena);
+            self.vertex_edges[vertex] = (other != end).then_some(other);
+        }
+        self.delete(edge);
+    }
+
+    /// Give `point` a site index, bringing a removed site at the same place back
+    fn add_site(&mut self, point: Point2) -> VertexHandle {
+        if let Some(vertex) = self.vertex_index(&point) {
+            if self.removed[vertex] {
+                self.removed[vertex] = false;
+                self.count_site(point);
+            }
+            return vertex;
+        }
+        let vertex = self.points.len();
+        self.points.push(point);
+        self.index.insert(point.ordered(), vertex);
+        self.vertex_edges.push(None);
+        self.removed.push(false);
+        if let Some(heights) = &mut self.heights {
+            heights.push(f64::NAN);
+        }
+        self.count_site(point);
+        vertex
+    }
+
+    /// Insert one point, walking from the triangle on the left of `start` to find it
+    fn insert_from(&mut self, point: Point2, start: EdgeRef) -> VertexHandle {
+        if let Some(vertex) = self.vertex_index(&point) {
+            if !self.removed[vertex] {
+                return vertex;
+            }
+        }
+        let arena = &self.quad_arena;
+        let mut split = None;
+        // Edges around the hole the new site is connected to, closed polygon or open chain
+        let (polygon, closed) = match self.walk(&point, start) {
+            Location::Vertex(vertex) => return vertex,
+            Location::Inside(edge) => {
+                let second = edge.lnext(arena);
+                (vec![edge, second, second.lnext(arena)], true)
+            }
+            Location::OnEdge(edge) if self.left_triangle(edge.sym()).is_some() => {
+                let quad = [edge, edge.sym()].map(|side| {
+                    let second = side.lnext(arena);
+                    [second, second.lnext(arena)]
+                });
+                split = Some(self.split_corners(edge));
+                self.unlink(edge);
+                (quad.concat(), true)
+            }
+            Location::OnEdge(edge) => {
+                let second = edge.lnext(arena);
+                let chain = vec![second, second.lnext(arena)];
+                split = Some(self.split_corners(edge));
+                self.unlink(edge);
+                (self.visible_chain(&point, chain), false)
+            }
+            Location::Outside(edge) => {
+                let chain = self.visible_chain(&point, vec![edge]);
+                let near = chain
+                    .iter()
+                    .flat_map(|side| [side.org(arena), side.dest(arena)])
+                    .find(|corner| nearly_equals(corner, &point));
+                if let Some(corner) = near {
+                    return self.vertex_index(&corner).unwrap();
+                }
+                (chain, false)
+            }
+        };
+        let appended = self.vertex_index(&point).is_none();
+        let vertex = self.add_site(point);
+
+        // The first spoke enters the face on the left of the first side
+        let sides = [polygon[0], polygon[0].sym()];
+        let before = self.tally(&[], &sides);
+        let mut base = make_edge(&mut self.quad_arena);
+        let first = polygon[0].org_vertex(&self.quad_arena);
+        base.set_org_vertex(&mut self.quad_arena, first);
+        base.set_dest(&mut self.quad_arena, point);
+        splice(&mut self.quad_arena, base, polygon[0]);
+        let after = self.tally(&[base], &sides);
+        self.retally(before, after);
+        // The last side of a closed polygon gets its triangle from the first and last spokes
+        let sides = if closed {
+            polygon.len() - 1
+        } else {
+            polygon.len()
+        };
+        for &side in &polygon[..sides] {
+            base = self.join(side, base.sym());
+        }
+        self.vertex_edges[vertex] = Some(base.sym());
+        let flips = self.legalize(polygon);
+        self.track_hull(&[vertex]);
+        self.log_insertion(Insertion::Local {
+            vertex,
+            appended,
+            flips,
+            split,
+        });
+        vertex
+    }
+
+    /// Sites of `edge` and the third corner of the triangle on its left
+    fn split_corners(&self, edge: EdgeRef) -> [VertexHandle; 3] {
+        let arena = &self.quad_arena;
+        [
+            edge.org(arena),
+            edge.dest(arena),
+            edge.lnext(arena).dest(arena),
+        ]
+        .map(|point| self.vertex_index(&point).unwrap())
+    }
+
+    fn log_insertion(&mut self, insertion: Insertion) {
+        if self.undo_log.len() == UNDO_DEPTH {
+            self.undo_log.pop_front();
+        }
+        self.undo_log.push_back(insertion);
+    }
+
+    /// Take back the latest insertion of `insert_delaunay` or `insert_points` and return the
+    /// handle of its site, None when there is nothing to undo. Inserting a point near-equal to a
+    /// site inserts nothing, so there is nothing to take back for it.
+    ///
+    /// The flips made by the insertion are flipped back and the edges of the site are deleted,
+    /// restoring the edges around it exactly; the last site of `points()` is dropped, other ones
+    /// count as removed. Insertions that triangulated the mesh again, when there was no triangle
+    /// to walk in, are taken back by triangulating it again without the site. Only the latest
+    /// insertions are kept, and any other change of the mesh forgets them all.
+    pub fn undo_last_insert(&mut self) -> Option<VertexHandle> {
+        let insertion = self.undo_log.pop_back()?;
+        let (vertex, appended) = match &insertion {
+            Insertion::Local {
+                vertex,
+                appended,
+                flips,
+                split,
+            } => {
+                let (vertex, appended, split) = (*vertex, *appended, *split);
+                for &edge in flips.iter().rev() {
+                    self.flip(edge);
+                }
+                let spokes: Vec<EdgeRef> = self.onext_ring(vertex).collect();
+                let neighbors: Vec<VertexHandle> = spokes
+                    .iter()
+                    .map(|spoke| self.vertex_index(&spoke.dest(&self.quad_arena)).unwrap())
+                    .collect();
+                for spoke in spokes {
+                    self.unlink(spoke);
+                }
+                if let Some([org, dest, corner]) = split {
+                    let edge_between = |from: VertexHandle, to: VertexHandle| {
+                        self.onext_ring(from)
+                            .find(|edge| edge.dest(&self.quad_arena) == self.points[to])
+                            .unwrap()
+                    };
+                    let (third, second) = (edge_between(corner, org), edge_between(dest, corner));
+                    self.join(third, second);
+                }
+                self.track_hull(&neighbors);
+                (vertex, appended)
+            }
+            Insertion::Rebuilt { vertex, appended } => (*vertex, *appended),
+        };
+        self.vertex_edges[vertex] = None;
+        if appended && vertex + 1 == self.points.len() {
+            let point = self.points.pop().unwrap();
+            self.index.remove(&point.ordered());
+            self.vertex_edges.pop();
+            self.removed.pop();
+            if let Some(heights) = &mut self.heights {
+                heights.pop();
+            }
+            self.forget_site(point);
+        } else {
+            self.removed[vertex] = true;
+            self.forget_site(self.points[vertex]);
+        }
+        if let Insertion::Rebuilt { .. } = insertion {
+            self.rebuild_with(&[]);
+        }
+        Some(vertex)
+    }
+
+    /// Lawson flips from the edges of `stack`, until every edge reached is locally Delaunay.
+    /// Returns the flipped edges, in order.
+    fn legalize(&mut self, mut stack: Vec<EdgeRef>) -> Vec<EdgeRef> {
+        let mut flips = vec![];
+        while let Some(edge) = stack.pop() {
+            if !self.is_edge_delaunay(edge) {
+                let around = [edge, edge.sym()].map(|side| {
+                    let second = side.lnext(&self.quad_arena);
+                    [second, second.lnext(&self.quad_arena)]
+                });
+                self.flip(edge);
+                flips.push(edge);
+                stack.extend(around.concat());
+            }
+        }
+        flips
+    }
+
+    /// Return true if `edge` needs no flip: the far corner of the triangle on either side is
+    /// outside of the circle through the other triangle. Edges without a triangle on both sides
+    /// pass, so do edges between two real sites that a flip would replace with a ghost edge.
+    pub fn is_edge_delaunay(&self, edge: EdgeRef) -> bool {
+        let (Some([a, b, c]), Some([_, _, d])) =
+            (self.left_triangle(edge), self.left_triangle(edge.sym()))
+        else {
+            return true;
+        };
+        // Ghost edges never replace an edge between two real sites
+        let ghost_flip = !self.ghosts.is_empty()
+            && !self.is_ghost(a)
+            && !self.is_ghost(b)
+            && (self.is_ghost(c) || self.is_ghost(d));
+        ghost_flip || !in_circle(&a, &b, &c, &d)
+    }
+
+    /// First live edge failing `is_edge_delaunay`, in arena order
+    pub fn non_delaunay_edge(&self) -> Option<EdgeRef> {
+        self.live_edges().find(|&edge| !self.is_edge_delaunay(edge))
+    }
+
+    /// Return true if every edge of the mesh passes `is_edge_delaunay`, in one pass over the edges
+    pub fn is_delaunay(&self) -> bool {
+        self.non_delaunay_edge().is_none()
+    }
+
+    /// Panic with the first edge failing `is_edge_delaunay`, if any
+    pub fn assert_delaunay(&self) {
+        if let Some(edge) = self.non_delaunay_edge() {
+            let (org, dest) = edge.org_dest(&self.quad_arena);
+            panic!("Edge {} from {} to {} isn't Delaunay", edge, org, dest);
+        }
+    }
+
+    /// Insert one point and return its handle, keeping the triangulation Delaunay.
+    ///
+    /// The face holding the point is located from any triangle and split, connecting the point to
+    /// its corners, then only the edges around the new site are legalized with flips. A point
+    /// outside of the hull is connected to every hull edge facing it, the edges it is strictly on
+    /// the left of, and the hull grows. Near-equal points and sites without a triangle yet are
+    /// handled like `insert_points` does.
+    pub fn insert_delaunay(&mut self, point: Point2) -> VertexHandle {
+        self.insert_one(point)
+    }
+
+    /// Insert one point, rebuilding when the mesh has no triangle to walk in
+    fn insert_one(&mut self, point: Point2) -> VertexHandle {
+        match self.any_triangle_edge() {
+            Some(start) => self.insert_from(point, start),
+            None => self.rebuild_with(&[point])[0],
+        }
+    }
+
+    /// Move a site, keeping the triangulation Delaunay and every handle valid.
+    ///
+    /// When the site stays inside the polygon of its neighbors, only its coordinates change and
+    /// the edges around it are legalized with flips. Other moves take the site out and insert it
+    /// back at its new position. Fails, leaving the mesh as is, if the site was removed or if the
+    /// new position is near-equal to another site.
+    pub fn move_vertex(&mut self, vertex: VertexHandle, new_pos: Point2) -> Result<(), MoveError> {
+        if self.removed[vertex] {
+            return Err(MoveError::Removed(vertex));
+        }
+        let collision = match self.vertex_index(&new_pos) {
+            Some(other) if !self.removed[other] => Some(other),
+            _ => self
+                .any_triangle_edge()
+                .and_then(|start| match self.walk(&new_pos, start) {
+                    Location::Vertex(other) => Some(other),
+                    _ => None,
+                })
+                .or_else(|| {
+                    (0..self.points.len()).find(|&other| {
+                        !self.removed[other] && nearly_equals(&self.points[other], &new_pos)
+                    })
+                }),
+        };
+        if let Some(other) = collision.filter(|&other| other != vertex) {
+            return Err(MoveError::Collision(other));
+        }
+
+        let ring: Vec<EdgeRef> = self.onext_ring(vertex).collect();
+        let links: Vec<EdgeRef> = ring
+            .iter()
+            .map(|edge| edge.lnext(&self.quad_arena))
+            .collect();
+        let stays_inside = !self.is_hull_vertex(vertex)
+            && links
+                .iter()
+                .all(|&link| left_of(&self.quad_arena, &new_pos, link));
+
+        let old_pos = self.points[vertex];
+        if !stays_inside {
+            self.remove_vertex(vertex);
+        }
+        self.index.remove(&old_pos.ordered());
+        self.index.insert(new_pos.ordered(), vertex);
+        self.points[vertex] = new_pos;
+        if stays_inside {
+            self.forget_site(old_pos);
+            self.count_site(new_pos);
+            // The edges of the site share its entry in the vertex table
+            if let Some(edge) = ring.first() {
+                let entry = edge.org_vertex(&self.quad_arena) as usize;
+                self.quad_arena.vertices_mut()[entry] = new_pos;
+            }
+            self.legalize([ring, links].concat());
+        } else {
+            self.insert_one(new_pos);
+        }
+        self.undo_log.clear();
+        Ok(())
+    }
+
+    /// Triangulate again from scratch with the sites left and `new_points`, for meshes without
+    /// triangle to walk in. Returns the handle of every new point.
+    fn rebuild_with(&mut self, new_points: &[Point2]) -> Vec<VertexHandle> {
+        // Existing sites are Ok, new points are Err with their position in the batch
+        let mut sites: Vec<(Point2, Result<VertexHandle, usize>)> = (0..self.points.len())
+            .filter(|&vertex| !self.removed[vertex])
+            .map(|vertex| (self.points[vertex], Ok(vertex)))
+            .chain(
+                new_points
+                    .iter()
+                    .copied()
+                    .enumerate()
+                    .map(|(idx, point)| (point, Err(idx))),
+            )
+            .collect();
+        sites.sort_by(|a, b| cmp_points(&a.0, &b.0).then(a.1.is_err().cmp(&b.1.is_err())));
+
+        let mut handles = vec![0; new_points.len()];
+        let mut kept: Vec<(Point2, VertexHandle)> = vec![];
+        let mut added = vec![];
+        for (point, site) in sites {
+            let handle = match (site, kept.last()) {
+                (Err(_), Some(&(last, handle))) if nearly_equals(&last, &point) => handle,
+                _ => {
+                    let handle = site.unwrap_or_else(|_| {
+                        let appended = self.vertex_index(&point).is_none();
+                        let vertex = self.add_site(point);
+                        added.push((vertex, appended));
+                        vertex
+                    });
+                    kept.push((point, handle));
+                    handle
+                }
+            };
+            if let Err(idx) = site {
+                handles[idx] = handle;
+            }
+        }
+
+        let points: Vec<Point2> = kept.iter().map(|(point, _)| *point).collect();
+        self.quad_arena = QuadEdgeArena::with_capacity(quad_capacity(self.points.len()));
+        self.vertex_edges = vec![None; self.points.len()];
+        if points.len() >= 2 {
+            compute_delaunay(&mut self.quad_arena, &points);
+        }
+        self.index_vertex_edges();
+        self.recount();
+        // Appended sites are taken back last first
+        added.sort_unstable();
+        for (vertex, appended) in added {
+            self.log_insertion(Insertion::Rebuilt { vertex, appended });
+        }
+        handles
+    }
+
+    /// Swap the diagonal of the quadrilateral formed by the two triangles around `edge`, keeping
+    /// the per-site edges valid. The quadrilateral must be strictly convex.
+    pub(crate) fn flip(&mut self, edge: EdgeRef) {
+        let org = self.vertex_index(&edge.org(&self.quad_arena)).unwrap();
+        let dest = self.vertex_index(&edge.dest(&self.quad_arena)).unwrap();
+        self.vertex_edges[org] = Some(edge.oprev(&self.quad_arena));
+        self.vertex_edges[dest] = Some(edge.sym().oprev(&self.quad_arena));
+        let before = self.tally(&[edge], &[edge, edge.sym()]);
+        let mut edge = edge;
+        swap(&mut self.quad_arena, &mut edge);
+        let after = self.tally(&[edge], &[edge, edge.sym()]);
+        self.retally(before, after);
+    }
+
+    /// `connect` keeping the counts of `edge_count` and `triangle_count`
+    fn join(&mut self, a: EdgeRef, b: EdgeRef) -> EdgeRef {
+        let before = self.tally(&[], &[a, b]);
+        let edge = connect(&mut self.quad_arena, a, b);
+        let after = self.tally(&[edge], &[edge, edge.sym()]);
+        self.retally(before, after);
+        edge
+    }
+
+    /// `delete_edge` keeping the counts of `edge_count` and `triangle_count`
+    fn delete(&mut self, edge: EdgeRef) {
+        let before = self.tally(&[edge], &[edge, edge.sym()]);
+        // What is left of the faces on both sides
+        let rest: Vec<EdgeRef> = [
+            edge.lnext(&self.quad_arena),
+            edge.sym().lnext(&self.quad_arena),
+        ]
+        .into_iter()
+        .filter(|side| side.canonical_id() != edge.canonical_id())
+        .collect();
+        delete_edge(&mut self.quad_arena, edge);
+        let after = self.tally(&[], &rest);
+        self.retally(before, after);
+    }
+
+    /// Add an edge between two sites of a collinear mesh, which have at most one edge each. The
+    /// sites are given by their entries in the vertex table, which the new edge shares.
+    fn link_collinear(&mut self, org: u32, dest: u32) {
+        let ends = [org, dest].map(|entry| self.quad_arena.vertices()[entry as usize]);
+        // The edge enters the faces on the left of the existing edges
+        let sides: Vec<EdgeRef> = ends
+            .iter()
+            .filter_map(|point| self.vertex_edges[self.vertex_index(point).unwrap()])
+            .flat_map(|existing| [existing, existing.sym()])
+            .collect();
+        let before = self.tally(&[], &sides);
+        let edge = make_edge(&mut self.quad_arena);
+        edge.set_org_vertex(&mut self.quad_arena, org);
+        edge.set_dest_vertex(&mut self.quad_arena, dest);
+        for (point, end) in ends.into_iter().zip([edge, edge.sym()]) {
+            let vertex = self.vertex_index(&point).unwrap();
+            if let Some(existing) = self.vertex_edges[vertex] {
+                splice(&mut self.quad_arena, end, existing);
+            }
+            self.vertex_edges[vertex] = Some(end);
+        }
+        let after = self.tally(&[edge], &[sides, vec![edge, edge.sym()]].concat());
+        self.retally(before, after);
+    }
+
+    /// Edges of `edges` counted by `edge_count`, and faces on the left of `faces` counted by
+    /// `triangle_count`, each face once
+    fn tally(&self, edges: &[EdgeRef], faces: &[EdgeRef]) -> (usize, usize) {
+        let real_edges = edges
+            .iter()
+            .filter(|edge| {
+                let (org, dest) = edge.org_dest(&self.quad_arena);
+                !self.is_ghost(org) && !self.is_ghost(dest)
+            })
+            .count();
+        let mut real_faces: Vec<EdgeRef> = faces
+            .iter()
+            .filter(|&&edge| {
+                self.left_triangle(edge)
+                    .is_some_and(|corners| !corners.iter().any(|&corner| self.is_ghost(corner)))
+            })
+            .map(|&edge| {
+                // A face is known by its smallest edge
+                let second = edge.lnext(&self.quad_arena);
+                edge.min(second).min(second.lnext(&self.quad_arena))
+            })
+            .collect();
+        real_faces.sort_unstable();
+        real_faces.dedup();
+        (real_edges, real_faces.len())
+    }
+
+    /// Update the counts of a change of the mesh, from the `tally` of what it touched before and
+    /// after
+    fn retally(&mut self, before: (usize, usize), after: (usize, usize)) {
+        self.samples = OnceLock::new();
+        self.hierarchy = OnceLock::new();
+        self.extent.edges = self.extent.edges + after.0 - before.0;
+        self.extent.triangles = self.extent.triangles + after.1 - before.1;
+    }
+
+    /// Count the sites, edges and triangles again, for a mesh built from scratch
+    fn recount(&mut self) {
+        self.samples = OnceLock::new();
+        self.hierarchy = OnceLock::new();
+        self.hull_edge = None;
+        self.track_hull(&[]);
+        let sites = self.real_sites();
+        let origin = sites.first().copied().unwrap_or_default();
+        self.extent = Extent {
+            sites: sites.len(),
+            edges: self.edges_iter().count(),
+            triangles: self.triangles_iter().count(),
+            bounds: bounding_rect(&sites),
+            origin,
+            sum: sites.iter().fold((0., 0.), |(x, y), site| {
+                (x + (site.x - origin.x), y + (site.y - origin.y))
+            }),
+        };
+    }
+
+    /// Sites of `site_count`
+    fn real_sites(&self) -> Vec<Point2> {
+        (0..self.points.len())
+            .filter(|&vertex| !self.removed[vertex] && !self.ghosts.contains(&vertex))
+            .map(|vertex| self.points[vertex])
+            .collect()
+    }
+
+    /// Add a site that joined the mesh to `site_count`, `bounds` and `centroid`
+    fn count_site(&mut self, point: Point2) {
+        self.samples = OnceLock::new();
+        self.hierarchy = OnceLock::new();
+        if self.is_ghost(point) {
+            return;
+        }
+        let extent = &mut self.extent;
+        if extent.sites == 0 {
+            extent.origin = point;
+            extent.sum = (0., 0.);
+        }
+        extent.sites += 1;
+        extent.sum.0 += point.x - extent.origin.x;
+        extent.sum.1 += point.y - extent.origin.y;
+        extent.bounds = match extent.bounds {
+            Some(bounds) => bounding_rect(&[bounds.min, bounds.max, point]),
+            None => bounding_rect(&[point]),
+        };
+    }
+
+    /// Take a site that left the mesh out of `site_count`, `bounds` and `centroid`. The site
+    /// must be removed already, the bounds are measured again when it was on them.
+    fn forget_site(&mut self, point: Point2) {
+        self.samples = OnceLock::new();
+        self.hierarchy = OnceLock::new();
+        if self.is_ghost(point) {
+            return;
+        }
+        let extent = &mut self.extent;
+        extent.sites -= 1;
+        extent.sum.0 -= point.x - extent.origin.x;
+        extent.sum.1 -= point.y - extent.origin.y;
+        let on_bounds = extent.bounds.is_some_and(|bounds| {
+            [bounds.min.x, bounds.max.x].contains(&point.x)
+                || [bounds.min.y, bounds.max.y].contains(&point.y)
+        });
+        if on_bounds {
+            self.extent.bounds = bounding_rect(&self.real_sites());
+        }
+    }
+
+    /// Return the corners of the face on the left of `edge`, starting at its origin, if that face
+    /// is a bounded triangle (the unbounded outer face is never returned)
+    pub fn left_triangle(&self, edge: EdgeRef) -> Option<[Point2; 3]> {
+        let second = edge.lnext(&self.quad_arena);
+        let third = second.lnext(&self.quad_arena);
+        if third.lnext(&self.quad_arena) != edge {
+            return None;
+        }
+        let corners = [
+            edge.org(&self.quad_arena),
+            second.org(&self.quad_arena),
+            third.org(&self.quad_arena),
+        ];
+        if !counter_clockwise(&corners[0], &corners[1], &corners[2]) {
+            return None;
+        }
+        Some(corners)
+    }
+
+    /// Call `f` with the corners of every bounded face, counter clockwise as `counter_clockwise`
+    /// defines it, in one walk over the arena and without allocating. Each face is reported once,
+    /// from its smallest edge.
+    pub fn for_each_face<F: FnMut([Point2; 3])>(&self, f: F) {
+        self.triangles_iter().for_each(f);
+    }
+
+    /// Lazily walk the edges of the mesh as (origin, destination) pairs, in the order of
+    /// `live_edges`, leaving out the edges of ghost sites
+    pub fn edges_iter(&self) -> impl FusedIterator<Item = (Point2, Point2)> + '_ {
+        self.live_edges()
+            .map(|edge| edge.org_dest(&self.quad_arena))
+            .filter(|&(org, dest)| !self.is_ghost(org) && !self.is_ghost(dest))
+            .fuse()
+    }
+
+    /// Lazily walk the bounded faces without a ghost corner, in the `for_each_face` order. A face
+    /// is only given by its smallest edge, so no set of visited faces is kept.
+    pub fn triangles_iter(&self) -> impl FusedIterator<Item = [Point2; 3]> + '_ {
+        self.live_edges()
+            .flat_map(|edge| [edge, edge.sym()])
+            .filter_map(|edge| {
+                let corners = self.left_triangle(edge)?;
+                let second = edge.lnext(&self.quad_arena);
+                (edge < second
+                    && edge < second.lnext(&self.quad_arena)
+                    && !corners.iter().any(|&corner| self.is_ghost(corner)))
+                .then_some(corners)
+            })
+            .fuse()
+    }
+
+    /// Lazily walk the directed edges of the convex hull of the real sites, in arena order, each
+    /// with the outside of the hull on its left. When no triangle has only real corners, as with
+    /// collinear sites, every edge between real sites is given in both directions.
+    pub fn hull_edges(&self) -> impl FusedIterator<Item = EdgeRef> + '_ {
+        self.live_edges()
+            .flat_map(|edge| [edge, edge.sym()])
+            .filter(|&edge| self.is_hull_edge(edge))
+            .fuse()
+    }
+
+    /// Return true if `edge` is one of `hull_edges`
+    fn is_hull_edge(&self, edge: EdgeRef) -> bool {
+        if !edge.is_live(&self.quad_arena) {
+            return false;
+        }
+        let (org, dest) = edge.org_dest(&self.quad_arena);
+        let real_face = self
+            .left_triangle(edge)
+            .is_some_and(|corners| !corners.iter().any(|&corner| self.is_ghost(corner)));
+        !real_face && !self.is_ghost(org) && !self.is_ghost(dest)
+    }
+
+    /// Corners of the convex hull of the sites as the mesh is now, counter clockwise as
+    /// `counter_clockwise` defines it from the smallest one in `cmp_points` order. Sites in the
+    /// middle of a hull side are corners too, like for `convex_hull_only_with` keeping them.
+    /// Without any triangle the sites are collinear, they are given in `cmp_points` order.
+    ///
+    /// The hull is walked from an edge kept on it by every change of the mesh, in time linear in
+    /// its size. Once `carve_holes` removed faces it walks the boundary that edge is on.
+    pub fn hull(&self) -> Vec<Point2> {
+        let start = match self.hull_edge {
+            Some(edge) if self.extent.triangles > 0 => {
+                debug_assert!(self.is_hull_edge(edge), "The hull edge {} is stale", edge);
+                edge
+            }
+            _ => {
+                let mut sites = self.real_sites();
+                sites.sort_by(cmp_points);
+                return sites;
+            }
+        };
+        let mut corners = vec![];
+        let mut edge = start;
+        loop {
+            corners.push(edge.org(&self.quad_arena));
+            // The next hull edge is the first one clockwise from the way back, past the edges
+            // of ghost sites
+            let back = edge.sym();
+            let mut next = back.oprev(&self.quad_arena);
+            while !self.is_hull_edge(next) && next != back {
+                next = next.oprev(&self.quad_arena);
+            }
+            edge = next;
+            if edge == start || corners.len() > self.extent.sites {
+                break;
+            }
+        }
+        // Hull edges have the outside on their left, so they turn clockwise
+        corners.reverse();
+        let first = (0..corners.len())
+            .min_by(|&a, &b| cmp_points(&corners[a], &corners[b]))
+            .unwrap_or(0);
+        corners.rotate_left(first);
+        corners
+    }
+
+    /// Keep `hull_edge` on the hull after a change of the mesh: it stays while it's a hull edge,
+    /// else a hull edge leaving one of the `around` sites replaces it, else the first one of
+    /// `hull_edges`
+    fn track_hull(&mut self, around: &[VertexHandle]) {
+        if self.hull_edge.is_some_and(|edge| self.is_hull_edge(edge)) {
+            return;
+        }
+        let found = around
+            .iter()
+            .flat_map(|&vertex| self.onext_ring(vertex))
+            .find(|&edge| self.is_hull_edge(edge));
+        self.hull_edge = found.or_else(|| self.hull_edges().next());
+    }
+
+    /// Edges of the mesh, see `edges_iter`
+    pub fn edges(&self) -> Vec<(Point2, Point2)> {
+        self.edges_iter().collect()
+    }
+
+    /// Bounded faces of the mesh, see `triangles_iter`
+    pub fn triangles(&self) -> Vec<[Point2; 3]> {
+        self.triangles_iter().collect()
+    }
+
+    /// Number of sites, edges and faces of the mesh, ghosts left out, without building any of
+    /// them. The faces are the triangles of `triangles_iter` and the unbounded face, so
+    /// `vertices - edges + faces == 2` for a mesh without holes. Every directed edge is either in
+    /// a triangle or on the hull, which gives the triangles from the edge and hull edge counts.
+    ///
+    /// Debug builds check Euler's relation on the whole arena first, and panic if the quad edges
+    /// don't form a planar subdivision.
+    pub fn counts(&self) -> (usize, usize, usize) {
+        debug_assert!(self.is_planar(), "The quad edges break Euler's relation");
+        let vertices = (0..self.points.len())
+            .filter(|&vertex| !self.removed[vertex] && !self.ghosts.contains(&vertex))
+            .count();
+        let edges = self.edges_iter().count();
+        let hull = self.hull_edges().count();
+        debug_assert_eq!((2 * edges - hull) % 3, 0, "A face isn't a triangle");
+        debug_assert_eq!((self.site_count(), self.edge_count()), (vertices, edges));
+        (vertices, edges, (2 * edges - hull) / 3 + 1)
+    }
+
+    /// Number of sites in the mesh, removed and ghost sites left out, in constant time. Like the
+    /// other counts, `bounds` and `centroid`, it is counted once at build time and kept up to
+    /// date by the insertions, removals and moves.
+    pub fn site_count(&self) -> usize {
+        self.extent.sites
+    }
+
+    /// Number of edges `edges_iter` gives, in constant time
+    pub fn edge_count(&self) -> usize {
+        self.extent.edges
+    }
+
+    /// Number of triangles `triangles_iter` gives, in constant time
+    pub fn triangle_count(&self) -> usize {
+        self.extent.triangles
+    }
+
+    /// Smallest rectangle holding the sites of `site_count`, isolated ones included, None
+    /// without sites
+    pub fn bounds(&self) -> Option<Rect> {
+        self.extent.bounds
+    }
+
+    /// Mean of the sites of `site_count`, None without sites
+    pub fn centroid(&self) -> Option<Point2> {
+        let extent = &self.extent;
+        (extent.sites > 0).then(|| Point2 {
+            x: extent.origin.x + extent.sum.0 / extent.sites as f64,
+            y: extent.origin.y + extent.sum.1 / extent.sites as f64,
+        })
+    }
+
+    /// Euler's relation on every live quad edge, ghosts and holes included: `V - E + F = 2C`, the
+    /// faces being the `lnext` rings and `C` the connected components with an edge
+    fn is_planar(&self) -> bool {
+        let edges: Vec<EdgeRef> = self.live_edges().collect();
+        let mut parent: Vec<u32> = (0..self.quad_arena.vertices().len() as u32).collect();
+        fn root(parent: &mut [u32], mut vertex: u32) -> u32 {
+            while parent[vertex as usize] != vertex {
+                parent[vertex as usize] = parent[parent[vertex as usize] as usize];
+                vertex = parent[vertex as usize];
+            }
+            vertex
+        }
+        let mut used = vec![false; parent.len()];
+        for edge in &edges {
+            let (org, dest) = (
+                edge.org_vertex(&self.quad_arena),
+                edge.dest_vertex(&self.quad_arena),
+            );
+            used[org as usize] = true;
+            used[dest as usize] = true;
+            let (org, dest) = (root(&mut parent, org), root(&mut parent, dest));
+            parent[org as usize] = dest;
+        }
+        let vertices = used.iter().filter(|&&used| used).count();
+        let components = (0..parent.len() as u32)
+            .filter(|&vertex| used[vertex as usize] && root(&mut parent, vertex) == vertex)
+            .count();
+
+        let mut visited = HashSet::new();
+        let mut faces = 0;
+        for edge in edges.iter().flat_map(|edge| [*edge, edge.sym()]) {
+            if !visited.insert(edge) {
+                continue;
+            }
+            faces += 1;
+            let mut next = edge.lnext(&self.quad_arena);
+            while next != edge {
+                visited.insert(next);
+                next = next.lnext(&self.quad_arena);
+            }
+        }
+        vertices + faces == edges.len() + 2 * components
+    }
+}
+
+/// Allocator of the test builds, counting the bytes allocated for tests measuring allocations
+#[cfg(test)]
+pub(crate) mod counting_alloc {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    /// Counts the bytes allocated by the current thread, tests run in parallel
+    struct CountingAllocator;
+
+    thread_local! {
+        /// Bytes allocated so far
+        pub(crate) static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+        /// Bytes allocated and not freed yet
+        pub(crate) static LIVE: Cell<usize> = const { Cell::new(0) };
+        /// Highest `LIVE` seen, tests reset it to `LIVE` before measuring
+        pub(crate) static PEAK: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// Account for `freed` bytes given back and `allocated` bytes taken
+    fn record(freed: usize, allocated: usize) {
+        ALLOCATED.with(|total| total.set(total.get() + allocated));
+        let live = LIVE.with(|live| {
+            live.set(live.get().saturating_sub(freed) + allocated);
+            live.get()
+        });
+        PEAK.with(|peak| peak.set(peak.get().max(live)));
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            record(0, layout.size());
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            record(layout.size(), 0);
+            System.dealloc(ptr, layout)
+        }
+
+        /// Grows in place when the system can, like large blocks usually do
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            record(layout.size(), new_size);
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::counting_alloc::ALLOCATED;
+    use super::*;
+    use crate::gns_delaunay::{triangulate, Phase};
+    use crate::hull::convex_hull_only_with;
+    use crate::robust_float::{signed_area, winding, Winding};
+    #[cfg(feature = "log")]
+    use crate::test_util::take_warnings;
+    use crate::test_util::{random_points, xorshift};
+    use std::cell::Cell;
+
+    /// Every live edge as a sorted pair of endpoints, sorted
+    fn edge_set(triangulation: &Triangulation) -> Vec<(Point2, Point2)> {
+        let mut edges: Vec<(Point2, Point2)> = triangulation
+            .quad_arena()
+            .iter()
+            .filter_map(|(_, quad_edge)| quad_edge.get_points(triangulation.quad_arena()))
+            .map(|(a, b)| match cmp_points(&a, &b) {
+                std::cmp::Ordering::Greater => (b, a),
+                _ => (a, b),
+            })
+            .collect();
+        edges.sort_by(|a, b| cmp_points(&a.0, &b.0).then(cmp_points(&a.1, &b.1)));
+        edges
+    }
+
+    #[test]
+    fn test_onext_ring() {
+        let points = vec![
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 1., y: 0. },
+            Point2 { x: 0., y: 1. },
+            Point2 { x: 1., y: 1. },
+            Point2 { x: 0.5, y: 0.5 },
+        ];
+        let triangulation = Triangulation::new(&points);
+        let center = triangulation
+            .vertex_index(&Point2 { x: 0.5, y: 0.5 })
+            .unwrap();
+        assert_eq!(triangulation.onext_ring(center).count(), 4);
+        let triangles = triangulation
+            .onext_ring(center)
+            .filter_map(|edge| triangulation.left_triangle(edge))
+            .count();
+        assert_eq!(triangles, 4);
+
+        let corner = triangulation
+            .vertex_index(&Point2 { x: 0., y: 0. })
+            .unwrap();
+        assert_eq!(triangulation.onext_ring(corner).count(), 3);
+    }
+
+    #[test]
+    fn test_fans() {
+        // Triangular lattice, every inner site has six neighbors
+        let points: Vec<Point2> = (0..36)
+            .map(|idx| Point2 {
+                x: (idx % 6) as f64 + (idx / 6) as f64 * 0.5,
+                y: (idx / 6) as f64,
+            })
+            .collect();
+        let triangulation = Triangulation::new(&points);
+        let fans: Vec<(Point2, Vec<Point2>, bool)> = triangulation.fans().collect();
+        assert_eq!(fans.len(), points.len());
+
+        let (_, rim, closed) = fans
+            .iter()
+            .find(|(center, _, _)| *center == Point2 { x: 3., y: 2. })
+            .unwrap();
+        assert!(closed);
+        let mut neighbors = rim.clone();
+        neighbors.sort_by(cmp_points);
+        let expected = [
+            (2., 2.),
+            (2.5, 1.),
+            (2.5, 3.),
+            (3.5, 1.),
+            (3.5, 3.),
+            (4., 2.),
+        ];
+        assert_eq!(neighbors, expected.map(|(x, y)| Point2 { x, y }));
+
+        // Every face is drawn once from each of its corners, counter clockwise
+        let mut faces = 0;
+        for (center, rim, closed) in &fans {
+            let sides = if *closed { rim.len() } else { rim.len() - 1 };
+            for idx in 0..sides {
+                let next = rim[(idx + 1) % rim.len()];
+                assert!(counter_clockwise(center, &rim[idx], &next));
+                faces += 1;
+            }
+            assert_eq!(
+                *closed,
+                !triangulation.is_hull_vertex(triangulation.vertex_index(center).unwrap())
+            );
+        }
+        assert_eq!(faces, 3 * triangulation.triangles().len());
+
+        let collinear = Triangulation::new(&[Point2 { x: 0., y: 0. }, Point2 { x: 1., y: 1. }]);
+        assert_eq!(collinear.fans().count(), 0);
+    }
+
+    #[test]
+    fn test_with_heights_merges_duplicates() {
+        let points = vec![
+            Point2 { x: 1., y: 0. },
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 1., y: 0. },
+        ];
+        let triangulation = Triangulation::with_heights(&points, &[1., 2., 3.]);
+        assert_eq!(triangulation.points().len(), 2);
+        assert_eq!(triangulation.heights(), Some(&[2., 3.][..]));
+    }
+
+    #[test]
+    fn test_remove_vertex() {
+        for seed in 1..20 {
+            let points = random_points(seed, 60, 100.);
+            let mut triangulation = Triangulation::new(&points);
+            let mut kept = triangulation.points().to_vec();
+            // Remove a mix of interior and hull sites
+            for step in 0..25 {
+                let vertex = (step * 7 + seed as usize) % triangulation.points().len();
+                if triangulation.is_removed(vertex) {
+                    continue;
+                }
+                let point = triangulation.points()[vertex];
+                triangulation.remove_vertex(vertex);
+                kept.retain(|p| *p != point);
+                assert_eq!(
+                    edge_set(&triangulation),
+                    edge_set(&Triangulation::new(&kept))
+                );
+            }
+            for vertex in 0..triangulation.points().len() {
+                assert_eq!(
+                    triangulation.is_removed(vertex),
+                    triangulation.onext_ring(vertex).count() == 0
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_remove_collinear_vertex() {
+        let points: Vec<Point2> = (0..4)
+            .map(|i| Point2 {
+                x: i as f64,
+                y: 2. * i as f64,
+            })
+            .collect();
+        let mut triangulation = Triangulation::new(&points);
+        triangulation.remove_vertex(1);
+        assert_eq!(
+            edge_set(&triangulation),
+            edge_set(&Triangulation::new(&[points[0], points[2], points[3]]))
+        );
+        triangulation.remove_vertex(3);
+        assert_eq!(edge_set(&triangulation), vec![(points[0], points[2])]);
+    }
+
+    #[test]
+    fn test_for_each_face() {
+        for seed in 1..10 {
+            let triangulation = Triangulation::new(&random_points(seed, 100, 100.));
+            let hull = (0..100)
+                .filter(|&vertex| triangulation.is_hull_vertex(vertex))
+                .count();
+            let mut faces = vec![];
+            triangulation.for_each_face(|mut corners| {
+                corners.sort_by(cmp_points);
+                faces.push(corners);
+            });
+            // A triangulation of n points with h of them on the hull has 2n - 2 - h faces
+            assert_eq!(faces.len(), 2 * 100 - 2 - hull);
+            faces.sort_by(|a, b| {
+                cmp_points(&a[0], &b[0])
+                    .then(cmp_points(&a[1], &b[1]))
+                    .then(cmp_points(&a[2], &b[2]))
+            });
+            faces.dedup();
+            assert_eq!(faces.len(), 2 * 100 - 2 - hull);
+        }
+    }
+
+    #[test]
+    fn test_live_edges() {
+        let points = random_points(3, 80, 100.);
+        let mut triangulation = Triangulation::new(&points);
+        for vertex in [0, 5, 17, 40, 79] {
+            triangulation.remove_vertex(vertex);
+        }
+        let live_quads = triangulation
+            .quad_arena()
+            .iter()
+            .filter(|(_, quad_edge)| quad_edge.get_points(triangulation.quad_arena()).is_some())
+            .count();
+        assert!(live_quads < triangulation.quad_arena().len());
+        assert_eq!(triangulation.live_edges().count(), live_quads);
+        assert_eq!(
+            triangulation.live_edges().count(),
+            edge_set(&triangulation).len()
+        );
+    }
+
+    #[test]
+    fn test_lazy_iterators() {
+        let mut triangulation = Triangulation::new(&random_points(8, 300, 100.));
+        triangulation.remove_vertex(10);
+        assert_eq!(
+            triangulation.edges_iter().collect::<Vec<_>>(),
+            triangulation.edges()
+        );
+        let mut faces = vec![];
+        triangulation.for_each_face(|corners| faces.push(corners));
+        assert_eq!(triangulation.triangles(), faces);
+        assert_eq!(
+            triangulation.edges().len(),
+            triangulation.live_edges().count()
+        );
+
+        let mut edges = triangulation.edges_iter();
+        assert_eq!(edges.size_hint().0, 0);
+        edges.by_ref().for_each(drop);
+        assert_eq!(edges.next(), None);
+        let mut triangles = triangulation.triangles_iter();
+        assert_eq!(triangles.size_hint().0, 0);
+        triangles.by_ref().for_each(drop);
+        assert_eq!(triangles.next(), None);
+    }
+
+    #[test]
+    fn test_lazy_iterators_do_not_allocate() {
+        let triangulation = Triangulation::new(&random_points(9, 1_000_000, 100.));
+        let before = ALLOCATED.with(Cell::get);
+        let (mut edges, mut faces) = (0, 0);
+        for (org, dest) in triangulation.edges_iter() {
+            assert_ne!(org, dest);
+            edges += 1;
+        }
+        for _ in triangulation.triangles_iter() {
+            faces += 1;
+        }
+        assert_eq!(ALLOCATED.with(Cell::get), before);
+        assert!(edges > 2_000_000 && faces > 1_000_000);
+    }
+
+    #[test]
+    fn test_ghosts() {
+        let sorted = |mut edges: Vec<(Point2, Point2)>| {
+            for edge in edges.iter_mut() {
+                if cmp_points(&edge.0, &edge.1).is_gt() {
+                    *edge = (edge.1, edge.0);
+                }
+            }
+            edges.sort_by(|a, b| cmp_points(&a.0, &b.0).then(cmp_points(&a.1, &b.1)));
+            edges
+        };
+        let grid: Vec<Point2> = (0..49)
+            .map(|i| Point2 {
+                x: (i % 7) as f64,
+                y: (i / 7) as f64,
+            })
+            .collect();
+        let collinear: Vec<Point2> = (0..6).map(|i| Point2 { x: i as f64, y: 0. }).collect();
+        for points in [random_points(12, 300, 100.), grid, collinear] {
+            let plain = Triangulation::new(&points);
+            let ghosted = Triangulation::with_ghosts(&points);
+            assert_eq!(sorted(ghosted.edges()), sorted(plain.edges()));
+            assert_eq!(ghosted.triangles().len(), plain.triangles().len());
+            assert_eq!(ghosted.points().len(), plain.points().len() + 3);
+
+            // Every real site is inside, the ghosts make the hull
+            for vertex in 0..ghosted.points().len() {
+                let ghost = ghosted.is_ghost(ghosted.points()[vertex]);
+                assert_eq!(ghosted.is_hull_vertex(vertex), ghost);
+                assert_eq!(ghost, vertex >= plain.points().len());
+            }
+            assert!(!plain.is_ghost(points[0]));
+            let outside = Point2 { x: -50., y: 20. };
+            let start = ghosted.any_triangle_edge().unwrap();
+            assert!(matches!(ghosted.walk(&outside, start), Location::Inside(_)));
+        }
+    }
+
+    #[test]
+    fn test_insert_points() {
+        let existing = random_points(21, 200, 100.);
+        // Spread wider than the existing sites, some land outside of their hull
+        let batch: Vec<Point2> = random_points(22, 300, 100.)
+            .into_iter()
+            .map(|point| Point2 {
+                x: point.x * 1.4 - 20.,
+                y: point.y * 1.4 - 20.,
+            })
+            .collect();
+        let mut triangulation = Triangulation::new(&existing);
+        let before = triangulation.points().to_vec();
+        let handles = triangulation.insert_points(&batch);
+
+        let all: Vec<Point2> = existing.iter().chain(batch.iter()).copied().collect();
+        assert_eq!(
+            edge_set(&triangulation),
+            edge_set(&Triangulation::new(&all))
+        );
+        assert_eq!(&triangulation.points()[..before.len()], &before[..]);
+        for (point, handle) in batch.iter().zip(handles) {
+            assert_eq!(triangulation.points()[handle], *point);
+            assert_eq!(triangulation.vertex_index(point), Some(handle));
+        }
+    }
+
+    #[test]
+    fn test_insert_duplicates() {
+        let existing = random_points(23, 100, 100.);
+        let mut triangulation = Triangulation::new(&existing);
+        let edges = edge_set(&triangulation);
+        let near = Point2 {
+            x: existing[7].x + f64::EPSILON,
+            y: existing[7].y,
+        };
+        let handles = triangulation.insert_points(&[existing[3], near, existing[3]]);
+        assert_eq!(
+            handles[0],
+            triangulation.vertex_index(&existing[3]).unwrap()
+        );
+        assert_eq!(
+            handles[1],
+            triangulation.vertex_index(&existing[7]).unwrap()
+        );
+        assert_eq!(handles[2], handles[0]);
+        assert_eq!(triangulation.points().len(), 100);
+        assert_eq!(edge_set(&triangulation), edges);
+    }
+
+    #[test]
+    fn test_insert_without_triangles() {
+        let batch = random_points(24, 50, 100.);
+        let mut triangulation = Triangulation::new(&[]);
+        triangulation.insert_points(&batch);
+        assert_eq!(
+            edge_set(&triangulation),
+            edge_set(&Triangulation::new(&batch))
+        );
+
+        let collinear: Vec<Point2> = (0..5)
+            .map(|i| Point2 {
+                x: i as f64,
+                y: i as f64,
+            })
+            .collect();
+        let mut triangulation = Triangulation::new(&collinear);
+        let handles = triangulation.insert_points(&batch);
+        let all: Vec<Point2> = collinear.iter().chain(batch.iter()).copied().collect();
+        assert_eq!(
+            edge_set(&triangulation),
+            edge_set(&Triangulation::new(&all))
+        );
+        assert_eq!(triangulation.points()[handles[9]], batch[9]);
+    }
+
+    #[test]
+    fn test_nearest_face() {
+        let corners = [
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 10., y: 0. },
+            Point2 { x: 10., y: 10. },
+            Point2 { x: 0., y: 10. },
+        ];
+        let mut points = corners.to_vec();
+        points.push(Point2 { x: 4., y: 6. });
+        let triangulation = Triangulation::new(&points);
+        let arena = triangulation.quad_arena();
+
+        let inside = Point2 { x: 2., y: 5. };
+        let edge = triangulation.nearest_face(inside);
+        let [a, b, c] = triangulation.left_triangle(edge).unwrap();
+        assert!(!counter_clockwise(&inside, &b, &a));
+        assert!(!counter_clockwise(&inside, &c, &b));
+        assert!(!counter_clockwise(&inside, &a, &c));
+
+        // Facing the inside of a hull edge
+        for (query, side) in [
+            (Point2 { x: 5., y: -3. }, [corners[0], corners[1]]),
+            (Point2 { x: 14., y: 3. }, [corners[1], corners[2]]),
+            (Point2 { x: -1., y: 9. }, [corners[3], corners[0]]),
+        ] {
+            let edge = triangulation.nearest_face(query);
+            assert!(triangulation.left_triangle(edge).is_some());
+            let (org, dest) = edge.org_dest(arena);
+            assert!((org, dest) == (side[0], side[1]) || (org, dest) == (side[1], side[0]));
+        }
+
+        // Beyond a hull vertex
+        for (query, corner) in [
+            (Point2 { x: 12., y: -2. }, corners[1]),
+            (Point2 { x: -3., y: 11. }, corners[3]),
+            (Point2 { x: 10., y: 15. }, corners[2]),
+        ] {
+            let edge = triangulation.nearest_face(query);
+            assert!(triangulation.left_triangle(edge).is_some());
+            assert_eq!(edge.org(arena), corner);
+        }
+    }
+
+    /// Every edge is locally Delaunay and every site's edges leave from it
+    fn verify_delaunay(triangulation: &Triangulation) {
+        triangulation.assert_delaunay();
+        for (vertex, point) in triangulation.points().iter().enumerate() {
+            for edge in triangulation.onext_ring(vertex) {
+                assert_eq!(edge.org(triangulation.quad_arena()), *point);
+            }
+        }
+    }
+
+    #[test]
+    fn test_is_delaunay() {
+        let triangulation = Triangulation::new(&random_points(31, 200, 100.));
+        assert!(triangulation.is_delaunay());
+        assert_eq!(triangulation.non_delaunay_edge(), None);
+
+        // Every constructor gives a Delaunay mesh, cocircular grids and duplicates included
+        let grid: Vec<Point2> = (0..100)
+            .map(|idx| Point2 {
+                x: (idx % 10) as f64,
+                y: (idx / 10) as f64,
+            })
+            .collect();
+        let mut twice = random_points(37, 300, 100.);
+        twice.extend(twice.clone());
+        for points in [
+            random_points(33, 3, 100.),
+            random_points(35, 2000, 100.),
+            grid,
+            twice,
+        ] {
+            Triangulation::new(&points).assert_delaunay();
+            Triangulation::with_ghosts(&points).assert_delaunay();
+            Triangulation::with_tiebreak(&points, 7).assert_delaunay();
+            Triangulation::with_recorder(&points, |_| {}).assert_delaunay();
+        }
+
+        // A kite, the short diagonal is the Delaunay one
+        let mut triangulation = Triangulation::new(&[
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 4., y: -1. },
+            Point2 { x: 8., y: 0. },
+            Point2 { x: 4., y: 1. },
+        ]);
+        let diagonal = triangulation
+            .live_edges()
+            .find(|&edge| {
+                triangulation.left_triangle(edge).is_some()
+                    && triangulation.left_triangle(edge.sym()).is_some()
+            })
+            .unwrap();
+        assert!(triangulation.is_edge_delaunay(diagonal));
+        triangulation.flip(diagonal);
+        assert!(!triangulation.is_delaunay());
+        let edge = triangulation.non_delaunay_edge().unwrap();
+        assert_eq!(edge.canonical_id(), diagonal.canonical_id());
+        let (org, dest) = edge.org_dest(triangulation.quad_arena());
+        assert_eq!(org.x + dest.x, 8.);
+        let panic = std::panic::catch_unwind(|| triangulation.assert_delaunay());
+        assert!(panic.is_err());
+    }
+
+    #[test]
+    fn test_vertex_edge_map() {
+        let mut triangulation = Triangulation::new(&random_points(47, 150, 100.));
+        triangulation.remove_vertex(20);
+        let map = triangulation.vertex_edge_map();
+        let total: usize = map.values().map(Vec::len).sum();
+        assert_eq!(total, 2 * triangulation.live_edges().count());
+        assert!(!map.contains_key(&20));
+        for (vertex, edges) in &map {
+            let mut edges = edges.clone();
+            let mut ring: Vec<EdgeRef> = triangulation.onext_ring(*vertex).collect();
+            edges.sort();
+            ring.sort();
+            assert_eq!(edges, ring);
+        }
+    }
+
+    #[test]
+    fn test_output_winding() {
+        // Top-left, top-right, bottom-left of a screen, the output goes down the left side first
+        let points = [
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 10., y: 0. },
+            Point2 { x: 0., y: 10. },
+        ];
+        let triangles = Triangulation::new(&points).triangles();
+        assert_eq!(triangles.len(), 1);
+        let [a, b, c] = triangles[0];
+        assert_eq!(winding(&a, &b, &c), Winding::CounterClockwise);
+        assert_eq!(signed_area(&a, &b, &c), 50.);
+        let start = [a, b, c].iter().position(|&p| p == points[0]).unwrap();
+        assert_eq!([a, b, c][(start + 1) % 3], points[2]);
+    }
+
+    #[test]
+    fn test_insert_delaunay() {
+        let points = random_points(37, 300, 100.);
+        let mut triangulation = Triangulation::new(&[]);
+        for (count, point) in points.iter().enumerate() {
+            let vertex = triangulation.insert_delaunay(*point);
+            assert_eq!(triangulation.points()[vertex], *point);
+            if count % 50 == 0 {
+                triangulation.assert_delaunay();
+            }
+        }
+        assert_eq!(
+            triangulation.insert_delaunay(points[7]),
+            triangulation.vertex_index(&points[7]).unwrap()
+        );
+        assert_eq!(edge_set(&triangulation), triangulate(&mut points.clone()));
+    }
+
+    #[test]
+    fn test_insert_outside_hull() {
+        let points = random_points(39, 200, 100.);
+        let mut triangulation = Triangulation::new(&points);
+        let hull_area = triangulation.hull_area();
+        // Far away, facing many hull edges at once
+        let far = Point2 { x: 5000., y: 40. };
+        let vertex = triangulation.insert_delaunay(far);
+        triangulation.assert_delaunay();
+        assert!(triangulation.is_hull_vertex(vertex));
+        assert!(triangulation.hull_area() > hull_area);
+        let mut all = points.clone();
+        all.push(far);
+        assert_eq!(edge_set(&triangulation), triangulate(&mut all));
+        // Every site is on the inner side of every hull edge
+        for edge in triangulation.hull_edges() {
+            let (org, dest) = edge.org_dest(triangulation.quad_arena());
+            assert!(all
+                .iter()
+                .all(|point| !counter_clockwise(&org, &dest, point)));
+        }
+    }
+
+    #[test]
+    fn test_undo_last_insert() {
+        let points = random_points(41, 200, 100.);
+        let mut triangulation = Triangulation::new(&points);
+        let before = edge_set(&triangulation);
+        // Inside, outside of the hull, and a duplicate that inserts nothing
+        let mut inserted: Vec<Point2> = random_points(42, 30, 100.)
+            .into_iter()
+            .map(|point| Point2 {
+                x: point.x * 1.4 - 20.,
+                y: point.y * 1.4 - 20.,
+            })
+            .collect();
+        inserted.push(points[5]);
+        let handles: Vec<VertexHandle> = inserted
+            .iter()
+            .map(|&point| triangulation.insert_delaunay(point))
+            .collect();
+        for &handle in handles[..30].iter().rev() {
+            assert_eq!(triangulation.undo_last_insert(), Some(handle));
+        }
+        assert_eq!(triangulation.undo_last_insert(), None);
+        assert_eq!(edge_set(&triangulation), before);
+        assert_eq!(triangulation.points(), Triangulation::new(&points).points());
+        triangulation.assert_delaunay();
+
+        // On an inner edge and on a hull edge of a grid, with heights
+        let grid: Vec<Point2> = (0..25)
+            .map(|idx| Point2 {
+                x: (idx % 5) as f64,
+                y: (idx / 5) as f64,
+            })
+            .collect();
+        let mut triangulation = Triangulation::with_heights(&grid, &[1.; 25]);
+        let before = edge_set(&triangulation);
+        triangulation.insert_points(&[Point2 { x: 1.5, y: 2. }, Point2 { x: 2.5, y: 0. }]);
+        assert_eq!(triangulation.undo_last_insert(), Some(26));
+        assert_eq!(triangulation.undo_last_insert(), Some(25));
+        assert_eq!(edge_set(&triangulation), before);
+        assert_eq!(triangulation.heights().unwrap().len(), 25);
+        for edge in triangulation.live_edges() {
+            for end in [edge, edge.sym()] {
+                let vertex = triangulation.vertex_index(&end.org(triangulation.quad_arena()));
+                assert!(triangulation
+                    .onext_ring(vertex.unwrap())
+                    .any(|other| other == end));
+            }
+        }
+
+        // Other changes forget the insertions
+        triangulation.insert_delaunay(Point2 { x: 0.5, y: 0.5 });
+        triangulation.remove_vertex(12);
+        assert_eq!(triangulation.undo_last_insert(), None);
+
+        // Without triangles to walk in the insertion triangulates again, and so does its undo
+        let mut triangulation = Triangulation::new(&grid[..3]);
+        let before = edge_set(&triangulation);
+        triangulation.insert_delaunay(Point2 { x: 1., y: 1. });
+        assert_eq!(triangulation.triangles().len(), 2);
+        assert_eq!(triangulation.undo_last_insert(), Some(3));
+        assert_eq!(edge_set(&triangulation), before);
+    }
+
+    /// Check that the edges leaving every site share one vertex table entry, at the site
+    fn check_shared_entries(triangulation: &Triangulation) {
+        let arena = triangulation.quad_arena();
+        for (vertex, site) in triangulation.points().iter().enumerate() {
+            let entries: HashSet<u32> = triangulation
+                .onext_ring(vertex)
+                .map(|edge| edge.org_vertex(arena))
+                .collect();
+            assert!(entries.len() <= 1);
+            for entry in entries {
+                assert_eq!(arena.vertices()[entry as usize], *site);
+            }
+        }
+    }
+
+    #[test]
+    fn test_collinear_removal_shares_entries() {
+        let line: Vec<Point2> = (0..6).map(|x| Point2 { x: x as f64, y: 0. }).collect();
+        let mut triangulation = Triangulation::new(&line);
+        let entries = triangulation.quad_arena().vertices().len();
+        for x in [2., 3., 0.] {
+            let vertex = triangulation.vertex_index(&Point2 { x, y: 0. }).unwrap();
+            triangulation.remove_vertex(vertex);
+            check_shared_entries(&triangulation);
+            assert_eq!(triangulation.quad_arena().vertices().len(), entries);
+        }
+        assert_eq!(triangulation.edge_count(), 2);
+    }
+
+    #[test]
+    fn test_move_vertex() {
+        let mut points = random_points(25, 120, 100.);
+        let mut triangulation = Triangulation::new(&points);
+        let vertex = triangulation.vertex_index(&points[10]).unwrap();
+        let other = triangulation.vertex_index(&points[20]).unwrap();
+
+        // Small steps first, then jumps across many triangles and out of the hull
+        let start = points[10];
+        let mut path: Vec<Point2> = (1..=5)
+            .map(|step| Point2 {
+                x: start.x + step as f64 * 0.05,
+                y: start.y - step as f64 * 0.03,
+            })
+            .collect();
+        path.extend([
+            Point2 { x: 50., y: 50. },
+            Point2 { x: 3., y: 97. },
+            Point2 { x: 110., y: -5. },
+            Point2 { x: 60., y: 40. },
+        ]);
+        let entries = triangulation.quad_arena().vertices().len();
+        for (step, target) in path.into_iter().enumerate() {
+            triangulation.move_vertex(vertex, target).unwrap();
+            verify_delaunay(&triangulation);
+            check_shared_entries(&triangulation);
+            if step < 5 {
+                // Small steps move the site's entry, the table doesn't grow
+                assert_eq!(triangulation.quad_arena().vertices().len(), entries);
+            }
+            assert_eq!(triangulation.points()[vertex], target);
+            assert_eq!(triangulation.vertex_index(&target), Some(vertex));
+            assert_eq!(triangulation.points()[other], points[20]);
+            points[10] = target;
+            assert_eq!(
+                edge_set(&triangulation),
+                edge_set(&Triangulation::new(&points))
+            );
+        }
+
+        let edges = edge_set(&triangulation);
+        assert_eq!(
+            triangulation.move_vertex(vertex, points[20]),
+            Err(MoveError::Collision(other))
+        );
+        assert_eq!(edge_set(&triangulation), edges);
+        triangulation.remove_vertex(other);
+        assert_eq!(
+            triangulation.move_vertex(other, points[10]),
+            Err(MoveError::Removed(other))
+        );
+    }
+
+    #[test]
+    fn test_far_from_origin() {
+        // Coordinates in 1/1024 steps, which an offset of 1e7 keeps exact
+        let local: Vec<Point2> = random_points(53, 300, 100.)
+            .into_iter()
+            .map(|point| Point2 {
+                x: (point.x * 1024.).round() / 1024.,
+                y: (point.y * 1024.).round() / 1024.,
+            })
+            .collect();
+        let offset = 1e7;
+        let shift = |point: &Point2, by: f64| Point2 {
+            x: point.x + by,
+            y: point.y + by,
+        };
+        let far: Vec<Point2> = local.iter().map(|point| shift(point, offset)).collect();
+        let near = Triangulation::new(&local);
+        let triangulation = Triangulation::new(&far);
+
+        let shifted: Vec<(Point2, Point2)> = edge_set(&triangulation)
+            .into_iter()
+            .map(|(a, b)| (shift(&a, -offset), shift(&b, -offset)))
+            .collect();
+        assert_eq!(shifted, edge_set(&near));
+        // Sites and edge endpoints are the input points, bit for bit
+        let bits = |point: &Point2| (point.x.to_bits(), point.y.to_bits());
+        let inputs: HashSet<(u64, u64)> = far.iter().map(bits).collect();
+        assert!(triangulation
+            .points()
+            .iter()
+            .all(|point| inputs.contains(&bits(point))));
+        assert!(triangulation
+            .edges()
+            .iter()
+            .all(|(a, b)| inputs.contains(&bits(a)) && inputs.contains(&bits(b))));
+
+        // Derived geometry doesn't lose the local detail to the offset
+        let area = near.hull_area();
+        assert!((triangulation.hull_area() - area).abs() < area * 1e-12);
+        let bounds = |by: f64| Rect {
+            min: shift(&Point2 { x: -10., y: -10. }, by),
+            max: shift(&Point2 { x: 110., y: 110. }, by),
+        };
+        let areas = triangulation.voronoi_areas(bounds(offset));
+        for (site, expected) in near.voronoi_areas(bounds(0.)).into_iter().enumerate() {
+            assert!((areas[site] - expected).abs() < 1e-6, "{}", site);
+        }
+        assert!((areas.iter().sum::<f64>() - 120. * 120.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_faces_counter_clockwise() {
+        // Random sites, then a grid full of cocircular quads, then an edited mesh
+        let grid: Vec<Point2> = (0..100)
+            .map(|i| Point2 {
+                x: (i % 10) as f64,
+                y: (i / 10) as f64,
+            })
+            .collect();
+        let mut edited = Triangulation::new(&random_points(26, 150, 100.));
+        edited.insert_points(&random_points(27, 50, 100.));
+        for vertex in [3, 40, 77] {
+            edited.remove_vertex(vertex);
+        }
+        let mut triangulations: Vec<Triangulation> = (30..35)
+            .map(|seed| Triangulation::new(&random_points(seed, 100, 100.)))
+            .collect();
+        triangulations.push(Triangulation::new(&grid));
+        triangulations.push(edited);
+
+        for triangulation in &triangulations {
+            let mut faces = 0;
+            triangulation.for_each_face(|[a, b, c]| {
+                assert!(robust::orient2d((&a).into(), (&b).into(), (&c).into()) < 0.);
+                faces += 1;
+            });
+            assert!(faces > 0);
+            let mesh = triangulation.to_halfedges();
+            for triangle in mesh.triangles.chunks_exact(3) {
+                let [a, b, c] = [0, 1, 2].map(|idx| triangulation.points()[triangle[idx]]);
+                assert!(robust::orient2d((&a).into(), (&b).into(), (&c).into()) < 0.);
+            }
+        }
+        // The grid has 2 triangles per cell whatever the diagonals, none of them flat
+        let mut faces = 0;
+        triangulations[5].for_each_face(|_| faces += 1);
+        assert_eq!(faces, 2 * 9 * 9);
+    }
+
+    #[test]
+    fn test_tiebreak_is_reproducible() {
+        let mut grid: Vec<Point2> = (0..144)
+            .map(|i| Point2 {
+                x: (i % 12) as f64,
+                y: (i / 12) as f64,
+            })
+            .collect();
+        let bits = |triangulation: &Triangulation| -> Vec<[u64; 4]> {
+            edge_set(triangulation)
+                .into_iter()
+                .map(|(a, b)| [a.x.to_bits(), a.y.to_bits(), b.x.to_bits(), b.y.to_bits()])
+                .collect()
+        };
+        let first = Triangulation::with_tiebreak(&grid, 7);
+        grid.reverse();
+        let second = Triangulation::with_tiebreak(&grid, 7);
+        assert_eq!(bits(&first), bits(&second));
+        verify_delaunay(&first);
+
+        // Every cell follows the hash rule, and another seed picks other diagonals
+        let diagonals = |triangulation: &Triangulation| -> Vec<(Point2, Point2)> {
+            edge_set(triangulation)
+                .into_iter()
+                .filter(|(a, b)| a.x != b.x && a.y != b.y)
+                .collect()
+        };
+        assert_eq!(diagonals(&first).len(), 11 * 11);
+        for (a, b) in diagonals(&first) {
+            let (x, y) = (a.x.min(b.x), a.y.min(b.y));
+            let mut corners =
+                [(x, y), (x + 1., y), (x, y + 1.), (x + 1., y + 1.)].map(|(x, y)| Point2 { x, y });
+            corners.sort_by(cmp_points);
+            let hash = corners.iter().fold(splitmix64(7), |hash, corner| {
+                splitmix64(splitmix64(hash ^ corner.x.to_bits()) ^ corner.y.to_bits())
+            });
+            assert_eq!(a == corners[0] || b == corners[0], hash.is_multiple_of(2));
+        }
+        assert_ne!(
+            diagonals(&first),
+            diagonals(&Triangulation::with_tiebreak(&grid, 8))
+        );
+    }
+
+    #[test]
+    fn test_with_recorder() {
+        let ordered = |(a, b): (Point2, Point2)| match cmp_points(&a, &b) {
+            std::cmp::Ordering::Greater => (b, a),
+            _ => (a, b),
+        };
+        // Random sites, and a grid for collinear base cases and cocircular merges
+        let grid: Vec<Point2> = (0..64)
+            .map(|idx| Point2 {
+                x: (idx % 8) as f64,
+                y: (idx / 8) as f64,
+            })
+            .collect();
+        let mut phases = HashSet::new();
+        for points in [random_points(12, 500, 100.), grid] {
+            let mut events = vec![];
+            let triangulation = Triangulation::with_recorder(&points, |event| events.push(event));
+
+            let mut replayed: Vec<(Point2, Point2)> = vec![];
+            for event in events {
+                match event {
+                    MeshEvent::MakeEdge(org, dest, phase)
+                    | MeshEvent::Connect(org, dest, phase) => {
+                        assert!(!replayed.contains(&ordered((org, dest))));
+                        replayed.push(ordered((org, dest)));
+                        phases.insert(phase);
+                    }
+                    MeshEvent::DeleteEdge(org, dest, phase) => {
+                        let at = replayed
+                            .iter()
+                            .position(|&edge| edge == ordered((org, dest)));
+                        replayed.swap_remove(at.expect("Only made edges are deleted"));
+                        assert_eq!(phase, Phase::CandidateRejection);
+                        phases.insert(phase);
+                    }
+                }
+            }
+            replayed.sort_by(|a, b| cmp_points(&a.0, &b.0).then(cmp_points(&a.1, &b.1)));
+            assert_eq!(replayed, edge_set(&triangulation));
+            assert_eq!(Triangulation::new(&points).edges(), triangulation.edges());
+        }
+        assert_eq!(phases.len(), 3);
+    }
+
+    #[test]
+    fn test_counts() {
+        // 6x4 grid: 5x3 cells, each split into 2 triangles by a diagonal
+        let grid: Vec<Point2> = (0..24)
+            .map(|idx| Point2 {
+                x: (idx % 6) as f64,
+                y: (idx / 6) as f64,
+            })
+            .collect();
+        let (cells, lines) = (5 * 3, 5 * 4 + 6 * 3);
+        let triangulation = Triangulation::new(&grid);
+        let (vertices, edges, faces) = triangulation.counts();
+        assert_eq!((vertices, edges, faces), (24, lines + cells, 2 * cells + 1));
+        assert_eq!(vertices + faces, edges + 2);
+        assert_eq!(faces, triangulation.triangles().len() + 1);
+        assert_eq!(Triangulation::with_ghosts(&grid).counts(), (24, 53, 31));
+
+        let mut triangulation = Triangulation::new(&random_points(13, 400, 100.));
+        triangulation.remove_vertex(7);
+        let (vertices, edges, faces) = triangulation.counts();
+        assert_eq!(vertices, 399);
+        assert_eq!(vertices + faces, edges + 2);
+        assert_eq!(edges, triangulation.edges().len());
+
+        let line: Vec<Point2> = (0..5).map(|x| Point2 { x: x as f64, y: 0. }).collect();
+        assert_eq!(Triangulation::new(&line).counts(), (5, 4, 1));
+        assert_eq!(Triangulation::new(&line[..1]).counts(), (1, 0, 1));
+    }
+
+    /// The kept counts, bounds and centroid are the ones measured on the whole mesh
+    fn check_extent(triangulation: &Triangulation) {
+        let sites = triangulation.real_sites();
+        let (vertices, edges, _) = triangulation.counts();
+        let triangles = triangulation.triangles_iter().count();
+        assert_eq!(triangulation.site_count(), vertices);
+        assert_eq!(triangulation.edge_count(), edges);
+        assert_eq!(triangulation.triangle_count(), triangles);
+        if triangles > 0 {
+            // Euler's formula, the unbounded face included
+            assert_eq!(vertices + triangles + 1, edges + 2);
+        }
+        assert_eq!(triangulation.bounds(), bounding_rect(&sites));
+        match triangulation.centroid() {
+            Some(centroid) => {
+                let count = sites.len() as f64;
+                let mean_x = sites.iter().map(|site| site.x).sum::<f64>() / count;
+                let mean_y = sites.iter().map(|site| site.y).sum::<f64>() / count;
+                assert!((centroid.x - mean_x).abs() < 1e-9 && (centroid.y - mean_y).abs() < 1e-9);
+            }
+            None => assert!(sites.is_empty()),
+        }
+    }
+
+    #[test]
+    fn test_extent() {
+        let mut next = xorshift(23);
+        let line: Vec<Point2> = (0..4).map(|x| Point2 { x: x as f64, y: 0. }).collect();
+        for mut triangulation in [
+            Triangulation::new(&random_points(5, 100, 100.)),
+            Triangulation::with_ghosts(&random_points(6, 100, 100.)),
+            Triangulation::new(&line),
+            Triangulation::new(&[]),
+        ] {
+            check_extent(&triangulation);
+            for _ in 0..300 {
+                let live: Vec<usize> = (0..triangulation.points().len())
+                    .filter(|&vertex| {
+                        !triangulation.is_removed(vertex)
+                            && !triangulation.is_ghost(triangulation.points()[vertex])
+                    })
+                    .collect();
+                let point = Point2 {
+                    x: next() * 120. - 10.,
+                    y: next() * 120. - 10.,
+                };
+                let pick = live.get((next() * live.len() as f64) as usize).copied();
+                match (next() * 5.) as usize {
+                    0 | 1 => {
+                        triangulation.insert_delaunay(point);
+                    }
+                    2 => {
+                        if let Some(vertex) = pick {
+                            triangulation.remove_vertex(vertex);
+                        }
+                    }
+                    3 => {
+                        if let Some(vertex) = pick {
+                            // A nudge mostly stays inside the neighbors, a jump doesn't
+                            let target = match next() < 0.5 {
+                                true => {
+                                    let site = triangulation.points()[vertex];
+                                    Point2 {
+                                        x: site.x + (next() - 0.5) * 0.1,
+                                        y: site.y + (next() - 0.5) * 0.1,
+                                    }
+                                }
+                                false => point,
+                            };
+                            let _ = triangulation.move_vertex(vertex, target);
+                        }
+                    }
+                    _ => {
+                        triangulation.undo_last_insert();
+                    }
+                }
+                check_extent(&triangulation);
+            }
+        }
+
+        // Isolated sites count in the bounds
+        let triangulation = Triangulation::new(&line[..1]);
+        assert_eq!(triangulation.bounds().unwrap().min, line[0]);
+        assert_eq!(triangulation.centroid(), Some(line[0]));
+        assert_eq!(Triangulation::new(&[]).bounds(), None);
+    }
+
+    #[test]
+    fn test_hull() {
+        let mut next = xorshift(29);
+        let check = |triangulation: &Triangulation| {
+            let expected = convex_hull_only_with(&triangulation.real_sites(), true);
+            assert_eq!(triangulation.hull(), expected);
+        };
+
+        // A spiral going out, every point outside of the hull so far
+        let mut triangulation = Triangulation::new(&[]);
+        for step in 0..200 {
+            let (angle, radius) = (step as f64 * 2.4, 1. + step as f64 * 0.5);
+            triangulation.insert_delaunay(Point2 {
+                x: radius * angle.cos(),
+                y: radius * angle.sin(),
+            });
+            check(&triangulation);
+        }
+        for _ in 0..20 {
+            triangulation.undo_last_insert();
+            check(&triangulation);
+        }
+
+        let line: Vec<Point2> = (0..4).map(|x| Point2 { x: x as f64, y: 0. }).collect();
+        for mut triangulation in [
+            Triangulation::new(&random_points(7, 50, 100.)),
+            Triangulation::with_ghosts(&random_points(8, 50, 100.)),
+            Triangulation::new(&line),
+        ] {
+            check(&triangulation);
+            for _ in 0..300 {
+                let point = Point2 {
+                    x: next() * 140. - 20.,
+                    y: next() * 140. - 20.,
+                };
+                if next() < 0.7 {
+                    triangulation.insert_delaunay(point);
+                } else {
+                    let live: Vec<usize> = (0..triangulation.points().len())
+                        .filter(|&vertex| {
+                            !triangulation.is_removed(vertex)
+                                && !triangulation.is_ghost(triangulation.points()[vertex])
+                        })
+                        .collect();
+                    if let Some(&vertex) = live.get((next() * live.len() as f64) as usize) {
+                        triangulation.remove_vertex(vertex);
+                    }
+                }
+                check(&triangulation);
+            }
+        }
+        assert!(Triangulation::new(&[]).hull().is_empty());
+    }
+
+    #[test]
+    fn test_walk_fallback() {
+        let points = [
+            (0., 9.),
+            (1., 7.),
+            (2., 0.),
+            (2., 4.),
+            (6., 4.),
+            (7., 5.),
+            (8., 3.),
+            (8., 6.),
+        ]
+        .map(|(x, y)| Point2 { x, y });
+        let mut triangulation = Triangulation::new(&points);
+        // Corrupt the mesh into a valid but not Delaunay one, by flipping the edges crossing the
+        // diagonals between these sites
+        for (a, b) in [(0, 3), (1, 6)] {
+            let arena = &triangulation.quad_arena;
+            let edge = triangulation
+                .live_edges()
+                .flat_map(|edge| [edge, edge.sym()])
+                .find(|&edge| {
+                    edge.lnext(arena).dest(arena) == points[a]
+                        && edge.sym().lnext(arena).dest(arena) == points[b]
+                })
+                .unwrap();
+            triangulation.flip(edge);
+        }
+        assert!(!triangulation.is_delaunay());
+        assert_eq!(triangulation.walk_fallbacks(), 0);
+
+        // From there, the walk goes around the point without ever reaching it
+        let point = Point2 { x: 1.625, y: 5.875 };
+        let arena = &triangulation.quad_arena;
+        let start = triangulation
+            .live_edges()
+            .flat_map(|edge| [edge, edge.sym()])
+            .find(|&edge| edge.org(arena) == points[0] && edge.dest(arena) == points[1])
+            .unwrap();
+        #[cfg(feature = "log")]
+        take_warnings();
+        let location = triangulation.locate_from(start, point);
+        assert_eq!(triangulation.walk_fallbacks(), 1);
+        #[cfg(feature = "log")]
+        assert_eq!(take_warnings().len(), 1);
+        let Location::Inside(edge) = location else {
+            panic!("{:?} isn't inside", location);
+        };
+        let [a, b, c] = triangulation.left_triangle(edge).unwrap();
+        assert_eq!(point_in_triangle(point, a, b, c), Region::Inside);
+
+        // Whatever the start, the walk ends, in the triangle holding the point
+        let starts: Vec<EdgeRef> = triangulation
+            .live_edges()
+            .flat_map(|edge| [edge, edge.sym()])
+            .filter(|&edge| triangulation.left_triangle(edge).is_some())
+            .collect();
+        for &start in &starts {
+            let Location::Inside(edge) = triangulation.locate_from(start, point) else {
+                panic!("Lost from {:?}", start);
+            };
+            let [a, b, c] = triangulation.left_triangle(edge).unwrap();
+            assert_eq!(point_in_triangle(point, a, b, c), Region::Inside);
+        }
+        assert!(triangulation.walk_fallbacks() > 1);
+    }
+}
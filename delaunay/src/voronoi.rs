@@ -0,0 +1,596 @@
+/// Voronoi diagram, the dual of the Delaunay triangulation
+///
+/// The Voronoi edge dual to a Delaunay edge joins the circumcenters of the two triangles around
+/// it. Hull edges only have one triangle, their dual is a ray going away from the hull.
+///
+/// The order-2 diagram splits the plane by pair of nearest sites. Only Delaunay neighbors can
+/// be such a pair, and the cell of a pair is cut by the bisectors with the neighbors of either.
+use std::collections::HashMap;
+
+use crate::range::clip_segment;
+use crate::robust_float::{circumcenter, cmp_points, nearly_equals, shoelace, Point2, Rect};
+use crate::triangulation::{Triangulation, VertexHandle};
+
+/// Segment given by its two endpoints
+pub type Segment = (Point2, Point2);
+
+/// Part of the ray (or of the whole line when `both_ways`) from `start` along `direction` inside
+/// `bounds`, the single point `start` when it misses `bounds`
+fn clip_ray(
+    start: Point2,
+    direction: (f64, f64),
+    both_ways: bool,
+    bounds: &Rect,
+) -> (Point2, Point2) {
+    let norm = direction.0.hypot(direction.1);
+    // Long enough to leave the bounds from anywhere inside of them
+    let length = (start.x - bounds.min.x).abs()
+        + (start.y - bounds.min.y).abs()
+        + (start.x - bounds.max.x).abs()
+        + (start.y - bounds.max.y).abs()
+        + 1.;
+    let end = |sign: f64| Point2 {
+        x: start.x + sign * direction.0 / norm * length,
+        y: start.y + sign * direction.1 / norm * length,
+    };
+    let from = if both_ways { end(-1.) } else { start };
+    clip_segment(from, end(1.), bounds).unwrap_or((start, start))
+}
+
+/// Order-2 Voronoi diagram, see `Triangulation::voronoi_order2`
+#[derive(Clone, Debug, Default)]
+pub struct Order2Diagram {
+    cells: HashMap<(VertexHandle, VertexHandle), Vec<Point2>>,
+}
+
+impl Order2Diagram {
+    /// Convex polygon of the places whose two nearest sites are `a` and `b`, in either order.
+    /// Empty when the two sites aren't Delaunay neighbors or when the cell is out of the bounds.
+    pub fn cell(&self, a: VertexHandle, b: VertexHandle) -> &[Point2] {
+        self.cells
+            .get(&(a.min(b), a.max(b)))
+            .map_or(&[], |polygon| polygon.as_slice())
+    }
+
+    /// Every pair of Delaunay neighbors, smaller handle first, with its cell, in no given order
+    pub fn cells(&self) -> impl Iterator<Item = ((VertexHandle, VertexHandle), &[Point2])> {
+        self.cells
+            .iter()
+            .map(|(&pair, polygon)| (pair, polygon.as_slice()))
+    }
+}
+
+/// Keep the part of the convex polygon where `normal . point <= offset`
+pub(crate) fn clip_half_plane(polygon: &[Point2], normal: (f64, f64), offset: f64) -> Vec<Point2> {
+    let side = |point: &Point2| normal.0 * point.x + normal.1 * point.y - offset;
+    let mut clipped = vec![];
+    for (idx, point) in polygon.iter().enumerate() {
+        let next = &polygon[(idx + 1) % polygon.len()];
+        let (here, there) = (side(point), side(next));
+        if here <= 0. {
+            clipped.push(*point);
+        }
+        if (here < 0. && there > 0.) || (here > 0. && there < 0.) {
+            let t = here / (here - there);
+            clipped.push(Point2 {
+                x: point.x + t * (next.x - point.x),
+                y: point.y + t * (next.y - point.y),
+            });
+        }
+    }
+    clipped
+}
+
+/// Keep the part of the convex polygon closer to `site` than to `other`
+fn clip_closer(polygon: &[Point2], site: &Point2, other: &Point2) -> Vec<Point2> {
+    // Positive on the side of `other` of the bisector, through the middle of the two sites. The
+    // squares of far away coordinates would leave no precision for the offset.
+    let normal = (other.x - site.x, other.y - site.y);
+    let offset = (normal.0 * (site.x + other.x) + normal.1 * (site.y + other.y)) / 2.;
+    clip_half_plane(polygon, normal, offset)
+}
+
+/// Corners of `bounds`, counter clockwise
+pub(crate) fn rect_corners(bounds: &Rect) -> [Point2; 4] {
+    [
+        bounds.min,
+        Point2 {
+            x: bounds.min.x,
+            y: bounds.max.y,
+        },
+        bounds.max,
+        Point2 {
+            x: bounds.max.x,
+            y: bounds.min.y,
+        },
+    ]
+}
+
+/// Area of a simple polygon, in either orientation
+pub(crate) fn polygon_area(polygon: &[Point2]) -> f64 {
+    shoelace(polygon).abs() / 2.
+}
+
+impl Triangulation {
+    /// Order-2 Voronoi diagram of the sites, clipped to `bounds`: the cell of every pair of
+    /// Delaunay neighbors, the places having these two sites as nearest sites. Cells are convex
+    /// polygons, counter clockwise as `counter_clockwise` defines it, and tile `bounds`. Ghost
+    /// sites are left out.
+    ///
+    /// A place nearest to `a` has its second nearest site among the neighbors of `a`, so the cell
+    /// of `a` and `b` is `bounds` cut by the bisectors of `a` and `b` with their neighbors.
+    pub fn voronoi_order2(&self, bounds: Rect) -> Order2Diagram {
+        let corners = rect_corners(&bounds);
+        let neighbors = |vertex: VertexHandle| {
+            self.onext_ring(vertex)
+                .map(|edge| edge.dest(self.quad_arena()))
+                .filter(|&point| !self.is_ghost(point))
+        };
+        let mut cells = HashMap::new();
+        for (org, dest) in self.edges_iter() {
+            let (a, b) = (
+                self.vertex_index(&org).unwrap(),
+                self.vertex_index(&dest).unwrap(),
+            );
+            let mut polygon = corners.to_vec();
+            for other in neighbors(a).chain(neighbors(b)) {
+                if polygon.is_empty() {
+                    break;
+                }
+                if other != org && other != dest {
+                    polygon = clip_closer(&polygon, &org, &other);
+                    polygon = clip_closer(&polygon, &dest, &other);
+                }
+            }
+            cells.insert((a.min(b), a.max(b)), polygon);
+        }
+        Order2Diagram { cells }
+    }
+
+    /// Voronoi cell of `site`, the places nearer to it than to any other site, clipped to
+    /// `bounds`. The polygon is convex, counter clockwise as `counter_clockwise` defines it, and
+    /// empty for removed and ghost sites or when the cell is out of the bounds.
+    ///
+    /// A site inside the hull has a bounded cell, the circumcenters of the triangles around it in
+    /// order, circumcenters shared by cocircular triangles given once. The cell of a hull site
+    /// is unbounded, it's cut from `bounds` by the bisectors with the neighbors of the site.
+    pub fn voronoi_cell(&self, site: VertexHandle, bounds: Rect) -> Vec<Point2> {
+        if site >= self.points().len()
+            || self.is_removed(site)
+            || self.is_ghost(self.points()[site])
+        {
+            return vec![];
+        }
+        if self.is_hull_vertex(site) || self.onext_ring(site).next().is_none() {
+            let mut polygon = rect_corners(&bounds).to_vec();
+            let point = self.points()[site];
+            for edge in self.onext_ring(site) {
+                if polygon.is_empty() {
+                    break;
+                }
+                polygon = clip_closer(&polygon, &point, &edge.dest(self.quad_arena()));
+            }
+            return polygon;
+        }
+
+        let mut cell: Vec<Point2> = vec![];
+        for edge in self.onext_ring(site) {
+            let [a, b, c] = self.left_triangle(edge).unwrap();
+            let center = circumcenter(&a, &b, &c);
+            if cell.last().is_none_or(|last| !nearly_equals(last, &center)) {
+                cell.push(center);
+            }
+        }
+        if cell.len() > 1 && nearly_equals(&cell[0], &cell[cell.len() - 1]) {
+            cell.pop();
+        }
+        // The bounds as four half-planes
+        let sides = [
+            ((-1., 0.), -bounds.min.x),
+            ((0., -1.), -bounds.min.y),
+            ((1., 0.), bounds.max.x),
+            ((0., 1.), bounds.max.y),
+        ];
+        for (normal, offset) in sides {
+            if cell.is_empty() {
+                break;
+            }
+            cell = clip_half_plane(&cell, normal, offset);
+        }
+        cell
+    }
+
+    /// Area of the `voronoi_cell` of every site, indexed like `points()`, the weights of Lloyd
+    /// relaxation and weighted centroids. Cells of hull sites are cut by `bounds` like the others,
+    /// so the areas sum to the area of `bounds` when it holds every site. Removed and ghost sites
+    /// have no area.
+    pub fn voronoi_areas(&self, bounds: Rect) -> Vec<f64> {
+        (0..self.points().len())
+            .map(|site| polygon_area(&self.voronoi_cell(site, bounds)))
+            .collect()
+    }
+
+    /// Discrete Voronoi diagram: for every cell of a `width` by `height` grid over `bounds`, row
+    /// after row from `bounds.min`, the index of the site nearest to the center of the cell. Of
+    /// sites at the same distance, the one of lowest index.
+    ///
+    /// Every cell walks the Delaunay graph from the site of the previous cell, the first cell of
+    /// a row from the site of the first cell of the row above, so the walks are a few steps and
+    /// the whole raster takes about linear time in the number of cells.
+    ///
+    /// Panics if the triangulation has no site left.
+    pub fn voronoi_raster(&self, bounds: Rect, width: usize, height: usize) -> Vec<u32> {
+        let mut raster = Vec::with_capacity(width * height);
+        if width == 0 || height == 0 {
+            return raster;
+        }
+        let (cell_width, cell_height) = (
+            bounds.width() / width as f64,
+            bounds.height() / height as f64,
+        );
+        let center = |column: usize, row: usize| Point2 {
+            x: bounds.min.x + (column as f64 + 0.5) * cell_width,
+            y: bounds.min.y + (row as f64 + 0.5) * cell_height,
+        };
+        let mut row_start = self
+            .nearest_site(center(0, 0))
+            .expect("The triangulation has no site");
+        for row in 0..height {
+            row_start = self.nearest_site_from(row_start, center(0, row));
+            let mut site = row_start;
+            raster.push(site as u32);
+            for column in 1..width {
+                site = self.nearest_site_from(site, center(column, row));
+                raster.push(site as u32);
+            }
+        }
+        raster
+    }
+
+    /// Delaunay edges and their dual Voronoi edges, in one walk over the mesh. `dual[i]` is the
+    /// dual of `primal[i]`, the primal edges come in the `edges_iter` order.
+    ///
+    /// Duals of inner edges join two circumcenters and aren't clipped. Duals of hull edges are
+    /// rays from the circumcenter of their triangle, cut where they leave `bounds`; a ray missing
+    /// `bounds` is given as the single point of its start. Without any triangle, the dual of an
+    /// edge is its bisector line, clipped to `bounds` the same way.
+    pub fn primal_and_dual(&self, bounds: Rect) -> (Vec<Segment>, Vec<Segment>) {
+        let mut primal = vec![];
+        let mut dual = vec![];
+        for edge in self.live_edges() {
+            let (org, dest) = edge.org_dest(self.quad_arena());
+            if self.is_ghost(org) || self.is_ghost(dest) {
+                continue;
+            }
+            // Corners in a fixed order, so the duals around a triangle meet at the same bits
+            let center = |mut corners: [Point2; 3]| {
+                corners.sort_by(cmp_points);
+                circumcenter(&corners[0], &corners[1], &corners[2])
+            };
+            let normal = (dest.y - org.y, org.x - dest.x);
+            let away_from = |corner: Point2| {
+                let side = normal.0 * (corner.x - org.x) + normal.1 * (corner.y - org.y);
+                if side > 0. {
+                    (-normal.0, -normal.1)
+                } else {
+                    normal
+                }
+            };
+            let segment = match (self.left_triangle(edge), self.left_triangle(edge.sym())) {
+                (Some(left), Some(right)) => (center(left), center(right)),
+                (Some(triangle), None) | (None, Some(triangle)) => {
+                    let third = *triangle
+                        .iter()
+                        .find(|&&corner| corner != org && corner != dest)
+                        .unwrap();
+                    clip_ray(center(triangle), away_from(third), false, &bounds)
+                }
+                (None, None) => {
+                    let middle = Point2 {
+                        x: (org.x + dest.x) / 2.,
+                        y: (org.y + dest.y) / 2.,
+                    };
+                    clip_ray(middle, normal, true, &bounds)
+                }
+            };
+            primal.push((org, dest));
+            dual.push(segment);
+        }
+        (primal, dual)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::xorshift;
+
+    #[test]
+    fn test_primal_and_dual() {
+        let mut next = xorshift(29);
+        let points: Vec<Point2> = (0..200)
+            .map(|_| Point2 {
+                x: next() * 100.,
+                y: next() * 100.,
+            })
+            .collect();
+        let triangulation = Triangulation::new(&points);
+        let bounds = Rect {
+            min: Point2 { x: -50., y: -50. },
+            max: Point2 { x: 150., y: 150. },
+        };
+        let (primal, dual) = triangulation.primal_and_dual(bounds);
+        assert_eq!(primal, triangulation.edges());
+        assert_eq!(dual.len(), primal.len());
+
+        for ((org, dest), (start, end)) in primal.iter().zip(&dual) {
+            // Both ends of the dual are as far from the two sites, and the dual is orthogonal
+            for end in [start, end] {
+                let (to_org, to_dest) = (end.distance(org), end.distance(dest));
+                assert!((to_org - to_dest).abs() < 1e-6 * to_org.max(1.));
+            }
+            let dot = (dest.x - org.x) * (end.x - start.x) + (dest.y - org.y) * (end.y - start.y);
+            assert!(dot.abs() < 1e-6 * org.distance(dest) * start.distance(end).max(1.));
+        }
+
+        // Rays end on the bounds, away from the hull
+        let hull_duals = primal
+            .iter()
+            .zip(&dual)
+            .filter(|((org, dest), _)| {
+                let vertex = |point| triangulation.vertex_index(point).unwrap();
+                triangulation.is_hull_vertex(vertex(org))
+                    && triangulation.is_hull_vertex(vertex(dest))
+            })
+            .filter(|(_, (_, end))| {
+                let on_border = |value: f64, min: f64, max: f64| {
+                    (value - min).abs() < 1e-9 || (value - max).abs() < 1e-9
+                };
+                on_border(end.x, bounds.min.x, bounds.max.x)
+                    || on_border(end.y, bounds.min.y, bounds.max.y)
+            })
+            .count();
+        assert!(hull_duals >= 3);
+
+        // Collinear sites have bisector lines across the bounds
+        let collinear: Vec<Point2> = (0..3).map(|i| Point2 { x: i as f64, y: 0. }).collect();
+        let (primal, dual) = Triangulation::new(&collinear).primal_and_dual(bounds);
+        assert_eq!(primal.len(), 2);
+        for (start, end) in dual {
+            assert_eq!((start.y.min(end.y), start.y.max(end.y)), (-50., 150.));
+            assert_eq!(start.x, end.x);
+        }
+    }
+
+    #[test]
+    fn test_voronoi_order2() {
+        let bounds = Rect {
+            min: Point2 { x: -10., y: -10. },
+            max: Point2 { x: 20., y: 20. },
+        };
+        let three = [
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 8., y: 1. },
+            Point2 { x: 3., y: 6. },
+        ];
+        let triangulation = Triangulation::new(&three);
+        let diagram = triangulation.voronoi_order2(bounds);
+        assert_eq!(diagram.cells().count(), 3);
+        let total: f64 = diagram
+            .cells()
+            .map(|(_, polygon)| polygon_area(polygon))
+            .sum();
+        assert!((total - 900.).abs() < 1e-9);
+        assert_eq!(diagram.cell(0, 1), diagram.cell(1, 0));
+        assert!(diagram.cell(0, 0).is_empty());
+
+        let mut next = xorshift(67);
+        let points: Vec<Point2> = (0..80)
+            .map(|_| Point2 {
+                x: next() * 10.,
+                y: next() * 10.,
+            })
+            .collect();
+        let triangulation = Triangulation::new(&points);
+        let diagram = triangulation.voronoi_order2(bounds);
+        let total: f64 = diagram
+            .cells()
+            .map(|(_, polygon)| polygon_area(polygon))
+            .sum();
+        assert!((total - 900.).abs() < 1e-6);
+        for (_, polygon) in diagram.cells().filter(|(_, polygon)| polygon.len() >= 3) {
+            // The winding of the whole polygon, clipping can leave a vertex in the middle of a side
+            assert!(shoelace(polygon) <= 0.);
+        }
+
+        // Places fall in the cell of their two nearest sites
+        let sites = triangulation.points();
+        for _ in 0..200 {
+            let place = Point2 {
+                x: next() * 30. - 10.,
+                y: next() * 30. - 10.,
+            };
+            let mut order: Vec<usize> = (0..sites.len()).collect();
+            order.sort_by(|&a, &b| {
+                sites[a]
+                    .distance(&place)
+                    .total_cmp(&sites[b].distance(&place))
+            });
+            let polygon = diagram.cell(order[0], order[1]);
+            let inside = (0..polygon.len()).all(|idx| {
+                let (a, b) = (&polygon[idx], &polygon[(idx + 1) % polygon.len()]);
+                (b.x - a.x) * (place.y - a.y) - (b.y - a.y) * (place.x - a.x) <= 1e-9
+            });
+            assert!(inside);
+        }
+    }
+
+    #[test]
+    fn test_voronoi_cell() {
+        let bounds = Rect {
+            min: Point2 { x: -2., y: -2. },
+            max: Point2 { x: 7., y: 7. },
+        };
+        let grid: Vec<Point2> = (0..36)
+            .map(|idx| Point2 {
+                x: (idx % 6) as f64,
+                y: (idx / 6) as f64,
+            })
+            .collect();
+        let triangulation = Triangulation::new(&grid);
+        let site = triangulation
+            .vertex_index(&Point2 { x: 2., y: 3. })
+            .unwrap();
+        let cell = triangulation.voronoi_cell(site, bounds);
+        assert_eq!(cell.len(), 4);
+        assert_eq!(polygon_area(&cell), 1.);
+        for corner in &cell {
+            assert_eq!(((corner.x - 2.).abs(), (corner.y - 3.).abs()), (0.5, 0.5));
+        }
+        // A corner of the grid reaches the corner of the bounds
+        let corner = triangulation
+            .vertex_index(&Point2 { x: 5., y: 5. })
+            .unwrap();
+        assert_eq!(
+            polygon_area(&triangulation.voronoi_cell(corner, bounds)),
+            2.5 * 2.5
+        );
+
+        // The cells tile the bounds, and hold the places nearest to their site
+        let mut next = xorshift(71);
+        let points: Vec<Point2> = (0..100)
+            .map(|_| Point2 {
+                x: next() * 5.,
+                y: next() * 5.,
+            })
+            .collect();
+        let triangulation = Triangulation::new(&points);
+        let sites = triangulation.points();
+        let cells: Vec<Vec<Point2>> = (0..sites.len())
+            .map(|site| triangulation.voronoi_cell(site, bounds))
+            .collect();
+        let total: f64 = cells.iter().map(|cell| polygon_area(cell)).sum();
+        assert!((total - 81.).abs() < 1e-9, "{}", total);
+        for cell in cells.iter().filter(|cell| cell.len() >= 3) {
+            assert!(crate::robust_float::orient2d_value(&cell[0], &cell[1], &cell[2]) <= 0.);
+        }
+        for _ in 0..200 {
+            let place = Point2 {
+                x: next() * 9. - 2.,
+                y: next() * 9. - 2.,
+            };
+            let nearest = (0..sites.len())
+                .min_by(|&a, &b| {
+                    sites[a]
+                        .distance(&place)
+                        .total_cmp(&sites[b].distance(&place))
+                })
+                .unwrap();
+            let cell = &cells[nearest];
+            let inside = (0..cell.len()).all(|idx| {
+                let (a, b) = (&cell[idx], &cell[(idx + 1) % cell.len()]);
+                (b.x - a.x) * (place.y - a.y) - (b.y - a.y) * (place.x - a.x) <= 1e-9
+            });
+            assert!(inside);
+        }
+
+        // A lone site owns the bounds, a site out of range nothing
+        let lone = Triangulation::new(&points[..1]);
+        assert_eq!(polygon_area(&lone.voronoi_cell(0, bounds)), 81.);
+        assert!(lone.voronoi_cell(1, bounds).is_empty());
+    }
+
+    #[test]
+    fn test_voronoi_areas() {
+        // Grid centered in the bounds: inner cells are unit squares, border cells reach the
+        // bounds and are alike by symmetry
+        let bounds = Rect {
+            min: Point2 { x: -1., y: -1. },
+            max: Point2 { x: 6., y: 6. },
+        };
+        let grid: Vec<Point2> = (0..36)
+            .map(|idx| Point2 {
+                x: (idx % 6) as f64,
+                y: (idx / 6) as f64,
+            })
+            .collect();
+        let mut triangulation = Triangulation::new(&grid);
+        let areas = triangulation.voronoi_areas(bounds);
+        assert_eq!(areas.len(), 36);
+        for (site, point) in triangulation.points().iter().enumerate() {
+            let border = [point.x, point.y]
+                .iter()
+                .filter(|&&coordinate| coordinate == 0. || coordinate == 5.)
+                .count();
+            let expected = [1., 1.5, 1.5 * 1.5][border];
+            assert!((areas[site] - expected).abs() < 1e-9, "{:?}", point);
+        }
+        assert!((areas.iter().sum::<f64>() - 49.).abs() < 1e-9);
+
+        // A removed site gives its cell to its neighbors
+        let site = triangulation
+            .vertex_index(&Point2 { x: 2., y: 3. })
+            .unwrap();
+        triangulation.remove_vertex(site);
+        let areas = triangulation.voronoi_areas(bounds);
+        assert_eq!(areas[site], 0.);
+        assert!((areas.iter().sum::<f64>() - 49.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_voronoi_raster() {
+        let mut next = xorshift(97);
+        let points: Vec<Point2> = (0..200)
+            .map(|_| Point2 {
+                x: next() * 100.,
+                y: next() * 100.,
+            })
+            .collect();
+        let triangulation = Triangulation::new(&points);
+        let sites = triangulation.points();
+        // Past the sites on every side
+        let bounds = Rect {
+            min: Point2 { x: -20., y: -10. },
+            max: Point2 { x: 120., y: 110. },
+        };
+        let (width, height) = (70, 40);
+        let raster = triangulation.voronoi_raster(bounds, width, height);
+        assert_eq!(raster.len(), width * height);
+        for (cell, &site) in raster.iter().enumerate() {
+            let center = Point2 {
+                x: -20. + ((cell % width) as f64 + 0.5) * 2.,
+                y: -10. + ((cell / width) as f64 + 0.5) * 3.,
+            };
+            let nearest = (0..sites.len())
+                .min_by(|&a, &b| {
+                    sites[a]
+                        .distance(&center)
+                        .total_cmp(&sites[b].distance(&center))
+                })
+                .unwrap();
+            assert_eq!(site as usize, nearest);
+        }
+
+        // Two sites split the raster along their bisector, the middle column is a tie the lower
+        // site wins
+        let pair = Triangulation::new(&[Point2 { x: 6., y: 2. }, Point2 { x: 1., y: 2. }]);
+        let low = pair.vertex_index(&Point2 { x: 1., y: 2. }).unwrap() as u32;
+        let bounds = Rect {
+            min: Point2 { x: 0., y: 0. },
+            max: Point2 { x: 7., y: 4. },
+        };
+        let raster = pair.voronoi_raster(bounds, 7, 4);
+        for row in raster.chunks(7) {
+            assert_eq!(row, [low, low, low, low, 1 - low, 1 - low, 1 - low]);
+        }
+        // Horizontal bisector, the middle row is the tie
+        let pair = Triangulation::new(&[Point2 { x: 2., y: 6. }, Point2 { x: 2., y: 1. }]);
+        let bounds = Rect {
+            min: Point2 { x: 0., y: 0. },
+            max: Point2 { x: 4., y: 7. },
+        };
+        let raster = pair.voronoi_raster(bounds, 4, 7);
+        let expected: Vec<u32> = (0..7).flat_map(|row| [u32::from(row > 3); 4]).collect();
+        assert_eq!(raster, expected);
+    }
+}
@@ -0,0 +1,73 @@
+/// Flat array API for WASM and JS callers: points go in as `[x0, y0, x1, y1, ...]` and lines come
+/// out as `[org.x, org.y, dest.x, dest.y, ...]`, so no struct needs to cross the boundary
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::gns_delaunay::triangulate;
+use crate::robust_float::Point2;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FlatInputError {
+    /// The coordinates can't be split in (x, y) pairs, holds the input length
+    OddLength(usize),
+}
+
+impl fmt::Display for FlatInputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlatInputError::OddLength(len) => {
+                write!(f, "Expected (x, y) pairs, got {} coordinates", len)
+            }
+        }
+    }
+}
+
+/// Triangulate flat `[x0, y0, x1, y1, ...]` coordinates, returning the flattened line endpoints
+/// `[org.x, org.y, dest.x, dest.y, ...]`, four values per line. Inputs with less than two distinct
+/// points have no lines.
+pub fn triangulate_flat(coords: &[f64]) -> Result<Vec<f64>, FlatInputError> {
+    if !coords.len().is_multiple_of(2) {
+        return Err(FlatInputError::OddLength(coords.len()));
+    }
+    let mut points: Vec<Point2> = coords
+        .chunks_exact(2)
+        .map(|xy| Point2 { x: xy[0], y: xy[1] })
+        .collect();
+    let lines = triangulate(&mut points);
+    let mut flat = Vec::with_capacity(lines.len() * 4);
+    for (org, dest) in lines {
+        flat.extend_from_slice(&[org.x, org.y, dest.x, dest.y]);
+    }
+    Ok(flat)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flat_round_trip() {
+        let coords = [0., 0., 1., 0., 0., 1., 1., 1., 0.4, 0.6];
+        let flat = triangulate_flat(&coords).unwrap();
+
+        let mut points: Vec<Point2> = coords
+            .chunks_exact(2)
+            .map(|xy| Point2 { x: xy[0], y: xy[1] })
+            .collect();
+        let lines = triangulate(&mut points);
+        assert_eq!(flat.len(), lines.len() * 4);
+        for (line, chunk) in lines.iter().zip(flat.chunks_exact(4)) {
+            assert_eq!(chunk, [line.0.x, line.0.y, line.1.x, line.1.y]);
+        }
+    }
+
+    #[test]
+    fn test_flat_bad_input() {
+        assert_eq!(
+            triangulate_flat(&[0., 0., 1.]),
+            Err(FlatInputError::OddLength(3))
+        );
+        assert_eq!(triangulate_flat(&[]), Ok(vec![]));
+        assert_eq!(triangulate_flat(&[2., 2., 2., 2.]), Ok(vec![]));
+    }
+}
@@ -0,0 +1,439 @@
+/// Weighted Delaunay (regular) triangulation and its dual, the power diagram
+///
+/// Every site carries a weight, the empty circle test becomes the power test: lifting the site
+/// (x, y, w) to (x, y, x² + y² - w), the regular triangulation is the projection of the lower
+/// convex hull of the lifted sites. Sites lifted above that hull have an empty power cell, they
+/// are hidden and not part of the triangulation.
+use crate::edge::EdgeRef;
+use crate::robust_float::{counter_clockwise, Point2, Rect};
+use crate::triangulation::{sanitize_with, Triangulation, VertexHandle};
+use crate::voronoi::{clip_half_plane, polygon_area, rect_corners};
+
+/// Regular triangulation of weighted sites
+pub struct WeightedTriangulation {
+    triangulation: Triangulation,
+    /// Weight of every site, indexed like `triangulation.points()`
+    weights: Vec<f64>,
+    hidden: Vec<usize>,
+}
+
+/// Exact sum of two floats: the rounded sum and its rounding error
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    let b_virtual = sum - a;
+    let a_virtual = sum - b_virtual;
+    (sum, (a - a_virtual) + (b - b_virtual))
+}
+
+/// Exact product of two floats: the rounded product and its rounding error
+fn two_product(a: f64, b: f64) -> (f64, f64) {
+    let product = a * b;
+    (product, a.mul_add(b, -product))
+}
+
+/// Add a float to an expansion, a sum of floats of increasing magnitude that don't overlap.
+/// Zero components are dropped.
+fn grow_expansion(expansion: &[f64], value: f64) -> Vec<f64> {
+    let mut sum = Vec::with_capacity(expansion.len() + 1);
+    let mut carry = value;
+    for &component in expansion {
+        let (high, low) = two_sum(carry, component);
+        if low != 0. {
+            sum.push(low);
+        }
+        carry = high;
+    }
+    if carry != 0. {
+        sum.push(carry);
+    }
+    sum
+}
+
+/// Exact sum of two expansions
+fn expansion_sum(a: &[f64], b: &[f64]) -> Vec<f64> {
+    b.iter()
+        .fold(a.to_vec(), |sum, &value| grow_expansion(&sum, value))
+}
+
+/// Exact product of two expansions
+fn expansion_product(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let mut product = vec![];
+    for &x in a {
+        for &y in b {
+            let (high, low) = two_product(x, y);
+            product = grow_expansion(&grow_expansion(&product, low), high);
+        }
+    }
+    product
+}
+
+/// Lifted site as exact expansions: x, y and x² + y² - weight
+fn lift(point: &Point2, weight: f64) -> [Vec<f64>; 3] {
+    let (xx, xx_low) = two_product(point.x, point.x);
+    let (yy, yy_low) = two_product(point.y, point.y);
+    let z = [xx_low, xx, yy_low, yy, -weight]
+        .into_iter()
+        .fold(vec![], |sum, value| grow_expansion(&sum, value));
+    [vec![point.x], vec![point.y], z]
+}
+
+/// Exact determinant of the rows of lifted sites p, q and r
+fn det3([p, q, r]: [&[Vec<f64>; 3]; 3]) -> Vec<f64> {
+    // z of each row times the 2x2 minor of x and y of the two others, cyclically
+    let minor = |a: &[Vec<f64>; 3], b: &[Vec<f64>; 3]| {
+        let negated: Vec<f64> = expansion_product(&b[0], &a[1]).iter().map(|v| -v).collect();
+        expansion_sum(&expansion_product(&a[0], &b[1]), &negated)
+    };
+    let terms = [(p, q, r), (q, r, p), (r, p, q)]
+        .map(|(row, next, last)| expansion_product(&row[2], &minor(next, last)));
+    expansion_sum(&expansion_sum(&terms[0], &terms[1]), &terms[2])
+}
+
+/// Return true if the lifted `d` is strictly below the plane of the lifted counter clockwise
+/// triangle (a, b, c), the weighted counterpart of `in_circle`. The determinant is evaluated
+/// exactly, the lift included.
+fn in_power_circle(sites: [(&Point2, f64); 4]) -> bool {
+    let [a, b, c, d] = sites.map(|(point, weight)| lift(point, weight));
+    // The determinant of the rows a - d, b - d and c - d, expanded over the rows. The sign is the
+    // one of robust::orient3d, the crate's counter clockwise being clockwise for it.
+    let positive = det3([&a, &b, &c]);
+    let negative = [[&d, &b, &c], [&a, &d, &c], [&a, &b, &d]]
+        .into_iter()
+        .fold(vec![], |sum, rows| expansion_sum(&sum, &det3(rows)));
+    let negated: Vec<f64> = negative.iter().map(|value| -value).collect();
+    // The largest component of the expansion gives its sign
+    expansion_sum(&positive, &negated)
+        .last()
+        .is_some_and(|&largest| largest < 0.)
+}
+
+/// Triangulate the weighted sites. Near-equal sites are merged like `sanitize_points_vec` does,
+/// the kept site keeps its weight. With all weights at zero this is the Delaunay triangulation.
+pub fn triangulate_weighted(points: &[(Point2, f64)]) -> WeightedTriangulation {
+    let positions: Vec<Point2> = points.iter().map(|(point, _)| *point).collect();
+    let weights: Vec<f64> = points.iter().map(|(_, weight)| *weight).collect();
+    let (positions, weights) = sanitize_with(&positions, &weights);
+    let mut weighted = WeightedTriangulation {
+        triangulation: Triangulation::from_sanitized(positions, None),
+        weights,
+        hidden: vec![],
+    };
+    weighted.make_regular();
+    weighted.hidden.sort_unstable();
+    weighted
+}
+
+impl WeightedTriangulation {
+    /// Underlying triangulation, hidden sites are removed from it
+    pub fn triangulation(&self) -> &Triangulation {
+        &self.triangulation
+    }
+
+    /// Weight of every site, indexed like `triangulation().points()`
+    pub fn weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    /// Indices of the sites whose power cell is empty, sorted
+    pub fn hidden_sites(&self) -> &[usize] {
+        &self.hidden
+    }
+
+    fn weight_of(&self, point: &Point2) -> f64 {
+        self.weights[self.triangulation.vertex_index(point).unwrap()]
+    }
+
+    /// Lawson flips driven by the power test, starting from the Delaunay triangulation.
+    ///
+    /// A non regular edge with a convex quadrilateral is flipped. When the quadrilateral isn't
+    /// convex, its reflex site lies inside the triangle of the three others and is lifted above
+    /// their plane, so it is hidden and removed. Both steps lower the lifted surface, which
+    /// guarantees termination.
+    fn make_regular(&mut self) {
+        let mut stack: Vec<EdgeRef> = self.triangulation.live_edges().collect();
+        while let Some(edge) = stack.pop() {
+            let arena = self.triangulation.quad_arena();
+            if edge.quad_edge(arena).is_deleted() {
+                continue;
+            }
+            let (Some([a, b, c]), Some([_, _, d])) = (
+                self.triangulation.left_triangle(edge),
+                self.triangulation.left_triangle(edge.sym()),
+            ) else {
+                continue;
+            };
+            let weight = |point| self.weight_of(point);
+            if !in_power_circle([
+                (&a, weight(&a)),
+                (&b, weight(&b)),
+                (&c, weight(&c)),
+                (&d, weight(&d)),
+            ]) {
+                continue;
+            }
+
+            let a_convex = counter_clockwise(&a, &d, &c);
+            let b_convex = counter_clockwise(&d, &b, &c);
+            if a_convex && b_convex {
+                let around = [edge, edge.sym()].map(|side| {
+                    let second = side.lnext(arena);
+                    [second, second.lnext(arena)]
+                });
+                self.triangulation.flip(edge);
+                stack.extend(around.concat());
+            } else {
+                let reflex = if a_convex { b } else { a };
+                let vertex = self.triangulation.vertex_index(&reflex).unwrap();
+                if self.triangulation.is_hull_vertex(vertex) {
+                    continue;
+                }
+                let neighbors: Vec<usize> = self
+                    .triangulation
+                    .onext_ring(vertex)
+                    .map(|edge| self.triangulation.vertex_index(&edge.dest(arena)).unwrap())
+                    .collect();
+                self.triangulation.remove_vertex(vertex);
+                self.hidden.push(vertex);
+                for neighbor in neighbors {
+                    stack.extend(self.triangulation.onext_ring(neighbor));
+                }
+            }
+        }
+    }
+
+    /// Power center of a triangle, the point with the same power distance
+    /// `|center - site|² - weight` to its three corners
+    pub fn power_center(&self, corners: &[Point2; 3]) -> Point2 {
+        let [w0, w1, w2] = corners.map(|corner| self.weight_of(&corner));
+        let [p0, p1, p2] = corners;
+        let norm = |p: &Point2| p.x * p.x + p.y * p.y;
+        let (ax, ay) = (2. * (p1.x - p0.x), 2. * (p1.y - p0.y));
+        let (bx, by) = (2. * (p2.x - p0.x), 2. * (p2.y - p0.y));
+        let r1 = norm(p1) - norm(p0) - (w1 - w0);
+        let r2 = norm(p2) - norm(p0) - (w2 - w0);
+        let det = ax * by - ay * bx;
+        Point2 {
+            x: (r1 * by - ay * r2) / det,
+            y: (ax * r2 - r1 * bx) / det,
+        }
+    }
+
+    /// Bounded edges of the power diagram, one per edge shared by two triangles, joining the
+    /// power centers of these triangles. Unbounded edges (dual of hull edges) are left out.
+    pub fn power_diagram_edges(&self) -> Vec<(Point2, Point2)> {
+        self.triangulation
+            .live_edges()
+            .filter_map(|edge| {
+                let left = self.triangulation.left_triangle(edge)?;
+                let right = self.triangulation.left_triangle(edge.sym())?;
+                Some((self.power_center(&left), self.power_center(&right)))
+            })
+            .collect()
+    }
+
+    /// Power cell of `site`, the places of smaller power distance to it than to any other site,
+    /// clipped to `bounds`. The polygon is convex, counter clockwise as `counter_clockwise`
+    /// defines it, and empty for hidden and ghost sites or when the cell is out of the bounds.
+    /// Unlike a Voronoi cell, a power cell doesn't have to hold its site.
+    ///
+    /// The cell is cut from `bounds` by the power bisectors with the neighbors of the site in the
+    /// regular triangulation.
+    pub fn power_cell(&self, site: VertexHandle, bounds: Rect) -> Vec<Point2> {
+        let triangulation = &self.triangulation;
+        if site >= triangulation.points().len()
+            || triangulation.is_removed(site)
+            || triangulation.is_ghost(triangulation.points()[site])
+        {
+            return vec![];
+        }
+        let point = triangulation.points()[site];
+        let mut polygon = rect_corners(&bounds).to_vec();
+        for edge in triangulation.onext_ring(site) {
+            if polygon.is_empty() {
+                break;
+            }
+            // |x - point|² - w_point <= |x - other|² - w_other, the offset taken through the
+            // middle of the two sites like `voronoi_cell` does
+            let other = edge.dest(triangulation.quad_arena());
+            let normal = (other.x - point.x, other.y - point.y);
+            let offset = (normal.0 * (point.x + other.x)
+                + normal.1 * (point.y + other.y)
+                + self.weights[site]
+                - self.weight_of(&other))
+                / 2.;
+            polygon = clip_half_plane(&polygon, normal, offset);
+        }
+        polygon
+    }
+
+    /// Area of the `power_cell` of every site, indexed like `triangulation().points()`. The areas
+    /// sum to the area of `bounds` when it holds every power cell that isn't empty. Hidden sites
+    /// have no area.
+    pub fn power_areas(&self, bounds: Rect) -> Vec<f64> {
+        (0..self.triangulation.points().len())
+            .map(|site| polygon_area(&self.power_cell(site, bounds)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robust_float::{cmp_points, in_circle};
+    use crate::test_util::{random_points, xorshift};
+
+    fn edge_set(triangulation: &Triangulation) -> Vec<(Point2, Point2)> {
+        let mut edges: Vec<(Point2, Point2)> = triangulation
+            .quad_arena()
+            .iter()
+            .filter_map(|(_, quad_edge)| quad_edge.get_points(triangulation.quad_arena()))
+            .map(|(a, b)| match cmp_points(&a, &b) {
+                std::cmp::Ordering::Greater => (b, a),
+                _ => (a, b),
+            })
+            .collect();
+        edges.sort_by(|a, b| cmp_points(&a.0, &b.0).then(cmp_points(&a.1, &b.1)));
+        edges
+    }
+
+    #[test]
+    fn test_zero_weights_is_delaunay() {
+        let mut next = xorshift(7);
+        let points: Vec<Point2> = (0..200)
+            .map(|_| Point2 {
+                x: next() * 10.,
+                y: next() * 10.,
+            })
+            .collect();
+        let weighted: Vec<(Point2, f64)> = points.iter().map(|point| (*point, 0.)).collect();
+        let regular = triangulate_weighted(&weighted);
+        assert!(regular.hidden_sites().is_empty());
+        assert_eq!(
+            edge_set(regular.triangulation()),
+            edge_set(&Triangulation::new(&points))
+        );
+    }
+
+    #[test]
+    fn test_heavy_site_hides_neighbor() {
+        // Lifting a = (0, 0) with weight w, the plane of a, b and c passes at 64.625 - 5w/16
+        // above d, while d is lifted to 34: d is hidden once w > 98
+        let a = Point2 { x: 0., y: 0. };
+        let b = Point2 { x: 10., y: 0. };
+        let c = Point2 { x: 5., y: 8. };
+        let d = Point2 { x: 5., y: 3. };
+
+        let regular = triangulate_weighted(&[(a, 90.), (b, 0.), (c, 0.), (d, 0.)]);
+        assert!(regular.hidden_sites().is_empty());
+        assert_eq!(edge_set(regular.triangulation()).len(), 6);
+        assert_eq!(regular.power_diagram_edges().len(), 3);
+
+        let regular = triangulate_weighted(&[(a, 110.), (b, 0.), (c, 0.), (d, 0.)]);
+        let hidden = regular.triangulation().vertex_index(&d).unwrap();
+        assert_eq!(regular.hidden_sites(), &[hidden]);
+        assert_eq!(
+            edge_set(regular.triangulation()),
+            vec![(a, c), (a, b), (c, b)]
+        );
+        assert!(regular.power_diagram_edges().is_empty());
+    }
+
+    #[test]
+    fn test_power_center() {
+        let corners = [
+            Point2 { x: 0., y: 0. },
+            Point2 { x: 2., y: 0. },
+            Point2 { x: 0., y: 2. },
+        ];
+        let regular = triangulate_weighted(&[(corners[0], 1.), (corners[1], 0.), (corners[2], 0.)]);
+        let center = regular.power_center(&corners);
+        let power = |corner: &Point2, weight: f64| {
+            (center.x - corner.x).powi(2) + (center.y - corner.y).powi(2) - weight
+        };
+        assert!((power(&corners[0], 1.) - power(&corners[1], 0.)).abs() < 1e-12);
+        assert!((power(&corners[0], 1.) - power(&corners[2], 0.)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_power_test_is_exact() {
+        // Far from the origin the rounded lift x² + y² loses the low bits the sign depends on,
+        // with zero weights the power test must still be the exact in-circle test
+        let offset = Point2 { x: 1e6, y: 1e6 };
+        let mut grid = xorshift(11);
+        let mut agreeing = 0;
+        for _ in 0..2000 {
+            // Points on a fine grid, many of them cocircular or nearly so
+            let [a, b, c, d] = [(); 4].map(|_| Point2 {
+                x: offset.x + (grid() * 8.).floor() / 1024.,
+                y: offset.y + (grid() * 8.).floor() / 1024.,
+            });
+            if !counter_clockwise(&a, &b, &c) {
+                continue;
+            }
+            assert_eq!(
+                in_power_circle([(&a, 0.), (&b, 0.), (&c, 0.), (&d, 0.)]),
+                in_circle(&a, &b, &c, &d),
+                "{:?}",
+                [a, b, c, d]
+            );
+            agreeing += 1;
+        }
+        assert!(agreeing > 100);
+
+        // A weight shifting the lift by less than its rounding error still counts: d is on the
+        // circle of a, b and c, any positive weight on d brings it below their plane
+        let [a, b, c, d] = [(0., -1.), (-1., 0.), (0., 1.), (1., 0.)].map(|(x, y)| Point2 {
+            x: offset.x + x,
+            y: offset.y + y,
+        });
+        assert!(counter_clockwise(&a, &b, &c));
+        assert!(!in_power_circle([(&a, 0.), (&b, 0.), (&c, 0.), (&d, 0.)]));
+        assert!(in_power_circle([(&a, 0.), (&b, 0.), (&c, 0.), (&d, 1e-9)]));
+        assert!(!in_power_circle([
+            (&a, 0.),
+            (&b, 0.),
+            (&c, 0.),
+            (&d, -1e-9)
+        ]));
+    }
+
+    #[test]
+    fn test_power_areas() {
+        let bounds = Rect {
+            min: Point2 { x: -1., y: -1. },
+            max: Point2 { x: 11., y: 11. },
+        };
+        let points = random_points(5, 100, 10.);
+
+        // Zero weights give the Voronoi diagram
+        let zero: Vec<(Point2, f64)> = points.iter().map(|point| (*point, 0.)).collect();
+        let regular = triangulate_weighted(&zero);
+        let voronoi = regular.triangulation().voronoi_areas(bounds);
+        let power = regular.power_areas(bounds);
+        for (power, voronoi) in power.iter().zip(&voronoi) {
+            assert!((power - voronoi).abs() < 1e-9);
+        }
+
+        // Weights move the cells but they still tile the bounds
+        let mut next = xorshift(6);
+        let weighted: Vec<(Point2, f64)> = points.iter().map(|point| (*point, next())).collect();
+        let regular = triangulate_weighted(&weighted);
+        let areas = regular.power_areas(bounds);
+        assert_eq!(areas.len(), 100);
+        assert!((areas.iter().sum::<f64>() - 144.).abs() < 1e-9);
+
+        // The hidden site has no cell, the heavy one takes its place
+        let a = Point2 { x: 0., y: 0. };
+        let b = Point2 { x: 10., y: 0. };
+        let c = Point2 { x: 5., y: 8. };
+        let d = Point2 { x: 5., y: 3. };
+        let regular = triangulate_weighted(&[(a, 110.), (b, 0.), (c, 0.), (d, 0.)]);
+        let hidden = regular.hidden_sites()[0];
+        assert!(regular.power_cell(hidden, bounds).is_empty());
+        let areas = regular.power_areas(bounds);
+        assert_eq!(areas[hidden], 0.);
+        assert!((areas.iter().sum::<f64>() - 144.).abs() < 1e-9);
+        let heavy = regular.triangulation().vertex_index(&a).unwrap();
+        assert!(areas[heavy] > areas.iter().sum::<f64>() / 2.);
+    }
+}